@@ -0,0 +1,420 @@
+use crate::progress::{self, CountingRead, TransferStage};
+use crate::throttle::ThrottledRead;
+use anyhow::Result;
+use bytes::Bytes;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempPath;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use zip::{ZipArchive, ZipWriter};
+
+/// Read buffer used when hashing or streaming an archive from disk
+const READ_BUFFER_SIZE: usize = 256 * 1024;
+
+/// A save archive written to a temporary file, so large saves never have to
+/// fit in memory. The file is removed when the archive is dropped.
+#[derive(Debug)]
+pub struct SaveArchive {
+    path: TempPath,
+    size: u64,
+    checksum: String,
+}
+
+impl SaveArchive {
+    /// Zip a save file or directory into a temporary file and hash the result
+    pub async fn create(save_path: &Path) -> Result<Self> {
+        let save_path = save_path.to_path_buf();
+        let tracker = progress::current();
+
+        tokio::task::spawn_blocking(move || progress::blocking_scope(tracker, || {
+            progress::start_stage(TransferStage::Compressing, source_size(&save_path));
+            let (file, path) = new_temp_file()?.into_parts();
+
+            let mut zip = ZipWriter::new(BufWriter::new(file));
+            if save_path.is_file() {
+                let file_name = save_path.file_name().unwrap().to_str().unwrap();
+                zip.start_file(file_name, zip_file_options(&save_path))?;
+                std::io::copy(&mut CountingRead::new(File::open(&save_path)?), &mut zip)?;
+            } else if save_path.is_dir() {
+                add_dir_to_zip_sync(&mut zip, &save_path, "")?;
+            }
+            zip.finish()?.flush()?;
+
+            // The zip writer seeks back to patch local headers, so hash in a second pass
+            let (size, checksum) = hash_file(&path)?;
+            Ok::<Self, anyhow::Error>(Self { path, size, checksum })
+        })).await?
+    }
+
+    /// Receive a downloaded archive into a temporary file, hashing it as it arrives
+    pub async fn receive<R: AsyncRead + Unpin>(mut reader: R) -> Result<Self> {
+        let (file, path) = new_temp_file()?.into_parts();
+        let mut file = tokio::fs::File::from_std(file);
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+        let mut size = 0u64;
+
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            crate::cancel::check()?;
+            crate::throttle::throttle().download.acquire(read).await;
+            progress::advance(read as u64);
+            hasher.update(&buffer[..read]);
+            file.write_all(&buffer[..read]).await?;
+            size += read as u64;
+        }
+        file.flush().await?;
+
+        Ok(Self { path, size, checksum: format!("{:x}", hasher.finalize()) })
+    }
+
+    /// Receive the body of a successful HTTP response
+    pub async fn receive_response(response: reqwest::Response) -> Result<Self> {
+        let stream = response.bytes_stream()
+            .map(|chunk| chunk.map_err(std::io::Error::other));
+        Self::receive(tokio_util::io::StreamReader::new(stream)).await
+    }
+
+    /// Write an object that is already in memory to a temporary file
+    pub async fn from_bytes(data: Bytes) -> Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            let (mut file, path) = new_temp_file()?.into_parts();
+            file.write_all(&data)?;
+            let checksum = format!("{:x}", Sha256::digest(&data));
+            Ok(Self { path, size: data.len() as u64, checksum })
+        }).await?
+    }
+
+    /// Copy a file that is already on this machine, hashing the copy
+    pub async fn copy_from(source: &Path) -> Result<Self> {
+        let source = source.to_path_buf();
+        let tracker = progress::current();
+        tokio::task::spawn_blocking(move || progress::blocking_scope(tracker, || {
+            let mut file = CountingRead::new(File::open(&source)?);
+            Self::write_blocking(|writer| {
+                std::io::copy(&mut file, writer)?;
+                Ok(())
+            })
+        })).await?
+    }
+
+    /// Blocking counterpart of [`SaveArchive::receive`] for synchronous transports
+    pub fn receive_blocking(reader: &mut dyn Read) -> Result<Self> {
+        Self::write_blocking(|writer| {
+            std::io::copy(&mut ThrottledRead::downloading(reader), writer)?;
+            Ok(())
+        })
+    }
+
+    /// Write a new archive with `write`, hashing it on the way; for transforms such as
+    /// encryption that produce an object from another file
+    pub(crate) fn write_blocking(write: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<Self> {
+        let (file, path) = new_temp_file()?.into_parts();
+        let mut writer = HashingWriter { inner: BufWriter::new(file), hasher: Sha256::new(), size: 0 };
+        write(&mut writer)?;
+        writer.inner.flush()?;
+
+        Ok(Self { path, size: writer.size, checksum: format!("{:x}", writer.hasher.finalize()) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the archive in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Hex SHA256 of the archive
+    pub fn checksum(&self) -> &str {
+        &self.checksum
+    }
+
+    /// Read `len` bytes starting at `offset`, e.g. one multipart upload part
+    pub async fn read_range(&self, offset: u64, len: u64) -> Result<Bytes> {
+        let path = self.path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut file = File::open(&path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut buffer = Vec::with_capacity(len as usize);
+            file.take(len).read_to_end(&mut buffer)?;
+            if buffer.len() as u64 != len {
+                return Err(anyhow::anyhow!("Archive {} ended early at byte {}", path.display(), offset + buffer.len() as u64));
+            }
+            Ok(Bytes::from(buffer))
+        }).await?
+    }
+
+    /// A request body that streams the archive from disk
+    pub fn body(&self) -> Result<reqwest::Body> {
+        progress::start_stage(TransferStage::Uploading, self.size);
+        let file = tokio::fs::File::from_std(File::open(&self.path)?);
+        let stream = tokio_util::io::ReaderStream::with_capacity(file, READ_BUFFER_SIZE);
+        Ok(reqwest::Body::wrap_stream(crate::throttle::upload_stream(stream)))
+    }
+
+    /// Put a downloaded archive in place: a `.zip` target gets a copy of the archive,
+    /// anything else is treated as the save location and the archive is extracted there
+    pub async fn restore_to(&self, local_path: &Path) -> Result<()> {
+        restore_archive_file(&self.path, local_path).await
+    }
+
+    /// Read the whole archive into memory; only meant for small archives and tests
+    pub async fn to_bytes(&self) -> Result<Bytes> {
+        Ok(Bytes::from(tokio::fs::read(&self.path).await?))
+    }
+}
+
+pub(crate) fn new_temp_file() -> Result<tempfile::NamedTempFile> {
+    Ok(tempfile::Builder::new()
+        .prefix("steam-cloud-sync-")
+        .suffix(".zip")
+        .tempfile()?)
+}
+
+/// Writer that hashes everything passing through it
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Copy an archive file to a `.zip` target, or extract it into the save location
+pub(crate) async fn restore_archive_file(archive_path: &Path, local_path: &Path) -> Result<()> {
+    // If the target path ends with .zip, save as ZIP file directly (for custom download location)
+    if local_path.extension().and_then(|s| s.to_str()) == Some("zip") {
+        if let Some(parent) = local_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(archive_path, local_path).await?;
+        Ok(())
+    } else {
+        // Otherwise, extract the ZIP to the game save directory
+        extract_archive_file(archive_path, local_path).await
+    }
+}
+
+/// Extract an archive file to the target path, streaming each entry from disk
+async fn extract_archive_file(archive_path: &Path, target_path: &Path) -> Result<()> {
+    let archive_path = archive_path.to_path_buf();
+    let target_path = target_path.to_path_buf();
+    let tracker = progress::current();
+
+    tokio::task::spawn_blocking(move || progress::blocking_scope(tracker, || {
+        let mut zip = ZipArchive::new(BufReader::new(File::open(&archive_path)?))?;
+        let total = (0..zip.len())
+            .filter_map(|i| zip.by_index_raw(i).ok().map(|file| file.size()))
+            .sum();
+        progress::start_stage(TransferStage::Extracting, total);
+
+        // Create target directory if it doesn't exist
+        if let Some(parent) = target_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // If there's only one file in the archive and target_path is a file path,
+        // extract directly to that file
+        if zip.len() == 1 && target_path.extension().is_some() {
+            let mut file = zip.by_index(0)?;
+            std::io::copy(&mut CountingRead::new(&mut file), &mut File::create(&target_path)?)?;
+        } else {
+            // Otherwise, extract all files to the target directory
+            let extract_dir = if target_path.is_dir() || target_path.extension().is_none() {
+                target_path
+            } else {
+                target_path.parent().unwrap().to_path_buf()
+            };
+
+            std::fs::create_dir_all(&extract_dir)?;
+
+            for i in 0..zip.len() {
+                let mut file = zip.by_index(i)?;
+                let file_path = match file.enclosed_name() {
+                    Some(path) => extract_dir.join(path),
+                    None => continue,
+                };
+
+                if file.name().ends_with('/') {
+                    std::fs::create_dir_all(&file_path)?;
+                } else {
+                    if let Some(parent) = file_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::io::copy(&mut CountingRead::new(&mut file), &mut File::create(&file_path)?)?;
+                }
+            }
+        }
+
+        Ok::<(), anyhow::Error>(())
+    })).await?
+}
+
+/// Bytes of all files in a save file or directory
+fn source_size(path: &Path) -> u64 {
+    if path.is_file() {
+        return std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    }
+    std::fs::read_dir(path).into_iter().flatten().flatten()
+        .map(|entry| source_size(&entry.path()))
+        .sum()
+}
+
+/// Size and hex SHA256 of a file, computed without loading it into memory
+pub(crate) fn hash_file(path: &Path) -> Result<(u64, String)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    let mut size = 0u64;
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+/// Entry options using the file's own mtime rather than the current time, so archiving
+/// an unchanged folder twice yields identical bytes (and an interrupted multipart
+/// upload of it can be resumed)
+fn zip_file_options(path: &Path) -> zip::write::FileOptions {
+    let options = zip::write::FileOptions::default();
+    let modified = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(chrono::DateTime::<chrono::Utc>::from);
+
+    match modified {
+        Ok(mtime) => {
+            use chrono::{Datelike, Timelike};
+            match zip::DateTime::from_date_and_time(
+                mtime.year().clamp(1980, 2107) as u16,
+                mtime.month() as u8,
+                mtime.day() as u8,
+                mtime.hour() as u8,
+                mtime.minute() as u8,
+                mtime.second() as u8,
+            ) {
+                Ok(dt) => options.last_modified_time(dt),
+                Err(_) => options.last_modified_time(zip::DateTime::default()),
+            }
+        }
+        Err(_) => options.last_modified_time(zip::DateTime::default()),
+    }
+}
+
+fn add_dir_to_zip_sync<W: Write + Seek>(zip: &mut ZipWriter<W>, dir: &Path, prefix: &str) -> Result<()> {
+    // Sorted so the archive layout doesn't depend on directory iteration order
+    let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path: PathBuf = entry.path();
+        let name = entry.file_name();
+        let file_name = format!("{}{}", prefix, name.to_str().unwrap());
+
+        if path.is_file() {
+            zip.start_file(&file_name, zip_file_options(&path))?;
+            std::io::copy(&mut CountingRead::new(File::open(&path)?), zip)?;
+        } else if path.is_dir() {
+            add_dir_to_zip_sync(zip, &path, &format!("{}/", file_name))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_archive_is_deterministic() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("b.sav"), b"second").await.unwrap();
+        tokio::fs::write(temp_dir.path().join("a.sav"), b"first").await.unwrap();
+
+        let first = SaveArchive::create(temp_dir.path()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+        let second = SaveArchive::create(temp_dir.path()).await.unwrap();
+        assert_eq!(first.checksum(), second.checksum());
+        assert_eq!(first.to_bytes().await.unwrap(), second.to_bytes().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_archive_streams_from_disk() {
+        let temp_dir = TempDir::new().unwrap();
+        let save_file = temp_dir.path().join("world.dat");
+        // Incompressible content so the archive is larger than a read buffer
+        let content: Vec<u8> = (0..READ_BUFFER_SIZE as u32 * 3).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        tokio::fs::write(&save_file, &content).await.unwrap();
+
+        let archive = SaveArchive::create(&save_file).await.unwrap();
+        let whole = archive.to_bytes().await.unwrap();
+        assert_eq!(archive.size(), whole.len() as u64);
+
+        let mut hasher = Sha256::new();
+        hasher.update(&whole);
+        assert_eq!(archive.checksum(), format!("{:x}", hasher.finalize()));
+
+        let middle = archive.read_range(1000, 5000).await.unwrap();
+        assert_eq!(middle, whole.slice(1000..6000));
+        assert!(archive.read_range(archive.size() - 10, 20).await.is_err());
+
+        let temp_path = archive.path().to_path_buf();
+        drop(archive);
+        assert!(!temp_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_receive_and_restore() {
+        let source_dir = TempDir::new().unwrap();
+        tokio::fs::create_dir_all(source_dir.path().join("profile")).await.unwrap();
+        tokio::fs::write(source_dir.path().join("profile/slot1.sav"), b"slot one").await.unwrap();
+        tokio::fs::write(source_dir.path().join("settings.ini"), b"[video]").await.unwrap();
+        let uploaded = SaveArchive::create(source_dir.path()).await.unwrap();
+        let bytes = uploaded.to_bytes().await.unwrap();
+
+        // Small reads so the body arrives in several pieces
+        let reader = tokio::io::BufReader::with_capacity(7, &bytes[..]);
+        let received = SaveArchive::receive(reader).await.unwrap();
+        assert_eq!(received.checksum(), uploaded.checksum());
+        assert_eq!(received.size(), uploaded.size());
+
+        let blocking = SaveArchive::receive_blocking(&mut &bytes[..]).unwrap();
+        assert_eq!(blocking.checksum(), uploaded.checksum());
+
+        let target_dir = TempDir::new().unwrap();
+        let restore_path = target_dir.path().join("saves");
+        received.restore_to(&restore_path).await.unwrap();
+        assert_eq!(tokio::fs::read(restore_path.join("profile/slot1.sav")).await.unwrap(), b"slot one");
+        assert_eq!(tokio::fs::read(restore_path.join("settings.ini")).await.unwrap(), b"[video]");
+
+        let zip_path = target_dir.path().join("export/backup.zip");
+        received.restore_to(&zip_path).await.unwrap();
+        assert_eq!(tokio::fs::read(&zip_path).await.unwrap(), bytes);
+    }
+}
@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::future::Future;
+use tokio_util::sync::CancellationToken;
+
+/// Error returned by an operation that was cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+tokio::task_local! {
+    /// Token of the operation the current task is running for
+    static CURRENT: CancellationToken;
+}
+
+thread_local! {
+    /// Same for blocking transports running on a `spawn_blocking` thread
+    static CURRENT_BLOCKING: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// Run `future` as an operation cancelled through `token`. Backends check the token at
+/// their cancellation points (between chunks and parts), so they can clean up after themselves.
+pub async fn scope<F: Future>(token: CancellationToken, future: F) -> F::Output {
+    CURRENT.scope(token, future).await
+}
+
+/// The token of the operation being run, if it is cancellable
+pub fn current() -> Option<CancellationToken> {
+    CURRENT.try_with(CancellationToken::clone).ok()
+        .or_else(|| CURRENT_BLOCKING.with(|token| token.borrow().clone()))
+}
+
+/// Fail with [`Cancelled`] if the current operation was cancelled
+pub fn check() -> anyhow::Result<()> {
+    match current() {
+        Some(token) if token.is_cancelled() => Err(Cancelled.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Run `future`, giving up with [`Cancelled`] as soon as the current operation is cancelled
+pub async fn cancellable<T>(future: impl Future<Output = anyhow::Result<T>>) -> anyhow::Result<T> {
+    match current() {
+        // Checked first, so work that is ready immediately doesn't outrun a cancellation
+        Some(token) => tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(Cancelled.into()),
+            result = future => result,
+        },
+        None => future.await,
+    }
+}
+
+/// Run blocking `f` on behalf of the operation with `token` (see [`current`])
+pub fn blocking_scope<T>(token: Option<CancellationToken>, f: impl FnOnce() -> T) -> T {
+    let previous = CURRENT_BLOCKING.with(|current| current.replace(token));
+    let result = f();
+    CURRENT_BLOCKING.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// Whether `error` is, or was caused by, a cancellation
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.is::<Cancelled>()
+            || cause.downcast_ref::<std::io::Error>()
+                .and_then(|e| e.get_ref())
+                .is_some_and(|inner| inner.is::<Cancelled>())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_and_checks() {
+        assert!(check().is_ok());
+
+        let token = CancellationToken::new();
+        let inner = token.clone();
+        let result = scope(token.clone(), async move {
+            check()?;
+            inner.cancel();
+            check()
+        }).await;
+        assert!(is_cancelled(&result.unwrap_err()));
+
+        // Pending work is abandoned once the token fires
+        let token = CancellationToken::new();
+        token.cancel();
+        let result: anyhow::Result<()> = scope(token, cancellable(std::future::pending())).await;
+        assert!(is_cancelled(&result.unwrap_err()));
+
+        // Cancellation reported through an io::Error still counts
+        let io = std::io::Error::other(Cancelled);
+        assert!(is_cancelled(&anyhow::Error::from(io)));
+        assert!(!is_cancelled(&anyhow::anyhow!("connection reset")));
+    }
+
+    #[test]
+    fn test_blocking_scope() {
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(blocking_scope(Some(token), check).is_err());
+        assert!(check().is_ok());
+    }
+}
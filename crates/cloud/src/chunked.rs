@@ -0,0 +1,732 @@
+use crate::{
+    object_key::{self, ObjectKey}, sanitize_user_id, version_info, CloudBackend, ObjectInfo, SaveArchive, SaveMetadata,
+    ReplicatedBackend, StorageInfo, UploadProgress, UploadSessionStore, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use fastcdc::v2020::StreamCDC;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use steam_cloud_sync_core::GameSave;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// Suffix of version manifests, which live next to regular archives under `saves/<user>/`
+pub const MANIFEST_SUFFIX: &str = ".manifest.json";
+/// Manifest layout version; bumped when older clients could not restore a manifest
+const MANIFEST_FORMAT: u32 = 1;
+
+// FastCDC bounds. Edits shift chunk boundaries only locally, so a small change in a
+// large file costs roughly one average-sized chunk.
+const MIN_CHUNK_SIZE: u32 = 256 * 1024;
+const AVG_CHUNK_SIZE: u32 = 1024 * 1024;
+const MAX_CHUNK_SIZE: u32 = 4 * 1024 * 1024;
+
+/// Chunks read ahead of the upload; bounds memory to a few maximum-size chunks
+const CHUNK_QUEUE_DEPTH: usize = 4;
+
+/// Unreferenced chunks younger than this survive garbage collection, since they may
+/// belong to an upload (possibly from another machine) whose manifest isn't written yet
+pub(crate) const GC_GRACE_PERIOD_HOURS: i64 = 1;
+
+/// One save version in chunked form: the file list and, per file, the ordered
+/// content hashes of its chunks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveManifest {
+    pub format: u32,
+    pub app_id: u32,
+    pub name: String,
+    pub created_at: String,
+    /// The save path was a single file rather than a directory
+    pub single_file: bool,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Path relative to the save folder, `/`-separated
+    pub path: String,
+    pub size: u64,
+    /// Hex SHA256 of each chunk's uncompressed content, in file order
+    pub chunks: Vec<String>,
+}
+
+impl SaveManifest {
+    /// Uncompressed size of the save
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    pub fn chunk_ids(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().flat_map(|f| f.chunks.iter().map(String::as_str))
+    }
+}
+
+/// Counters for one chunked upload
+#[derive(Debug, Default)]
+struct UploadStats {
+    new_chunks: u32,
+    reused_chunks: u32,
+    uploaded_bytes: u64,
+}
+
+/// Stores saves as content-defined chunks plus a small manifest per version, on
+/// top of any backend that supports plain object access. Only chunks the backend
+/// doesn't have yet are uploaded, so versions of a large save that differ in a
+/// few files share almost all of their storage.
+///
+/// Chunks are kept per user under `chunks/<user>/<first two hex digits>/<sha256>`,
+/// deflate-compressed. Saves uploaded as plain archives keep working: listing
+/// merges both kinds, and downloads/deletes of archives go to the inner backend.
+pub struct ChunkedBackend {
+    inner: Box<dyn CloudBackend>,
+    gc_grace: chrono::Duration,
+}
+
+impl ChunkedBackend {
+    pub fn new(inner: Box<dyn CloudBackend>) -> Self {
+        Self {
+            inner,
+            gc_grace: chrono::Duration::hours(GC_GRACE_PERIOD_HOURS),
+        }
+    }
+
+    /// Whether a save's `file_id` refers to a chunked version rather than an archive
+    pub fn is_manifest(file_id: &str) -> bool {
+        file_id.ends_with(MANIFEST_SUFFIX)
+    }
+
+    fn chunk_prefix(user: &str) -> String {
+        format!("chunks/{}/", user)
+    }
+
+    fn chunk_key(user: &str, chunk_id: &str) -> String {
+        format!("{}{}/{}", Self::chunk_prefix(user), &chunk_id[..2], chunk_id)
+    }
+
+    /// Fetch and parse a manifest, returning it with the SHA256 of its bytes
+    async fn load_manifest(&self, key: &str) -> Result<(SaveManifest, String)> {
+        let data = self.inner.get_object(key).await?;
+        let manifest: SaveManifest = serde_json::from_slice(&data)
+            .map_err(|e| anyhow::anyhow!("Invalid manifest {}: {}", key, e))?;
+        if manifest.format > MANIFEST_FORMAT {
+            return Err(anyhow::anyhow!(
+                "Manifest {} was written by a newer version (format {}); please update",
+                key, manifest.format
+            ));
+        }
+        Ok((manifest, sha256_hex(&data)))
+    }
+
+    fn metadata_for(&self, key: &str, manifest: &SaveManifest, checksum: String) -> SaveMetadata {
+        SaveMetadata {
+            game_id: manifest.app_id.to_string(),
+            timestamp: manifest.created_at.clone(),
+            size_bytes: manifest.total_size(),
+            checksum,
+            compressed: true,
+            encrypted: self.inner.encrypts_objects(),
+            file_id: key.to_string(),
+            info: None,
+        }
+    }
+
+    async fn manifest_keys(&self, user: &str) -> Result<Vec<String>> {
+        Ok(self.inner.list_objects(&format!("{}{}/", self.save_prefix(), user)).await?
+            .into_iter()
+            .map(|object| object.key)
+            .filter(|key| Self::is_manifest(key))
+            .collect())
+    }
+
+    /// Chunks an upload may reuse: those a manifest of the user refers to, which garbage
+    /// collection keeps while their versions exist. Any other stored chunk may be collected
+    /// before the upload's manifest is written, so it is uploaded again, which also makes
+    /// it young enough to survive collection.
+    async fn reusable_chunks(&self, user: &str) -> Result<HashSet<String>> {
+        let mut referenced = HashSet::new();
+        for key in self.manifest_keys(user).await? {
+            match self.load_manifest(&key).await {
+                Ok((manifest, _)) => referenced.extend(manifest.chunk_ids().map(str::to_string)),
+                Err(e) => eprintln!("[Chunked] Not reusing the chunks of unreadable manifest {}: {}", key, e),
+            }
+        }
+        Ok(referenced)
+    }
+
+    /// Chunk one file and upload the chunks not in `known`, returning the file's chunk list
+    async fn upload_file(
+        &self,
+        user: &str,
+        path: &Path,
+        known: &mut HashSet<String>,
+        stats: &mut UploadStats,
+    ) -> Result<(u64, Vec<String>)> {
+        // Chunking runs on the blocking pool and hands chunks over a bounded queue,
+        // so at most a few chunks are in memory regardless of the file size
+        let (tx, mut rx) = mpsc::channel::<(String, Vec<u8>)>(CHUNK_QUEUE_DEPTH);
+        let source = path.to_path_buf();
+        let producer = tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(&source)?;
+            for chunk in StreamCDC::new(file, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE) {
+                let chunk = chunk?;
+                if tx.blocking_send((sha256_hex(&chunk.data), chunk.data)).is_err() {
+                    break; // the upload side gave up
+                }
+            }
+            Ok::<(), anyhow::Error>(())
+        });
+
+        let mut size = 0u64;
+        let mut chunk_ids = Vec::new();
+        while let Some((chunk_id, data)) = rx.recv().await {
+            size += data.len() as u64;
+            if known.contains(&chunk_id) {
+                stats.reused_chunks += 1;
+            } else {
+                let encoded = tokio::task::spawn_blocking(move || encode_chunk(&data)).await??;
+                stats.uploaded_bytes += encoded.len() as u64;
+                self.inner.put_object(&Self::chunk_key(user, &chunk_id), Bytes::from(encoded)).await?;
+                stats.new_chunks += 1;
+                known.insert(chunk_id.clone());
+            }
+            chunk_ids.push(chunk_id);
+        }
+        producer.await??;
+
+        Ok((size, chunk_ids))
+    }
+
+    /// Reassemble one file from its chunks, writing to a temporary sibling first
+    /// so a failed download doesn't leave a truncated save behind
+    async fn restore_file(&self, user: &str, file: &ManifestFile, target: &Path) -> Result<()> {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut partial = target.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let result = async {
+            let mut out = tokio::fs::File::create(&partial).await?;
+            let mut written = 0u64;
+            for chunk_id in &file.chunks {
+                let encoded = self.inner.get_object(&Self::chunk_key(user, chunk_id)).await
+                    .map_err(|e| {
+                        // Keep the cause, so a dropped connection stays retryable
+                        let message = format!("Missing chunk {} of {}: {}", chunk_id, file.path, e);
+                        e.context(message)
+                    })?;
+                let chunk_id = chunk_id.clone();
+                let data = tokio::task::spawn_blocking(move || decode_chunk(&chunk_id, &encoded)).await??;
+                out.write_all(&data).await?;
+                written += data.len() as u64;
+            }
+            out.flush().await?;
+
+            if written != file.size {
+                return Err(anyhow::anyhow!("{} restored to {} bytes, expected {}", file.path, written, file.size));
+            }
+            Ok::<(), anyhow::Error>(())
+        }.await;
+
+        match result {
+            Ok(()) => Ok(tokio::fs::rename(&partial, target).await?),
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn restore_manifest(&self, user: &str, manifest: &SaveManifest, local_path: &Path) -> Result<()> {
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        let targets = restore_targets(manifest.single_file, &paths, local_path)?;
+
+        for (file, target) in manifest.files.iter().zip(targets) {
+            self.restore_file(user, file, &target).await?;
+        }
+        Ok(())
+    }
+
+    /// Delete chunks no manifest of the user refers to any more.
+    /// Returns `(freed_bytes, deleted_chunks)`.
+    pub async fn collect_garbage(&self, user_id: &str) -> Result<(u64, u32)> {
+        let user = sanitize_user_id(user_id);
+
+        // Any unreadable manifest aborts the run: its chunks must not be mistaken for garbage
+        let mut referenced = HashSet::new();
+        for key in self.manifest_keys(&user).await? {
+            let (manifest, _) = self.load_manifest(&key).await?;
+            referenced.extend(manifest.chunk_ids().map(str::to_string));
+        }
+
+        let cutoff = chrono::Utc::now() - self.gc_grace;
+        let mut freed_bytes = 0u64;
+        let mut deleted_chunks = 0u32;
+        for chunk in self.inner.list_objects(&Self::chunk_prefix(&user)).await? {
+            let chunk_id = chunk.key.rsplit('/').next().unwrap_or_default();
+            let old_enough = chunk.last_modified.is_some_and(|modified| modified < cutoff);
+            if referenced.contains(chunk_id) || !old_enough {
+                continue;
+            }
+
+            self.inner.delete_object(&chunk.key).await?;
+            freed_bytes += chunk.size_bytes;
+            deleted_chunks += 1;
+        }
+
+        eprintln!("[Chunked] Garbage collection for {}: deleted {} chunks ({} bytes)", user, deleted_chunks, freed_bytes);
+        Ok((freed_bytes, deleted_chunks))
+    }
+}
+
+#[async_trait]
+impl CloudBackend for ChunkedBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let user = sanitize_user_id(user_id);
+        let save_path = game_save.save_path.clone();
+        if !save_path.exists() {
+            return Err(anyhow::anyhow!("Save path {} does not exist", save_path.display()));
+        }
+
+        let single_file = save_path.is_file();
+        let files = tokio::task::spawn_blocking(move || collect_save_files(&save_path)).await??;
+
+        let mut known = self.reusable_chunks(&user).await?;
+        let mut stats = UploadStats::default();
+        let mut manifest_files = Vec::with_capacity(files.len());
+        for (relative_path, path) in files {
+            let (size, chunks) = self.upload_file(&user, &path, &mut known, &mut stats).await?;
+            manifest_files.push(ManifestFile { path: relative_path, size, chunks });
+        }
+
+        let manifest = SaveManifest {
+            format: MANIFEST_FORMAT,
+            app_id: game_save.app_id,
+            name: game_save.name.clone(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            single_file,
+            files: manifest_files,
+        };
+        let data = serde_json::to_vec(&manifest)?;
+        let checksum = sha256_hex(&data);
+
+        // The manifest goes up last, so a version is only visible once all its chunks are stored
+        let key = ObjectKey::new(&user, game_save.app_id, MANIFEST_SUFFIX)
+            .with_prefix(self.save_prefix())
+            .to_string();
+        self.inner.put_object(&key, Bytes::from(data)).await?;
+
+        let info = VersionInfo::describe(game_save, &checksum, version_info::FORMAT_CHUNKED).await;
+        version_info::store_version_info(self.inner.as_ref(), &key, &info).await;
+
+        eprintln!(
+            "[Chunked] Stored {} ({} bytes in {} files): {} new chunks ({} bytes sent), {} reused",
+            key, manifest.total_size(), manifest.files.len(), stats.new_chunks, stats.uploaded_bytes, stats.reused_chunks
+        );
+
+        Ok(SaveMetadata { info: Some(info), ..self.metadata_for(&key, &manifest, checksum) })
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        if !Self::is_manifest(&metadata.file_id) {
+            return self.inner.download_save(metadata, local_path).await;
+        }
+
+        let (manifest, checksum) = self.load_manifest(&metadata.file_id).await?;
+        if metadata.checksum.len() == 64 && checksum != metadata.checksum {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                metadata.file_id, metadata.checksum, checksum
+            ));
+        }
+        let user = save_key_user(&metadata.file_id, self.save_prefix())?;
+
+        if local_path.extension().and_then(|s| s.to_str()) == Some("zip") {
+            // Export: rebuild the save in a scratch folder and hand out a regular archive
+            let scratch = tempfile::TempDir::new()?;
+            let root = match (manifest.single_file, manifest.files.first()) {
+                (true, Some(file)) => scratch.path().join(&file.path),
+                _ => scratch.path().join(&manifest.name),
+            };
+            self.restore_manifest(user, &manifest, &root).await?;
+            SaveArchive::create(&root).await?.restore_to(local_path).await
+        } else {
+            self.restore_manifest(user, &manifest, local_path).await
+        }
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        let mut versions = Vec::new();
+        for key in self.manifest_keys(&sanitize_user_id(user_id)).await? {
+            if game_id.is_some_and(|gid| object_key::game_id_from_key_with_prefix(&key, self.save_prefix()) != gid) {
+                continue;
+            }
+
+            match self.load_manifest(&key).await {
+                Ok((manifest, checksum)) => versions.push(self.metadata_for(&key, &manifest, checksum)),
+                Err(e) => eprintln!("[Chunked] Skipping unreadable manifest {}: {}", key, e),
+            }
+        }
+        version_info::attach_version_info(self.inner.as_ref(), &mut versions).await;
+
+        let mut saves = self.inner.list_saves(user_id, game_id).await?;
+        saves.extend(versions);
+
+        saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(saves)
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        if !Self::is_manifest(&metadata.file_id) {
+            return self.inner.delete_save(metadata).await;
+        }
+
+        self.inner.delete_object(&metadata.file_id).await?;
+        let _ = self.inner.delete_object(&VersionInfo::sidecar_key(&metadata.file_id)).await;
+
+        // The version is gone either way; leftover chunks are collected next time
+        let user = save_key_user(&metadata.file_id, self.save_prefix())?;
+        if let Err(e) = self.collect_garbage(user).await {
+            eprintln!("[Chunked] Garbage collection after deleting {} failed: {}", metadata.file_id, e);
+        }
+        Ok(())
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        self.inner.resume_upload(upload_id, offset, data).await
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.inner.test_connection().await
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        let mut info = self.inner.get_storage_info(user_id).await?;
+        // Chunks are shared between versions, so they count towards usage but not the file count
+        let chunks = self.inner.list_objects(&Self::chunk_prefix(&sanitize_user_id(user_id))).await?;
+        info.used_bytes += chunks.iter().map(|c| c.size_bytes).sum::<u64>();
+        Ok(info)
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        self.inner.get_bucket_storage_info().await
+    }
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        self.inner.set_upload_session_store(store);
+    }
+
+    fn encrypts_objects(&self) -> bool {
+        self.inner.encrypts_objects()
+    }
+
+    fn replication(&self) -> Option<ReplicatedBackend> {
+        self.inner.replication()
+    }
+
+    fn save_prefix(&self) -> &str {
+        self.inner.save_prefix()
+    }
+
+    /// Size of the chunks the upload would send, before compression
+    async fn estimate_upload(&self, game_save: &GameSave, user_id: &str) -> Result<u64> {
+        let mut known = self.reusable_chunks(&sanitize_user_id(user_id)).await?;
+        let save_path = game_save.save_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut new_bytes = 0;
+            for (_, path) in collect_save_files(&save_path)? {
+                let file = std::fs::File::open(&path)?;
+                for chunk in StreamCDC::new(file, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE) {
+                    let chunk = chunk?;
+                    if known.insert(sha256_hex(&chunk.data)) {
+                        new_bytes += chunk.length as u64;
+                    }
+                }
+            }
+            Ok(new_bytes)
+        }).await?
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        self.inner.put_object(key, data).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        self.inner.get_object(key).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        self.inner.get_archive(key).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        self.inner.put_archive(key, archive).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        self.inner.object_exists(key).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        self.inner.list_objects(prefix).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.inner.delete_object(key).await
+    }
+
+    async fn move_object(&self, from: &str, to: &str) -> Result<()> {
+        self.inner.move_object(from, to).await
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        self.inner.get_object_tagged(key).await
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        self.inner.put_object_if(key, data, expected_tag).await
+    }
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
+fn encode_chunk(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len() / 2), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Decompress a chunk and check it against its content hash
+fn decode_chunk(chunk_id: &str, encoded: &[u8]) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    DeflateDecoder::new(encoded).read_to_end(&mut data)?;
+    let actual = sha256_hex(&data);
+    if actual != chunk_id {
+        return Err(anyhow::anyhow!("Chunk {} is corrupt (content hashes to {})", chunk_id, actual));
+    }
+    Ok(data)
+}
+
+/// All files of a save as (relative path, absolute path), sorted for stable manifests
+pub(crate) fn collect_save_files(save_path: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if save_path.is_file() {
+        let name = save_path.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid save file name: {}", save_path.display()))?;
+        return Ok(vec![(name.to_string(), save_path.to_path_buf())]);
+    }
+
+    fn walk(dir: &Path, prefix: &str, out: &mut Vec<(String, PathBuf)>) -> Result<()> {
+        let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = format!("{}{}", prefix, name);
+            if path.is_file() {
+                out.push((relative_path, path));
+            } else if path.is_dir() {
+                walk(&path, &format!("{}/", relative_path), out)?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(save_path, "", &mut files)?;
+    Ok(files)
+}
+
+/// The sanitized user a manifest key in the save tree under `prefix` belongs to
+pub(crate) fn save_key_user<'a>(key: &'a str, prefix: &str) -> Result<&'a str> {
+    object_key::user_from_key(key, prefix)
+        .ok_or_else(|| anyhow::anyhow!("Unexpected manifest key: {}", key))
+}
+
+/// Where each file of a manifest goes when restoring to `local_path`, with the same
+/// placement rules as archive extraction: a single-file save goes straight to a file
+/// path, everything else into the target directory
+pub(crate) fn restore_targets(single_file: bool, paths: &[&str], local_path: &Path) -> Result<Vec<PathBuf>> {
+    if single_file && paths.len() == 1 && local_path.extension().is_some() {
+        return Ok(vec![local_path.to_path_buf()]);
+    }
+
+    let extract_dir = if local_path.is_dir() || local_path.extension().is_none() {
+        local_path.to_path_buf()
+    } else {
+        local_path.parent().unwrap_or(local_path).to_path_buf()
+    };
+    std::fs::create_dir_all(&extract_dir)?;
+
+    paths.iter().map(|path| enclosed_path(&extract_dir, path)).collect()
+}
+
+/// Join a manifest path onto `dir`, rejecting paths that would escape it
+fn enclosed_path(dir: &Path, relative_path: &str) -> Result<PathBuf> {
+    let mut path = dir.to_path_buf();
+    for component in Path::new(relative_path).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            _ => return Err(anyhow::anyhow!("Invalid path in manifest: {}", relative_path)),
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalFolderBackend;
+    use tempfile::TempDir;
+
+    /// Deterministic, incompressible test data
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(2654435761) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn chunk_count(root: &Path) -> usize {
+        fn count(dir: &Path) -> usize {
+            std::fs::read_dir(dir).map(|entries| {
+                entries.flatten().map(|e| if e.path().is_dir() { count(&e.path()) } else { 1 }).sum()
+            }).unwrap_or(0)
+        }
+        count(&root.join("chunks"))
+    }
+
+    #[tokio::test]
+    async fn test_versions_share_unchanged_chunks() {
+        let storage = TempDir::new().unwrap();
+        let mut backend = ChunkedBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())));
+        backend.gc_grace = chrono::Duration::zero();
+
+        let save_dir = TempDir::new().unwrap();
+        let world = noise(6 * 1024 * 1024, 1);
+        std::fs::create_dir_all(save_dir.path().join("world")).unwrap();
+        std::fs::write(save_dir.path().join("world/region.dat"), &world).unwrap();
+        std::fs::write(save_dir.path().join("options.txt"), b"fov=90").unwrap();
+        let game_save = GameSave { app_id: 1086940, name: "BG3".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        let first = backend.upload_save(&game_save, "player").await.unwrap();
+        let chunks_after_first = chunk_count(storage.path());
+        assert!(chunks_after_first > 3);
+        assert_eq!(first.size_bytes, world.len() as u64 + 6);
+
+        // Change a few bytes in the middle of the big file and the small file
+        let mut edited = world.clone();
+        edited[3_000_000..3_000_010].copy_from_slice(b"0123456789");
+        std::fs::write(save_dir.path().join("world/region.dat"), &edited).unwrap();
+        std::fs::write(save_dir.path().join("options.txt"), b"fov=100").unwrap();
+
+        let second = backend.upload_save(&game_save, "player").await.unwrap();
+        let new_chunks = chunk_count(storage.path()) - chunks_after_first;
+        assert!(new_chunks <= 3, "uploaded {} new chunks for a small edit", new_chunks);
+
+        let saves = backend.list_saves("player", Some("1086940")).await.unwrap();
+        assert_eq!(saves.len(), 2);
+        assert!(saves.iter().all(|s| ChunkedBackend::is_manifest(&s.file_id)));
+
+        // Both versions restore byte for byte
+        let restore = TempDir::new().unwrap();
+        backend.download_save(&first, &restore.path().join("v1")).await.unwrap();
+        backend.download_save(&second, &restore.path().join("v2")).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("v1/world/region.dat")).unwrap(), world);
+        assert_eq!(std::fs::read(restore.path().join("v1/options.txt")).unwrap(), b"fov=90");
+        assert_eq!(std::fs::read(restore.path().join("v2/world/region.dat")).unwrap(), edited);
+        assert_eq!(std::fs::read(restore.path().join("v2/options.txt")).unwrap(), b"fov=100");
+
+        // Deleting the first version frees only the chunks the second doesn't use
+        backend.delete_save(&first).await.unwrap();
+        assert_eq!(chunk_count(storage.path()), chunks_after_first);
+        backend.download_save(&second, &restore.path().join("v2-again")).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("v2-again/world/region.dat")).unwrap(), edited);
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_chunks_are_uploaded_again() {
+        fn chunk_files(dir: &Path) -> Vec<PathBuf> {
+            std::fs::read_dir(dir).into_iter().flatten().flatten()
+                .flat_map(|e| if e.path().is_dir() { chunk_files(&e.path()) } else { vec![e.path()] })
+                .collect()
+        }
+
+        let storage = TempDir::new().unwrap();
+        let backend = ChunkedBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())));
+        let save_dir = TempDir::new().unwrap();
+        std::fs::write(save_dir.path().join("world.dat"), noise(1024 * 1024, 3)).unwrap();
+        let game_save = GameSave { app_id: 1086940, name: "BG3".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        // Chunks whose version is gone and that are past the grace period can be
+        // collected at any time, so an upload must not count on them
+        let first = backend.upload_save(&game_save, "player").await.unwrap();
+        std::fs::remove_file(storage.path().join(&first.file_id)).unwrap();
+        let long_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 3600);
+        for chunk in chunk_files(&storage.path().join("chunks")) {
+            std::fs::File::options().write(true).open(&chunk).unwrap().set_modified(long_ago).unwrap();
+        }
+
+        let second = backend.upload_save(&game_save, "player").await.unwrap();
+        let recently = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        for chunk in chunk_files(&storage.path().join("chunks")) {
+            assert!(std::fs::metadata(&chunk).unwrap().modified().unwrap() > recently, "{} was reused", chunk.display());
+        }
+        let restore = TempDir::new().unwrap();
+        backend.download_save(&second, restore.path()).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("world.dat")).unwrap(), noise(1024 * 1024, 3));
+    }
+
+    #[tokio::test]
+    async fn test_single_file_save_and_zip_export() {
+        let storage = TempDir::new().unwrap();
+        let backend = ChunkedBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())));
+
+        let save_dir = TempDir::new().unwrap();
+        let save_file = save_dir.path().join("profile.sav");
+        std::fs::write(&save_file, b"level=12").unwrap();
+        let game_save = GameSave { app_id: 105600, name: "Terraria".to_string(), save_path: save_file.clone() };
+        let metadata = backend.upload_save(&game_save, "player").await.unwrap();
+
+        let restore = TempDir::new().unwrap();
+        let target = restore.path().join("restored.sav");
+        backend.download_save(&metadata, &target).await.unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"level=12");
+
+        let export = restore.path().join("export.zip");
+        backend.download_save(&metadata, &export).await.unwrap();
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&export).unwrap()).unwrap();
+        assert_eq!(zip.len(), 1);
+        let mut content = String::new();
+        zip.by_name("profile.sav").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "level=12");
+    }
+
+    #[test]
+    fn test_corrupt_chunk_is_rejected() {
+        let data = noise(1000, 7);
+        let id = sha256_hex(&data);
+        let encoded = encode_chunk(&data).unwrap();
+        assert_eq!(decode_chunk(&id, &encoded).unwrap(), data);
+        assert!(decode_chunk(&sha256_hex(b"other"), &encoded).is_err());
+        assert!(enclosed_path(Path::new("/saves"), "../escape").is_err());
+    }
+}
@@ -1,18 +1,84 @@
-use crate::{CloudBackend, SaveMetadata, StorageInfo};
+use crate::object_key::UNKNOWN_GAME;
+use crate::quota::{self, QuotaStatus};
+use crate::retention::{plan_retention, RetentionPlan, RetentionRules};
+use crate::scrub::{self, ScrubOutcome, ScrubReport, ScrubResult};
+use crate::{cancel, progress, Cancelled, CloudBackend, RetryPolicy, RetryingBackend, SaveMetadata, StorageInfo, TransferProgress, TransferStage};
 use anyhow::Result;
-use std::path::Path;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 use steam_cloud_sync_core::GameSave;
 
 /// Progress callback for upload/download operations
 pub type ProgressCallback = Box<dyn Fn(u64, u64) + Send + Sync>;
 
+/// Batch operations running at the same time unless configured otherwise
+pub const DEFAULT_CONCURRENCY: usize = 3;
+
+/// How long a cancelled operation gets to clean up (abort multipart uploads, remove
+/// partial files) before it is dropped, which aborts any request still in flight
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How often a running transfer reports its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the storage usage checked against the quota is trusted. This service's own
+/// uploads and deletes keep it current; refetching picks up other devices' changes.
+const USAGE_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
 /// Cloud save service for managing save files
 pub struct CloudSaveService {
     backend: Box<dyn CloudBackend + Send + Sync>,
     progress_tx: Option<mpsc::UnboundedSender<ProgressUpdate>>,
     user_id: String,
+    concurrency: usize,
+    /// Operations in progress, by the id their progress updates carry
+    running: Mutex<HashMap<Uuid, (RunningOperation, CancellationToken)>>,
+    /// Parent of every operation's token; replaced after [`CloudSaveService::cancel_all`]
+    cancel_all: Mutex<CancellationToken>,
+    /// Which versions to prune after each upload
+    retention: Mutex<RetentionRules>,
+    /// Storage the user's saves may take; `None` is unlimited
+    quota_bytes: Mutex<Option<u64>>,
+    /// Storage used by the user's saves, as far as this service knows
+    usage: Mutex<Usage>,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    /// Bytes stored, and when they were fetched
+    stored: Option<(Instant, u64)>,
+    /// Bytes of uploads that passed the quota check and haven't finished yet
+    reserved: u64,
+}
+
+/// Quota held for an upload while it runs, so concurrent uploads can't each fit in
+/// the same free space; released when dropped, whether the upload succeeded or not
+struct QuotaReservation<'a> {
+    usage: &'a Mutex<Usage>,
+    bytes: u64,
+}
+
+impl Drop for QuotaReservation<'_> {
+    fn drop(&mut self) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.reserved = usage.reserved.saturating_sub(self.bytes);
+    }
+}
+
+/// An operation that can be cancelled with [`CloudSaveService::cancel_operation`]
+#[derive(Debug, Clone)]
+pub struct RunningOperation {
+    pub game_id: String,
+    pub operation_type: OperationType,
+    /// Byte counts of a transfer, for showing its progress live
+    pub progress: Option<Arc<TransferProgress>>,
 }
 
 /// Progress update message
@@ -25,14 +91,24 @@ pub struct ProgressUpdate {
     pub total_bytes: u64,
     pub status: OperationStatus,
     pub error: Option<String>,
+    /// What the bytes of an `InProgress` update are counting
+    pub stage: Option<TransferStage>,
+    /// Average rate of the current stage
+    pub bytes_per_sec: u64,
+    /// Time left for the current stage at that rate, when it can be estimated
+    pub eta: Option<Duration>,
 }
 
 #[derive(Debug, Clone)]
 pub enum OperationType {
     Upload,
     Download,
+    /// A download replacing the local save
+    Restore,
     Delete,
     List,
+    /// A batch of operations; `bytes_processed` and `total_bytes` count finished and total operations
+    Batch,
 }
 
 #[derive(Debug, Clone)]
@@ -41,17 +117,50 @@ pub enum OperationStatus {
     InProgress,
     Completed,
     Failed,
+    Cancelled,
+}
+
+impl OperationStatus {
+    /// `Failed`, or `Cancelled` if the error came from cancelling the operation
+    fn for_error(error: &anyhow::Error) -> Self {
+        if cancel::is_cancelled(error) {
+            OperationStatus::Cancelled
+        } else {
+            OperationStatus::Failed
+        }
+    }
 }
 
 impl CloudSaveService {
+    /// A service over `backend`, retrying transient failures with the default [`RetryPolicy`]
     pub fn new(backend: Box<dyn CloudBackend + Send + Sync>) -> Self {
+        Self::with_retry_policy(backend, RetryPolicy::default())
+    }
+    
+    pub fn with_retry_policy(backend: Box<dyn CloudBackend + Send + Sync>, policy: RetryPolicy) -> Self {
         Self {
-            backend,
+            backend: Box::new(RetryingBackend::with_policy(backend, policy)),
             progress_tx: None,
             user_id: "default_user".to_string(), // TODO: Make this configurable
+            concurrency: DEFAULT_CONCURRENCY,
+            running: Mutex::new(HashMap::new()),
+            cancel_all: Mutex::new(CancellationToken::new()),
+            retention: Mutex::new(RetentionRules::default()),
+            quota_bytes: Mutex::new(None),
+            usage: Mutex::new(Usage::default()),
         }
     }
     
+    /// How many operations of a batch may run at the same time (at least one)
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+    
     pub fn with_user_id(mut self, user_id: String) -> Self {
         self.user_id = user_id;
         self
@@ -62,6 +171,35 @@ impl CloudSaveService {
         self
     }
     
+    /// Prune old versions by `rules` after every upload (the default keeps everything)
+    pub fn with_retention(self, rules: RetentionRules) -> Self {
+        self.set_retention(rules);
+        self
+    }
+    
+    /// Replace the retention rules; applies from the next upload on
+    pub fn set_retention(&self, rules: RetentionRules) {
+        *self.retention.lock().unwrap() = rules;
+    }
+    
+    pub fn retention(&self) -> RetentionRules {
+        self.retention.lock().unwrap().clone()
+    }
+    
+    /// Refuse uploads that would take the user's saves over `quota_bytes` (`None` is unlimited)
+    pub fn with_quota(self, quota_bytes: Option<u64>) -> Self {
+        self.set_quota(quota_bytes);
+        self
+    }
+    
+    pub fn set_quota(&self, quota_bytes: Option<u64>) {
+        *self.quota_bytes.lock().unwrap() = quota_bytes;
+    }
+    
+    pub fn quota(&self) -> Option<u64> {
+        *self.quota_bytes.lock().unwrap()
+    }
+    
     /// Upload a save file to the cloud
     pub async fn upload_save(&self, game_id: &str, local_path: &Path) -> Result<SaveMetadata> {
         let operation_id = Uuid::new_v4();
@@ -75,6 +213,9 @@ impl CloudSaveService {
             total_bytes: 0,
             status: OperationStatus::Starting,
             error: None,
+            stage: None,
+            bytes_per_sec: 0,
+            eta: None,
         }).await;
         
         // Create GameSave from local path
@@ -83,38 +224,38 @@ impl CloudSaveService {
         
         let game_save = GameSave {
             app_id,
-            name: format!("save_{}", chrono::Utc::now().timestamp()),
+            name: game_id.to_string(),
             save_path: local_path.to_path_buf(),
         };
         
-        // Get file size for progress tracking
-        let file_size = tokio::fs::metadata(local_path).await?.len();
-        
-        // Send progress update with file size
-        self.send_progress(ProgressUpdate {
-            operation_id,
-            game_id: game_id.to_string(),
-            operation_type: OperationType::Upload,
-            bytes_processed: 0,
-            total_bytes: file_size,
-            status: OperationStatus::InProgress,
-            error: None,
-        }).await;
-        
         // Upload to cloud using existing backend
-        match self.backend.upload_save(&game_save, &self.user_id).await {
+        let upload = self.tracked(operation_id, game_id, OperationType::Upload, TransferProgress::new(), async {
+            let _reservation = self.check_quota(&game_save, game_id).await?;
+            let metadata = self.backend.upload_save(&game_save, &self.user_id).await?;
+            self.adjust_usage(metadata.size_bytes as i64);
+            Ok(metadata)
+        });
+        match self.cancellable(operation_id, game_id, OperationType::Upload, upload).await {
             Ok(metadata) => {
                 // Send completion progress
                 self.send_progress(ProgressUpdate {
                     operation_id,
                     game_id: game_id.to_string(),
                     operation_type: OperationType::Upload,
-                    bytes_processed: file_size,
-                    total_bytes: file_size,
+                    bytes_processed: metadata.size_bytes,
+                    total_bytes: metadata.size_bytes,
                     status: OperationStatus::Completed,
                     error: None,
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
+                // The upload succeeded either way; pruning is retried after the next one
+                if let Err(e) = self.apply_retention(game_id).await {
+                    eprintln!("[Retention] Failed to prune old versions of {}: {}", game_id, e);
+                }
+                
                 Ok(metadata)
             },
             Err(e) => {
@@ -124,9 +265,12 @@ impl CloudSaveService {
                     game_id: game_id.to_string(),
                     operation_type: OperationType::Upload,
                     bytes_processed: 0,
-                    total_bytes: file_size,
-                    status: OperationStatus::Failed,
+                    total_bytes: 0,
+                    status: OperationStatus::for_error(&e),
                     error: Some(e.to_string()),
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
                 Err(e)
@@ -136,31 +280,45 @@ impl CloudSaveService {
     
     /// Download a save file from the cloud
     pub async fn download_save(&self, save_metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        self.download_as(OperationType::Download, save_metadata, local_path).await
+    }
+    
+    /// Download reported as an operation of `operation_type`
+    async fn download_as(&self, operation_type: OperationType, save_metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
         let operation_id = Uuid::new_v4();
         
         // Send starting progress
         self.send_progress(ProgressUpdate {
             operation_id,
             game_id: save_metadata.game_id.clone(),
-            operation_type: OperationType::Download,
+            operation_type: operation_type.clone(),
             bytes_processed: 0,
             total_bytes: save_metadata.size_bytes,
             status: OperationStatus::Starting,
             error: None,
+            stage: None,
+            bytes_per_sec: 0,
+            eta: None,
         }).await;
         
         // Download from cloud using existing backend
-        match self.backend.download_save(save_metadata, local_path).await {
+        let progress = TransferProgress::new();
+        progress.start_stage(TransferStage::Downloading, save_metadata.size_bytes);
+        let download = self.tracked(operation_id, &save_metadata.game_id, operation_type.clone(), progress, self.backend.download_save(save_metadata, local_path));
+        match self.cancellable(operation_id, &save_metadata.game_id, operation_type.clone(), download).await {
             Ok(_) => {
                 // Send completion progress
                 self.send_progress(ProgressUpdate {
                     operation_id,
                     game_id: save_metadata.game_id.clone(),
-                    operation_type: OperationType::Download,
+                    operation_type: operation_type.clone(),
                     bytes_processed: save_metadata.size_bytes,
                     total_bytes: save_metadata.size_bytes,
                     status: OperationStatus::Completed,
                     error: None,
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
                 Ok(())
@@ -169,11 +327,14 @@ impl CloudSaveService {
                 self.send_progress(ProgressUpdate {
                     operation_id,
                     game_id: save_metadata.game_id.clone(),
-                    operation_type: OperationType::Download,
+                    operation_type: operation_type.clone(),
                     bytes_processed: 0,
                     total_bytes: save_metadata.size_bytes,
-                    status: OperationStatus::Failed,
+                    status: OperationStatus::for_error(&e),
                     error: Some(e.to_string()),
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
                 Err(e)
@@ -191,7 +352,7 @@ impl CloudSaveService {
         }
         
         // Download the save file
-        self.download_save(save_metadata, local_path).await
+        self.download_as(OperationType::Restore, save_metadata, local_path).await
     }
     
     /// Delete a save file from the cloud
@@ -207,10 +368,15 @@ impl CloudSaveService {
             total_bytes: 0,
             status: OperationStatus::Starting,
             error: None,
+            stage: None,
+            bytes_per_sec: 0,
+            eta: None,
         }).await;
         
-        match self.backend.delete_save(save_metadata).await {
+        match self.cancellable(operation_id, &save_metadata.game_id, OperationType::Delete, self.backend.delete_save(save_metadata)).await {
             Ok(_) => {
+                self.adjust_usage(-(save_metadata.size_bytes as i64));
+                
                 // Send completion progress
                 self.send_progress(ProgressUpdate {
                     operation_id,
@@ -220,6 +386,9 @@ impl CloudSaveService {
                     total_bytes: 0,
                     status: OperationStatus::Completed,
                     error: None,
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
                 Ok(())
@@ -231,8 +400,11 @@ impl CloudSaveService {
                     operation_type: OperationType::Delete,
                     bytes_processed: 0,
                     total_bytes: 0,
-                    status: OperationStatus::Failed,
+                    status: OperationStatus::for_error(&e),
                     error: Some(e.to_string()),
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
                 Err(e)
@@ -253,9 +425,12 @@ impl CloudSaveService {
             total_bytes: 0,
             status: OperationStatus::Starting,
             error: None,
+            stage: None,
+            bytes_per_sec: 0,
+            eta: None,
         }).await;
         
-        match self.backend.list_saves(&self.user_id, game_id).await {
+        match self.cancellable(operation_id, game_id.unwrap_or("all"), OperationType::List, self.backend.list_saves(&self.user_id, game_id)).await {
             Ok(saves) => {
                 // Send completion progress
                 self.send_progress(ProgressUpdate {
@@ -266,6 +441,9 @@ impl CloudSaveService {
                     total_bytes: 0,
                     status: OperationStatus::Completed,
                     error: None,
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
                 Ok(saves)
@@ -277,8 +455,11 @@ impl CloudSaveService {
                     operation_type: OperationType::List,
                     bytes_processed: 0,
                     total_bytes: 0,
-                    status: OperationStatus::Failed,
+                    status: OperationStatus::for_error(&e),
                     error: Some(e.to_string()),
+                    stage: None,
+                    bytes_per_sec: 0,
+                    eta: None,
                 }).await;
                 
                 Err(e)
@@ -286,10 +467,154 @@ impl CloudSaveService {
         }
     }
     
+    /// What the retention policy of the game would prune now, without deleting anything
+    pub async fn preview_retention(&self, game_id: &str) -> Result<RetentionPlan> {
+        let policy = self.retention.lock().unwrap().policy_for(game_id);
+        let saves = self.backend.list_saves(&self.user_id, Some(game_id)).await?;
+        Ok(plan_retention(saves, policy, chrono::Utc::now()))
+    }
+    
+    /// Delete the versions of the game its retention policy doesn't keep; returns what
+    /// was pruned. Stops at the first failure, leaving the rest for the next run.
+    pub async fn apply_retention(&self, game_id: &str) -> Result<RetentionPlan> {
+        let plan = self.preview_retention(game_id).await?;
+        self.prune(game_id, plan).await
+    }
+    
+    /// [`Self::preview_retention`] for every game, to see what pruning would free when
+    /// the quota runs out. Versions of unknown games are left alone.
+    pub async fn preview_retention_all(&self) -> Result<RetentionPlan> {
+        let rules = self.retention();
+        let mut by_game: BTreeMap<String, Vec<SaveMetadata>> = BTreeMap::new();
+        for save in self.backend.list_saves(&self.user_id, None).await? {
+            if save.game_id != UNKNOWN_GAME {
+                by_game.entry(save.game_id.clone()).or_default().push(save);
+            }
+        }
+        
+        let now = chrono::Utc::now();
+        let mut plan = RetentionPlan::default();
+        for (game_id, saves) in by_game {
+            let game_plan = plan_retention(saves, rules.policy_for(&game_id), now);
+            plan.keep.extend(game_plan.keep);
+            plan.prune.extend(game_plan.prune);
+        }
+        Ok(plan)
+    }
+    
+    /// [`Self::apply_retention`] for every game
+    pub async fn apply_retention_all(&self) -> Result<RetentionPlan> {
+        let plan = self.preview_retention_all().await?;
+        self.prune("all games", plan).await
+    }
+    
+    /// Delete the versions `plan` prunes. Stops at the first failure, leaving the rest
+    /// for the next run.
+    async fn prune(&self, scope: &str, plan: RetentionPlan) -> Result<RetentionPlan> {
+        for save in &plan.prune {
+            cancel::check()?;
+            self.backend.delete_save(save).await?;
+            self.adjust_usage(-(save.size_bytes as i64));
+        }
+        if !plan.prune.is_empty() {
+            println!(
+                "[Retention] Pruned {} old versions of {} ({} bytes), keeping {}",
+                plan.prune.len(), scope, plan.pruned_bytes(), plan.keep.len()
+            );
+        }
+        Ok(plan)
+    }
+    
+    /// Storage used against the quota; `None` without a quota
+    pub async fn quota_status(&self) -> Result<Option<QuotaStatus>> {
+        let Some(quota_bytes) = self.quota() else {
+            return Ok(None);
+        };
+        Ok(Some(QuotaStatus { used_bytes: self.used_bytes().await?, quota_bytes }))
+    }
+    
+    /// Fail with [`quota::QuotaExceeded`] if uploading `game_save` could take the user's
+    /// saves over the quota, counting the uploads still running; otherwise reserve its
+    /// bytes until the returned reservation is dropped. Retention runs right after the
+    /// upload, so what it would prune of the game now counts as freed; with the new
+    /// version it prunes at least as much.
+    async fn check_quota(&self, game_save: &GameSave, game_id: &str) -> Result<Option<QuotaReservation<'_>>> {
+        let Some(quota_bytes) = self.quota() else {
+            return Ok(None);
+        };
+        let upload_bytes = self.backend.estimate_upload(game_save, &self.user_id).await?;
+        let used_bytes = self.used_bytes().await?;
+        if let Ok(reservation) = self.reserve(used_bytes, upload_bytes, quota_bytes) {
+            return Ok(Some(reservation));
+        }
+        let prunable = self.preview_retention(game_id).await?.pruned_bytes();
+        self.reserve(used_bytes.saturating_sub(prunable), upload_bytes, quota_bytes).map(Some)
+    }
+    
+    /// Reserve `upload_bytes` if they fit in the quota next to `used_bytes` and the
+    /// reservations of running uploads; checked and taken under one lock
+    fn reserve(&self, used_bytes: u64, upload_bytes: u64, quota_bytes: u64) -> Result<QuotaReservation<'_>> {
+        let mut usage = self.usage.lock().unwrap();
+        quota::check_quota(used_bytes.saturating_add(usage.reserved), upload_bytes, quota_bytes)?;
+        usage.reserved += upload_bytes;
+        Ok(QuotaReservation { usage: &self.usage, bytes: upload_bytes })
+    }
+    
+    /// Bytes used by the user's saves, fetched again once [`USAGE_CACHE_TTL`] passed
+    async fn used_bytes(&self) -> Result<u64> {
+        if let Some((fetched_at, used_bytes)) = self.usage.lock().unwrap().stored {
+            if fetched_at.elapsed() < USAGE_CACHE_TTL {
+                return Ok(used_bytes);
+            }
+        }
+        let used_bytes = self.backend.get_storage_info(&self.user_id).await?.used_bytes;
+        self.usage.lock().unwrap().stored = Some((Instant::now(), used_bytes));
+        Ok(used_bytes)
+    }
+    
+    /// Account for bytes this service stored or deleted since usage was fetched
+    fn adjust_usage(&self, delta: i64) {
+        if let Some((_, used_bytes)) = self.usage.lock().unwrap().stored.as_mut() {
+            *used_bytes = used_bytes.saturating_add_signed(delta);
+        }
+    }
+    
+    /// Download every version of the user's saves, or `sample` of them picked at random,
+    /// and check that each matches its recorded SHA256 and opens (see [`scrub::scrub_version`]).
+    /// `on_result` is called after each version, e.g. to record it.
+    pub async fn scrub(&self, sample: Option<usize>, on_result: impl Fn(&ScrubResult)) -> Result<ScrubReport> {
+        let started_at = chrono::Utc::now();
+        let saves = self.backend.list_saves(&self.user_id, None).await?;
+        let total_versions = saves.len();
+        
+        let mut results = Vec::new();
+        for save in scrub::sample_versions(saves, sample) {
+            cancel::check()?;
+            let result = scrub::scrub_version(self.backend.as_ref(), &save).await;
+            if let Some(detail) = &result.detail {
+                eprintln!("[Scrub] {} is {}: {}", result.file_id, result.outcome.as_str(), detail);
+            }
+            on_result(&result);
+            results.push(result);
+        }
+        
+        let report = ScrubReport { started_at, finished_at: chrono::Utc::now(), total_versions, results };
+        println!(
+            "[Scrub] Checked {} of {} versions: {} corrupt, {} missing, {} unverifiable",
+            report.results.len(), total_versions,
+            report.count(ScrubOutcome::Corrupt), report.count(ScrubOutcome::Missing), report.count(ScrubOutcome::Unverifiable)
+        );
+        Ok(report)
+    }
+    
     /// Get storage information (combines user and bucket info)
     pub async fn get_storage_info(&self) -> Result<StorageInfo> {
         // Get user-specific storage info
         let mut storage_info = self.backend.get_storage_info(&self.user_id).await?;
+        self.usage.lock().unwrap().stored = Some((Instant::now(), storage_info.used_bytes));
+        if storage_info.total_bytes.is_none() {
+            storage_info.total_bytes = self.quota();
+        }
         
         // Get bucket-wide storage info and add it to the result
         match self.backend.get_bucket_storage_info().await {
@@ -306,6 +631,114 @@ impl CloudSaveService {
         Ok(storage_info)
     }
     
+    /// Operations currently running, by operation id
+    pub fn running_operations(&self) -> Vec<(Uuid, RunningOperation)> {
+        self.running.lock().unwrap().iter()
+            .map(|(id, (operation, _))| (*id, operation.clone()))
+            .collect()
+    }
+    
+    /// Cancel a running operation (or batch); returns whether it was running
+    pub fn cancel_operation(&self, operation_id: Uuid) -> bool {
+        match self.running.lock().unwrap().get(&operation_id) {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Cancel everything that is running, including operations queued in batches
+    pub fn cancel_all(&self) {
+        let mut cancel_all = self.cancel_all.lock().unwrap();
+        cancel_all.cancel();
+        *cancel_all = CancellationToken::new();
+    }
+    
+    /// Run `operation` so that it can be cancelled under `operation_id`. Backends see the
+    /// token through [`cancel::current`]; an operation that has not wound down within
+    /// [`CANCEL_GRACE_PERIOD`] of being cancelled is dropped.
+    async fn cancellable<T>(
+        &self,
+        operation_id: Uuid,
+        game_id: &str,
+        operation_type: OperationType,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        // Operations of a batch are cancelled together with it
+        let token = cancel::current()
+            .unwrap_or_else(|| self.cancel_all.lock().unwrap().clone())
+            .child_token();
+        if token.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        
+        let running = RunningOperation { game_id: game_id.to_string(), operation_type, progress: None };
+        self.running.lock().unwrap().insert(operation_id, (running, token.clone()));
+        
+        let operation = cancel::scope(token.clone(), operation);
+        tokio::pin!(operation);
+        let result = tokio::select! {
+            result = &mut operation => result,
+            _ = token.cancelled() => {
+                eprintln!("Cancelling operation {} for game {}", operation_id, game_id);
+                match tokio::time::timeout(CANCEL_GRACE_PERIOD, &mut operation).await {
+                    Ok(result) => result,
+                    Err(_) => Err(Cancelled.into()),
+                }
+            }
+        };
+        
+        self.running.lock().unwrap().remove(&operation_id);
+        result
+    }
+    
+    /// Run `operation` reporting into `progress`, and send an `InProgress` update with
+    /// its byte counts, rate and ETA every [`PROGRESS_INTERVAL`] until it finishes
+    async fn tracked<T>(
+        &self,
+        operation_id: Uuid,
+        game_id: &str,
+        operation_type: OperationType,
+        progress: Arc<TransferProgress>,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        if let Some((running, _)) = self.running.lock().unwrap().get_mut(&operation_id) {
+            running.progress = Some(progress.clone());
+        }
+        let operation = progress::scope(progress.clone(), operation);
+        tokio::pin!(operation);
+        let mut ticks = tokio::time::interval(PROGRESS_INTERVAL);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticks.tick().await; // The first tick completes immediately
+        
+        loop {
+            tokio::select! {
+                result = &mut operation => return result,
+                _ = ticks.tick() => {
+                    let snapshot = progress.snapshot();
+                    self.send_progress(ProgressUpdate {
+                        operation_id,
+                        game_id: game_id.to_string(),
+                        operation_type: operation_type.clone(),
+                        // Small extras (sidecars, manifests) may go past the stage's estimate
+                        bytes_processed: match snapshot.total {
+                            0 => snapshot.bytes,
+                            total => snapshot.bytes.min(total),
+                        },
+                        total_bytes: snapshot.total,
+                        status: OperationStatus::InProgress,
+                        error: None,
+                        stage: Some(snapshot.stage),
+                        bytes_per_sec: snapshot.bytes_per_sec,
+                        eta: snapshot.eta,
+                    }).await;
+                }
+            }
+        }
+    }
+    
     /// Send progress update through channel
     async fn send_progress(&self, update: ProgressUpdate) {
         if let Some(tx) = &self.progress_tx {
@@ -313,28 +746,78 @@ impl CloudSaveService {
         }
     }
     
-    /// Batch upload multiple saves
+    /// Batch upload multiple saves, several at a time. Saves of the same game are
+    /// uploaded one after another in the order given; results are in input order.
     pub async fn batch_upload_saves(&self, saves: Vec<(&str, &Path)>) -> Result<Vec<Result<SaveMetadata>>> {
-        let mut results = Vec::new();
-        
-        for (game_id, local_path) in saves {
-            let result = self.upload_save(game_id, local_path).await;
-            results.push(result);
-        }
-        
-        Ok(results)
+        let items = saves.into_iter().map(|(game_id, local_path)| (game_id.to_string(), local_path)).collect();
+        Ok(self.run_batch(OperationType::Upload, items, |game_id, local_path| async move {
+            self.upload_save(&game_id, local_path).await
+        }).await)
     }
     
-    /// Batch download multiple saves
+    /// Batch download multiple saves, several at a time. Saves of the same game are
+    /// downloaded one after another in the order given; results are in input order.
     pub async fn batch_download_saves(&self, saves: Vec<(&SaveMetadata, &Path)>) -> Result<Vec<Result<()>>> {
-        let mut results = Vec::new();
+        let items = saves.into_iter().map(|(save_metadata, local_path)| (save_metadata.game_id.clone(), (save_metadata, local_path))).collect();
+        Ok(self.run_batch(OperationType::Download, items, |_, (save_metadata, local_path)| async move {
+            self.download_save(save_metadata, local_path).await
+        }).await)
+    }
+    
+    /// Sync several games, several at a time (see [`CloudSaveService::sync_game_saves`])
+    pub async fn sync_all_games(&self, games: Vec<(String, PathBuf)>) -> Vec<Result<SyncResult>> {
+        self.run_batch(OperationType::Batch, games, |game_id, local_path| async move {
+            self.sync_game_saves(&game_id, &local_path).await
+        }).await
+    }
+    
+    /// Run `operation` on each item with bounded concurrency and report batch progress
+    async fn run_batch<T, R, F, Fut>(&self, operation_type: OperationType, items: Vec<(String, T)>, operation: F) -> Vec<Result<R>>
+    where
+        F: Fn(String, T) -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        let operation_id = Uuid::new_v4();
+        let total = items.len() as u64;
+        let batch_progress = |finished: u64, status: OperationStatus, error: Option<String>| ProgressUpdate {
+            operation_id,
+            game_id: "batch".to_string(),
+            operation_type: OperationType::Batch,
+            bytes_processed: finished,
+            total_bytes: total,
+            status,
+            error,
+            stage: None,
+            bytes_per_sec: 0,
+            eta: None,
+        };
+        self.send_progress(batch_progress(0, OperationStatus::Starting, None)).await;
+        eprintln!("Running {} {:?} operations, {} at a time", total, operation_type, self.concurrency);
         
-        for (save_metadata, local_path) in saves {
-            let result = self.download_save(save_metadata, local_path).await;
-            results.push(result);
-        }
+        let slots = Mutex::new((0..total).map(|_| None).collect());
+        let _ = self.cancellable(operation_id, "batch", OperationType::Batch, async {
+            run_bounded(self.concurrency, items, &operation, &slots, |finished| {
+                // Progress is sent on an unbounded channel, so this never waits
+                if let Some(tx) = &self.progress_tx {
+                    let _ = tx.send(batch_progress(finished, OperationStatus::InProgress, None));
+                }
+            }).await;
+            Ok(())
+        }).await;
+        // Items that finished before a cancellation keep their results
+        let results: Vec<Result<R>> = slots.into_inner().unwrap().into_iter()
+            .map(|slot| slot.unwrap_or_else(|| Err(Cancelled.into())))
+            .collect();
         
-        Ok(results)
+        let failed = results.iter().filter(|result| result.is_err()).count();
+        let cancelled = results.iter().filter(|result| result.as_ref().is_err_and(cancel::is_cancelled)).count();
+        let (status, error) = match failed {
+            0 => (OperationStatus::Completed, None),
+            failed if failed == cancelled => (OperationStatus::Cancelled, Some(format!("{} of {} operations cancelled", cancelled, total))),
+            failed => (OperationStatus::Failed, Some(format!("{} of {} operations failed", failed, total))),
+        };
+        self.send_progress(batch_progress(total, status, error)).await;
+        results
     }
     
     /// Sync saves for a game (upload if local is newer, download if cloud is newer)
@@ -388,4 +871,357 @@ pub enum SyncResult {
     Uploaded(SaveMetadata),
     Downloaded(SaveMetadata),
     NoAction,
-}
\ No newline at end of file
+}
+
+/// Run `operation` over `items`, at most `concurrency` at a time. Items sharing a key run
+/// one after another in input order; `finished` is called with the number done so far.
+/// Each result goes into the slot of its item as soon as it is done, so the results of
+/// finished items survive when the batch is dropped halfway.
+async fn run_bounded<T, R, F, Fut>(
+    concurrency: usize,
+    items: Vec<(String, T)>,
+    operation: &F,
+    slots: &Mutex<Vec<Option<Result<R>>>>,
+    finished: impl Fn(u64),
+) where
+    F: Fn(String, T) -> Fut,
+    Fut: Future<Output = Result<R>>,
+{
+    // One queue per key, in the order keys first appear
+    let mut queues: Vec<Vec<(usize, String, T)>> = Vec::new();
+    let mut queue_of_key: HashMap<String, usize> = HashMap::new();
+    for (index, (key, item)) in items.into_iter().enumerate() {
+        let queue = *queue_of_key.entry(key.clone()).or_insert_with(|| {
+            queues.push(Vec::new());
+            queues.len() - 1
+        });
+        queues[queue].push((index, key, item));
+    }
+    
+    let done = std::sync::atomic::AtomicU64::new(0);
+    futures::stream::iter(queues)
+        .map(|queue| async {
+            for (index, key, item) in queue {
+                let result = operation(key, item).await;
+                slots.lock().unwrap()[index] = Some(result);
+                finished(done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<()>()
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RetentionPolicy, UploadProgress};
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Backend whose listings never finish unless cancelled
+    struct StuckBackend;
+
+    #[async_trait]
+    impl CloudBackend for StuckBackend {
+        async fn upload_save(&self, _game_save: &GameSave, _user_id: &str) -> Result<SaveMetadata> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn download_save(&self, _metadata: &SaveMetadata, _local_path: &Path) -> Result<()> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn list_saves(&self, _user_id: &str, _game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+            cancel::cancellable(std::future::pending()).await
+        }
+        async fn delete_save(&self, _metadata: &SaveMetadata) -> Result<()> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn resume_upload(&self, _upload_id: &str, _offset: u64, _data: Bytes) -> Result<UploadProgress> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn test_connection(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn get_storage_info(&self, _user_id: &str) -> Result<StorageInfo> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tracked_reports_bytes_rate_and_eta() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let service = CloudSaveService::new(Box::new(StuckBackend)).with_progress_channel(tx);
+        let operation_id = Uuid::new_v4();
+
+        let result = service.tracked(operation_id, "730", OperationType::Upload, TransferProgress::new(), async {
+            progress::start_stage(TransferStage::Uploading, 1000);
+            progress::advance(400);
+            tokio::time::sleep(PROGRESS_INTERVAL + Duration::from_millis(100)).await;
+            Ok(7)
+        }).await;
+        assert_eq!(result.unwrap(), 7);
+
+        let update = rx.try_recv().unwrap();
+        assert_eq!(update.operation_id, operation_id);
+        assert!(matches!(update.status, OperationStatus::InProgress));
+        assert_eq!(update.stage, Some(TransferStage::Uploading));
+        assert_eq!((update.bytes_processed, update.total_bytes), (400, 1000));
+        assert!(update.bytes_per_sec > 0);
+        assert!(update.eta.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_operation() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let service = CloudSaveService::new(Box::new(StuckBackend)).with_progress_channel(tx);
+
+        let (listed, cancelled) = tokio::join!(service.list_saves(Some("730")), async {
+            while service.running_operations().is_empty() {
+                tokio::task::yield_now().await;
+            }
+            let (operation_id, running) = service.running_operations().remove(0);
+            assert_eq!(running.game_id, "730");
+            service.cancel_operation(operation_id)
+        });
+
+        assert!(cancelled);
+        assert!(cancel::is_cancelled(&listed.unwrap_err()));
+        assert!(service.running_operations().is_empty());
+        let mut last = None;
+        while let Ok(update) = rx.try_recv() {
+            last = Some(update.status);
+        }
+        assert!(matches!(last, Some(OperationStatus::Cancelled)));
+
+        // Cancel all reaches every operation, then new operations run normally again
+        let (first, second, _) = tokio::join!(service.list_saves(None), service.list_saves(Some("440")), async {
+            while service.running_operations().len() < 2 {
+                tokio::task::yield_now().await;
+            }
+            service.cancel_all();
+        });
+        assert!(cancel::is_cancelled(&first.unwrap_err()));
+        assert!(cancel::is_cancelled(&second.unwrap_err()));
+        assert!(!service.cancel_all.lock().unwrap().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_run_bounded_limits_concurrency_and_orders_per_key() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let log = Mutex::new(Vec::new());
+        let items: Vec<(String, u32)> = (0..12).map(|i| (format!("game{}", i % 4), i)).collect();
+        let progress = Mutex::new(Vec::new());
+
+        let slots = Mutex::new((0..12).map(|_| None).collect());
+        run_bounded(3, items, &|key: String, i: u32| {
+            let (running, peak, log) = (&running, &peak, &log);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10 + (12 - i as u64) * 2)).await;
+                log.lock().unwrap().push((key, i));
+                running.fetch_sub(1, Ordering::SeqCst);
+                if i == 5 { Err(anyhow::anyhow!("boom")) } else { Ok(i * 10) }
+            }
+        }, &slots, |finished| progress.lock().unwrap().push(finished)).await;
+
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert!(peak.load(Ordering::SeqCst) > 1);
+        for (i, result) in slots.into_inner().unwrap().into_iter().enumerate() {
+            match i {
+                5 => assert!(result.unwrap().is_err()),
+                _ => assert_eq!(result.unwrap().unwrap(), i as u32 * 10),
+            }
+        }
+        // Each game's items finished in input order
+        let log = log.into_inner().unwrap();
+        for game in 0..4 {
+            let order: Vec<u32> = log.iter().filter(|(key, _)| *key == format!("game{}", game)).map(|(_, i)| *i).collect();
+            assert_eq!(order, [game, game + 4, game + 8]);
+        }
+        assert_eq!(progress.into_inner().unwrap(), (1..=12).collect::<Vec<u64>>());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_batch_keeps_finished_results() {
+        let service = CloudSaveService::new(Box::new(StuckBackend)).with_concurrency(2);
+        let items = vec![("730".to_string(), 0u32), ("440".to_string(), 1), ("570".to_string(), 2)];
+
+        let (results, _) = tokio::join!(
+            service.run_batch(OperationType::Batch, items, |_, i| async move {
+                match i {
+                    0 => Ok(i),
+                    // Ignores cancellation, so it's dropped after the grace period
+                    _ => std::future::pending().await,
+                }
+            }),
+            async {
+                while service.running_operations().is_empty() {
+                    tokio::task::yield_now().await;
+                }
+                service.cancel_all();
+            }
+        );
+
+        assert_eq!(*results[0].as_ref().unwrap(), 0);
+        assert!(cancel::is_cancelled(results[1].as_ref().unwrap_err()));
+        assert!(cancel::is_cancelled(results[2].as_ref().unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn test_uploads_prune_by_retention_policy() {
+        let storage_dir = tempfile::TempDir::new().unwrap();
+        let save_dir = tempfile::TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        let rules = RetentionRules {
+            default: RetentionPolicy::KeepLast { count: 2 },
+            per_game: [("413150".to_string(), RetentionPolicy::KeepAll)].into_iter().collect(),
+        };
+        let service = CloudSaveService::new(Box::new(crate::LocalFolderBackend::with_root(storage_dir.path().to_path_buf())))
+            .with_user_id("user-1".to_string())
+            .with_retention(rules);
+
+        let mut uploaded = Vec::new();
+        for _ in 0..3 {
+            uploaded.push(service.upload_save("105600", save_dir.path()).await.unwrap());
+            service.upload_save("413150", save_dir.path()).await.unwrap();
+        }
+        let kept: Vec<String> = service.list_saves(Some("105600")).await.unwrap().into_iter().map(|save| save.file_id).collect();
+        assert_eq!(kept.len(), 2);
+        assert!(!kept.contains(&uploaded[0].file_id));
+        assert_eq!(service.list_saves(Some("413150")).await.unwrap().len(), 3);
+
+        // A stricter policy only shows what it would prune until applied
+        let mut rules = service.retention();
+        rules.per_game.insert("413150".to_string(), RetentionPolicy::KeepLast { count: 1 });
+        service.set_retention(rules);
+        assert_eq!(service.preview_retention("413150").await.unwrap().prune.len(), 2);
+        assert_eq!(service.list_saves(Some("413150")).await.unwrap().len(), 3);
+        assert_eq!(service.apply_retention("413150").await.unwrap().prune.len(), 2);
+        assert_eq!(service.list_saves(Some("413150")).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_over_quota_are_refused_until_pruned() {
+        let storage_dir = tempfile::TempDir::new().unwrap();
+        let save_dir = tempfile::TempDir::new().unwrap();
+        // Hardly compressible, so the archive is about as large as the save
+        let data: Vec<u8> = (0..4000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), &data).await.unwrap();
+        let service = CloudSaveService::new(Box::new(crate::LocalFolderBackend::with_root(storage_dir.path().to_path_buf())))
+            .with_user_id("user-1".to_string());
+        for _ in 0..3 {
+            service.upload_save("105600", save_dir.path()).await.unwrap();
+        }
+        assert_eq!(service.quota_status().await.unwrap(), None);
+
+        service.set_quota(Some(u64::MAX));
+        let used = service.quota_status().await.unwrap().unwrap().used_bytes;
+        service.set_quota(Some(used + 2000));
+        let status = service.quota_status().await.unwrap().unwrap();
+        assert_eq!(status.level(), crate::QuotaLevel::Ok);
+        let err = service.upload_save("105600", save_dir.path()).await.unwrap_err();
+        assert!(crate::is_quota_exceeded(&err), "{}", err);
+        assert_eq!(service.list_saves(Some("105600")).await.unwrap().len(), 3);
+
+        // Pruning by retention frees enough for the upload
+        service.set_retention(RetentionRules { default: RetentionPolicy::KeepLast { count: 1 }, ..RetentionRules::default() });
+        assert_eq!(service.preview_retention_all().await.unwrap().prune.len(), 2);
+        let pruned = service.apply_retention_all().await.unwrap();
+        assert_eq!(pruned.prune.len(), 2);
+        assert!(service.quota_status().await.unwrap().unwrap().used_bytes < used);
+        service.upload_save("105600", save_dir.path()).await.unwrap();
+        assert_eq!(service.list_saves(Some("105600")).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_uploads_share_the_quota() {
+        let storage_dir = tempfile::TempDir::new().unwrap();
+        let save_dirs = [tempfile::TempDir::new().unwrap(), tempfile::TempDir::new().unwrap()];
+        for (seed, dir) in save_dirs.iter().enumerate() {
+            let data: Vec<u8> = (0..4000u32).map(|i| (i.wrapping_add(seed as u32).wrapping_mul(2654435761) >> 13) as u8).collect();
+            tokio::fs::write(dir.path().join("slot1.sav"), &data).await.unwrap();
+        }
+        let service = CloudSaveService::new(Box::new(crate::LocalFolderBackend::with_root(storage_dir.path().to_path_buf())))
+            .with_user_id("user-1".to_string());
+        // Room for one of the saves, not both
+        service.set_quota(Some(6000));
+
+        let (first, second) = tokio::join!(
+            service.upload_save("105600", save_dirs[0].path()),
+            service.upload_save("730", save_dirs[1].path()),
+        );
+        let refused: Vec<_> = [&first, &second].into_iter().filter_map(|result| result.as_ref().err()).collect();
+        assert_eq!(refused.len(), 1, "{:?} {:?}", first.is_ok(), second.is_ok());
+        assert!(crate::is_quota_exceeded(refused[0]), "{}", refused[0]);
+        assert_eq!(service.usage.lock().unwrap().reserved, 0);
+        assert_eq!(service.list_saves(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quota_counts_retention_and_stored_chunks() {
+        let storage_dir = tempfile::TempDir::new().unwrap();
+        let chunk_dir = tempfile::TempDir::new().unwrap();
+        let save_dir = tempfile::TempDir::new().unwrap();
+        let data = |seed: u32| -> Vec<u8> { (0..4000u32).map(|i| (i.wrapping_add(seed).wrapping_mul(2654435761) >> 13) as u8).collect() };
+        tokio::fs::write(save_dir.path().join("slot1.sav"), data(0)).await.unwrap();
+
+        // Versions retention prunes right after the upload count as freed
+        let service = CloudSaveService::new(Box::new(crate::LocalFolderBackend::with_root(storage_dir.path().to_path_buf())))
+            .with_user_id("user-1".to_string());
+        for _ in 0..3 {
+            service.upload_save("105600", save_dir.path()).await.unwrap();
+        }
+        service.set_quota(Some(u64::MAX));
+        let used = service.quota_status().await.unwrap().unwrap().used_bytes;
+        service.set_quota(Some(used + 2000));
+        service.set_retention(RetentionRules { default: RetentionPolicy::KeepLast { count: 1 }, ..RetentionRules::default() });
+        service.upload_save("105600", save_dir.path()).await.unwrap();
+        assert_eq!(service.list_saves(Some("105600")).await.unwrap().len(), 1);
+
+        // Chunked uploads only count the chunks the store doesn't hold yet
+        let service = CloudSaveService::new(Box::new(crate::ChunkedBackend::new(Box::new(
+            crate::LocalFolderBackend::with_root(chunk_dir.path().to_path_buf()),
+        ))))
+        .with_user_id("user-1".to_string());
+        service.upload_save("105600", save_dir.path()).await.unwrap();
+        service.set_quota(Some(u64::MAX));
+        let used = service.quota_status().await.unwrap().unwrap().used_bytes;
+        service.set_quota(Some(used + 2000));
+        service.upload_save("105600", save_dir.path()).await.unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), data(7)).await.unwrap();
+        let err = service.upload_save("105600", save_dir.path()).await.unwrap_err();
+        assert!(crate::is_quota_exceeded(&err), "{}", err);
+        assert_eq!(service.list_saves(Some("105600")).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_scrub_reports_damaged_versions() {
+        let storage_dir = tempfile::TempDir::new().unwrap();
+        let save_dir = tempfile::TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        let service = CloudSaveService::new(Box::new(crate::LocalFolderBackend::with_root(storage_dir.path().to_path_buf())))
+            .with_user_id("user-1".to_string());
+        let mut uploaded = Vec::new();
+        for _ in 0..3 {
+            uploaded.push(service.upload_save("105600", save_dir.path()).await.unwrap());
+        }
+        tokio::fs::write(storage_dir.path().join(&uploaded[1].file_id), b"bit rot").await.unwrap();
+
+        let checked = AtomicUsize::new(0);
+        let report = service.scrub(None, |_| { checked.fetch_add(1, Ordering::SeqCst); }).await.unwrap();
+        assert_eq!((report.total_versions, report.results.len()), (3, 3));
+        assert_eq!(checked.load(Ordering::SeqCst), 3);
+        assert_eq!(report.count(ScrubOutcome::Ok), 2);
+        let problems: Vec<_> = report.problems().map(|result| result.file_id.as_str()).collect();
+        assert_eq!(problems, vec![uploaded[1].file_id.as_str()]);
+
+        let report = service.scrub(Some(1), |_| {}).await.unwrap();
+        assert_eq!((report.total_versions, report.results.len()), (3, 1));
+    }
+}
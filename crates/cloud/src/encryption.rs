@@ -0,0 +1,766 @@
+use crate::{
+    object_key::{self, ObjectKey}, sanitize_user_id, version_info, CloudBackend, ObjectInfo, ReplicatedBackend, SaveArchive, SaveMetadata,
+    StorageInfo, UploadProgress, UploadSessionStore, VersionInfo,
+};
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chacha20poly1305::aead::rand_core::RngCore;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use steam_cloud_sync_core::GameSave;
+use tokio::sync::OnceCell;
+use zeroize::Zeroizing;
+
+/// Every encrypted object starts with this, followed by a format byte
+const MAGIC: &[u8; 5] = b"GSENC";
+const OBJECT_FORMAT: u8 = 1;
+const KEY_ID_LEN: usize = 8;
+/// Random part of the per-segment nonce; the rest is a segment counter and a last-segment flag
+const NONCE_PREFIX_LEN: usize = 19;
+const HEADER_LEN: usize = MAGIC.len() + 1 + KEY_ID_LEN + NONCE_PREFIX_LEN;
+const TAG_LEN: usize = 16;
+/// Plaintext bytes per authenticated segment
+const SEGMENT_SIZE: usize = 64 * 1024;
+
+/// Suffix of encrypted save archives
+pub const ENCRYPTED_ARCHIVE_SUFFIX: &str = ".zip.enc";
+const KEYRING_FORMAT: u32 = 1;
+
+/// The symmetric key objects are encrypted with. It never leaves this machine
+/// unwrapped; the keyring only holds it encrypted under the passphrase.
+pub struct DataKey {
+    id: [u8; KEY_ID_LEN],
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl DataKey {
+    fn generate() -> Self {
+        let mut id = [0u8; KEY_ID_LEN];
+        OsRng.fill_bytes(&mut id);
+        let mut key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(key.as_mut());
+        Self { id, key }
+    }
+
+    pub fn id(&self) -> String {
+        hex::encode(self.id)
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(self.key.as_ref().into())
+    }
+}
+
+/// Argon2id parameters, stored with the keyring so they can be raised later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    fn generate(memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Self { salt: hex::encode(salt), memory_kib, iterations, parallelism }
+    }
+
+    fn recommended() -> Self {
+        Self::generate(64 * 1024, 3, 1)
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<Zeroizing<[u8; 32]>> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow::anyhow!("Invalid key derivation parameters: {}", e))?;
+        let salt = hex::decode(&self.salt)?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+        Ok(key)
+    }
+}
+
+/// The data key wrapped with a passphrase-derived key. Stored locally only; to read
+/// encrypted saves on another machine, copy the keyring file there (it is useless
+/// without the passphrase).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyring {
+    pub format: u32,
+    pub key_id: String,
+    pub kdf: KdfParams,
+    pub nonce: String,
+    pub wrapped_key: String,
+}
+
+impl Keyring {
+    /// Generate a new data key and wrap it with `passphrase`
+    pub fn create(passphrase: &str) -> Result<(Self, DataKey)> {
+        Self::create_with(passphrase, KdfParams::recommended())
+    }
+
+    fn create_with(passphrase: &str, kdf: KdfParams) -> Result<(Self, DataKey)> {
+        let data_key = DataKey::generate();
+        Ok((Self::wrap(&data_key, passphrase, kdf)?, data_key))
+    }
+
+    fn wrap(data_key: &DataKey, passphrase: &str, kdf: KdfParams) -> Result<Self> {
+        let kek = kdf.derive_key(passphrase)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let wrapped_key = XChaCha20Poly1305::new(kek.as_ref().into())
+            .encrypt(&nonce, Payload { msg: data_key.key.as_ref(), aad: &data_key.id })
+            .map_err(|_| anyhow::anyhow!("Failed to wrap data key"))?;
+
+        Ok(Self {
+            format: KEYRING_FORMAT,
+            key_id: data_key.id(),
+            kdf,
+            nonce: hex::encode(nonce),
+            wrapped_key: hex::encode(wrapped_key),
+        })
+    }
+
+    /// Unwrap the data key
+    pub fn unlock(&self, passphrase: &str) -> Result<DataKey> {
+        if self.format > KEYRING_FORMAT {
+            return Err(anyhow::anyhow!("Keyring was written by a newer version (format {}); please update", self.format));
+        }
+
+        let id: [u8; KEY_ID_LEN] = hex::decode(&self.key_id)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid key id in keyring"))?;
+        let nonce: [u8; 24] = hex::decode(&self.nonce)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid nonce in keyring"))?;
+        let kek = self.kdf.derive_key(passphrase)?;
+        let key = Zeroizing::new(
+            XChaCha20Poly1305::new(kek.as_ref().into())
+                .decrypt(&XNonce::from(nonce), Payload { msg: &hex::decode(&self.wrapped_key)?, aad: &id })
+                .map_err(|_| anyhow::anyhow!("Wrong encryption passphrase"))?,
+        );
+
+        let key: [u8; 32] = key.as_slice().try_into().map_err(|_| anyhow::anyhow!("Invalid data key length"))?;
+        Ok(DataKey { id, key: Zeroizing::new(key) })
+    }
+
+    /// Re-wrap the same data key under a new passphrase. Stored objects stay readable
+    /// and don't need to be re-encrypted.
+    pub fn rewrap(&self, old_passphrase: &str, new_passphrase: &str) -> Result<Self> {
+        let data_key = self.unlock(old_passphrase)?;
+        let kdf = KdfParams::generate(self.kdf.memory_kib, self.kdf.iterations, self.kdf.parallelism);
+        Self::wrap(&data_key, new_passphrase, kdf)
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)
+                .map_err(|e| anyhow::anyhow!("Invalid keyring {}: {}", path.display(), e))?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the keyring atomically, so an interrupted rotation can't lose the key
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("json.partial");
+        let mut file = std::fs::File::create(&partial)?;
+        file.write_all(&serde_json::to_vec_pretty(self)?)?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+/// Change the passphrase protecting the keyring at `path`
+pub fn rotate_passphrase(path: &Path, old_passphrase: &str, new_passphrase: &str) -> Result<()> {
+    if new_passphrase.is_empty() {
+        return Err(anyhow::anyhow!("The new passphrase must not be empty"));
+    }
+    let keyring = Keyring::load(path)?
+        .ok_or_else(|| anyhow::anyhow!("No keyring at {}", path.display()))?;
+    keyring.rewrap(old_passphrase, new_passphrase)?.save(path)?;
+    eprintln!("[Encryption] Re-wrapped data key {} under a new passphrase", keyring.key_id);
+    Ok(())
+}
+
+/// Whether `data` is an encrypted object
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.len() >= HEADER_LEN && data.starts_with(MAGIC)
+}
+
+fn segment_nonce(prefix: &[u8], index: u32, last: bool) -> XNonce {
+    let mut nonce = [0u8; 24];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..NONCE_PREFIX_LEN + 4].copy_from_slice(&index.to_be_bytes());
+    nonce[23] = last as u8;
+    XNonce::from(nonce)
+}
+
+/// Encrypt an object: a header naming the key, then the plaintext in authenticated
+/// segments. Segment nonces carry a counter and a final-segment flag, so reordering
+/// or truncating segments fails authentication.
+pub fn encrypt(key: &DataKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut prefix);
+    let segments = plaintext.len().div_ceil(SEGMENT_SIZE).max(1);
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + segments * TAG_LEN);
+    encrypt_to(key, &prefix, plaintext, plaintext.len() as u64, &mut out)?;
+    Ok(out)
+}
+
+/// Encrypt `len` bytes read from `plaintext` into `out` one segment at a time, so the
+/// object never has to fit in memory
+fn encrypt_to<W: Write + ?Sized>(
+    key: &DataKey,
+    prefix: &[u8; NONCE_PREFIX_LEN],
+    mut plaintext: impl Read,
+    len: u64,
+    out: &mut W,
+) -> Result<()> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(OBJECT_FORMAT);
+    header.extend_from_slice(&key.id);
+    header.extend_from_slice(prefix);
+    out.write_all(&header)?;
+
+    let segments = len.div_ceil(SEGMENT_SIZE as u64).max(1);
+    let mut segment = vec![0u8; SEGMENT_SIZE];
+    let cipher = key.cipher();
+    for index in 0..segments {
+        let segment_len = (len - index * SEGMENT_SIZE as u64).min(SEGMENT_SIZE as u64) as usize;
+        plaintext.read_exact(&mut segment[..segment_len])?;
+        let nonce = segment_nonce(prefix, index as u32, index + 1 == segments);
+        let sealed = cipher.encrypt(&nonce, Payload { msg: &segment[..segment_len], aad: &header })
+            .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+        out.write_all(&sealed)?;
+    }
+    Ok(())
+}
+
+/// Nonce prefix for sealing an archive, derived from its plaintext under the data key.
+/// Sealing the same archive again gives the same bytes, so an interrupted multipart
+/// upload of it can resume; different archives get unrelated prefixes.
+fn archive_nonce_prefix(key: &DataKey, checksum: &str) -> [u8; NONCE_PREFIX_LEN] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.key.as_ref())
+        .expect("HMAC takes keys of any length");
+    mac.update(b"archive-nonce-prefix:");
+    mac.update(checksum.as_bytes());
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    prefix.copy_from_slice(&mac.finalize().into_bytes()[..NONCE_PREFIX_LEN]);
+    prefix
+}
+
+/// Whether the file at `path` holds an encrypted object
+fn is_encrypted_file(path: &Path) -> Result<bool> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)?.take(HEADER_LEN as u64).read_to_end(&mut header)?;
+    Ok(is_encrypted(&header))
+}
+
+/// Error of an object that fails authentication: it was damaged or altered in storage,
+/// rather than sealed with another key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TamperedObject;
+
+impl std::fmt::Display for TamperedObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Decryption failed: the object is corrupted or was tampered with")
+    }
+}
+
+impl std::error::Error for TamperedObject {}
+
+/// Whether `error` is, or was caused by, a [`TamperedObject`]
+pub fn is_tampered(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| cause.is::<TamperedObject>())
+}
+
+/// Name the object in an error reading it, keeping the cause for [`is_tampered`]
+fn read_error(key: &str, error: anyhow::Error) -> anyhow::Error {
+    let message = format!("Cannot read {}: {}", key, error);
+    error.context(message)
+}
+
+/// Decrypt an object produced by [`encrypt`]
+pub fn decrypt(key: &DataKey, data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    decrypt_to(key, data, data.len() as u64, &mut out)?;
+    Ok(out)
+}
+
+/// Decrypt an object of `len` bytes read from `sealed` into `out` one segment at a time
+fn decrypt_to<W: Write + ?Sized>(key: &DataKey, mut sealed: impl Read, len: u64, out: &mut W) -> Result<()> {
+    let mut header = [0u8; HEADER_LEN];
+    if len < HEADER_LEN as u64 {
+        return Err(anyhow::anyhow!("Object is not encrypted"));
+    }
+    sealed.read_exact(&mut header)?;
+    if !is_encrypted(&header) {
+        return Err(anyhow::anyhow!("Object is not encrypted"));
+    }
+    if header[MAGIC.len()] > OBJECT_FORMAT {
+        return Err(anyhow::anyhow!("Object was encrypted by a newer version (format {}); please update", header[MAGIC.len()]));
+    }
+    let key_id = &header[MAGIC.len() + 1..MAGIC.len() + 1 + KEY_ID_LEN];
+    if key_id != key.id {
+        return Err(anyhow::anyhow!(
+            "Object was encrypted with a different key ({}, this keyring holds {}); use the keyring and passphrase it was uploaded with",
+            hex::encode(key_id), key.id()
+        ));
+    }
+    let prefix = &header[HEADER_LEN - NONCE_PREFIX_LEN..];
+
+    let body_len = len - HEADER_LEN as u64;
+    let sealed_segment = (SEGMENT_SIZE + TAG_LEN) as u64;
+    let segments = body_len.div_ceil(sealed_segment).max(1);
+    let mut buffer = vec![0u8; SEGMENT_SIZE + TAG_LEN];
+    let cipher = key.cipher();
+    for index in 0..segments {
+        let segment_len = (body_len - index * sealed_segment).min(sealed_segment) as usize;
+        sealed.read_exact(&mut buffer[..segment_len])?;
+        let nonce = segment_nonce(prefix, index as u32, index + 1 == segments);
+        let segment = cipher.decrypt(&nonce, Payload { msg: &buffer[..segment_len], aad: &header })
+            .map_err(|_| TamperedObject)?;
+        out.write_all(&segment)?;
+    }
+    Ok(())
+}
+
+/// Encrypts everything stored through it on the client; the backend only ever sees
+/// ciphertext. Objects written before encryption was enabled stay readable.
+///
+/// Place it directly over the storage backend: versioning layers such as
+/// [`crate::ChunkedBackend`] store through `put_object`, so their chunks and manifests
+/// get encrypted too. Used on its own, save archives are encrypted whole and stored
+/// as `<key>.zip.enc`.
+///
+/// The keyring is read (or created) on first use, so a wrong passphrase surfaces as
+/// an error from the first operation rather than at construction.
+pub struct EncryptedBackend {
+    inner: Box<dyn CloudBackend>,
+    keyring_path: PathBuf,
+    passphrase: Zeroizing<String>,
+    key: OnceCell<Arc<DataKey>>,
+}
+
+impl EncryptedBackend {
+    pub fn new(inner: Box<dyn CloudBackend>, keyring_path: PathBuf, passphrase: String) -> Self {
+        Self {
+            inner,
+            keyring_path,
+            passphrase: Zeroizing::new(passphrase),
+            key: OnceCell::new(),
+        }
+    }
+
+    /// Whether a save's `file_id` refers to an encrypted archive
+    pub fn is_encrypted_archive(file_id: &str) -> bool {
+        file_id.ends_with(ENCRYPTED_ARCHIVE_SUFFIX)
+    }
+
+    async fn data_key(&self) -> Result<Arc<DataKey>> {
+        self.key.get_or_try_init(|| async {
+            if self.passphrase.is_empty() {
+                return Err(anyhow::anyhow!("Encryption is enabled but no passphrase is set"));
+            }
+
+            // Argon2 is deliberately slow; keep it off the async workers
+            let path = self.keyring_path.clone();
+            let passphrase = self.passphrase.clone();
+            tokio::task::spawn_blocking(move || {
+                let key = match Keyring::load(&path)? {
+                    Some(keyring) => keyring.unlock(&passphrase)?,
+                    None => {
+                        let (keyring, key) = Keyring::create(&passphrase)?;
+                        keyring.save(&path)?;
+                        eprintln!("[Encryption] Created new keyring {} (key {})", path.display(), key.id());
+                        key
+                    }
+                };
+                Ok(Arc::new(key))
+            }).await?
+        }).await.cloned()
+    }
+
+    async fn seal(&self, data: Bytes) -> Result<Bytes> {
+        let key = self.data_key().await?;
+        Ok(Bytes::from(tokio::task::spawn_blocking(move || encrypt(&key, &data)).await??))
+    }
+
+    async fn open(&self, data: Bytes) -> Result<Bytes> {
+        if !is_encrypted(&data) {
+            return Ok(data);
+        }
+        let key = self.data_key().await?;
+        Ok(Bytes::from(tokio::task::spawn_blocking(move || decrypt(&key, &data)).await??))
+    }
+
+    /// Encrypt an archive into a new temporary file, segment by segment
+    async fn seal_archive(&self, archive: &SaveArchive) -> Result<SaveArchive> {
+        let key = self.data_key().await?;
+        let (path, size, checksum) = (archive.path().to_path_buf(), archive.size(), archive.checksum().to_string());
+        tokio::task::spawn_blocking(move || {
+            let prefix = archive_nonce_prefix(&key, &checksum);
+            let plaintext = BufReader::new(File::open(&path)?);
+            SaveArchive::write_blocking(|out| encrypt_to(&key, &prefix, plaintext, size, out))
+        }).await?
+    }
+
+    /// Decrypt an archive into a new temporary file, segment by segment. Plain archives
+    /// are returned as they are.
+    async fn open_archive(&self, sealed: SaveArchive) -> Result<SaveArchive> {
+        let path = sealed.path().to_path_buf();
+        if !tokio::task::spawn_blocking(move || is_encrypted_file(&path)).await?? {
+            return Ok(sealed);
+        }
+        let key = self.data_key().await?;
+        tokio::task::spawn_blocking(move || {
+            let reader = BufReader::new(File::open(sealed.path())?);
+            SaveArchive::write_blocking(|out| decrypt_to(&key, reader, sealed.size(), out))
+        }).await?
+    }
+
+    fn archive_metadata(&self, key: &str, object: &ObjectInfo) -> SaveMetadata {
+        SaveMetadata {
+            game_id: object_key::game_id_from_key_with_prefix(key, self.save_prefix()),
+            timestamp: object.last_modified.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            size_bytes: object.size_bytes,
+            checksum: String::new(),
+            compressed: true,
+            encrypted: true,
+            file_id: key.to_string(),
+            info: None,
+        }
+    }
+}
+
+#[async_trait]
+impl CloudBackend for EncryptedBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let archive = SaveArchive::create(&game_save.save_path).await?;
+        let sealed = self.seal_archive(&archive).await?;
+        let size = sealed.size();
+
+        let key = ObjectKey::new(&sanitize_user_id(user_id), game_save.app_id, ENCRYPTED_ARCHIVE_SUFFIX)
+            .with_prefix(self.save_prefix())
+            .to_string();
+        self.inner.put_archive(&key, &sealed).await?;
+        eprintln!("[Encryption] Stored encrypted archive {} ({} bytes)", key, size);
+
+        // Written through this backend, so the sidecar is encrypted like the archive
+        let info = VersionInfo::describe(game_save, archive.checksum(), version_info::FORMAT_ENCRYPTED_ZIP).await;
+        version_info::store_version_info(self, &key, &info).await;
+
+        Ok(SaveMetadata {
+            game_id: game_save.app_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            size_bytes: size,
+            checksum: archive.checksum().to_string(),
+            compressed: true,
+            encrypted: true,
+            file_id: key,
+            info: Some(info),
+        })
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        if !Self::is_encrypted_archive(&metadata.file_id) {
+            return self.inner.download_save(metadata, local_path).await;
+        }
+
+        let sealed = self.inner.get_archive(&metadata.file_id).await?;
+        let path = sealed.path().to_path_buf();
+        if !tokio::task::spawn_blocking(move || is_encrypted_file(&path)).await?? {
+            return Err(anyhow::anyhow!("{} is not a valid encrypted archive", metadata.file_id));
+        }
+        let archive = self.open_archive(sealed).await?;
+
+        // The checksum covers the plaintext archive; authentication already covered the rest
+        let expected_checksum = version_info::expected_checksum(self, metadata).await;
+        if expected_checksum.len() == 64 && archive.checksum() != expected_checksum {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                metadata.file_id, expected_checksum, archive.checksum()
+            ));
+        }
+        archive.restore_to(local_path).await
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        let mut archives = Vec::new();
+        for object in self.inner.list_objects(&format!("{}{}/", self.save_prefix(), sanitize_user_id(user_id))).await? {
+            if !Self::is_encrypted_archive(&object.key) {
+                continue;
+            }
+            if game_id.is_some_and(|gid| object_key::game_id_from_key_with_prefix(&object.key, self.save_prefix()) != gid) {
+                continue;
+            }
+            archives.push(self.archive_metadata(&object.key, &object));
+        }
+        version_info::attach_version_info(self, &mut archives).await;
+
+        let mut saves = self.inner.list_saves(user_id, game_id).await?;
+        saves.extend(archives);
+
+        saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(saves)
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        if Self::is_encrypted_archive(&metadata.file_id) {
+            self.inner.delete_object(&metadata.file_id).await?;
+            let _ = self.inner.delete_object(&VersionInfo::sidecar_key(&metadata.file_id)).await;
+            Ok(())
+        } else {
+            self.inner.delete_save(metadata).await
+        }
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        self.inner.resume_upload(upload_id, offset, data).await
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.inner.test_connection().await?;
+        // Also catches a wrong passphrase before the first sync
+        self.data_key().await.map(|_| ())
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        // Storage counts every version under the user's prefix, encrypted archives included
+        self.inner.get_storage_info(user_id).await
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        self.inner.get_bucket_storage_info().await
+    }
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        self.inner.set_upload_session_store(store);
+    }
+
+    fn encrypts_objects(&self) -> bool {
+        true
+    }
+
+    fn replication(&self) -> Option<ReplicatedBackend> {
+        self.inner.replication()
+    }
+
+    fn save_prefix(&self) -> &str {
+        self.inner.save_prefix()
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        let sealed = self.seal(data).await?;
+        self.inner.put_object(key, sealed).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        let data = self.inner.get_object(key).await?;
+        self.open(data).await
+            .map_err(|e| read_error(key, e))
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        let sealed = self.inner.get_archive(key).await?;
+        self.open_archive(sealed).await
+            .map_err(|e| read_error(key, e))
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        let sealed = self.seal_archive(archive).await?;
+        self.inner.put_archive(key, &sealed).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        self.inner.object_exists(key).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        self.inner.list_objects(prefix).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.inner.delete_object(key).await
+    }
+
+    async fn move_object(&self, from: &str, to: &str) -> Result<()> {
+        // Sealed objects aren't bound to their key, and plain ones must stay plain
+        self.inner.move_object(from, to).await
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        match self.inner.get_object_tagged(key).await? {
+            Some((data, tag)) => {
+                let data = self.open(data).await
+                    .map_err(|e| read_error(key, e))?;
+                Ok(Some((data, tag)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        let sealed = self.seal(data).await?;
+        self.inner.put_object_if(key, sealed, expected_tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalFolderBackend;
+    use tempfile::TempDir;
+
+    fn cheap_kdf() -> KdfParams {
+        KdfParams::generate(256, 1, 1)
+    }
+
+    #[test]
+    fn test_encrypt_roundtrip_and_tamper_detection() {
+        let (_, key) = Keyring::create_with("hunter2", cheap_kdf()).unwrap();
+        for len in [0, 10, SEGMENT_SIZE, 3 * SEGMENT_SIZE + 17] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let sealed = encrypt(&key, &plaintext).unwrap();
+            assert!(is_encrypted(&sealed));
+            assert_eq!(decrypt(&key, &sealed).unwrap(), plaintext);
+        }
+
+        let sealed = encrypt(&key, &vec![7u8; 2 * SEGMENT_SIZE + 1]).unwrap();
+        let mut flipped = sealed.clone();
+        flipped[HEADER_LEN + 5] ^= 1;
+        assert!(decrypt(&key, &flipped).is_err());
+        // Dropping the final segment must not look like a shorter valid object
+        let truncated = &sealed[..HEADER_LEN + 2 * (SEGMENT_SIZE + TAG_LEN)];
+        assert!(decrypt(&key, truncated).is_err());
+
+        let (_, other_key) = Keyring::create_with("hunter2", cheap_kdf()).unwrap();
+        let err = decrypt(&other_key, &sealed).unwrap_err().to_string();
+        assert!(err.contains("different key"), "{}", err);
+    }
+
+    #[test]
+    fn test_keyring_unlock_and_rotation() {
+        let (keyring, key) = Keyring::create_with("old secret", cheap_kdf()).unwrap();
+        let sealed = encrypt(&key, b"save data").unwrap();
+
+        let err = keyring.unlock("wrong").err().unwrap().to_string();
+        assert!(err.contains("Wrong encryption passphrase"), "{}", err);
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("keyring.json");
+        keyring.save(&path).unwrap();
+        rotate_passphrase(&path, "old secret", "new secret").unwrap();
+
+        let rotated = Keyring::load(&path).unwrap().unwrap();
+        assert_eq!(rotated.key_id, keyring.key_id);
+        assert!(rotated.unlock("old secret").is_err());
+        assert_eq!(decrypt(&rotated.unlock("new secret").unwrap(), &sealed).unwrap(), b"save data");
+    }
+
+    #[tokio::test]
+    async fn test_backend_stores_only_ciphertext() {
+        let storage = TempDir::new().unwrap();
+        let keys = TempDir::new().unwrap();
+        let keyring_path = keys.path().join("keyring.json");
+        let (keyring, _) = Keyring::create_with("passphrase", cheap_kdf()).unwrap();
+        keyring.save(&keyring_path).unwrap();
+
+        let backend = EncryptedBackend::new(
+            Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())),
+            keyring_path.clone(),
+            "passphrase".to_string(),
+        );
+
+        let save_dir = TempDir::new().unwrap();
+        std::fs::write(save_dir.path().join("chat.log"), b"secret account name").unwrap();
+        let game_save = GameSave { app_id: 730, name: "CS2".to_string(), save_path: save_dir.path().to_path_buf() };
+        let metadata = backend.upload_save(&game_save, "player").await.unwrap();
+        assert!(metadata.encrypted);
+
+        let stored = std::fs::read(storage.path().join(&metadata.file_id)).unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.windows(6).any(|w| w == b"secret"));
+
+        let saves = backend.list_saves("player", Some("730")).await.unwrap();
+        assert_eq!(saves.len(), 1);
+        assert!(saves[0].encrypted);
+        assert_eq!(saves[0].checksum, metadata.checksum);
+        assert_eq!(saves[0].info.as_ref().unwrap().game_name, "CS2");
+
+        let sidecar = std::fs::read(storage.path().join(VersionInfo::sidecar_key(&metadata.file_id))).unwrap();
+        assert!(is_encrypted(&sidecar));
+
+        let restore = TempDir::new().unwrap();
+        backend.download_save(&saves[0], restore.path()).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("chat.log")).unwrap(), b"secret account name");
+
+        // A wrong passphrase fails clearly instead of producing garbage
+        let wrong = EncryptedBackend::new(
+            Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())),
+            keyring_path,
+            "not it".to_string(),
+        );
+        let err = wrong.download_save(&metadata, restore.path()).await.unwrap_err().to_string();
+        assert!(err.contains("Wrong encryption passphrase"), "{}", err);
+    }
+    #[tokio::test]
+    async fn test_large_archives_stream_and_count_once() {
+        let storage = TempDir::new().unwrap();
+        let keys = TempDir::new().unwrap();
+        let keyring_path = keys.path().join("keyring.json");
+        Keyring::create_with("passphrase", cheap_kdf()).unwrap().0.save(&keyring_path).unwrap();
+        let backend = EncryptedBackend::new(
+            Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())),
+            keyring_path,
+            "passphrase".to_string(),
+        );
+
+        // Several segments of hardly compressible data
+        let mut state = 0x9e37_79b9u32;
+        let data: Vec<u8> = (0..300_000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        }).collect();
+        let save_dir = TempDir::new().unwrap();
+        std::fs::write(save_dir.path().join("world.dat"), &data).unwrap();
+        let game_save = GameSave { app_id: 730, name: "CS2".to_string(), save_path: save_dir.path().to_path_buf() };
+        let metadata = backend.upload_save(&game_save, "player").await.unwrap();
+        assert!(metadata.size_bytes > 4 * SEGMENT_SIZE as u64);
+
+        // The encrypted archive is counted once; its sidecar isn't a version
+        let info = backend.get_storage_info("player").await.unwrap();
+        assert_eq!((info.used_bytes, info.file_count), (metadata.size_bytes, 1));
+
+        let restore = TempDir::new().unwrap();
+        backend.download_save(&metadata, restore.path()).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("world.dat")).unwrap(), data);
+
+        // Sealing the same archive again gives the same bytes, so uploads of it can resume
+        let archive = SaveArchive::create(save_dir.path()).await.unwrap();
+        let first = backend.seal_archive(&archive).await.unwrap();
+        let second = backend.seal_archive(&archive).await.unwrap();
+        assert_eq!(first.checksum(), second.checksum());
+        let opened = backend.open_archive(first).await.unwrap();
+        assert_eq!(opened.checksum(), archive.checksum());
+    }
+}
@@ -0,0 +1,566 @@
+use crate::archive::hash_file;
+use crate::chunked::{collect_save_files, restore_targets, save_key_user, sha256_hex, GC_GRACE_PERIOD_HOURS};
+use crate::{
+    object_key::{self, ObjectKey}, sanitize_user_id, version_info, CloudBackend, ObjectInfo, SaveArchive, SaveMetadata,
+    ReplicatedBackend, StorageInfo, UploadProgress, UploadSessionStore, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use steam_cloud_sync_core::GameSave;
+use tokio::io::AsyncWriteExt;
+
+/// Suffix of file-level version manifests, stored next to regular archives under `saves/<user>/`
+pub const FILE_MANIFEST_SUFFIX: &str = ".files.json";
+/// Manifest layout version; bumped when older clients could not restore a manifest
+const FILE_MANIFEST_FORMAT: u32 = 1;
+
+/// One save version as a list of whole files, each stored once by content hash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileManifest {
+    pub format: u32,
+    pub app_id: u32,
+    pub name: String,
+    pub created_at: String,
+    /// The save path was a single file rather than a directory
+    pub single_file: bool,
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    /// Path relative to the save folder, `/`-separated
+    pub path: String,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub modified: i64,
+    /// Hex SHA256 of the file content, which is also its storage key
+    pub sha256: String,
+}
+
+impl FileManifest {
+    /// Uncompressed size of the save
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+/// Stores each save version as a manifest of files and uploads only the files that
+/// changed since the previous version of the same game; unchanged files are referenced
+/// from earlier uploads. Lighter than [`crate::ChunkedBackend`], but a one-byte edit
+/// re-uploads the whole file.
+///
+/// File contents live under `files/<user>/<first two hex digits>/<sha256>`. Plain
+/// archive saves keep working: listing merges both kinds, and downloads/deletes of
+/// archives go to the inner backend.
+pub struct IncrementalBackend {
+    inner: Box<dyn CloudBackend>,
+    gc_grace: chrono::Duration,
+}
+
+impl IncrementalBackend {
+    pub fn new(inner: Box<dyn CloudBackend>) -> Self {
+        Self {
+            inner,
+            gc_grace: chrono::Duration::hours(GC_GRACE_PERIOD_HOURS),
+        }
+    }
+
+    /// Whether a save's `file_id` refers to a file-level version rather than an archive
+    pub fn is_file_manifest(file_id: &str) -> bool {
+        file_id.ends_with(FILE_MANIFEST_SUFFIX)
+    }
+
+    fn blob_prefix(user: &str) -> String {
+        format!("files/{}/", user)
+    }
+
+    fn blob_key(user: &str, sha256: &str) -> String {
+        format!("{}{}/{}", Self::blob_prefix(user), &sha256[..2], sha256)
+    }
+
+    /// Fetch and parse a manifest, returning it with the SHA256 of its bytes
+    async fn load_manifest(&self, key: &str) -> Result<(FileManifest, String)> {
+        let data = self.inner.get_object(key).await?;
+        let manifest: FileManifest = serde_json::from_slice(&data)
+            .map_err(|e| anyhow::anyhow!("Invalid file manifest {}: {}", key, e))?;
+        if manifest.format > FILE_MANIFEST_FORMAT {
+            return Err(anyhow::anyhow!(
+                "File manifest {} was written by a newer version (format {}); please update",
+                key, manifest.format
+            ));
+        }
+        Ok((manifest, sha256_hex(&data)))
+    }
+
+    fn metadata_for(&self, key: &str, manifest: &FileManifest, checksum: String) -> SaveMetadata {
+        SaveMetadata {
+            game_id: manifest.app_id.to_string(),
+            timestamp: manifest.created_at.clone(),
+            size_bytes: manifest.total_size(),
+            checksum,
+            compressed: false,
+            encrypted: self.inner.encrypts_objects(),
+            file_id: key.to_string(),
+            info: None,
+        }
+    }
+
+    async fn manifest_keys(&self, user: &str) -> Result<Vec<String>> {
+        Ok(self.inner.list_objects(&format!("{}{}/", self.save_prefix(), user)).await?
+            .into_iter()
+            .map(|object| object.key)
+            .filter(|key| Self::is_file_manifest(key))
+            .collect())
+    }
+
+    /// Stored files an upload may reuse: those a manifest of the user refers to, which
+    /// garbage collection keeps while their versions exist. Any other stored file may be
+    /// collected before the upload's manifest is written, so it is uploaded again.
+    async fn reusable_files(&self, user: &str) -> Result<HashSet<String>> {
+        let mut referenced = HashSet::new();
+        for key in self.manifest_keys(user).await? {
+            match self.load_manifest(&key).await {
+                Ok((manifest, _)) => referenced.extend(manifest.files.into_iter().map(|f| f.sha256)),
+                Err(e) => eprintln!("[Incremental] Not reusing the files of unreadable manifest {}: {}", key, e),
+            }
+        }
+        Ok(referenced)
+    }
+
+    /// The most recent manifest of a game. Keys embed a sortable timestamp, so the
+    /// lexically greatest key is the newest.
+    async fn latest_manifest(&self, user: &str, app_id: u32) -> Result<Option<FileManifest>> {
+        let prefix = format!("{}{}/{}_", self.save_prefix(), user, app_id);
+        let latest = self.inner.list_objects(&prefix).await?
+            .into_iter()
+            .map(|object| object.key)
+            .filter(|key| Self::is_file_manifest(key))
+            .max();
+
+        match latest {
+            Some(key) => Ok(Some(self.load_manifest(&key).await?.0)),
+            None => Ok(None),
+        }
+    }
+
+    /// Download one file, check it against its hash and move it into place
+    async fn restore_file(&self, user: &str, entry: &FileEntry, target: &Path) -> Result<()> {
+        let data = self.inner.get_object(&Self::blob_key(user, &entry.sha256)).await
+            .map_err(|e| {
+                // Keep the cause, so a dropped connection stays retryable
+                let message = format!("Missing content of {}: {}", entry.path, e);
+                e.context(message)
+            })?;
+        let actual = sha256_hex(&data);
+        if actual != entry.sha256 {
+            return Err(anyhow::anyhow!("{} is corrupt in storage (content hashes to {})", entry.path, actual));
+        }
+
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut partial = target.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let mut file = tokio::fs::File::create(&partial).await?;
+        file.write_all(&data).await?;
+        file.flush().await?;
+        let file = file.into_std().await;
+        // Keep the original mtime so the next upload from this machine sees the file as unchanged
+        let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(entry.modified.max(0) as u64));
+        drop(file);
+
+        tokio::fs::rename(&partial, target).await?;
+        Ok(())
+    }
+
+    async fn restore_manifest(&self, user: &str, manifest: &FileManifest, local_path: &Path) -> Result<()> {
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        let targets = restore_targets(manifest.single_file, &paths, local_path)?;
+
+        for (entry, target) in manifest.files.iter().zip(targets) {
+            self.restore_file(user, entry, &target).await?;
+        }
+        Ok(())
+    }
+
+    /// Delete stored files no manifest of the user refers to any more.
+    /// Returns `(freed_bytes, deleted_files)`.
+    pub async fn collect_garbage(&self, user_id: &str) -> Result<(u64, u32)> {
+        let user = sanitize_user_id(user_id);
+
+        // Any unreadable manifest aborts the run: its files must not be mistaken for garbage
+        let mut referenced = HashSet::new();
+        for key in self.manifest_keys(&user).await? {
+            let (manifest, _) = self.load_manifest(&key).await?;
+            referenced.extend(manifest.files.into_iter().map(|f| f.sha256));
+        }
+
+        let cutoff = chrono::Utc::now() - self.gc_grace;
+        let mut freed_bytes = 0u64;
+        let mut deleted_files = 0u32;
+        for blob in self.inner.list_objects(&Self::blob_prefix(&user)).await? {
+            let sha256 = blob.key.rsplit('/').next().unwrap_or_default();
+            let old_enough = blob.last_modified.is_some_and(|modified| modified < cutoff);
+            if referenced.contains(sha256) || !old_enough {
+                continue;
+            }
+
+            self.inner.delete_object(&blob.key).await?;
+            freed_bytes += blob.size_bytes;
+            deleted_files += 1;
+        }
+
+        eprintln!("[Incremental] Garbage collection for {}: deleted {} files ({} bytes)", user, deleted_files, freed_bytes);
+        Ok((freed_bytes, deleted_files))
+    }
+}
+
+#[async_trait]
+impl CloudBackend for IncrementalBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let user = sanitize_user_id(user_id);
+        let save_path = game_save.save_path.clone();
+        if !save_path.exists() {
+            return Err(anyhow::anyhow!("Save path {} does not exist", save_path.display()));
+        }
+
+        let single_file = save_path.is_file();
+        let files = tokio::task::spawn_blocking(move || collect_save_files(&save_path)).await??;
+
+        // A broken previous manifest only costs us the shortcut, not the upload
+        let previous = match self.latest_manifest(&user, game_save.app_id).await {
+            Ok(previous) => previous,
+            Err(e) => {
+                eprintln!("[Incremental] Ignoring previous version of {}: {}", game_save.app_id, e);
+                None
+            }
+        };
+        let previous_files: HashMap<&str, &FileEntry> = previous.iter()
+            .flat_map(|m| m.files.iter())
+            .map(|f| (f.path.as_str(), f))
+            .collect();
+        let mut stored = self.reusable_files(&user).await?;
+
+        let mut entries = Vec::with_capacity(files.len());
+        let (mut uploaded, mut uploaded_bytes) = (0u32, 0u64);
+        for (relative_path, path) in files {
+            let meta = tokio::fs::metadata(&path).await?;
+            let modified = meta.modified().map(modified_secs).unwrap_or(0);
+
+            // Same size and mtime as last time: trust the previous hash instead of re-reading
+            let unchanged = previous_files.get(relative_path.as_str())
+                .filter(|f| f.size == meta.len() && f.modified == modified && modified != 0);
+            let (size, sha256) = match unchanged {
+                Some(f) => (f.size, f.sha256.clone()),
+                None => {
+                    let hash_path = path.clone();
+                    tokio::task::spawn_blocking(move || hash_file(&hash_path)).await??
+                }
+            };
+
+            let key = Self::blob_key(&user, &sha256);
+            if !stored.contains(&sha256) {
+                let data = tokio::fs::read(&path).await?;
+                if sha256_hex(&data) != sha256 {
+                    return Err(anyhow::anyhow!("{} changed while uploading; please retry", path.display()));
+                }
+                self.inner.put_object(&key, Bytes::from(data)).await?;
+                uploaded += 1;
+                uploaded_bytes += size;
+            }
+            stored.insert(sha256.clone());
+
+            entries.push(FileEntry { path: relative_path, size, modified, sha256 });
+        }
+
+        let manifest = FileManifest {
+            format: FILE_MANIFEST_FORMAT,
+            app_id: game_save.app_id,
+            name: game_save.name.clone(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            single_file,
+            files: entries,
+        };
+        let data = serde_json::to_vec(&manifest)?;
+        let checksum = sha256_hex(&data);
+
+        // The manifest goes up last, so a version is only visible once all its files are stored
+        let key = ObjectKey::new(&user, game_save.app_id, FILE_MANIFEST_SUFFIX)
+            .with_prefix(self.save_prefix())
+            .to_string();
+        self.inner.put_object(&key, Bytes::from(data)).await?;
+
+        let info = VersionInfo::describe(game_save, &checksum, version_info::FORMAT_FILES).await;
+        version_info::store_version_info(self.inner.as_ref(), &key, &info).await;
+
+        eprintln!(
+            "[Incremental] Stored {} ({} files): uploaded {} changed files ({} bytes), {} unchanged",
+            key, manifest.files.len(), uploaded, uploaded_bytes, manifest.files.len() as u32 - uploaded
+        );
+
+        Ok(SaveMetadata { info: Some(info), ..self.metadata_for(&key, &manifest, checksum) })
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        if !Self::is_file_manifest(&metadata.file_id) {
+            return self.inner.download_save(metadata, local_path).await;
+        }
+
+        let (manifest, checksum) = self.load_manifest(&metadata.file_id).await?;
+        if metadata.checksum.len() == 64 && checksum != metadata.checksum {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                metadata.file_id, metadata.checksum, checksum
+            ));
+        }
+        let user = save_key_user(&metadata.file_id, self.save_prefix())?;
+
+        if local_path.extension().and_then(|s| s.to_str()) == Some("zip") {
+            // Export: rebuild the save in a scratch folder and hand out a regular archive
+            let scratch = tempfile::TempDir::new()?;
+            let root = match (manifest.single_file, manifest.files.first()) {
+                (true, Some(file)) => scratch.path().join(&file.path),
+                _ => scratch.path().join(&manifest.name),
+            };
+            self.restore_manifest(user, &manifest, &root).await?;
+            SaveArchive::create(&root).await?.restore_to(local_path).await
+        } else {
+            self.restore_manifest(user, &manifest, local_path).await
+        }
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        let mut versions = Vec::new();
+        for key in self.manifest_keys(&sanitize_user_id(user_id)).await? {
+            if game_id.is_some_and(|gid| object_key::game_id_from_key_with_prefix(&key, self.save_prefix()) != gid) {
+                continue;
+            }
+
+            match self.load_manifest(&key).await {
+                Ok((manifest, checksum)) => versions.push(self.metadata_for(&key, &manifest, checksum)),
+                Err(e) => eprintln!("[Incremental] Skipping unreadable manifest {}: {}", key, e),
+            }
+        }
+        version_info::attach_version_info(self.inner.as_ref(), &mut versions).await;
+
+        let mut saves = self.inner.list_saves(user_id, game_id).await?;
+        saves.extend(versions);
+
+        saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(saves)
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        if !Self::is_file_manifest(&metadata.file_id) {
+            return self.inner.delete_save(metadata).await;
+        }
+
+        self.inner.delete_object(&metadata.file_id).await?;
+        let _ = self.inner.delete_object(&VersionInfo::sidecar_key(&metadata.file_id)).await;
+
+        // The version is gone either way; leftover files are collected next time
+        let user = save_key_user(&metadata.file_id, self.save_prefix())?;
+        if let Err(e) = self.collect_garbage(user).await {
+            eprintln!("[Incremental] Garbage collection after deleting {} failed: {}", metadata.file_id, e);
+        }
+        Ok(())
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        self.inner.resume_upload(upload_id, offset, data).await
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.inner.test_connection().await
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        let mut info = self.inner.get_storage_info(user_id).await?;
+        // Stored files are shared between versions, so they count towards usage but not the file count
+        let blobs = self.inner.list_objects(&Self::blob_prefix(&sanitize_user_id(user_id))).await?;
+        info.used_bytes += blobs.iter().map(|b| b.size_bytes).sum::<u64>();
+        Ok(info)
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        self.inner.get_bucket_storage_info().await
+    }
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        self.inner.set_upload_session_store(store);
+    }
+
+    fn encrypts_objects(&self) -> bool {
+        self.inner.encrypts_objects()
+    }
+
+    fn replication(&self) -> Option<ReplicatedBackend> {
+        self.inner.replication()
+    }
+
+    fn save_prefix(&self) -> &str {
+        self.inner.save_prefix()
+    }
+
+    /// Size of the files the upload would send; unchanged files are trusted to
+    /// the previous manifest as in [`Self::upload_save`]
+    async fn estimate_upload(&self, game_save: &GameSave, user_id: &str) -> Result<u64> {
+        let user = sanitize_user_id(user_id);
+        let save_path = game_save.save_path.clone();
+        let files = tokio::task::spawn_blocking(move || collect_save_files(&save_path)).await??;
+        let previous = self.latest_manifest(&user, game_save.app_id).await.unwrap_or(None);
+        let previous_files: HashMap<&str, &FileEntry> = previous.iter()
+            .flat_map(|m| m.files.iter())
+            .map(|f| (f.path.as_str(), f))
+            .collect();
+        let mut stored = self.reusable_files(&user).await?;
+
+        let mut new_bytes = 0;
+        for (relative_path, path) in files {
+            let meta = tokio::fs::metadata(&path).await?;
+            let modified = meta.modified().map(modified_secs).unwrap_or(0);
+            let unchanged = previous_files.get(relative_path.as_str())
+                .is_some_and(|f| f.size == meta.len() && f.modified == modified && modified != 0);
+            if unchanged {
+                continue;
+            }
+            let (size, sha256) = tokio::task::spawn_blocking(move || hash_file(&path)).await??;
+            if !stored.contains(&sha256) {
+                new_bytes += size;
+            }
+            stored.insert(sha256);
+        }
+        Ok(new_bytes)
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        self.inner.put_object(key, data).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        self.inner.get_object(key).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        self.inner.get_archive(key).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        self.inner.put_archive(key, archive).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        self.inner.object_exists(key).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        self.inner.list_objects(prefix).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.inner.delete_object(key).await
+    }
+
+    async fn move_object(&self, from: &str, to: &str) -> Result<()> {
+        self.inner.move_object(from, to).await
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        self.inner.get_object_tagged(key).await
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        self.inner.put_object_if(key, data, expected_tag).await
+    }
+}
+
+/// Seconds since the Unix epoch of a file's modification time, as stored in manifests
+fn modified_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalFolderBackend;
+    use tempfile::TempDir;
+
+    fn blob_count(root: &Path) -> usize {
+        fn count(dir: &Path) -> usize {
+            std::fs::read_dir(dir).map(|entries| {
+                entries.flatten().map(|e| if e.path().is_dir() { count(&e.path()) } else { 1 }).sum()
+            }).unwrap_or(0)
+        }
+        count(&root.join("files"))
+    }
+
+    #[tokio::test]
+    async fn test_only_changed_files_are_uploaded() {
+        let storage = TempDir::new().unwrap();
+        let mut backend = IncrementalBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())));
+        backend.gc_grace = chrono::Duration::zero();
+
+        let save_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(save_dir.path().join("slots")).unwrap();
+        std::fs::write(save_dir.path().join("slots/1.sav"), b"slot one").unwrap();
+        std::fs::write(save_dir.path().join("slots/2.sav"), b"slot two").unwrap();
+        std::fs::write(save_dir.path().join("settings.ini"), b"volume=5").unwrap();
+        let game_save = GameSave { app_id: 367520, name: "Hollow Knight".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        let first = backend.upload_save(&game_save, "player").await.unwrap();
+        assert_eq!(blob_count(storage.path()), 3);
+        assert_eq!(first.size_bytes, 24);
+
+        std::fs::write(save_dir.path().join("slots/2.sav"), b"slot two, later").unwrap();
+        let second = backend.upload_save(&game_save, "player").await.unwrap();
+        assert_eq!(blob_count(storage.path()), 4, "only the changed file should be uploaded");
+
+        let saves = backend.list_saves("player", Some("367520")).await.unwrap();
+        assert_eq!(saves.len(), 2);
+        assert!(saves.iter().all(|s| IncrementalBackend::is_file_manifest(&s.file_id)));
+
+        // Every version restores to the complete folder
+        let restore = TempDir::new().unwrap();
+        backend.download_save(&first, &restore.path().join("v1")).await.unwrap();
+        backend.download_save(&second, &restore.path().join("v2")).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("v1/slots/2.sav")).unwrap(), b"slot two");
+        assert_eq!(std::fs::read(restore.path().join("v2/slots/1.sav")).unwrap(), b"slot one");
+        assert_eq!(std::fs::read(restore.path().join("v2/slots/2.sav")).unwrap(), b"slot two, later");
+        assert_eq!(std::fs::read(restore.path().join("v2/settings.ini")).unwrap(), b"volume=5");
+
+        // Deleting the first version drops only the file content the second doesn't reference
+        backend.delete_save(&first).await.unwrap();
+        assert_eq!(blob_count(storage.path()), 3);
+        backend.download_save(&second, &restore.path().join("v2-again")).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("v2-again/slots/1.sav")).unwrap(), b"slot one");
+    }
+
+    #[tokio::test]
+    async fn test_restored_files_keep_modification_time() {
+        let storage = TempDir::new().unwrap();
+        let backend = IncrementalBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())));
+
+        let save_dir = TempDir::new().unwrap();
+        let save_file = save_dir.path().join("world.sav");
+        std::fs::write(&save_file, b"seed=42").unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        std::fs::File::options().write(true).open(&save_file).unwrap().set_modified(mtime).unwrap();
+        let game_save = GameSave { app_id: 105600, name: "Terraria".to_string(), save_path: save_file };
+
+        let metadata = backend.upload_save(&game_save, "player").await.unwrap();
+        let restore = TempDir::new().unwrap();
+        let target = restore.path().join("world.sav");
+        backend.download_save(&metadata, &target).await.unwrap();
+
+        assert_eq!(std::fs::read(&target).unwrap(), b"seed=42");
+        assert_eq!(modified_secs(std::fs::metadata(&target).unwrap().modified().unwrap()), 1_600_000_000);
+    }
+}
@@ -0,0 +1,820 @@
+use crate::chunked::collect_save_files;
+use crate::{
+    object_key, sanitize_user_id, CloudBackend, LegacySave, ObjectInfo, ReplicatedBackend, SaveArchive, SaveMetadata,
+    StorageInfo, UploadProgress, UploadSessionStore, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use steam_cloud_sync_core::GameSave;
+use uuid::Uuid;
+
+/// Index layout version; bumped when older clients could not read an index.
+/// Format 1 kept every entry in the index object itself; since format 2 each version
+/// has its own entry object and the index object only caches them.
+const INDEX_FORMAT: u32 = 2;
+/// Attempts at reading a consistent set of entries while other clients change them
+const MAX_INDEX_ATTEMPTS: u32 = 8;
+/// How often the index is checked against the stored versions, to pick up versions
+/// whose entry could not be written, and uploads by clients that don't write entries
+const REBUILD_INTERVAL: chrono::TimeDelta = chrono::TimeDelta::days(1);
+
+/// A user's save versions, as cached in one object per user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveIndex {
+    pub format: u32,
+    pub updated_at: String,
+    pub entries: Vec<IndexEntry>,
+    /// Entry object each entry was read from, by file id, so entries that haven't
+    /// changed since aren't fetched again
+    #[serde(default)]
+    pub sources: HashMap<String, String>,
+    /// When the index was last checked against the stored versions (RFC 3339)
+    #[serde(default)]
+    pub rebuilt_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Storage key of the version, as in `SaveMetadata::file_id`
+    pub file_id: String,
+    pub game_id: String,
+    #[serde(default)]
+    pub game_name: String,
+    /// When the version was uploaded (RFC 3339)
+    pub uploaded_at: String,
+    /// Newest modification time among the save's files, if known (RFC 3339)
+    #[serde(default)]
+    pub save_modified_at: Option<String>,
+    pub size_bytes: u64,
+    pub checksum: String,
+    #[serde(default)]
+    pub compressed: bool,
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Name of the machine that uploaded the version
+    #[serde(default)]
+    pub device: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Copy of the version's sidecar, so listing from the index doesn't need to fetch it
+    #[serde(default)]
+    pub info: Option<VersionInfo>,
+}
+
+impl SaveIndex {
+    fn new() -> Self {
+        Self {
+            format: INDEX_FORMAT,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            entries: Vec::new(),
+            sources: HashMap::new(),
+            rebuilt_at: None,
+        }
+    }
+}
+
+impl IndexEntry {
+    fn from_metadata(metadata: &SaveMetadata) -> Self {
+        Self {
+            file_id: metadata.file_id.clone(),
+            game_id: metadata.game_id.clone(),
+            game_name: metadata.info.as_ref().map(|info| info.game_name.clone()).unwrap_or_default(),
+            uploaded_at: metadata.timestamp.clone(),
+            save_modified_at: metadata.info.as_ref().and_then(|info| info.local_modified_at.clone()),
+            size_bytes: metadata.size_bytes,
+            checksum: metadata.checksum.clone(),
+            compressed: metadata.compressed,
+            encrypted: metadata.encrypted,
+            device: metadata.info.as_ref().map(|info| info.device.clone()).filter(|device| !device.is_empty()),
+            tags: Vec::new(),
+            info: metadata.info.clone(),
+        }
+    }
+
+    pub fn to_metadata(&self) -> SaveMetadata {
+        SaveMetadata {
+            game_id: self.game_id.clone(),
+            timestamp: self.uploaded_at.clone(),
+            size_bytes: self.size_bytes,
+            checksum: self.checksum.clone(),
+            compressed: self.compressed,
+            encrypted: self.encrypted,
+            file_id: self.file_id.clone(),
+            info: self.info.clone(),
+        }
+    }
+}
+
+/// Name of this machine, recorded with each upload
+pub fn device_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok().map(|name| name.trim().to_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Keeps an index of each user's save versions next to the saves and answers
+/// `list_saves` from it in a couple of requests instead of parsing every stored key.
+///
+/// Every version has its own entry object, `index/<user>/<version>_<revision>_<unique>.json`.
+/// Entries are never overwritten: a change writes the next revision and then removes
+/// the ones before, so concurrent uploads from several machines can't drop each
+/// other's entries, whatever conditional writes the storage supports. The per-user
+/// `index/<user>.json` only caches the entries; listing checks it against one listing
+/// of the entry objects and fetches just the entries that changed.
+///
+/// Versions whose entry could not be written are picked up by [`IndexedBackend::rebuild_index`],
+/// which runs on the next listing after such a failure and otherwise once a day. It also
+/// lists storage without an index (older uploads) the old way, by parsing object keys.
+pub struct IndexedBackend {
+    inner: Box<dyn CloudBackend>,
+    device: String,
+    /// Users whose index missed a change since it was last rebuilt
+    dirty: Mutex<HashSet<String>>,
+}
+
+impl IndexedBackend {
+    pub fn new(inner: Box<dyn CloudBackend>) -> Self {
+        Self { inner, device: device_name(), dirty: Mutex::new(HashSet::new()) }
+    }
+
+    /// Record uploads under a specific device name instead of the host name
+    pub fn with_device(mut self, device: impl Into<String>) -> Self {
+        self.device = device.into();
+        self
+    }
+
+    fn index_key(user: &str) -> String {
+        format!("index/{}.json", user)
+    }
+
+    fn entries_prefix(user: &str) -> String {
+        format!("index/{}/", user)
+    }
+
+    /// Stable id of a version in entry keys; a hash, as version keys contain `/`
+    fn version_id(file_id: &str) -> String {
+        hex::encode(&Sha256::digest(file_id.as_bytes())[..16])
+    }
+
+    fn entry_key(user: &str, file_id: &str, revision: u32) -> String {
+        format!(
+            "{}{}_{:06}_{}.json",
+            Self::entries_prefix(user), Self::version_id(file_id), revision, Uuid::new_v4().simple()
+        )
+    }
+
+    /// Version id and revision of an entry key
+    fn parse_entry_key<'a>(user: &str, key: &'a str) -> Option<(&'a str, u32)> {
+        let name = key.strip_prefix(&Self::entries_prefix(user))?.strip_suffix(".json")?;
+        let mut parts = name.split('_');
+        let (version, revision) = (parts.next()?, parts.next()?.parse().ok()?);
+        parts.next()?;
+        Some((version, revision))
+    }
+
+    fn parse_index(key: &str, data: &[u8]) -> Result<SaveIndex> {
+        let index: SaveIndex = serde_json::from_slice(data)
+            .map_err(|e| anyhow::anyhow!("Invalid save index {}: {}", key, e))?;
+        if index.format > INDEX_FORMAT {
+            return Err(anyhow::anyhow!(
+                "Save index {} was written by a newer version (format {}); please update",
+                key, index.format
+            ));
+        }
+        Ok(index)
+    }
+
+    fn parse_entry(key: &str, data: &[u8]) -> Result<IndexEntry> {
+        serde_json::from_slice(data).map_err(|e| anyhow::anyhow!("Invalid save index entry {}: {}", key, e))
+    }
+
+    fn mark_dirty(&self, user: &str) {
+        self.dirty.lock().unwrap().insert(user.to_string());
+    }
+
+    /// The user's cached index. One that can't be parsed is ignored, as it can be
+    /// rebuilt from the entries; one written by a newer version is an error.
+    async fn read_cache(&self, user: &str) -> Result<Option<SaveIndex>> {
+        let key = Self::index_key(user);
+        let Some((data, _)) = self.inner.get_object_tagged(&key).await? else {
+            return Ok(None);
+        };
+        match Self::parse_index(&key, &data) {
+            Ok(index) => Ok(Some(index)),
+            Err(e) if index_format(&data).is_some_and(|format| format > INDEX_FORMAT) => Err(e),
+            Err(e) => {
+                eprintln!("[Index] Ignoring cached index: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Keys of the entry revisions of a user, or of one of their versions: version id ->
+    /// keys, newest revision first
+    async fn list_revisions(&self, user: &str, file_id: Option<&str>) -> Result<HashMap<String, Vec<String>>> {
+        let prefix = match file_id {
+            Some(file_id) => format!("{}{}_", Self::entries_prefix(user), Self::version_id(file_id)),
+            None => Self::entries_prefix(user),
+        };
+        let mut revisions: HashMap<String, Vec<(u32, String)>> = HashMap::new();
+        for object in self.inner.list_objects(&prefix).await? {
+            if let Some((version, revision)) = Self::parse_entry_key(user, &object.key) {
+                revisions.entry(version.to_string()).or_default().push((revision, object.key.clone()));
+            }
+        }
+        Ok(revisions.into_iter()
+            .map(|(version, mut keys)| {
+                keys.sort_by(|a, b| b.cmp(a));
+                (version, keys.into_iter().map(|(_, key)| key).collect())
+            })
+            .collect())
+    }
+
+    /// The user's index, or `None` if none has been written yet
+    pub async fn load_index(&self, user_id: &str) -> Result<Option<SaveIndex>> {
+        let user = sanitize_user_id(user_id);
+        if self.read_cache(&user).await?.is_none() && self.list_revisions(&user, None).await?.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.current_index(user_id).await?))
+    }
+
+    /// The user's index, rebuilt first if it missed a change or is due for a check
+    async fn current_index(&self, user_id: &str) -> Result<SaveIndex> {
+        let user = sanitize_user_id(user_id);
+        let cached = self.read_cache(&user).await?;
+        let due = match &cached {
+            Some(index) if index.format == INDEX_FORMAT => index.rebuilt_at.as_deref()
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                .is_none_or(|at| chrono::Utc::now().signed_duration_since(at) >= REBUILD_INTERVAL),
+            _ => true,
+        };
+        if due || self.dirty.lock().unwrap().contains(&user) {
+            return self.rebuild_index(user_id).await;
+        }
+        self.refresh_index(&user, cached, None).await
+    }
+
+    /// Read the current entries, taking unchanged ones from `cached`, and update the
+    /// cached index if anything changed. Entries removed while they are being read
+    /// are superseded or deleted, so the entries are listed again.
+    async fn refresh_index(&self, user: &str, cached: Option<SaveIndex>, rebuilt_at: Option<String>) -> Result<SaveIndex> {
+        let cached = cached.filter(|index| index.format == INDEX_FORMAT);
+        let from_cache: HashMap<&str, &IndexEntry> = cached.iter()
+            .flat_map(|index| index.entries.iter().filter_map(|entry| {
+                Some((index.sources.get(&entry.file_id)?.as_str(), entry))
+            }))
+            .collect();
+        let rebuilt_at = rebuilt_at.or_else(|| cached.as_ref().and_then(|index| index.rebuilt_at.clone()));
+
+        'attempts: for attempt in 0..MAX_INDEX_ATTEMPTS {
+            let mut index = SaveIndex { rebuilt_at: rebuilt_at.clone(), ..SaveIndex::new() };
+            for keys in self.list_revisions(user, None).await?.into_values() {
+                let key = &keys[0];
+                let entry = match from_cache.get(key.as_str()) {
+                    Some(entry) => (*entry).clone(),
+                    None => match self.inner.get_object_tagged(key).await? {
+                        Some((data, _)) => Self::parse_entry(key, &data)?,
+                        None => {
+                            eprintln!("[Index] Entries of {} changed while reading them (attempt {})", user, attempt + 1);
+                            continue 'attempts;
+                        }
+                    },
+                };
+                index.sources.insert(entry.file_id.clone(), key.clone());
+                index.entries.push(entry);
+            }
+            index.entries.sort_by(|a, b| a.uploaded_at.cmp(&b.uploaded_at).then_with(|| a.file_id.cmp(&b.file_id)));
+
+            let unchanged = cached.as_ref()
+                .is_some_and(|cached| cached.sources == index.sources && cached.rebuilt_at == index.rebuilt_at);
+            if !unchanged {
+                // Only a cache: a write lost to a concurrent one is redone by the next listing
+                let key = Self::index_key(user);
+                if let Err(e) = self.inner.put_object(&key, Bytes::from(serde_json::to_vec(&index)?)).await {
+                    eprintln!("[Index] Failed to update the cached index {}: {}", key, e);
+                }
+            }
+            return Ok(index);
+        }
+
+        Err(anyhow::anyhow!("Could not read the save index of {}: too many concurrent changes", user))
+    }
+
+    /// Write the first entry of a version
+    async fn add_entry(&self, user: &str, entry: &IndexEntry) -> Result<()> {
+        let key = Self::entry_key(user, &entry.file_id, 0);
+        self.inner.put_object(&key, Bytes::from(serde_json::to_vec(entry)?)).await
+    }
+
+    /// Apply `change` to the newest entry of version `file_id`, write it as the next
+    /// revision and remove the ones before. `false` if the version has no entry.
+    async fn update_entry(&self, user: &str, file_id: &str, change: impl FnOnce(&mut IndexEntry)) -> Result<bool> {
+        let Some(previous) = self.list_revisions(user, Some(file_id)).await?.into_values().next() else {
+            return Ok(false);
+        };
+        let Some((data, _)) = self.inner.get_object_tagged(&previous[0]).await? else {
+            return Ok(false);
+        };
+        let mut entry = Self::parse_entry(&previous[0], &data)?;
+        change(&mut entry);
+
+        // A repair moves the version to a new key, so its entry starts over
+        let revision = match entry.file_id == file_id {
+            true => Self::parse_entry_key(user, &previous[0]).map_or(0, |(_, revision)| revision + 1),
+            false => 0,
+        };
+        self.inner.put_object(&Self::entry_key(user, &entry.file_id, revision), Bytes::from(serde_json::to_vec(&entry)?)).await?;
+        for key in &previous {
+            self.inner.delete_object(key).await?;
+        }
+        Ok(true)
+    }
+
+    /// Remove every revision of the entry of version `file_id`
+    async fn remove_entry(&self, user: &str, file_id: &str) -> Result<()> {
+        for key in self.list_revisions(user, Some(file_id)).await?.into_values().flatten() {
+            self.inner.delete_object(&key).await?;
+        }
+        Ok(())
+    }
+
+    /// Replace the tags of one version
+    pub async fn set_tags(&self, user_id: &str, file_id: &str, tags: Vec<String>) -> Result<()> {
+        if !self.update_entry(&sanitize_user_id(user_id), file_id, |entry| entry.tags = tags).await? {
+            return Err(anyhow::anyhow!("No save version {} in the index", file_id));
+        }
+        Ok(())
+    }
+
+    /// Re-sync the index with storage: versions found by listing get an entry, entries
+    /// whose versions are gone are removed along with superseded revisions, and names,
+    /// devices and tags are kept. Entries of an index written by an older release
+    /// (format 1) are carried over first.
+    pub async fn rebuild_index(&self, user_id: &str) -> Result<SaveIndex> {
+        let user = sanitize_user_id(user_id);
+        // Cleared first, so a change missed while rebuilding marks the index again
+        self.dirty.lock().unwrap().remove(&user);
+        let result = self.rebuild(user_id, &user).await;
+        if result.is_err() {
+            self.mark_dirty(&user);
+        }
+        result
+    }
+
+    async fn rebuild(&self, user_id: &str, user: &str) -> Result<SaveIndex> {
+        let mut cached = self.read_cache(user).await?;
+
+        // Entries are listed before the versions: a version is stored before its entry
+        // is written, so an entry whose version isn't listed afterwards really is gone
+        let mut revisions = self.list_revisions(user, None).await?;
+        if let Some(legacy) = cached.take_if(|index| index.format < INDEX_FORMAT) {
+            for entry in &legacy.entries {
+                if !revisions.contains_key(&Self::version_id(&entry.file_id)) {
+                    self.add_entry(user, entry).await?;
+                }
+            }
+            revisions = self.list_revisions(user, None).await?;
+        }
+        for superseded in revisions.values().flat_map(|keys| &keys[1..]) {
+            self.inner.delete_object(superseded).await?;
+        }
+        let index = self.refresh_index(user, cached, None).await?;
+
+        let stored = self.inner.list_saves(user_id, None).await?;
+        let mut changed = 0;
+        for metadata in stored.iter().filter(|metadata| !index.sources.contains_key(&metadata.file_id)) {
+            self.add_entry(user, &IndexEntry::from_metadata(metadata)).await?;
+            changed += 1;
+        }
+        for entry in index.entries.iter().filter(|entry| !stored.iter().any(|m| m.file_id == entry.file_id)) {
+            self.remove_entry(user, &entry.file_id).await?;
+            changed += 1;
+        }
+        if changed > 0 {
+            eprintln!("[Index] Rebuilt index of {}: {} of {} stored saves changed", user, changed, stored.len());
+        }
+
+        self.refresh_index(user, Some(index), Some(chrono::Utc::now().to_rfc3339())).await
+    }
+}
+
+/// Format of a serialized index, if it has one
+fn index_format(data: &[u8]) -> Option<u32> {
+    #[derive(Deserialize)]
+    struct Format {
+        format: u32,
+    }
+    serde_json::from_slice::<Format>(data).ok().map(|f| f.format)
+}
+
+/// Newest modification time among the files of a save
+fn latest_modification(save_path: &Path) -> Option<String> {
+    collect_save_files(save_path).ok()?
+        .iter()
+        .filter_map(|(_, path)| std::fs::metadata(path).ok()?.modified().ok())
+        .max()
+        .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339())
+}
+
+#[async_trait]
+impl CloudBackend for IndexedBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let metadata = self.inner.upload_save(game_save, user_id).await?;
+
+        let save_modified_at = match metadata.info.as_ref().and_then(|info| info.local_modified_at.clone()) {
+            Some(modified_at) => Some(modified_at),
+            None => {
+                let save_path = game_save.save_path.clone();
+                tokio::task::spawn_blocking(move || latest_modification(&save_path)).await?
+            }
+        };
+        let entry = IndexEntry {
+            game_name: game_save.name.clone(),
+            save_modified_at,
+            device: Some(self.device.clone()),
+            ..IndexEntry::from_metadata(&metadata)
+        };
+
+        // The save itself is stored either way; the next listing rebuilds the index
+        let user = sanitize_user_id(user_id);
+        if let Err(e) = self.add_entry(&user, &entry).await {
+            eprintln!("[Index] Failed to record {} in the save index: {}", metadata.file_id, e);
+            self.mark_dirty(&user);
+        }
+        Ok(metadata)
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        self.inner.download_save(metadata, local_path).await
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        let index = match self.current_index(user_id).await {
+            Ok(index) => index,
+            Err(e) => {
+                eprintln!("[Index] Save index unreadable, listing by key: {}", e);
+                return self.inner.list_saves(user_id, game_id).await;
+            }
+        };
+
+        let mut saves: Vec<SaveMetadata> = index.entries.iter()
+            .filter(|entry| game_id.is_none_or(|gid| entry.game_id == gid))
+            .map(IndexEntry::to_metadata)
+            .collect();
+        saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(saves)
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        self.inner.delete_save(metadata).await?;
+
+        let Some(user) = object_key::user_from_key(&metadata.file_id, self.save_prefix()) else {
+            return Ok(());
+        };
+        if let Err(e) = self.remove_entry(user, &metadata.file_id).await {
+            eprintln!("[Index] Failed to remove {} from the save index: {}", metadata.file_id, e);
+            self.mark_dirty(user);
+        }
+        Ok(())
+    }
+
+    async fn set_version_label(&self, metadata: &SaveMetadata, label: Option<String>) -> Result<VersionInfo> {
+        let info = self.inner.set_version_label(metadata, label).await?;
+
+        let Some(user) = object_key::user_from_key(&metadata.file_id, self.save_prefix()) else {
+            return Ok(info);
+        };
+        let result = self.update_entry(user, &metadata.file_id, |entry| entry.info = Some(info.clone())).await;
+        if let Err(e) = result {
+            eprintln!("[Index] Failed to record the label of {} in the save index: {}", metadata.file_id, e);
+            self.mark_dirty(user);
+        }
+        Ok(info)
+    }
+
+    async fn repair_save(&self, save: &LegacySave, app_id: u32, game_name: &str) -> Result<String> {
+        let new_key = self.inner.repair_save(save, app_id, game_name).await?;
+
+        // The entry moves along, keeping its upload time, tags and label
+        let Some(user) = object_key::user_from_key(&save.key, self.save_prefix()) else {
+            return Ok(new_key);
+        };
+        let result = self.update_entry(user, &save.key, |entry| {
+            entry.file_id = new_key.clone();
+            entry.game_id = app_id.to_string();
+            entry.game_name = game_name.to_string();
+            if let Some(info) = &mut entry.info {
+                info.game_name = game_name.to_string();
+            }
+        }).await;
+        if let Err(e) = result {
+            eprintln!("[Index] Failed to record the repair of {} in the save index: {}", save.key, e);
+            self.mark_dirty(user);
+        }
+        Ok(new_key)
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        self.inner.resume_upload(upload_id, offset, data).await
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.inner.test_connection().await
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        self.inner.get_storage_info(user_id).await
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        self.inner.get_bucket_storage_info().await
+    }
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        self.inner.set_upload_session_store(store);
+    }
+
+    fn encrypts_objects(&self) -> bool {
+        self.inner.encrypts_objects()
+    }
+
+    fn replication(&self) -> Option<ReplicatedBackend> {
+        self.inner.replication()
+    }
+
+    fn save_prefix(&self) -> &str {
+        self.inner.save_prefix()
+    }
+
+    async fn estimate_upload(&self, game_save: &GameSave, user_id: &str) -> Result<u64> {
+        self.inner.estimate_upload(game_save, user_id).await
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        self.inner.put_object(key, data).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        self.inner.get_object(key).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        self.inner.get_archive(key).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        self.inner.put_archive(key, archive).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        self.inner.object_exists(key).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        self.inner.list_objects(prefix).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.inner.delete_object(key).await
+    }
+
+    async fn move_object(&self, from: &str, to: &str) -> Result<()> {
+        self.inner.move_object(from, to).await
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        self.inner.get_object_tagged(key).await
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        self.inner.put_object_if(key, data, expected_tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalFolderBackend;
+    use tempfile::TempDir;
+
+    fn game_save(dir: &Path, app_id: u32, name: &str) -> GameSave {
+        let save_dir = dir.join(format!("{}", app_id));
+        std::fs::create_dir_all(&save_dir).unwrap();
+        std::fs::write(save_dir.join("save.dat"), format!("{} progress", name)).unwrap();
+        GameSave { app_id, name: name.to_string(), save_path: save_dir }
+    }
+
+    #[tokio::test]
+    async fn test_index_is_bootstrapped_and_kept_current() {
+        let storage = TempDir::new().unwrap();
+        let saves = TempDir::new().unwrap();
+
+        // An upload from before the index existed
+        let legacy = LocalFolderBackend::with_root(storage.path().to_path_buf());
+        legacy.upload_save(&game_save(saves.path(), 105600, "Terraria"), "player").await.unwrap();
+
+        let backend = IndexedBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())))
+            .with_device("desktop");
+        let listed = backend.list_saves("player", None).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(backend.load_index("player").await.unwrap().is_some());
+
+        let uploaded = backend.upload_save(&game_save(saves.path(), 1086940, "Baldur's Gate 3"), "player").await.unwrap();
+        let index = backend.load_index("player").await.unwrap().unwrap();
+        let entry = index.entries.iter().find(|e| e.file_id == uploaded.file_id).unwrap();
+        assert_eq!(entry.game_name, "Baldur's Gate 3");
+        assert_eq!(entry.device.as_deref(), Some("desktop"));
+        assert!(entry.save_modified_at.is_some());
+
+        backend.set_tags("player", &uploaded.file_id, vec!["before boss".to_string()]).await.unwrap();
+        let index = backend.load_index("player").await.unwrap().unwrap();
+        assert_eq!(index.entries.iter().find(|e| e.file_id == uploaded.file_id).unwrap().tags, vec!["before boss"]);
+
+        backend.set_version_label(&uploaded, Some("act 2 start".to_string())).await.unwrap();
+        let listed = backend.list_saves("player", Some("1086940")).await.unwrap();
+        let info = listed[0].info.as_ref().unwrap();
+        assert_eq!(info.label.as_deref(), Some("act 2 start"));
+        assert_eq!(info.sha256, uploaded.checksum);
+
+        assert_eq!(backend.list_saves("player", Some("1086940")).await.unwrap().len(), 1);
+        backend.delete_save(&uploaded).await.unwrap();
+        assert_eq!(backend.list_saves("player", None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_uploads_keep_both_entries() {
+        let storage = TempDir::new().unwrap();
+        let saves = TempDir::new().unwrap();
+        let first = IndexedBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())))
+            .with_device("laptop");
+        let second = IndexedBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())))
+            .with_device("desktop");
+
+        let (a, b) = (game_save(saves.path(), 730, "CS2"), game_save(saves.path(), 570, "Dota 2"));
+        let (a, b) = tokio::join!(first.upload_save(&a, "player"), second.upload_save(&b, "player"));
+        a.unwrap();
+        b.unwrap();
+
+        let index = first.load_index("player").await.unwrap().unwrap();
+        assert_eq!(index.entries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_cache_does_not_hide_versions() {
+        let storage = TempDir::new().unwrap();
+        let saves = TempDir::new().unwrap();
+        let backend = IndexedBackend::new(Box::new(LocalFolderBackend::with_root(storage.path().to_path_buf())));
+
+        let first = backend.upload_save(&game_save(saves.path(), 730, "CS2"), "player").await.unwrap();
+        assert_eq!(backend.list_saves("player", None).await.unwrap().len(), 1);
+        let stale = std::fs::read(storage.path().join("index/player.json")).unwrap();
+
+        // Another machine uploads and labels, then a slow writer puts back the cache it had read
+        let second = backend.upload_save(&game_save(saves.path(), 570, "Dota 2"), "player").await.unwrap();
+        backend.set_tags("player", &first.file_id, vec!["keep".to_string()]).await.unwrap();
+        std::fs::write(storage.path().join("index/player.json"), &stale).unwrap();
+
+        let index = backend.load_index("player").await.unwrap().unwrap();
+        let ids: Vec<&str> = index.entries.iter().map(|e| e.file_id.as_str()).collect();
+        assert_eq!(ids, vec![first.file_id.as_str(), second.file_id.as_str()]);
+        assert_eq!(index.entries[0].tags, vec!["keep"]);
+        // Superseded revisions are gone
+        assert_eq!(std::fs::read_dir(storage.path().join("index/player")).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missed_entries_are_rebuilt() {
+        let storage = TempDir::new().unwrap();
+        let saves = TempDir::new().unwrap();
+        let local = || LocalFolderBackend::with_root(storage.path().to_path_buf());
+        let backend = IndexedBackend::new(Box::new(local()));
+
+        // An index written by an older release keeps its tags
+        let legacy_upload = local().upload_save(&game_save(saves.path(), 105600, "Terraria"), "player").await.unwrap();
+        let legacy = SaveIndex {
+            format: 1,
+            entries: vec![IndexEntry { tags: vec!["old".to_string()], ..IndexEntry::from_metadata(&legacy_upload) }],
+            ..SaveIndex::new()
+        };
+        std::fs::create_dir_all(storage.path().join("index")).unwrap();
+        std::fs::write(storage.path().join("index/player.json"), serde_json::to_vec(&legacy).unwrap()).unwrap();
+        let listed = backend.load_index("player").await.unwrap().unwrap();
+        assert_eq!((listed.format, listed.entries[0].tags.clone()), (INDEX_FORMAT, vec!["old".to_string()]));
+
+        // The entry can't be written (a file is in the way), but the save is stored
+        std::fs::write(storage.path().join("index/player.blocked"), b"").unwrap();
+        std::fs::rename(storage.path().join("index/player"), storage.path().join("index/player.moved")).unwrap();
+        std::fs::rename(storage.path().join("index/player.blocked"), storage.path().join("index/player")).unwrap();
+        let missed = backend.upload_save(&game_save(saves.path(), 730, "CS2"), "player").await.unwrap();
+        std::fs::remove_file(storage.path().join("index/player")).unwrap();
+        std::fs::rename(storage.path().join("index/player.moved"), storage.path().join("index/player")).unwrap();
+
+        let listed = backend.list_saves("player", None).await.unwrap();
+        assert!(listed.iter().any(|save| save.file_id == missed.file_id));
+
+        // Uploads that bypass the index show up with the next scheduled rebuild
+        let bypassed = local().upload_save(&game_save(saves.path(), 570, "Dota 2"), "player").await.unwrap();
+        assert_eq!(backend.list_saves("player", None).await.unwrap().len(), 2);
+        let mut cache = backend.load_index("player").await.unwrap().unwrap();
+        cache.rebuilt_at = Some((chrono::Utc::now() - REBUILD_INTERVAL).to_rfc3339());
+        std::fs::write(storage.path().join("index/player.json"), serde_json::to_vec(&cache).unwrap()).unwrap();
+        let listed = backend.list_saves("player", None).await.unwrap();
+        assert_eq!(listed.len(), 3);
+        assert!(listed.iter().any(|save| save.file_id == bypassed.file_id));
+    }
+
+    /// Storage whose save tree is under a custom prefix, like an S3 bucket configured with one
+    struct Prefixed(LocalFolderBackend);
+
+    #[async_trait]
+    impl CloudBackend for Prefixed {
+        async fn upload_save(&self, _game_save: &GameSave, _user_id: &str) -> Result<SaveMetadata> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn download_save(&self, _metadata: &SaveMetadata, _local_path: &Path) -> Result<()> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn list_saves(&self, _user_id: &str, _game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+            Ok(Vec::new())
+        }
+        async fn delete_save(&self, _metadata: &SaveMetadata) -> Result<()> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn resume_upload(&self, _upload_id: &str, _offset: u64, _data: Bytes) -> Result<UploadProgress> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn test_connection(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn get_storage_info(&self, _user_id: &str) -> Result<StorageInfo> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        fn save_prefix(&self) -> &str {
+            "team/saves/"
+        }
+        async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+            self.0.put_object(key, data).await
+        }
+        async fn get_object(&self, key: &str) -> Result<Bytes> {
+            self.0.get_object(key).await
+        }
+        async fn object_exists(&self, key: &str) -> Result<bool> {
+            self.0.object_exists(key).await
+        }
+        async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+            self.0.list_objects(prefix).await
+        }
+        async fn delete_object(&self, key: &str) -> Result<()> {
+            self.0.delete_object(key).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_save_prefix_keeps_entries_per_user() {
+        let storage = TempDir::new().unwrap();
+        let saves = TempDir::new().unwrap();
+        let storage_backend = Prefixed(LocalFolderBackend::with_root(storage.path().to_path_buf()));
+        let backend = IndexedBackend::new(Box::new(crate::ChunkedBackend::new(Box::new(storage_backend))));
+
+        let uploaded = backend.upload_save(&game_save(saves.path(), 105600, "Terraria"), "player").await.unwrap();
+        assert!(uploaded.file_id.starts_with("team/saves/player/105600_"), "{}", uploaded.file_id);
+        let listed = backend.list_saves("player", Some("105600")).await.unwrap();
+        assert_eq!(listed.len(), 1);
+
+        backend.set_version_label(&uploaded, Some("before boss".to_string())).await.unwrap();
+        let index = backend.load_index("player").await.unwrap().unwrap();
+        let label = index.entries[0].info.as_ref().and_then(|info| info.label.clone());
+        assert_eq!(label.as_deref(), Some("before boss"));
+
+        backend.delete_save(&uploaded).await.unwrap();
+        assert!(backend.list_saves("player", None).await.unwrap().is_empty());
+        assert!(backend.dirty.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stale_tag_is_rejected() {
+        let storage = TempDir::new().unwrap();
+        let backend = LocalFolderBackend::with_root(storage.path().to_path_buf());
+
+        assert!(backend.put_object_if("index/player.json", Bytes::from_static(b"v1"), None).await.unwrap());
+        assert!(!backend.put_object_if("index/player.json", Bytes::from_static(b"v1b"), None).await.unwrap());
+
+        let (_, tag) = backend.get_object_tagged("index/player.json").await.unwrap().unwrap();
+        assert!(backend.put_object_if("index/player.json", Bytes::from_static(b"v2"), Some(&tag)).await.unwrap());
+        assert!(!backend.put_object_if("index/player.json", Bytes::from_static(b"v3"), Some(&tag)).await.unwrap());
+        assert_eq!(backend.get_object("index/player.json").await.unwrap(), Bytes::from_static(b"v2"));
+    }
+}
@@ -4,26 +4,70 @@ use bytes::Bytes;
 use chrono;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::sync::Arc;
 use steam_cloud_sync_core::GameSave;
-use sha2::{Digest, Sha256};
 use sha1::Sha1;
+use sha2::Digest;
 use hmac::{Hmac, Mac};
-use zip::{ZipArchive, ZipWriter};
-use std::io::{Write, Cursor};
-use tokio::fs;
-use uuid::Uuid;
 
+pub mod archive;
+pub mod cancel;
+pub mod chunked;
+pub mod encryption;
 pub mod cloud_save_service;
-pub mod game_mapping;
-
+pub mod incremental;
+pub mod index;
+mod listing;
+pub mod local_folder;
+pub mod migration;
+pub mod multipart;
+pub mod object_key;
+pub mod progress;
+pub mod quota;
+pub mod repair;
+pub mod replication;
+pub mod retention;
+pub mod retry;
+pub mod scrub;
+pub mod sftp;
+pub mod throttle;
+pub mod version_info;
+pub mod webdav;
+
+pub use archive::SaveArchive;
+pub use cancel::Cancelled;
+pub use chunked::{ChunkedBackend, SaveManifest};
+pub use encryption::{EncryptedBackend, Keyring};
 pub use cloud_save_service::*;
-use game_mapping::extract_and_map_game_id;
+pub use incremental::{FileManifest, IncrementalBackend};
+pub use index::{IndexEntry, IndexedBackend, SaveIndex};
+pub use local_folder::LocalFolderBackend;
+pub use migration::{migrate, MigrationProgress, MigrationReport};
+pub use multipart::{MemoryUploadSessionStore, UploadSession, UploadSessionStore, UploadedPart};
+pub use object_key::{KeyLayout, ObjectKey};
+pub use progress::{ProgressSnapshot, TransferProgress, TransferStage};
+pub use quota::{is_quota_exceeded, QuotaExceeded, QuotaLevel, QuotaStatus};
+pub use repair::{find_legacy_saves, Candidate, LegacySave};
+pub use replication::{ReplicaState, ReplicaStatus, ReplicatedBackend};
+pub use retention::{plan_retention, RetentionPlan, RetentionPolicy, RetentionRules};
+pub use retry::{RetryPolicy, RetryingBackend};
+pub use scrub::{ScrubOutcome, ScrubReport, ScrubResult};
+pub use sftp::{SftpAuth, SftpBackend, SftpConfig};
+pub use throttle::{set_bandwidth_limits, TokenBucket};
+pub use version_info::VersionInfo;
+pub use webdav::WebDavBackend;
+use object_key::game_id_from_key;
+use futures::TryStreamExt;
+use listing::{ListPage, ListedObject};
 use std::io::Read;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Copy)]
 pub enum BackendType {
     TencentCOS,
     S3,
+    LocalFolder,
+    WebDav,
+    Sftp,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,7 +84,13 @@ pub struct SaveMetadata {
     pub size_bytes: u64,
     pub checksum: String,
     pub compressed: bool,
+    /// Stored encrypted on the client side (see `encryption`)
+    #[serde(default)]
+    pub encrypted: bool,
     pub file_id: String,
+    /// Details recorded in the version's sidecar, when it has one (see `version_info`)
+    #[serde(default)]
+    pub info: Option<VersionInfo>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,30 +102,12 @@ pub struct StorageInfo {
     pub bucket_total_objects: Option<u32>, // 整个存储桶对象数量（可选）
 }
 
-/// Builder for SaveMetadata to help with XML parsing
-#[derive(Debug, Default)]
-struct SaveMetadataBuilder {
-    file_id: Option<String>,
-    size_bytes: Option<u64>,
-    timestamp: Option<String>,
-    checksum: Option<String>,
-}
-
-impl SaveMetadataBuilder {
-    fn new() -> Self {
-        Self::default()
-    }
-    
-    fn build(self) -> Option<SaveMetadata> {
-        Some(SaveMetadata {
-            game_id: String::new(), // Will be filled later from file path
-            timestamp: self.timestamp.unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
-            size_bytes: self.size_bytes.unwrap_or(0),
-            checksum: self.checksum.unwrap_or_default(),
-            compressed: true, // Assume compressed for .zip files
-            file_id: self.file_id?,
-        })
-    }
+/// A raw object in the backend's storage, as returned by [`CloudBackend::list_objects`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size_bytes: u64,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Extract value from XML tag
@@ -94,90 +126,11 @@ fn extract_xml_value(line: &str, tag: &str) -> Option<String> {
     None
 }
 
-/// Extract game ID from file path
-/// Supports multiple path formats:
-/// - saves/user_id/game_id/filename.zip
-/// - saves/user_id/game_id_timestamp_uuid.zip
-fn extract_game_id_from_path(file_path: &str) -> String {
-    let parts: Vec<&str> = file_path.split('/').collect();
-    
-    if parts.len() >= 3 {
-        // Check if it's a directory format: saves/user_id/game_id/filename.zip
-        if parts.len() >= 4 {
-            return parts[2].to_string(); // game_id is the third part
-        }
-        
-        // Check if it's a file format: saves/user_id/game_id_timestamp_uuid.zip
-        let filename = parts[2];
-        if let Some(first_underscore) = filename.find('_') {
-            return filename[..first_underscore].to_string();
-        }
-        
-        // Fallback: use the filename without extension
-        if let Some(dot) = filename.rfind('.') {
-            return filename[..dot].to_string();
-        }
-        
-        return filename.to_string();
-    }
-    
-    // Fallback: extract from the full path
-    "unknown".to_string()
-}
-
-/// Helper function to extract a save archive to the target path
-async fn extract_save_archive_helper(data: &[u8], target_path: &Path) -> Result<()> {
-    let data = data.to_vec();
-    let target_path = target_path.to_path_buf();
-    
-    tokio::task::spawn_blocking(move || {
-        let cursor = Cursor::new(&data);
-        let mut zip = ZipArchive::new(cursor)?;
-        
-        // Create target directory if it doesn't exist
-        if let Some(parent) = target_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        
-        // If there's only one file in the archive and target_path is a file path,
-        // extract directly to that file
-        if zip.len() == 1 && target_path.extension().is_some() {
-            let mut file = zip.by_index(0)?;
-            let mut contents = Vec::new();
-            std::io::Read::read_to_end(&mut file, &mut contents)?;
-            std::fs::write(&target_path, contents)?;
-        } else {
-            // Otherwise, extract all files to the target directory
-            let extract_dir = if target_path.is_dir() || target_path.extension().is_none() {
-                target_path
-            } else {
-                target_path.parent().unwrap().to_path_buf()
-            };
-            
-            std::fs::create_dir_all(&extract_dir)?;
-            
-            for i in 0..zip.len() {
-                let mut file = zip.by_index(i)?;
-                let file_path = match file.enclosed_name() {
-                    Some(path) => extract_dir.join(path),
-                    None => continue,
-                };
-                
-                if file.name().ends_with('/') {
-                    std::fs::create_dir_all(&file_path)?;
-                } else {
-                    if let Some(parent) = file_path.parent() {
-                        std::fs::create_dir_all(parent)?;
-                    }
-                    let mut contents = Vec::new();
-                    std::io::Read::read_to_end(&mut file, &mut contents)?;
-                    std::fs::write(&file_path, contents)?;
-                }
-            }
-        }
-        
-        Ok::<(), anyhow::Error>(())
-    }).await?
+/// Strip everything but alphanumerics, '_' and '-' so the user id is safe to use in object keys
+pub(crate) fn sanitize_user_id(user_id: &str) -> String {
+    user_id.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+        .collect::<String>()
 }
 
 #[async_trait]
@@ -190,16 +143,142 @@ pub trait CloudBackend: Send + Sync {
     async fn test_connection(&self) -> Result<()>;
     async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo>;
     async fn get_bucket_storage_info(&self) -> Result<(u64, u32)>; // (total_bytes, total_objects)
+
+    /// Where in-flight multipart uploads are recorded so they can resume after a restart.
+    /// Backends without multipart uploads ignore this.
+    fn set_upload_session_store(&mut self, _store: Arc<dyn UploadSessionStore>) {}
+
+    /// Whether objects stored through this backend are encrypted on the client
+    fn encrypts_objects(&self) -> bool {
+        false
+    }
+
+    /// The replicated storage under this backend, if saves are mirrored to several backends
+    fn replication(&self) -> Option<ReplicatedBackend> {
+        None
+    }
+
+    /// Start of the tree save versions are stored under, ending in `/`
+    fn save_prefix(&self) -> &str {
+        object_key::SAVES_PREFIX
+    }
+
+    /// Upper bound of the bytes uploading `game_save` adds to storage, checked against the
+    /// quota before the upload. Formats that only store what changed override this.
+    async fn estimate_upload(&self, game_save: &GameSave, _user_id: &str) -> Result<u64> {
+        quota::local_save_size(&game_save.save_path).await
+    }
+
+    // Plain object access below the save layer, used by storage formats that
+    // manage their own keys (e.g. the chunk store in `chunked`)
+
+    async fn put_object(&self, _key: &str, _data: Bytes) -> Result<()> {
+        Err(anyhow::anyhow!("This backend does not support direct object access"))
+    }
+    async fn get_object(&self, _key: &str) -> Result<Bytes> {
+        Err(anyhow::anyhow!("This backend does not support direct object access"))
+    }
+    async fn object_exists(&self, _key: &str) -> Result<bool> {
+        Err(anyhow::anyhow!("This backend does not support direct object access"))
+    }
+    /// All objects whose key starts with `prefix`
+    async fn list_objects(&self, _prefix: &str) -> Result<Vec<ObjectInfo>> {
+        Err(anyhow::anyhow!("This backend does not support direct object access"))
+    }
+    /// Delete an object; deleting a missing object is not an error
+    async fn delete_object(&self, _key: &str) -> Result<()> {
+        Err(anyhow::anyhow!("This backend does not support direct object access"))
+    }
+    /// Download an object into a temporary file, hashing it on the way, so large objects
+    /// never have to fit in memory. Backends that can stream downloads override this.
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        SaveArchive::from_bytes(self.get_object(key).await?).await
+    }
+    /// Upload an object from a temporary file. Backends that can stream uploads override this.
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        self.put_object(key, archive.to_bytes().await?).await
+    }
+    /// Move an object to another key as stored, without re-encoding it
+    async fn move_object(&self, from: &str, to: &str) -> Result<()> {
+        move_object_by_copy(self, from, to).await
+    }
+
+    /// Read an object together with a version tag for `put_object_if`; `None` if it doesn't exist
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        get_object_tagged_by_content(self, key).await
+    }
+    /// Write an object only if its version tag still equals `expected_tag` (`None`: only if
+    /// it doesn't exist yet). Returns `false` when another writer got there first.
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        put_object_if_by_content(self, key, data, expected_tag).await
+    }
+
+    /// Set (or with `None`, clear) the user label of a save version, stored in its sidecar
+    async fn set_version_label(&self, metadata: &SaveMetadata, label: Option<String>) -> Result<VersionInfo> {
+        version_info::set_label(self, metadata, label).await
+    }
+
+    /// Move a legacy save to the canonical key of `app_id` once the user confirmed its
+    /// game (see [`repair`]); returns the new key
+    async fn repair_save(&self, save: &LegacySave, app_id: u32, game_name: &str) -> Result<String> {
+        repair::repair_save(self, save, app_id, game_name).await
+    }
+}
+
+/// Content hash used as the version tag by backends without native ETags
+pub(crate) fn content_tag(data: &[u8]) -> String {
+    format!("sha256:{:x}", sha2::Sha256::digest(data))
+}
+
+pub(crate) async fn get_object_tagged_by_content<B: CloudBackend + ?Sized>(backend: &B, key: &str) -> Result<Option<(Bytes, String)>> {
+    if !backend.object_exists(key).await? {
+        return Ok(None);
+    }
+    let data = backend.get_object(key).await?;
+    let tag = content_tag(&data);
+    Ok(Some((data, tag)))
+}
+
+/// Compare-then-write for backends without conditional writes. This narrows the race
+/// between concurrent writers to the time between the check and the write, but doesn't close it.
+pub(crate) async fn put_object_if_by_content<B: CloudBackend + ?Sized>(
+    backend: &B,
+    key: &str,
+    data: Bytes,
+    expected_tag: Option<&str>,
+) -> Result<bool> {
+    let current = get_object_tagged_by_content(backend, key).await?.map(|(_, tag)| tag);
+    if current.as_deref() != expected_tag {
+        return Ok(false);
+    }
+    backend.put_object(key, data).await?;
+    Ok(true)
+}
+
+/// Copy-then-delete for backends without a native move. The copy is read back and
+/// compared before the original is deleted.
+pub(crate) async fn move_object_by_copy<B: CloudBackend + ?Sized>(backend: &B, from: &str, to: &str) -> Result<()> {
+    let data = backend.get_object(from).await?;
+    let expected = content_tag(&data);
+    backend.put_object(to, data).await?;
+    let written = content_tag(&backend.get_object(to).await?);
+    if written != expected {
+        return Err(anyhow::anyhow!("{} changed while being moved to {}: expected {}, got {}", from, to, expected, written));
+    }
+    backend.delete_object(from).await
 }
 
 pub fn backend(kind: BackendType) -> Box<dyn CloudBackend> {
     match kind {
         BackendType::TencentCOS => Box::new(TencentCOSBackend::new()),
         BackendType::S3 => Box::new(S3Backend::new()),
+        BackendType::LocalFolder => Box::new(LocalFolderBackend::new()),
+        BackendType::WebDav => Box::new(WebDavBackend::new()),
+        BackendType::Sftp => Box::new(SftpBackend::new()),
     }
 }
 
-pub fn backend_with_settings(kind: BackendType, tencent_credentials: Option<(String, String, String, String)>, s3_config: Option<(String, String)>) -> Box<dyn CloudBackend> {
+pub fn backend_with_settings(kind: BackendType, tencent_credentials: Option<(String, String, String, String)>, s3_config: Option<S3Config>, local_folder: Option<std::path::PathBuf>, webdav_credentials: Option<(String, String, String)>, sftp_config: Option<SftpConfig>) -> Box<dyn CloudBackend> {
     match kind {
         BackendType::TencentCOS => {
             if let Some((secret_id, secret_key, bucket, region)) = tencent_credentials {
@@ -209,12 +288,33 @@ pub fn backend_with_settings(kind: BackendType, tencent_credentials: Option<(Str
             }
         }
         BackendType::S3 => {
-            if let Some((bucket, prefix)) = s3_config {
-                Box::new(S3Backend::with_config(bucket, prefix))
+            if let Some(config) = s3_config {
+                Box::new(S3Backend::with_s3_config(config))
             } else {
                 Box::new(S3Backend::new())
             }
         }
+        BackendType::LocalFolder => {
+            if let Some(root) = local_folder {
+                Box::new(LocalFolderBackend::with_root(root))
+            } else {
+                Box::new(LocalFolderBackend::new())
+            }
+        }
+        BackendType::WebDav => {
+            if let Some((url, username, password)) = webdav_credentials {
+                Box::new(WebDavBackend::with_credentials(url, username, password))
+            } else {
+                Box::new(WebDavBackend::new())
+            }
+        }
+        BackendType::Sftp => {
+            if let Some(config) = sftp_config {
+                Box::new(SftpBackend::with_config(config))
+            } else {
+                Box::new(SftpBackend::new())
+            }
+        }
     }
 }
 
@@ -224,26 +324,29 @@ pub struct TencentCOSBackend {
     secret_key: Option<String>,
     bucket: String,
     region: String,
+    upload_sessions: Arc<dyn UploadSessionStore>,
 }
 
 impl TencentCOSBackend {
     pub fn new() -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: retry::http_client(),
             secret_id: None,
             secret_key: None,
             bucket: "steam-cloud-sync".to_string(),
             region: "ap-beijing".to_string(),
+            upload_sessions: Arc::new(MemoryUploadSessionStore::new()),
         }
     }
 
     pub fn with_credentials(secret_id: String, secret_key: String, bucket: String, region: String) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: retry::http_client(),
             secret_id: Some(secret_id),
             secret_key: Some(secret_key),
             bucket,
             region,
+            upload_sessions: Arc::new(MemoryUploadSessionStore::new()),
         }
     }
 
@@ -251,6 +354,53 @@ impl TencentCOSBackend {
         format!("https://{}.cos.{}.myqcloud.com/{}", self.bucket, self.region, object_key)
     }
 
+    /// One page of the objects below `prefix`, starting after `marker`
+    async fn list_page(&self, prefix: &str, marker: Option<String>) -> Result<ListPage> {
+        let max_keys = listing::PAGE_SIZE.to_string();
+        let mut query = vec![("prefix", prefix), ("max-keys", max_keys.as_str())];
+        if let Some(marker) = &marker {
+            query.push(("marker", marker));
+        }
+        let response = self.signed_request(reqwest::Method::GET, "", &query)?.send().await?;
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("Failed to list objects on Tencent COS: {} - {}", status, body));
+        }
+        listing::parse_list_bucket(&body)
+    }
+
+    /// Every object below `prefix`, following the listing across pages
+    async fn list_all(&self, prefix: &str) -> Result<Vec<ListedObject>> {
+        listing::paginate(|marker| self.list_page(prefix, marker)).try_collect().await
+    }
+
+    /// Build a signed request for an object. COS signs query parameters with
+    /// lower-cased names and URL-encoded values, while the URL keeps the documented casing.
+    fn signed_request(&self, method: reqwest::Method, object_key: &str, query: &[(&str, &str)]) -> Result<reqwest::RequestBuilder> {
+        let sign_query = query.iter()
+            .map(|(key, value)| format!("{}={}", key.to_lowercase(), urlencoding::encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let url_query = query.iter()
+            .map(|(key, value)| if value.is_empty() { key.to_string() } else { format!("{}={}", key, urlencoding::encode(value)) })
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut url = self.get_cos_url(object_key);
+        if !url_query.is_empty() {
+            url.push('?');
+            url.push_str(&url_query);
+        }
+
+        let (authorization, _) = self.generate_cos_authorization(method.as_str(), object_key, &sign_query, 0)?;
+        Ok(self.client
+            .request(method, &url)
+            .header("Authorization", authorization)
+            .header("Host", format!("{}.cos.{}.myqcloud.com", self.bucket, self.region)))
+    }
+
     async fn test_bucket_access(&self) -> Result<()> {
         eprintln!("[TencentCOS] Testing bucket access: bucket={}, region={}", self.bucket, self.region);
         
@@ -307,52 +457,13 @@ impl TencentCOSBackend {
         }
     }
 
-    async fn compress_save(&self, save_path: &Path) -> Result<Vec<u8>> {
-        let save_path = save_path.to_path_buf();
-        
-        tokio::task::spawn_blocking(move || {
-            let mut buffer = Vec::new();
-            {
-                let cursor = Cursor::new(&mut buffer);
-                let mut zip = ZipWriter::new(cursor);
-                
-                if save_path.is_file() {
-                    let file_name = save_path.file_name().unwrap().to_str().unwrap();
-                    let file_data = std::fs::read(&save_path)?;
-                    zip.start_file(file_name, zip::write::FileOptions::default())?;
-                    zip.write_all(&file_data)?;
-                } else if save_path.is_dir() {
-                    Self::add_dir_to_zip_sync(&mut zip, &save_path, "")?;
-                }
-                
-                zip.finish()?;
-            }
-            Ok::<Vec<u8>, anyhow::Error>(buffer)
-        }).await?
-    }
-
-    fn add_dir_to_zip_sync<W: Write + std::io::Seek>(zip: &mut ZipWriter<W>, dir: &Path, prefix: &str) -> Result<()> {
-        let entries = std::fs::read_dir(dir)?;
-        
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
-            let name = entry.file_name();
-            let file_name = format!("{}{}", prefix, name.to_str().unwrap());
-
-            if path.is_file() {
-                let file_data = std::fs::read(&path)?;
-                zip.start_file(&file_name, zip::write::FileOptions::default())?;
-                zip.write_all(&file_data)?;
-            } else if path.is_dir() {
-                Self::add_dir_to_zip_sync(zip, &path, &format!("{}/", file_name))?;
-            }
-        }
-        Ok(())
+    async fn compress_save(&self, save_path: &Path) -> Result<SaveArchive> {
+        SaveArchive::create(save_path).await
     }
 
+    #[cfg(test)]
     fn calculate_sha256(data: &[u8]) -> String {
-        let mut hasher = Sha256::new();
+        let mut hasher = sha2::Sha256::new();
         hasher.update(data);
         format!("{:x}", hasher.finalize())
     }
@@ -445,22 +556,21 @@ impl TencentCOSBackend {
         Ok((authorization, key_time))
     }
 
-    async fn upload_to_cos(&self, object_key: &str, data: &[u8]) -> Result<()> {
+    async fn upload_to_cos(&self, object_key: &str, archive: &SaveArchive) -> Result<()> {
         let url = self.get_cos_url(object_key);
         let content_type = "application/octet-stream";
-        let content_sha256 = Self::calculate_sha256(data);
         
         // Generate authorization header
-        let (authorization, _key_time) = self.generate_cos_authorization("PUT", object_key, "", data.len())?;;
+        let (authorization, _key_time) = self.generate_cos_authorization("PUT", object_key, "", archive.size() as usize)?;;
         
         let response = self.client
             .put(&url)
             .header("Content-Type", content_type)
-            .header("Content-Length", data.len())
+            .header("Content-Length", archive.size())
             .header("Authorization", authorization)
             .header("Host", format!("{}.cos.{}.myqcloud.com", self.bucket, self.region))
-            .header("x-cos-meta-sha256", &content_sha256) // Store SHA256 as metadata
-            .body(data.to_vec())
+            .header("x-cos-meta-sha256", archive.checksum()) // Store SHA256 as metadata
+            .body(archive.body()?)
             .send()
             .await?;
 
@@ -474,28 +584,120 @@ impl TencentCOSBackend {
     }
 }
 
+#[async_trait]
+impl multipart::MultipartApi for TencentCOSBackend {
+    async fn initiate_upload(&self, object_key: &str, checksum: &str) -> Result<String> {
+        let response = self.signed_request(reqwest::Method::POST, object_key, &[("uploads", "")])?
+            .header("Content-Type", "application/octet-stream")
+            .header("x-cos-meta-sha256", checksum)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("Failed to initiate multipart upload on Tencent COS: {} - {}", status, body));
+        }
+
+        extract_xml_value(&body, "UploadId")
+            .ok_or_else(|| anyhow::anyhow!("Tencent COS returned no UploadId: {}", body))
+    }
+
+    async fn upload_part(&self, object_key: &str, upload_id: &str, part_number: u32, data: Bytes) -> Result<String> {
+        let part_number = part_number.to_string();
+        let response = self.signed_request(reqwest::Method::PUT, object_key, &[("partNumber", &part_number), ("uploadId", upload_id)])?
+            .header("Content-Length", data.len())
+            .body(throttle::upload_body(data))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to upload part {} to Tencent COS: {} - {}", part_number, status, body));
+        }
+
+        response.headers().get("ETag")
+            .and_then(|v| v.to_str().ok())
+            .map(|etag| etag.to_string())
+            .ok_or_else(|| anyhow::anyhow!("Tencent COS returned no ETag for part {}", part_number))
+    }
+
+    async fn complete_upload(&self, session: &UploadSession) -> Result<()> {
+        let mut body = String::from("<CompleteMultipartUpload>");
+        for part in session.sorted_parts() {
+            body.push_str(&format!("<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>", part.part_number, part.etag));
+        }
+        body.push_str("</CompleteMultipartUpload>");
+
+        let response = self.signed_request(reqwest::Method::POST, &session.object_key, &[("uploadId", &session.upload_id)])?
+            .header("Content-Type", "application/xml")
+            .body(body)
+            .send()
+            .await?;
+
+        // COS may report a failed completion inside a 200 response
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() || body.contains("<Error>") {
+            return Err(anyhow::anyhow!("Failed to complete multipart upload on Tencent COS: {} - {}", status, body));
+        }
+        Ok(())
+    }
+
+    async fn abort_upload(&self, object_key: &str, upload_id: &str) -> Result<()> {
+        let response = self.signed_request(reqwest::Method::DELETE, object_key, &[("uploadId", upload_id)])?
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to abort multipart upload on Tencent COS: {} - {}", status, body));
+        }
+        Ok(())
+    }
+
+    fn is_unknown_upload(&self, error: &anyhow::Error) -> bool {
+        error.to_string().contains("NoSuchUpload")
+    }
+}
+
 #[async_trait]
 impl CloudBackend for TencentCOSBackend {
     async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
-        let compressed_data = self.compress_save(&game_save.save_path).await?;
-        let checksum = Self::calculate_sha256(&compressed_data);
+        let archive = self.compress_save(&game_save.save_path).await?;
+        let checksum = archive.checksum().to_string();
         
         // Create filename with user ID and timestamp for separation
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
         let sanitized_user_id = user_id.chars()
             .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
             .collect::<String>();
-        let object_key = format!("saves/{}/{}_{}_{}.zip", sanitized_user_id, game_save.app_id, timestamp, Uuid::new_v4());
+        let object_key = ObjectKey::new(&sanitized_user_id, game_save.app_id, ".zip").to_string();
+        let size_bytes = archive.size();
         
-        self.upload_to_cos(&object_key, &compressed_data).await?;
+        // Large archives go up in parts so an interrupted upload can be resumed
+        let object_key = if size_bytes > multipart::MIN_PART_SIZE {
+            let scope = format!("cos:{}:{}:{}:{}", self.bucket, self.region, sanitized_user_id, game_save.app_id);
+            multipart::run_multipart_upload(self, self.upload_sessions.as_ref(), &scope, &object_key, &checksum, &archive).await?
+        } else {
+            self.upload_to_cos(&object_key, &archive).await?;
+            object_key
+        };
+
+        // COS lists ETags rather than SHA256, so record the real checksum next to the archive
+        let info = VersionInfo::describe(game_save, &checksum, version_info::FORMAT_ZIP).await;
+        version_info::store_version_info(self, &object_key, &info).await;
 
         Ok(SaveMetadata {
             game_id: game_save.app_id.to_string(),
             timestamp: chrono::Utc::now().to_rfc3339(),
-            size_bytes: compressed_data.len() as u64,
+            size_bytes,
             checksum,
             compressed: true,
+            encrypted: false,
             file_id: object_key,
+            info: Some(info),
         })
     }
 
@@ -518,53 +720,48 @@ impl CloudBackend for TencentCOSBackend {
             return Err(anyhow::anyhow!("Failed to download from Tencent COS: {} - {}", status, body));
         }
 
-        let data = response.bytes().await?;
+        let archive = SaveArchive::receive_response(response).await?;
         
         // Verify checksum with improved handling for TencentCOS
-        let calculated_checksum = Self::calculate_sha256(&data);
+        let calculated_checksum = archive.checksum();
+        let expected_checksum = version_info::expected_checksum(self, metadata).await;
         
         // Handle different checksum types more gracefully
-        match metadata.checksum.len() {
+        match expected_checksum.len() {
             64 => {
                 // This should be a SHA256 hash
-                if calculated_checksum != metadata.checksum {
-                    eprintln!("⚠️  [TencentCOS] SHA256 checksum mismatch:");
-                    eprintln!("   Expected: {}", metadata.checksum);
-                    eprintln!("   Calculated: {}", calculated_checksum);
-                    eprintln!("   File size: {} bytes", data.len());
-                    eprintln!("   Continuing download (integrity warning)...");
+                if calculated_checksum != expected_checksum {
+                    // Never restore a damaged or tampered archive over the local save
+                    return Err(anyhow::anyhow!(
+                        "Checksum mismatch for {}: expected {}, got {}",
+                        metadata.file_id, expected_checksum, calculated_checksum
+                    ));
                 } else {
                     eprintln!("✅ [TencentCOS] SHA256 checksum verified");
                 }
             },
             32 => {
                 // This is likely an MD5/ETag, skip SHA256 verification
-                eprintln!("ℹ️  [TencentCOS] MD5/ETag checksum detected ({})", metadata.checksum);
+                eprintln!("ℹ️  [TencentCOS] MD5/ETag checksum detected ({})", expected_checksum);
                 eprintln!("   File SHA256: {}", calculated_checksum);
                 eprintln!("   Skipping verification (different hash types)");
             },
             40 => {
                 // This might be SHA1
-                eprintln!("ℹ️  [TencentCOS] SHA1 checksum detected ({})", metadata.checksum);
+                eprintln!("ℹ️  [TencentCOS] SHA1 checksum detected ({})", expected_checksum);
                 eprintln!("   File SHA256: {}", calculated_checksum);
                 eprintln!("   Skipping verification (different hash types)");
             },
             _ => {
                 // Unknown format, log and continue
                 eprintln!("⚠️  [TencentCOS] Unknown checksum format:");
-                eprintln!("   Stored: '{}' ({} chars)", metadata.checksum, metadata.checksum.len());
+                eprintln!("   Stored: '{}' ({} chars)", expected_checksum, expected_checksum.len());
                 eprintln!("   File SHA256: {}", calculated_checksum);
                 eprintln!("   Continuing download (no verification possible)...");
             }
         }
 
-        // If the target path ends with .zip, save as ZIP file directly (for custom download location)
-        if local_path.extension().and_then(|s| s.to_str()) == Some("zip") {
-            fs::write(local_path, data).await?;
-        } else {
-            // Otherwise, extract the ZIP to the game save directory
-            extract_save_archive_helper(&data, local_path).await?;
-        }
+        archive.restore_to(local_path).await?;
         
         Ok(())
     }
@@ -581,125 +778,53 @@ impl CloudBackend for TencentCOSBackend {
         let prefix = format!("saves/{}/", sanitized_user_id);
         println!("🔍 [DEBUG] Using prefix: '{}'", prefix);
         
-        // Use COS LIST Objects API
-        let url = format!("https://{}.cos.{}.myqcloud.com/?prefix={}", 
-            self.bucket, self.region, urlencoding::encode(&prefix));
-        
-        println!("📡 [DEBUG] Request URL: {}", url);
-        
-        // Generate authorization header for GET request
-        let query_params = format!("prefix={}", urlencoding::encode(&prefix));
-        let (authorization, _) = self.generate_cos_authorization("GET", "", &query_params, 0)?;
-        
-        let response = self.client
-            .get(&url)
-            .header("Authorization", authorization)
-            .header("Host", format!("{}.cos.{}.myqcloud.com", self.bucket, self.region))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            println!("❌ [DEBUG] COS request failed: {} - {}", status, body);
-            return Err(anyhow::anyhow!("Failed to list saves from Tencent COS: {} - {}", status, body));
-        }
-
-        let body = response.text().await?;
-        println!("📄 [DEBUG] COS response length: {} chars", body.len());
+        // Every page of the listing, so users with more than 1000 objects see all their saves
+        let objects = self.list_all(&prefix).await?;
+        println!("📄 [DEBUG] COS listing returned {} objects", objects.len());
         
         let mut saves = Vec::new();
-        
-        // Improved XML parsing using a more robust approach
-        let mut current_object: Option<SaveMetadataBuilder> = None;
-        
-        for line in body.lines() {
-            let line = line.trim();
-            
-            // Start of a new object
-            if line.starts_with("<Contents>") {
-                current_object = Some(SaveMetadataBuilder::new());
+        for object in objects {
+            // Only include .zip files in saves directory
+            if !object.info.key.starts_with("saves/") || !object.info.key.ends_with(".zip") {
                 continue;
             }
             
-            // End of current object - finalize it
-            if line.starts_with("</Contents>") {
-                if let Some(builder) = current_object.take() {
-                    if let Some(metadata) = builder.build() {
-                        // Only include .zip files in saves directory
-                        if metadata.file_id.starts_with("saves/") && metadata.file_id.ends_with(".zip") {
-                            println!("✅ [DEBUG] Found save: {} ({} bytes, {})", 
-                                metadata.file_id, metadata.size_bytes, metadata.timestamp);
-                            saves.push(metadata);
-                        }
-                    }
-                }
-                continue;
-            }
-            
-            // Parse fields within current object
-            if let Some(ref mut builder) = current_object {
-                if let Some(key) = extract_xml_value(line, "Key") {
-                    builder.file_id = Some(key);
-                } else if let Some(size_str) = extract_xml_value(line, "Size") {
-                    if let Ok(size) = size_str.parse::<u64>() {
-                        builder.size_bytes = Some(size);
-                    }
-                } else if let Some(modified) = extract_xml_value(line, "LastModified") {
-                    // Parse ISO 8601 timestamp from COS
-                    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(&modified) {
-                        builder.timestamp = Some(dt.to_rfc3339());
-                    } else {
-                        // Try alternative timestamp format
-                        println!("⚠️ [DEBUG] Failed to parse timestamp: {}", modified);
-                        builder.timestamp = Some(chrono::Utc::now().to_rfc3339());
-                    }
-                } else if let Some(etag) = extract_xml_value(line, "ETag") {
-                    // Clean up ETag: remove quotes, whitespace, and other formatting
-                    let cleaned_etag = etag
-                        .trim()                           // Remove leading/trailing whitespace
-                        .trim_matches('"')               // Remove quotes
-                        .trim_matches('\'')              // Remove single quotes
-                        .chars()
-                        .filter(|c| c.is_ascii_alphanumeric()) // Keep only alphanumeric characters
-                        .collect::<String>();
-                    
-                    if !cleaned_etag.is_empty() {
-                        println!("🏷️ [DEBUG] Raw ETag: '{}' ({}), Cleaned: '{}' ({})", 
-                            etag, etag.len(), cleaned_etag, cleaned_etag.len());
-                        builder.checksum = Some(cleaned_etag);
-                    } else {
-                        println!("⚠️ [DEBUG] Empty ETag after cleaning: '{}'", etag);
-                    }
-                }
-            }
+            let metadata = SaveMetadata {
+                game_id: String::new(), // Will be filled later from file path
+                timestamp: object.info.last_modified
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                size_bytes: object.info.size_bytes,
+                checksum: object.etag.unwrap_or_default(),
+                compressed: true,
+                encrypted: false,
+                file_id: object.info.key,
+                info: None,
+            };
+            println!("✅ [DEBUG] Found save: {} ({} bytes, {})", 
+                metadata.file_id, metadata.size_bytes, metadata.timestamp);
+            saves.push(metadata);
         }
         
         println!("📊 [DEBUG] Found {} total saves after parsing", saves.len());
         
-        // Extract game_id from file path and map to app_id if needed
+        // The app id is part of the key; legacy keys show up as unknown until repaired
         for save in &mut saves {
-            // Use the improved mapping function that handles game names
-            save.game_id = extract_and_map_game_id(&save.file_id);
+            save.game_id = game_id_from_key(&save.file_id);
             println!("🎮 [DEBUG] Mapped {} -> game_id: {}", save.file_id, save.game_id);
         }
         
         // Filter by game_id if specified
         if let Some(gid) = game_id {
             println!("🔍 [DEBUG] Filtering saves for game_id: {}", gid);
-            
-            // Get all possible names for this app_id
-            let possible_names = game_mapping::get_possible_names_for_appid(gid);
-            println!("🔍 [DEBUG] Possible names for {}: {:?}", gid, possible_names);
-            
-            // Filter saves that match any of the possible names
-            saves = saves.into_iter()
-                .filter(|save| possible_names.contains(&save.game_id))
-                .collect();
+            saves.retain(|save| save.game_id == gid);
             
             println!("📊 [DEBUG] {} saves match game_id {}", saves.len(), gid);
         }
         
+        // Replace ETags with the SHA256 recorded at upload, where there is one
+        version_info::attach_version_info(self, &mut saves).await;
+        
         saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
         
         println!("✅ [DEBUG] Returning {} saves for user {}", saves.len(), user_id);
@@ -725,19 +850,15 @@ impl CloudBackend for TencentCOSBackend {
             return Err(anyhow::anyhow!("Failed to delete from Tencent COS: {} - {}", status, body));
         }
 
+        if let Err(e) = self.delete_object(&VersionInfo::sidecar_key(&metadata.file_id)).await {
+            eprintln!("⚠️  [TencentCOS] Failed to delete version info of {}: {}", metadata.file_id, e);
+        }
+
         Ok(())
     }
 
-    async fn resume_upload(&self, _upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
-        let total_size = offset + data.len() as u64;
-        let checksum = Self::calculate_sha256(&data);
-        
-        // Simplified implementation - would need proper multipart upload support
-        Ok(UploadProgress {
-            bytes_uploaded: offset + data.len() as u64,
-            total_bytes: total_size,
-            checksum,
-        })
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        multipart::resume_session_part(self, self.upload_sessions.as_ref(), upload_id, offset, data).await
     }
 
     async fn test_connection(&self) -> Result<()> {
@@ -754,92 +875,13 @@ impl CloudBackend for TencentCOSBackend {
         
         eprintln!("[TencentCOS] Getting storage info for user: {}, prefix: {}", sanitized_user_id, prefix);
         
-        // Use COS LIST Objects API to get user's storage info
-        let url = format!("https://{}.cos.{}.myqcloud.com/?prefix={}", 
-            self.bucket, self.region, urlencoding::encode(&prefix));
-        
-        eprintln!("[TencentCOS] Request URL: {}", url);
-        
-        // Generate authorization header for GET request
-        let query_params = format!("prefix={}", urlencoding::encode(&prefix));
-        let (authorization, _) = self.generate_cos_authorization("GET", "", &query_params, 0)?;
-        
-        eprintln!("[TencentCOS] Authorization generated, sending request...");
-        
-        let response = self.client
-            .get(&url)
-            .header("Authorization", authorization)
-            .header("Host", format!("{}.cos.{}.myqcloud.com", self.bucket, self.region))
-            .send()
-            .await?;
-
-        eprintln!("[TencentCOS] Response status: {}", response.status());
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            eprintln!("[TencentCOS] Error response body: {}", body);
-            return Err(anyhow::anyhow!("Failed to get storage info from Tencent COS: {} - {}", status, body));
-        }
-
-        let body = response.text().await?;
-        let mut used_bytes = 0u64;
-        let mut file_count = 0u32;
-        
-        // Debug: Print the complete XML response
-        eprintln!("[TencentCOS] Complete response body:\n{}", body);
-        eprintln!("[TencentCOS] Response length: {} bytes", body.len());
-        
-        // Parse XML response to calculate storage usage
-        // Look for <Contents> sections which contain file information
-        let mut in_contents = false;
-        let mut current_size: Option<u64> = None;
-        
-        eprintln!("[TencentCOS] Starting XML parsing...");
-        let mut contents_found = 0;
-        
-        for (line_num, line) in body.lines().enumerate() {
-            let line = line.trim();
-            
-            if line.contains("<Contents>") {
-                in_contents = true;
-                current_size = None;
-                contents_found += 1;
-                eprintln!("[TencentCOS] Found <Contents> #{} at line {}", contents_found, line_num);
-            } else if line.contains("</Contents>") {
-                if let Some(size) = current_size {
-                    used_bytes += size;
-                    file_count += 1;
-                    eprintln!("[TencentCOS] Processed file: {} bytes (total: {} bytes, {} files)", size, used_bytes, file_count);
-                } else {
-                    eprintln!("[TencentCOS] Warning: </Contents> without size at line {}", line_num);
-                }
-                in_contents = false;
-                current_size = None;
-            } else if in_contents && line.starts_with("<Size>") && line.ends_with("</Size>") {
-                if let Some(start) = line.find("<Size>") {
-                    if let Some(end) = line.find("</Size>") {
-                        let size_str = &line[start + 6..end];
-                        match size_str.parse::<u64>() {
-                            Ok(size) => {
-                                current_size = Some(size);
-                                eprintln!("[TencentCOS] Parsed size: {} bytes", size);
-                            },
-                            Err(e) => {
-                                eprintln!("[TencentCOS] Failed to parse size '{}': {}", size_str, e);
-                            }
-                        }
-                    }
-                }
-            } else if in_contents && line.contains("<Key>") && line.contains("</Key>") {
-                if let Some(start) = line.find("<Key>") {
-                    if let Some(end) = line.find("</Key>") {
-                        let key = &line[start + 5..end];
-                        eprintln!("[TencentCOS] Processing key: {}", key);
-                    }
-                }
-            }
-        }
+        let objects: Vec<_> = self.list_all(&prefix).await
+            .map_err(|e| anyhow::anyhow!("Failed to get storage info from Tencent COS: {}", e))?
+            .into_iter()
+            .filter(|o| version_info::counts_towards_usage(&o.info.key))
+            .collect();
+        let used_bytes = objects.iter().map(|o| o.info.size_bytes).sum();
+        let file_count = objects.len() as u32;
         
         eprintln!("[TencentCOS] Storage info result: {} bytes, {} files", used_bytes, file_count);
         
@@ -853,227 +895,343 @@ impl CloudBackend for TencentCOSBackend {
     }
     
     async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
-        // Use COS LIST Objects API to get entire bucket storage info
-        let url = format!("https://{}.cos.{}.myqcloud.com/", self.bucket, self.region);
-        
-        eprintln!("[TencentCOS] Getting bucket storage info, URL: {}", url);
+        let objects = self.list_all("").await
+            .map_err(|e| anyhow::anyhow!("Failed to get bucket info from Tencent COS: {}", e))?;
+        let total_bytes = objects.iter().map(|o| o.info.size_bytes).sum();
+        let total_objects = objects.len() as u32;
         
-        // Generate authorization header for GET request
-        let (authorization, _) = self.generate_cos_authorization("GET", "", "", 0)?;
+        eprintln!("[TencentCOS] Bucket storage info result: {} bytes, {} objects", total_bytes, total_objects);
         
-        let response = self.client
-            .get(&url)
-            .header("Authorization", authorization)
-            .header("Host", format!("{}.cos.{}.myqcloud.com", self.bucket, self.region))
+        Ok((total_bytes, total_objects))
+    }
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        self.upload_sessions = store;
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        let response = self.signed_request(reqwest::Method::PUT, key, &[])?
+            .header("Content-Type", "application/octet-stream")
+            .header("Content-Length", data.len())
+            .body(throttle::upload_body(data))
             .send()
             .await?;
 
-        eprintln!("[TencentCOS] Bucket info response status: {}", response.status());
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to upload {} to Tencent COS: {} - {}", key, status, body));
+        }
+        Ok(())
+    }
 
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        let response = self.signed_request(reqwest::Method::GET, key, &[])?.send().await?;
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            eprintln!("[TencentCOS] Bucket error response body: {}", body);
-            return Err(anyhow::anyhow!("Failed to get bucket info from Tencent COS: {} - {}", status, body));
+            return Err(anyhow::anyhow!("Failed to download {} from Tencent COS: {} - {}", key, status, body));
         }
+        throttle::read_response(response).await
+    }
 
-        let body = response.text().await?;
-        let mut total_bytes = 0u64;
-        let mut total_objects = 0u32;
-        
-        // Debug: Print the complete bucket XML response
-        eprintln!("[TencentCOS] Complete bucket response body:\n{}", body);
-        eprintln!("[TencentCOS] Bucket response length: {} bytes", body.len());
-        
-        // Parse XML response to calculate total storage usage
-        let mut in_contents = false;
-        let mut current_size: Option<u64> = None;
-        
-        eprintln!("[TencentCOS] Starting bucket XML parsing...");
-        let mut bucket_contents_found = 0;
-        
-        for (line_num, line) in body.lines().enumerate() {
-            let line = line.trim();
-            
-            if line.contains("<Contents>") {
-                in_contents = true;
-                current_size = None;
-                bucket_contents_found += 1;
-                eprintln!("[TencentCOS] Found bucket <Contents> #{} at line {}", bucket_contents_found, line_num);
-            } else if line.contains("</Contents>") {
-                if let Some(size) = current_size {
-                    total_bytes += size;
-                    total_objects += 1;
-                    eprintln!("[TencentCOS] Processed bucket file: {} bytes (total: {} bytes, {} objects)", size, total_bytes, total_objects);
-                } else {
-                    eprintln!("[TencentCOS] Warning: bucket </Contents> without size at line {}", line_num);
-                }
-                in_contents = false;
-            } else if in_contents && line.contains("<Size>") {
-                if let Some(start) = line.find("<Size>") {
-                    if let Some(end) = line.find("</Size>") {
-                        let size_str = &line[start + 6..end];
-                        match size_str.parse::<u64>() {
-                            Ok(size) => {
-                                current_size = Some(size);
-                                eprintln!("[TencentCOS] Parsed bucket size: {} bytes", size);
-                            },
-                            Err(e) => {
-                                eprintln!("[TencentCOS] Failed to parse bucket size '{}': {}", size_str, e);
-                            }
-                        }
-                    }
-                }
-            } else if in_contents && line.contains("<Key>") && line.contains("</Key>") {
-                if let Some(start) = line.find("<Key>") {
-                    if let Some(end) = line.find("</Key>") {
-                        let key = &line[start + 5..end];
-                        eprintln!("[TencentCOS] Processing bucket key: {}", key);
-                    }
-                }
-            }
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        let response = self.signed_request(reqwest::Method::GET, key, &[])?.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to download {} from Tencent COS: {} - {}", key, status, body));
+        }
+        SaveArchive::receive_response(response).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        if archive.size() > multipart::MIN_PART_SIZE {
+            let scope = format!("cos:{}:{}:{}", self.bucket, self.region, key);
+            multipart::run_multipart_upload(self, self.upload_sessions.as_ref(), &scope, key, archive.checksum(), archive).await?;
+            return Ok(());
+        }
+        self.upload_to_cos(key, archive).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        let response = self.signed_request(reqwest::Method::HEAD, key, &[])?.send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status => Err(anyhow::anyhow!("Failed to check {} on Tencent COS: {}", key, status)),
+        }
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        Ok(self.list_all(prefix).await?.into_iter().map(|object| object.info).collect())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        let response = self.signed_request(reqwest::Method::DELETE, key, &[])?.send().await?;
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to delete {} from Tencent COS: {} - {}", key, status, body));
+        }
+        Ok(())
+    }
+}
+
+/// Well-known S3-compatible storage providers, used to pre-fill endpoint and addressing settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum S3Provider {
+    #[default]
+    Aws,
+    MinIO,
+    CloudflareR2,
+    BackblazeB2,
+    Wasabi,
+    Custom,
+}
+
+impl S3Provider {
+    pub const ALL: [S3Provider; 6] = [
+        S3Provider::Aws,
+        S3Provider::MinIO,
+        S3Provider::CloudflareR2,
+        S3Provider::BackblazeB2,
+        S3Provider::Wasabi,
+        S3Provider::Custom,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            S3Provider::Aws => "Amazon S3",
+            S3Provider::MinIO => "MinIO",
+            S3Provider::CloudflareR2 => "Cloudflare R2",
+            S3Provider::BackblazeB2 => "Backblaze B2",
+            S3Provider::Wasabi => "Wasabi",
+            S3Provider::Custom => "Custom",
+        }
+    }
+
+    pub fn default_region(&self) -> &'static str {
+        match self {
+            S3Provider::CloudflareR2 => "auto",
+            S3Provider::BackblazeB2 => "us-west-004",
+            _ => "us-east-1",
+        }
+    }
+
+    /// Endpoint URL for a region. `None` means the AWS default resolver is used.
+    /// R2 endpoints contain the account id, so the returned URL is a template to complete.
+    pub fn endpoint_url(&self, region: &str) -> Option<String> {
+        match self {
+            S3Provider::Aws => None,
+            S3Provider::MinIO => Some("http://localhost:9000".to_string()),
+            S3Provider::CloudflareR2 => Some("https://<account_id>.r2.cloudflarestorage.com".to_string()),
+            S3Provider::BackblazeB2 => Some(format!("https://s3.{}.backblazeb2.com", region)),
+            S3Provider::Wasabi => Some(format!("https://s3.{}.wasabisys.com", region)),
+            S3Provider::Custom => Some(String::new()),
+        }
+    }
+
+    /// MinIO and most self-hosted servers don't support virtual-hosted bucket names
+    pub fn force_path_style(&self) -> bool {
+        matches!(self, S3Provider::MinIO | S3Provider::CloudflareR2 | S3Provider::Custom)
+    }
+}
+
+/// Explicit connection settings for [`S3Backend`]
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub bucket: String,
+    pub prefix: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// Custom endpoint for S3-compatible services, `None` for AWS
+    pub endpoint_url: Option<String>,
+    pub force_path_style: bool,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            bucket: "steam-cloud-sync".to_string(),
+            prefix: "saves/".to_string(),
+            region: "us-east-1".to_string(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            endpoint_url: None,
+            force_path_style: false,
         }
-        
-        eprintln!("[TencentCOS] Bucket storage info result: {} bytes, {} objects", total_bytes, total_objects);
-        
-        Ok((total_bytes, total_objects))
     }
 }
 
 pub struct S3Backend {
-    config: Option<aws_config::SdkConfig>,
+    config: S3Config,
     bucket: String,
     prefix: String,
+    upload_sessions: Arc<dyn UploadSessionStore>,
 }
 
 impl S3Backend {
     pub fn new() -> Self {
-        Self {
-            config: None,
-            bucket: "steam-cloud-sync".to_string(),
-            prefix: "saves/".to_string(),
-        }
+        Self::with_s3_config(S3Config::default())
     }
 
     pub fn with_config(bucket: String, prefix: String) -> Self {
-        Self {
-            config: None,
+        Self::with_s3_config(S3Config {
             bucket,
             prefix,
+            ..S3Config::default()
+        })
+    }
+
+    pub fn with_s3_config(config: S3Config) -> Self {
+        Self {
+            bucket: config.bucket.clone(),
+            prefix: config.prefix.clone(),
+            config,
+            upload_sessions: Arc::new(MemoryUploadSessionStore::new()),
         }
     }
 
-    async fn get_client(&self) -> Result<aws_sdk_s3::Client> {
-        let config = match &self.config {
-            Some(cfg) => cfg.clone(),
-            None => aws_config::defaults(aws_config::BehaviorVersion::latest()).load().await,
+    /// One page of the objects below `prefix`, continuing from `token`
+    async fn list_page(&self, client: &aws_sdk_s3::Client, prefix: &str, token: Option<String>) -> Result<ListPage> {
+        let response = client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .max_keys(listing::PAGE_SIZE)
+            .set_continuation_token(token)
+            .send()
+            .await?;
+
+        let objects = response.contents().iter()
+            .filter_map(|object| Some(ListedObject {
+                info: ObjectInfo {
+                    key: object.key()?.to_string(),
+                    size_bytes: object.size().unwrap_or(0) as u64,
+                    last_modified: object.last_modified()
+                        .and_then(|m| chrono::DateTime::from_timestamp(m.secs(), m.subsec_nanos())),
+                },
+                etag: object.e_tag().map(listing::clean_etag),
+            }))
+            .collect();
+
+        let next = match response.is_truncated() {
+            Some(true) => match response.next_continuation_token() {
+                Some(token) => Some(token.to_string()),
+                None => return Err(anyhow::anyhow!("Truncated S3 listing without a continuation token")),
+            },
+            _ => None,
         };
-        Ok(aws_sdk_s3::Client::new(&config))
-    }
-
-    async fn compress_save(&self, save_path: &Path) -> Result<Vec<u8>> {
-        let save_path = save_path.to_path_buf();
-        
-        tokio::task::spawn_blocking(move || {
-            let mut buffer = Vec::new();
-            {
-                let cursor = Cursor::new(&mut buffer);
-                let mut zip = ZipWriter::new(cursor);
-                
-                if save_path.is_file() {
-                    let file_name = save_path.file_name().unwrap().to_str().unwrap();
-                    let file_data = std::fs::read(&save_path)?;
-                    zip.start_file(file_name, zip::write::FileOptions::default())?;
-                    zip.write_all(&file_data)?;
-                } else if save_path.is_dir() {
-                    Self::add_dir_to_zip_sync(&mut zip, &save_path, "")?;
-                }
-                
-                zip.finish()?;
-            }
-            Ok::<Vec<u8>, anyhow::Error>(buffer)
-        }).await?
+        Ok(ListPage { objects, next })
     }
 
-    fn add_dir_to_zip_sync<W: Write + std::io::Seek>(zip: &mut ZipWriter<W>, dir: &Path, prefix: &str) -> Result<()> {
-        let entries = std::fs::read_dir(dir)?;
-        
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
-            let name = entry.file_name();
-            let file_name = format!("{}{}", prefix, name.to_str().unwrap());
+    /// Every object below `prefix`, following continuation tokens across pages
+    async fn list_all(&self, prefix: &str) -> Result<Vec<ListedObject>> {
+        let client = self.get_client().await?;
+        listing::paginate(|token| self.list_page(&client, prefix, token)).try_collect().await
+    }
 
-            if path.is_file() {
-                let file_data = std::fs::read(&path)?;
-                zip.start_file(&file_name, zip::write::FileOptions::default())?;
-                zip.write_all(&file_data)?;
-            } else if path.is_dir() {
-                Self::add_dir_to_zip_sync(zip, &path, &format!("{}/", file_name))?;
+    /// Build a client from the configured settings. Without static credentials the
+    /// standard AWS environment/profile chain is used, as before.
+    async fn get_client(&self) -> Result<aws_sdk_s3::Client> {
+        use aws_sdk_s3::config::{
+            Credentials, Region, RequestChecksumCalculation, ResponseChecksumValidation,
+        };
+
+        let region = Region::new(self.config.region.clone());
+        let mut builder = if !self.config.access_key.is_empty() && !self.config.secret_key.is_empty() {
+            aws_sdk_s3::config::Builder::new()
+                .behavior_version(aws_config::BehaviorVersion::latest())
+                .credentials_provider(Credentials::new(
+                    self.config.access_key.clone(),
+                    self.config.secret_key.clone(),
+                    None,
+                    None,
+                    "steam-cloud-sync-settings",
+                ))
+        } else {
+            let sdk_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+                .region(region.clone())
+                .load()
+                .await;
+            aws_sdk_s3::config::Builder::from(&sdk_config)
+        };
+
+        builder = builder
+            .region(region)
+            .force_path_style(self.config.force_path_style);
+
+        if let Some(endpoint) = self.config.endpoint_url.as_deref().filter(|e| !e.is_empty()) {
+            if endpoint.contains('<') {
+                return Err(anyhow::anyhow!("S3 endpoint URL is incomplete: {}", endpoint));
             }
+            // Many S3-compatible services reject the default CRC32 checksum headers
+            builder = builder
+                .endpoint_url(endpoint)
+                .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+                .response_checksum_validation(ResponseChecksumValidation::WhenRequired);
         }
-        Ok(())
+
+        Ok(aws_sdk_s3::Client::from_conf(builder.build()))
     }
 
-    fn calculate_sha256(data: &[u8]) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(data);
-        format!("{:x}", hasher.finalize())
+    async fn compress_save(&self, save_path: &Path) -> Result<SaveArchive> {
+        SaveArchive::create(save_path).await
     }
+
 }
 
 #[async_trait]
-impl CloudBackend for S3Backend {
-    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+impl multipart::MultipartApi for S3Backend {
+    async fn initiate_upload(&self, object_key: &str, checksum: &str) -> Result<String> {
         let client = self.get_client().await?;
-        let compressed_data = self.compress_save(&game_save.save_path).await?;
-        let checksum = Self::calculate_sha256(&compressed_data);
-        
-        // Create filename with user ID and timestamp for separation
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-        let sanitized_user_id = user_id.chars()
-            .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
-            .collect::<String>();
-        let key = format!("{}{}/{}/{}_{}_{}.zip", self.prefix, sanitized_user_id, game_save.app_id, game_save.name, timestamp, Uuid::new_v4());
-
-        // Multipart upload for large files with resumability
-        let multipart_upload = client
+        let output = client
             .create_multipart_upload()
             .bucket(&self.bucket)
-            .key(&key)
+            .key(object_key)
+            .metadata("sha256", checksum)
             .send()
             .await?;
 
-        let upload_id = multipart_upload.upload_id().unwrap();
-        const CHUNK_SIZE: usize = 5 * 1024 * 1024; // 5MB minimum for S3 multipart
-        let mut parts = Vec::new();
+        output.upload_id()
+            .map(|id| id.to_string())
+            .ok_or_else(|| anyhow::anyhow!("S3 returned no upload id for {}", object_key))
+    }
 
-        for (i, chunk) in compressed_data.chunks(CHUNK_SIZE).enumerate() {
-            let part_number = (i + 1) as i32;
-            
-            let upload_part = client
-                .upload_part()
-                .bucket(&self.bucket)
-                .key(&key)
-                .upload_id(upload_id)
-                .part_number(part_number)
-                .body(aws_sdk_s3::primitives::ByteStream::from(chunk.to_vec()))
-                .send()
-                .await?;
+    async fn upload_part(&self, object_key: &str, upload_id: &str, part_number: u32, data: Bytes) -> Result<String> {
+        let client = self.get_client().await?;
+        throttle::before_upload(data.len()).await;
+        let output = client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(object_key)
+            .upload_id(upload_id)
+            .part_number(part_number as i32)
+            .body(aws_sdk_s3::primitives::ByteStream::from(data))
+            .send()
+            .await?;
 
-            parts.push(
+        Ok(output.e_tag().unwrap_or_default().to_string())
+    }
+
+    async fn complete_upload(&self, session: &UploadSession) -> Result<()> {
+        let client = self.get_client().await?;
+        let parts = session.sorted_parts().into_iter()
+            .map(|part| {
                 aws_sdk_s3::types::CompletedPart::builder()
-                    .part_number(part_number)
-                    .e_tag(upload_part.e_tag().unwrap_or_default())
+                    .part_number(part.part_number as i32)
+                    .e_tag(part.etag)
                     .build()
-            );
-        }
+            })
+            .collect();
 
         client
             .complete_multipart_upload()
             .bucket(&self.bucket)
-            .key(&key)
-            .upload_id(upload_id)
+            .key(&session.object_key)
+            .upload_id(&session.upload_id)
             .multipart_upload(
                 aws_sdk_s3::types::CompletedMultipartUpload::builder()
                     .set_parts(Some(parts))
@@ -1081,14 +1239,56 @@ impl CloudBackend for S3Backend {
             )
             .send()
             .await?;
+        Ok(())
+    }
+
+    async fn abort_upload(&self, object_key: &str, upload_id: &str) -> Result<()> {
+        let client = self.get_client().await?;
+        client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(object_key)
+            .upload_id(upload_id)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    fn is_unknown_upload(&self, error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| format!("{:?}", cause).contains("NoSuchUpload"))
+    }
+}
+
+#[async_trait]
+impl CloudBackend for S3Backend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let archive = self.compress_save(&game_save.save_path).await?;
+        let checksum = archive.checksum().to_string();
+        
+        // Create filename with user ID and timestamp for separation
+        let sanitized_user_id = user_id.chars()
+            .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+            .collect::<String>();
+        let key = ObjectKey::new(&sanitized_user_id, game_save.app_id, ".zip").with_prefix(&self.prefix).to_string();
+        let size_bytes = archive.size();
+
+        // Multipart upload, resumed from the session store when the same archive was interrupted before
+        let scope = format!("s3:{}:{}:{}:{}", self.config.endpoint_url.as_deref().unwrap_or("aws"), self.bucket, sanitized_user_id, game_save.app_id);
+        let key = multipart::run_multipart_upload(self, self.upload_sessions.as_ref(), &scope, &key, &checksum, &archive).await?;
+
+        // Multipart ETags aren't content hashes, so record the real checksum next to the archive
+        let info = VersionInfo::describe(game_save, &checksum, version_info::FORMAT_ZIP).await;
+        version_info::store_version_info(self, &key, &info).await;
 
         Ok(SaveMetadata {
             game_id: game_save.app_id.to_string(),
             timestamp: chrono::Utc::now().to_rfc3339(),
-            size_bytes: compressed_data.len() as u64,
+            size_bytes,
             checksum,
             compressed: true,
+            encrypted: false,
             file_id: key,
+            info: Some(info),
         })
     }
 
@@ -1102,102 +1302,94 @@ impl CloudBackend for S3Backend {
             .send()
             .await?;
 
-        let data = response.body.collect().await?.into_bytes();
+        let archive = SaveArchive::receive(response.body.into_async_read()).await?;
         
         // Verify checksum with improved handling for S3
-        let calculated_checksum = Self::calculate_sha256(&data);
+        let calculated_checksum = archive.checksum();
+        let expected_checksum = version_info::expected_checksum(self, metadata).await;
         
         // Handle different checksum types more gracefully
-        match metadata.checksum.len() {
+        match expected_checksum.len() {
             64 => {
                 // This should be a SHA256 hash
-                if calculated_checksum != metadata.checksum {
-                    eprintln!("⚠️  [S3] SHA256 checksum mismatch:");
-                    eprintln!("   Expected: {}", metadata.checksum);
-                    eprintln!("   Calculated: {}", calculated_checksum);
-                    eprintln!("   File size: {} bytes", data.len());
-                    eprintln!("   Continuing download (integrity warning)...");
+                if calculated_checksum != expected_checksum {
+                    // Never restore a damaged or tampered archive over the local save
+                    return Err(anyhow::anyhow!(
+                        "Checksum mismatch for {}: expected {}, got {}",
+                        metadata.file_id, expected_checksum, calculated_checksum
+                    ));
                 } else {
                     eprintln!("✅ [S3] SHA256 checksum verified");
                 }
             },
             32 => {
                 // This is likely an MD5/ETag, skip SHA256 verification
-                eprintln!("ℹ️  [S3] MD5/ETag checksum detected ({})", metadata.checksum);
+                eprintln!("ℹ️  [S3] MD5/ETag checksum detected ({})", expected_checksum);
                 eprintln!("   File SHA256: {}", calculated_checksum);
                 eprintln!("   Skipping verification (different hash types)");
             },
             40 => {
                 // This might be SHA1
-                eprintln!("ℹ️  [S3] SHA1 checksum detected ({})", metadata.checksum);
+                eprintln!("ℹ️  [S3] SHA1 checksum detected ({})", expected_checksum);
                 eprintln!("   File SHA256: {}", calculated_checksum);
                 eprintln!("   Skipping verification (different hash types)");
             },
             _ => {
                 // Unknown format, log and continue
                 eprintln!("⚠️  [S3] Unknown checksum format:");
-                eprintln!("   Stored: '{}' ({} chars)", metadata.checksum, metadata.checksum.len());
+                eprintln!("   Stored: '{}' ({} chars)", expected_checksum, expected_checksum.len());
                 eprintln!("   File SHA256: {}", calculated_checksum);
                 eprintln!("   Continuing download (no verification possible)...");
             }
         }
 
-        // If the target path ends with .zip, save as ZIP file directly (for custom download location)
-        if local_path.extension().and_then(|s| s.to_str()) == Some("zip") {
-            fs::write(local_path, &data).await?;
-        } else {
-            // Otherwise, extract the ZIP to the game save directory
-            extract_save_archive_helper(&data, local_path).await?;
-        }
+        archive.restore_to(local_path).await?;
         
         Ok(())
     }
 
     async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
-        let client = self.get_client().await?;
-        
         // Sanitize user ID for path safety
         let sanitized_user_id = user_id.chars()
             .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
             .collect::<String>();
         
-        // Construct prefix based on user ID and optionally game ID
-        let prefix = match game_id {
-            Some(gid) => format!("{}{}/{}/", self.prefix, sanitized_user_id, gid),
-            None => format!("{}{}/", self.prefix, sanitized_user_id),
-        };
+        // Older versions sit in per-game folders, newer ones directly below the user
+        let prefix = format!("{}{}/", self.prefix, sanitized_user_id);
         
-        let response = client
-            .list_objects_v2()
-            .bucket(&self.bucket)
-            .prefix(&prefix)
-            .send()
-            .await?;
-
         let mut saves = Vec::new();
         
-        for object in response.contents() {
-            if let (Some(key), Some(size), Some(modified)) = (object.key(), object.size(), object.last_modified()) {
-                // Extract game ID from the key path
-                // Format: saves/user_id/app_id/game_name_timestamp_uuid.zip
-                let key_parts: Vec<&str> = key.split('/').collect();
-                let extracted_game_id = if key_parts.len() >= 4 {
-                    key_parts[2].to_string() // app_id part
-                } else {
-                    "unknown".to_string()
-                };
-                
-                saves.push(SaveMetadata {
-                    game_id: extracted_game_id,
-                    timestamp: modified.to_string(),
-                    size_bytes: size as u64,
-                    checksum: object.e_tag().unwrap_or_default().trim_matches('"').to_string(),
-                    compressed: true,
-                    file_id: key.to_string(),
-                });
+        for object in self.list_all(&prefix).await? {
+            let key = object.info.key;
+            // Only archives; other objects (e.g. chunked-save manifests) are listed elsewhere
+            if !key.ends_with(".zip") {
+                continue;
+            }
+            
+            let extracted_game_id = ObjectKey::parse_with_prefix(&key, &self.prefix)
+                .map(|parsed| parsed.app_id.to_string())
+                .unwrap_or_else(|| object_key::UNKNOWN_GAME.to_string());
+            if game_id.is_some_and(|gid| extracted_game_id != gid) {
+                continue;
             }
+            
+            saves.push(SaveMetadata {
+                game_id: extracted_game_id,
+                timestamp: object.info.last_modified
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                size_bytes: object.info.size_bytes,
+                checksum: object.etag.unwrap_or_default(),
+                compressed: true,
+                encrypted: false,
+                file_id: key,
+                info: None,
+            });
         }
         
+        // Replace ETags with the SHA256 recorded at upload, where there is one
+        version_info::attach_version_info(self, &mut saves).await;
+        
         // Sort by timestamp (newest first)
         saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
         
@@ -1214,19 +1406,15 @@ impl CloudBackend for S3Backend {
             .send()
             .await?;
 
+        if let Err(e) = self.delete_object(&VersionInfo::sidecar_key(&metadata.file_id)).await {
+            eprintln!("⚠️  [S3] Failed to delete version info of {}: {}", metadata.file_id, e);
+        }
+
         Ok(())
     }
 
-    async fn resume_upload(&self, _upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
-        // For S3, resume would involve continuing a multipart upload
-        // This is a simplified implementation
-        let checksum = Self::calculate_sha256(&data);
-        
-        Ok(UploadProgress {
-            bytes_uploaded: offset + data.len() as u64,
-            total_bytes: offset + data.len() as u64,
-            checksum,
-        })
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        multipart::resume_session_part(self, self.upload_sessions.as_ref(), upload_id, offset, data).await
     }
 
     async fn test_connection(&self) -> Result<()> {
@@ -1248,8 +1436,6 @@ impl CloudBackend for S3Backend {
     }
     
     async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
-        let client = self.get_client().await?;
-        
         // Sanitize user ID for path safety
         let sanitized_user_id = user_id.chars()
             .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
@@ -1258,77 +1444,147 @@ impl CloudBackend for S3Backend {
         let prefix = format!("{}{}/", self.prefix, sanitized_user_id);
         
         // List objects with the user's prefix to calculate storage usage
-        let mut used_bytes = 0u64;
-        let mut file_count = 0u32;
-        
-        let list_objects_output = client
-            .list_objects_v2()
-            .bucket(&self.bucket)
-            .prefix(&prefix)
-            .send()
-            .await?;
-        
-        if let Some(contents) = list_objects_output.contents {
-            for object in contents {
-                if let Some(size) = object.size {
-                    used_bytes += size as u64;
-                    file_count += 1;
-                }
-            }
-        }
+        let objects: Vec<_> = self.list_all(&prefix).await?
+            .into_iter()
+            .filter(|o| version_info::counts_towards_usage(&o.info.key))
+            .collect();
         
         Ok(StorageInfo {
-            used_bytes,
+            used_bytes: objects.iter().map(|o| o.info.size_bytes).sum(),
             total_bytes: None, // S3 doesn't have fixed quota limits by default
-            file_count,
+            file_count: objects.len() as u32,
             bucket_used_bytes: None, // Will be filled by combined call
             bucket_total_objects: None, // Will be filled by combined call
         })
     }
     
     async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
-        let client = self.get_client().await?;
-        
         // List all objects in the bucket to calculate total storage usage
-        let mut total_bytes = 0u64;
-        let mut total_objects = 0u32;
-        let mut continuation_token: Option<String> = None;
-        
-        loop {
-            let mut request = client
-                .list_objects_v2()
-                .bucket(&self.bucket);
-            
-            if let Some(token) = &continuation_token {
-                request = request.continuation_token(token);
-            }
-            
-            let list_objects_output = request.send().await?;
-            
-            if let Some(contents) = list_objects_output.contents {
-                for object in contents {
-                    if let Some(size) = object.size {
-                        total_bytes += size as u64;
-                        total_objects += 1;
-                    }
-                }
-            }
-            
-            // Check if there are more objects to list
-            if list_objects_output.is_truncated.unwrap_or(false) {
-                continuation_token = list_objects_output.next_continuation_token;
-            } else {
-                break;
-            }
+        let objects = self.list_all("").await?;
+        Ok((objects.iter().map(|o| o.info.size_bytes).sum(), objects.len() as u32))
+    }
+
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        self.upload_sessions = store;
+    }
+
+    fn save_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        let client = self.get_client().await?;
+        throttle::before_upload(data.len()).await;
+        client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(aws_sdk_s3::primitives::ByteStream::from(data))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        let client = self.get_client().await?;
+        let response = client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        throttle::read_download(response.body.into_async_read()).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        let client = self.get_client().await?;
+        let response = client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        SaveArchive::receive(response.body.into_async_read()).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        if archive.size() <= multipart::MIN_PART_SIZE {
+            return self.put_object(key, archive.to_bytes().await?).await;
+        }
+        let scope = format!("s3:{}:{}:{}", self.config.endpoint_url.as_deref().unwrap_or("aws"), self.bucket, key);
+        multipart::run_multipart_upload(self, self.upload_sessions.as_ref(), &scope, key, archive.checksum(), archive).await?;
+        Ok(())
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        let client = self.get_client().await?;
+        match client.head_object().bucket(&self.bucket).key(key).send().await {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        let client = self.get_client().await?;
+        let response = match client.get_object().bucket(&self.bucket).key(key).send().await {
+            Ok(response) => response,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let etag = response.e_tag().map(str::to_string);
+        let data = throttle::read_download(response.body.into_async_read()).await?;
+        // Providers without ETags fall back to comparing content
+        let tag = etag.unwrap_or_else(|| content_tag(&data));
+        Ok(Some((data, tag)))
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        if expected_tag.is_some_and(|tag| tag.starts_with("sha256:")) {
+            return put_object_if_by_content(self, key, data, expected_tag).await;
+        }
+
+        let client = self.get_client().await?;
+        throttle::before_upload(data.len()).await;
+        let request = client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(aws_sdk_s3::primitives::ByteStream::from(data));
+        let request = match expected_tag {
+            Some(tag) => request.if_match(tag),
+            None => request.if_none_match("*"),
+        };
+
+        match request.send().await {
+            Ok(_) => Ok(true),
+            // 412: the precondition failed; 409: a concurrent conditional write won
+            Err(e) if e.raw_response().is_some_and(|r| matches!(r.status().as_u16(), 409 | 412)) => Ok(false),
+            Err(e) => Err(e.into()),
         }
-        
-        Ok((total_bytes, total_objects))
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        Ok(self.list_all(prefix).await?.into_iter().map(|object| object.info).collect())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        let client = self.get_client().await?;
+        client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
     use tempfile::TempDir;
     use tokio_test;
     use wiremock::{MockServer, Mock, ResponseTemplate};
@@ -1397,15 +1653,87 @@ mod tests {
         let save_file = temp_dir.path().join("test.save");
         tokio::fs::write(&save_file, b"test save content").await.unwrap();
 
-        let compressed = backend.compress_save(&save_file).await.unwrap();
-        assert!(!compressed.is_empty());
+        let archive = backend.compress_save(&save_file).await.unwrap();
+        assert!(archive.size() > 0);
         
         // Verify it's actually a ZIP file
+        let compressed = archive.to_bytes().await.unwrap();
         let cursor = Cursor::new(&compressed);
         let mut zip = zip::ZipArchive::new(cursor).unwrap();
         assert_eq!(zip.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_s3_download_refuses_mismatched_archive() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/steam-cloud-sync/saves/user-1/105600_a.zip"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"not the uploaded archive".to_vec()))
+            .mount(&mock_server)
+            .await;
+
+        let backend = S3Backend::with_s3_config(S3Config {
+            access_key: "test".to_string(),
+            secret_key: "test".to_string(),
+            endpoint_url: Some(mock_server.uri()),
+            force_path_style: true,
+            ..S3Config::default()
+        });
+        let metadata = SaveMetadata {
+            game_id: "105600".to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            size_bytes: 24,
+            checksum: "0".repeat(64),
+            compressed: true,
+            encrypted: false,
+            file_id: "saves/user-1/105600_a.zip".to_string(),
+            info: None,
+        };
+
+        let temp_dir = TempDir::new().unwrap();
+        let restore_path = temp_dir.path().join("restore");
+        let err = backend.download_save(&metadata, &restore_path).await.unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+        assert!(!restore_path.exists());
+    }
+
+    #[test]
+    fn test_s3_provider_presets() {
+        assert_eq!(S3Provider::Aws.endpoint_url("us-east-1"), None);
+        assert_eq!(
+            S3Provider::Wasabi.endpoint_url("eu-central-1").as_deref(),
+            Some("https://s3.eu-central-1.wasabisys.com")
+        );
+        assert_eq!(
+            S3Provider::BackblazeB2.endpoint_url(S3Provider::BackblazeB2.default_region()).as_deref(),
+            Some("https://s3.us-west-004.backblazeb2.com")
+        );
+        assert!(S3Provider::MinIO.force_path_style());
+        assert!(!S3Provider::Aws.force_path_style());
+    }
+
+    #[tokio::test]
+    async fn test_s3_client_from_settings() {
+        let backend = S3Backend::with_s3_config(S3Config {
+            access_key: "minioadmin".to_string(),
+            secret_key: "minioadmin".to_string(),
+            endpoint_url: S3Provider::MinIO.endpoint_url("us-east-1"),
+            force_path_style: true,
+            ..S3Config::default()
+        });
+        let client = backend.get_client().await.unwrap();
+        assert_eq!(client.config().region().map(|r| r.as_ref()), Some("us-east-1"));
+
+        // An R2 template that still contains the placeholder must not be used
+        let backend = S3Backend::with_s3_config(S3Config {
+            access_key: "key".to_string(),
+            secret_key: "secret".to_string(),
+            endpoint_url: S3Provider::CloudflareR2.endpoint_url("auto"),
+            ..S3Config::default()
+        });
+        assert!(backend.get_client().await.is_err());
+    }
+
     #[test]
     fn test_sha256_calculation() {
         let data = b"test data";
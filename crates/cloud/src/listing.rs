@@ -0,0 +1,170 @@
+use crate::ObjectInfo;
+use anyhow::Result;
+use futures::{Stream, TryStreamExt};
+use serde::Deserialize;
+use std::future::Future;
+
+/// Objects requested per listing call; 1000 is the maximum COS and S3 accept
+pub(crate) const PAGE_SIZE: i32 = 1000;
+
+/// An object from a bucket listing, with its ETag
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ListedObject {
+    pub info: ObjectInfo,
+    pub etag: Option<String>,
+}
+
+/// One page of a bucket listing
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ListPage {
+    pub objects: Vec<ListedObject>,
+    /// Where the next page starts (COS marker, S3 continuation token); `None` on the last page
+    pub next: Option<String>,
+}
+
+/// All objects of a paginated listing. `fetch` is called with the token of the page to
+/// fetch (`None` for the first one) and pages are requested as the stream is consumed.
+pub(crate) fn paginate<F, Fut>(fetch: F) -> impl Stream<Item = Result<ListedObject>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<ListPage>>,
+{
+    // State: the fetch function, the next token, and whether the last page was seen
+    futures::stream::try_unfold((fetch, None::<String>, false), |(mut fetch, token, done)| async move {
+        if done {
+            return Ok(None);
+        }
+
+        let page = fetch(token.clone()).await?;
+        if page.next.is_some() && page.next == token {
+            return Err(anyhow::anyhow!("Object listing did not advance past {:?}", token));
+        }
+        let done = page.next.is_none();
+        Ok(Some((page.objects, (fetch, page.next, done))))
+    })
+    .map_ok(|objects| futures::stream::iter(objects.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+/// Strip the quotes ETags are listed with
+pub(crate) fn clean_etag(etag: &str) -> String {
+    etag.trim().trim_matches('"').trim_matches('\'').to_string()
+}
+
+/// Body of a COS `GET Bucket` (ListObjects v1) response
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResult {
+    #[serde(default)]
+    is_truncated: bool,
+    #[serde(default)]
+    next_marker: Option<String>,
+    #[serde(default)]
+    contents: Vec<ListContents>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListContents {
+    key: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    last_modified: Option<String>,
+    #[serde(rename = "ETag", default)]
+    etag: Option<String>,
+}
+
+/// Parse a COS bucket listing page
+pub(crate) fn parse_list_bucket(xml: &str) -> Result<ListPage> {
+    let result: ListBucketResult = quick_xml::de::from_str(xml)
+        .map_err(|e| anyhow::anyhow!("Invalid bucket listing: {}", e))?;
+
+    let objects: Vec<ListedObject> = result.contents.into_iter()
+        .map(|contents| ListedObject {
+            info: ObjectInfo {
+                key: contents.key,
+                size_bytes: contents.size,
+                last_modified: contents.last_modified.as_deref()
+                    .and_then(|m| chrono::DateTime::parse_from_rfc3339(m).ok())
+                    .map(|m| m.with_timezone(&chrono::Utc)),
+            },
+            etag: contents.etag.as_deref().map(clean_etag),
+        })
+        .collect();
+
+    // NextMarker is only sent when a delimiter was requested; otherwise the last key is the marker
+    let next = match result.is_truncated {
+        true => match result.next_marker.filter(|m| !m.is_empty()).or_else(|| objects.last().map(|o| o.info.key.clone())) {
+            Some(marker) => Some(marker),
+            None => return Err(anyhow::anyhow!("Truncated bucket listing without a marker to continue from")),
+        },
+        false => None,
+    };
+
+    Ok(ListPage { objects, next })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(keys: &[&str], next: Option<&str>) -> ListPage {
+        ListPage {
+            objects: keys.iter()
+                .map(|key| ListedObject {
+                    info: ObjectInfo { key: key.to_string(), size_bytes: 1, last_modified: None },
+                    etag: None,
+                })
+                .collect(),
+            next: next.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_bucket() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+    <Name>saves-1250000000</Name>
+    <Prefix>saves/user/</Prefix>
+    <Marker></Marker>
+    <MaxKeys>1000</MaxKeys>
+    <IsTruncated>true</IsTruncated>
+    <Contents><Key>saves/user/730_20240101_120000_a&amp;b.zip</Key><LastModified>2024-01-01T12:00:00.000Z</LastModified><ETag>&quot;5d41402abc4b2a76b9719d911017c592&quot;</ETag><Size>1024</Size><StorageClass>STANDARD</StorageClass></Contents>
+    <Contents>
+        <Key>saves/user/730_20240102_120000_c.zip</Key>
+        <Size>2048</Size>
+    </Contents>
+</ListBucketResult>"#;
+
+        let page = parse_list_bucket(xml).unwrap();
+        assert_eq!(page.objects.len(), 2);
+        assert_eq!(page.objects[0].info.key, "saves/user/730_20240101_120000_a&b.zip");
+        assert_eq!(page.objects[0].info.size_bytes, 1024);
+        assert!(page.objects[0].info.last_modified.is_some());
+        assert_eq!(page.objects[0].etag.as_deref(), Some("5d41402abc4b2a76b9719d911017c592"));
+        assert_eq!(page.objects[1].info.size_bytes, 2048);
+        assert_eq!(page.next.as_deref(), Some("saves/user/730_20240102_120000_c.zip"));
+
+        let last = parse_list_bucket("<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>").unwrap();
+        assert!(last.objects.is_empty());
+        assert_eq!(last.next, None);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_follows_tokens() {
+        let objects: Vec<ListedObject> = paginate(|token| async move {
+            Ok(match token.as_deref() {
+                None => page(&["a", "b"], Some("b")),
+                Some("b") => page(&["c"], Some("c")),
+                _ => page(&["d"], None),
+            })
+        }).try_collect().await.unwrap();
+
+        let keys: Vec<&str> = objects.iter().map(|o| o.info.key.as_str()).collect();
+        assert_eq!(keys, ["a", "b", "c", "d"]);
+
+        let stuck: Result<Vec<ListedObject>> = paginate(|_| async { Ok(page(&["a"], Some("a"))) }).try_collect().await;
+        assert!(stuck.is_err());
+    }
+}
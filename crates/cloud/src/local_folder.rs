@@ -0,0 +1,595 @@
+use crate::{
+    archive::{hash_file, restore_archive_file}, content_tag,
+    object_key::{self, ObjectKey}, progress::{self, CountingRead, TransferStage}, sanitize_user_id,
+    version_info::{self, VERSION_INFO_SUFFIX}, CloudBackend, ObjectInfo, SaveArchive, SaveMetadata,
+    StorageInfo, UploadProgress, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use steam_cloud_sync_core::GameSave;
+use tokio::fs;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use uuid::Uuid;
+
+/// Extension of the sidecar file that stored the SHA256 of an archive before
+/// version info sidecars (`.meta.json`) replaced it; still read for older uploads
+const CHECKSUM_SUFFIX: &str = ".sha256";
+/// Extension used while an object is being written, renamed away on completion
+const PARTIAL_SUFFIX: &str = ".partial";
+/// Held while a conditional write compares and replaces an object
+const LOCK_SUFFIX: &str = ".lock";
+/// A lock older than this was left behind by a crashed writer
+const STALE_LOCK_SECS: u64 = 30;
+/// Directory (relative to the root) holding in-flight resumable uploads
+const UPLOADS_DIR: &str = ".uploads";
+
+/// Cloud backend storing saves in a plain directory tree, e.g. a NAS share or
+/// an external drive. Objects use the same key layout as Tencent COS, so
+/// `saves/<user>/<app_id>_<timestamp>_<uuid>.zip` ends up as a file at that
+/// relative path below `root`.
+pub struct LocalFolderBackend {
+    root: PathBuf,
+}
+
+/// Removes its lock file when dropped
+struct LockFile(PathBuf);
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+impl Default for LocalFolderBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalFolderBackend {
+    pub fn new() -> Self {
+        let root = dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("steam-cloud-sync")
+            .join("local-backend");
+        Self { root }
+    }
+
+    pub fn with_root(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Map an object key to a path below the root, rejecting keys that would escape it
+    fn object_path(&self, object_key: &str) -> Result<PathBuf> {
+        let mut path = self.root.clone();
+        for part in object_key.trim_start_matches('/').split('/') {
+            if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+                return Err(anyhow::anyhow!("Invalid object key: {}", object_key));
+            }
+            path.push(part);
+        }
+        Ok(path)
+    }
+
+    fn checksum_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(CHECKSUM_SUFFIX);
+        PathBuf::from(name)
+    }
+
+    fn calculate_sha256(data: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Temporary sibling of `path` used while it is being written; creates the parent directory
+    async fn partial_path(path: &Path) -> Result<PathBuf> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(PARTIAL_SUFFIX);
+        Ok(PathBuf::from(partial))
+    }
+
+    /// Write a file atomically: write to a temporary sibling, then rename over the target
+    async fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+        let partial = Self::partial_path(path).await?;
+
+        let mut file = fs::File::create(&partial).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        drop(file);
+
+        fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    /// Take the lock file next to `path`, waiting for other writers and breaking stale locks.
+    /// The lock is released when the returned guard is dropped.
+    async fn lock(path: &Path) -> Result<LockFile> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(LOCK_SUFFIX);
+        let lock_path = PathBuf::from(lock_path);
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&lock_path).await {
+                Ok(_) => return Ok(LockFile(lock_path)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&lock_path).await.ok()
+                        .and_then(|m| m.modified().ok())
+                        .and_then(|m| m.elapsed().ok())
+                        .is_some_and(|age| age.as_secs() > STALE_LOCK_SECS);
+                    if stale {
+                        eprintln!("[LocalFolder] Breaking stale lock {}", lock_path.display());
+                        let _ = fs::remove_file(&lock_path).await;
+                    } else {
+                        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Copy a file into place atomically without reading it into memory. A failed copy
+    /// leaves neither the target nor the temporary sibling behind.
+    async fn copy_atomic(path: &Path, source: &Path) -> Result<()> {
+        let partial = Self::partial_path(path).await?;
+
+        let (source, target) = (source.to_path_buf(), partial.clone());
+        let tracker = progress::current();
+        let copied = tokio::task::spawn_blocking(move || progress::blocking_scope(tracker, || {
+            let mut source = std::fs::File::open(&source)?;
+            progress::start_stage(TransferStage::Uploading, source.metadata()?.len());
+            let mut file = std::fs::File::create(&target)?;
+            std::io::copy(&mut CountingRead::new(&mut source), &mut file)?;
+            file.sync_all()?;
+            Ok::<(), anyhow::Error>(())
+        })).await?;
+
+        let result = match copied {
+            Ok(()) => fs::rename(&partial, path).await.map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            let _ = fs::remove_file(&partial).await;
+        }
+        result
+    }
+
+    /// Recursively collect all files below `dir` as (relative key, metadata).
+    /// Sidecars, partial writes and in-flight uploads are skipped.
+    fn collect_objects(root: &Path, dir: &Path, out: &mut Vec<(String, std::fs::Metadata)>) -> Result<()> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+
+            if metadata.is_dir() {
+                if dir == root && entry.file_name() == UPLOADS_DIR {
+                    continue;
+                }
+                Self::collect_objects(root, &path, out)?;
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.ends_with(CHECKSUM_SUFFIX) || name.ends_with(VERSION_INFO_SUFFIX)
+                || name.ends_with(PARTIAL_SUFFIX) || name.ends_with(LOCK_SUFFIX)
+            {
+                continue;
+            }
+
+            let key = path
+                .strip_prefix(root)?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            out.push((key, metadata));
+        }
+        Ok(())
+    }
+
+    async fn list_files(&self, prefix: &str) -> Result<Vec<(String, std::fs::Metadata)>> {
+        let root = self.root.clone();
+        let prefix = prefix.trim_end_matches('/');
+        let dir = if prefix.is_empty() { root.clone() } else { self.object_path(prefix)? };
+
+        tokio::task::spawn_blocking(move || {
+            let mut objects = Vec::new();
+            Self::collect_objects(&root, &dir, &mut objects)?;
+            Ok::<_, anyhow::Error>(objects)
+        }).await?
+    }
+
+    fn modified_rfc3339(metadata: &std::fs::Metadata) -> String {
+        metadata
+            .modified()
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+            .unwrap_or_else(|_| chrono::Utc::now().to_rfc3339())
+    }
+}
+
+#[async_trait]
+impl CloudBackend for LocalFolderBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let archive = SaveArchive::create(&game_save.save_path).await?;
+        let checksum = archive.checksum().to_string();
+
+        let object_key = ObjectKey::new(&sanitize_user_id(user_id), game_save.app_id, ".zip").to_string();
+        let path = self.object_path(&object_key)?;
+
+        Self::copy_atomic(&path, archive.path()).await?;
+        let info = VersionInfo::describe(game_save, &checksum, version_info::FORMAT_ZIP).await;
+        version_info::store_version_info(self, &object_key, &info).await;
+
+        eprintln!("[LocalFolder] Stored {} ({} bytes)", path.display(), archive.size());
+
+        Ok(SaveMetadata {
+            game_id: game_save.app_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            size_bytes: archive.size(),
+            checksum,
+            compressed: true,
+            encrypted: false,
+            file_id: object_key,
+            info: Some(info),
+        })
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        let path = self.object_path(&metadata.file_id)?;
+        // The archive is already on disk, so hash and extract it in place
+        let hash_path = path.clone();
+        let (_, calculated_checksum) = tokio::task::spawn_blocking(move || hash_file(&hash_path)).await?
+            .map_err(|e| anyhow::anyhow!("Failed to read {} from local folder: {}", metadata.file_id, e))?;
+
+        let expected_checksum = version_info::expected_checksum(self, metadata).await;
+        if expected_checksum.len() == 64 && calculated_checksum != expected_checksum {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                metadata.file_id, expected_checksum, calculated_checksum
+            ));
+        }
+
+        restore_archive_file(&path, local_path).await
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        let prefix = format!("saves/{}/", sanitize_user_id(user_id));
+        let mut saves = Vec::new();
+
+        for (key, file_metadata) in self.list_files(&prefix).await? {
+            if !key.ends_with(".zip") {
+                continue;
+            }
+
+            let info = version_info::load_version_info(self, &key).await;
+            let checksum = match &info {
+                Some(info) => info.sha256.clone(),
+                None => fs::read_to_string(Self::checksum_path(&self.object_path(&key)?)).await
+                    .map(|s| s.trim().to_string())
+                    .unwrap_or_default(),
+            };
+
+            saves.push(SaveMetadata {
+                game_id: object_key::game_id_from_key(&key),
+                timestamp: Self::modified_rfc3339(&file_metadata),
+                size_bytes: file_metadata.len(),
+                checksum,
+                compressed: true,
+                encrypted: false,
+                file_id: key,
+                info,
+            });
+        }
+
+        if let Some(gid) = game_id {
+            saves.retain(|save| save.game_id == gid);
+        }
+
+        saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(saves)
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        let path = self.object_path(&metadata.file_id)?;
+        fs::remove_file(&path).await
+            .map_err(|e| anyhow::anyhow!("Failed to delete {} from local folder: {}", metadata.file_id, e))?;
+
+        // The sidecars are optional (older or hand-copied archives may not have them)
+        let _ = fs::remove_file(Self::checksum_path(&path)).await;
+        let _ = self.delete_object(&VersionInfo::sidecar_key(&metadata.file_id)).await;
+        Ok(())
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        let upload_id = upload_id.chars()
+            .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+            .collect::<String>();
+        if upload_id.is_empty() {
+            return Err(anyhow::anyhow!("Invalid upload id"));
+        }
+
+        let dir = self.root.join(UPLOADS_DIR);
+        fs::create_dir_all(&dir).await?;
+        let path = dir.join(format!("{}{}", upload_id, PARTIAL_SUFFIX));
+
+        let mut file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(&path).await?;
+        let current_len = file.metadata().await?.len();
+        if offset > current_len {
+            return Err(anyhow::anyhow!(
+                "Cannot resume upload {} at offset {}: only {} bytes received so far",
+                upload_id, offset, current_len
+            ));
+        }
+
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        file.write_all(&data).await?;
+        file.set_len(offset + data.len() as u64).await?;
+        file.sync_all().await?;
+
+        Ok(UploadProgress {
+            bytes_uploaded: offset + data.len() as u64,
+            total_bytes: offset + data.len() as u64,
+            checksum: Self::calculate_sha256(&data),
+        })
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        fs::create_dir_all(&self.root).await
+            .map_err(|e| anyhow::anyhow!("Cannot create folder {}: {}", self.root.display(), e))?;
+
+        // Make sure the folder is actually writable (read-only shares are a common mistake)
+        let probe = self.root.join(format!(".probe-{}", Uuid::new_v4()));
+        fs::write(&probe, b"steam-cloud-sync").await
+            .map_err(|e| anyhow::anyhow!("Folder {} is not writable: {}", self.root.display(), e))?;
+        fs::remove_file(&probe).await?;
+
+        Ok(())
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        let prefix = format!("saves/{}/", sanitize_user_id(user_id));
+        let versions: Vec<_> = self.list_files(&prefix).await?
+            .into_iter()
+            .filter(|(key, _)| version_info::counts_towards_usage(key))
+            .collect();
+
+        Ok(StorageInfo {
+            used_bytes: versions.iter().map(|(_, m)| m.len()).sum(),
+            total_bytes: None, // Free space of the underlying volume is not tracked
+            file_count: versions.len() as u32,
+            bucket_used_bytes: None, // Will be filled by combined call
+            bucket_total_objects: None, // Will be filled by combined call
+        })
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        let objects = self.list_files("").await?;
+        Ok((objects.iter().map(|(_, m)| m.len()).sum(), objects.len() as u32))
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        Self::write_atomic(&self.object_path(key)?, &data).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        let data = fs::read(self.object_path(key)?).await
+            .map_err(|e| anyhow::anyhow!("Failed to read {} from local folder: {}", key, e))?;
+        Ok(Bytes::from(data))
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        SaveArchive::copy_from(&self.object_path(key)?).await
+            .map_err(|e| anyhow::anyhow!("Failed to read {} from local folder: {}", key, e))
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        Self::copy_atomic(&self.object_path(key)?, archive.path()).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        Ok(fs::try_exists(self.object_path(key)?).await?)
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        // Walk the deepest directory the prefix names, then match the rest of it
+        let dir = prefix.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        Ok(self.list_files(dir).await?
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, metadata)| ObjectInfo {
+                key,
+                size_bytes: metadata.len(),
+                last_modified: metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from),
+            })
+            .collect())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.object_path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(anyhow::anyhow!("Failed to delete {} from local folder: {}", key, e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        match fs::read(self.object_path(key)?).await {
+            Ok(data) => {
+                let tag = content_tag(&data);
+                Ok(Some((Bytes::from(data), tag)))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("Failed to read {} from local folder: {}", key, e)),
+        }
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        let path = self.object_path(key)?;
+        let _lock = Self::lock(&path).await?;
+
+        let current = match fs::read(&path).await {
+            Ok(current) => Some(content_tag(&current)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        if current.as_deref() != expected_tag {
+            return Ok(false);
+        }
+
+        Self::write_atomic(&path, &data).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn game_save(dir: &Path, app_id: u32) -> GameSave {
+        GameSave {
+            app_id,
+            name: "Test Game".to_string(),
+            save_path: dir.to_path_buf(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_local_folder_roundtrip() {
+        let cloud_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        let restore_dir = TempDir::new().unwrap();
+
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        tokio::fs::create_dir_all(save_dir.path().join("profiles")).await.unwrap();
+        tokio::fs::write(save_dir.path().join("profiles/main.cfg"), b"config").await.unwrap();
+
+        let backend = LocalFolderBackend::with_root(cloud_dir.path().to_path_buf());
+        backend.test_connection().await.unwrap();
+
+        let metadata = backend.upload_save(&game_save(save_dir.path(), 105600), "user-1").await.unwrap();
+        assert!(metadata.file_id.starts_with("saves/user-1/105600_"));
+        assert_eq!(metadata.checksum.len(), 64);
+
+        let listed = backend.list_saves("user-1", Some("105600")).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].file_id, metadata.file_id);
+        assert_eq!(listed[0].checksum, metadata.checksum);
+        assert_eq!(listed[0].game_id, "105600");
+        assert!(backend.list_saves("user-1", Some("730")).await.unwrap().is_empty());
+        assert!(backend.list_saves("user-2", None).await.unwrap().is_empty());
+
+        let target = restore_dir.path().join("restored");
+        backend.download_save(&listed[0], &target).await.unwrap();
+        assert_eq!(tokio::fs::read(target.join("slot1.sav")).await.unwrap(), b"slot one");
+        assert_eq!(tokio::fs::read(target.join("profiles/main.cfg")).await.unwrap(), b"config");
+
+        let info = backend.get_storage_info("user-1").await.unwrap();
+        assert_eq!(info.file_count, 1);
+        assert_eq!(info.used_bytes, metadata.size_bytes);
+
+        backend.delete_save(&listed[0]).await.unwrap();
+        assert!(backend.list_saves("user-1", None).await.unwrap().is_empty());
+        assert_eq!(backend.get_bucket_storage_info().await.unwrap(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_local_folder_rejects_corrupt_archive() {
+        let cloud_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+
+        let backend = LocalFolderBackend::with_root(cloud_dir.path().to_path_buf());
+        let metadata = backend.upload_save(&game_save(save_dir.path(), 730), "user").await.unwrap();
+
+        tokio::fs::write(cloud_dir.path().join(&metadata.file_id), b"not a zip").await.unwrap();
+        let target = save_dir.path().join("restore");
+        assert!(backend.download_save(&metadata, &target).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_version_info_sidecar() {
+        let cloud_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        tokio::fs::write(save_dir.path().join("slot2.sav"), b"slot two").await.unwrap();
+
+        let backend = LocalFolderBackend::with_root(cloud_dir.path().to_path_buf());
+        let metadata = backend.upload_save(&game_save(save_dir.path(), 730), "user").await.unwrap();
+        let sidecar = cloud_dir.path().join(VersionInfo::sidecar_key(&metadata.file_id));
+        assert!(sidecar.exists());
+
+        let listed = backend.list_saves("user", None).await.unwrap();
+        let info = listed[0].info.clone().unwrap();
+        assert_eq!(info.sha256, metadata.checksum);
+        assert_eq!(info.game_name, "Test Game");
+        assert_eq!(info.archive_format, version_info::FORMAT_ZIP);
+        assert_eq!(info.file_count, 2);
+        assert!(info.local_modified_at.is_some());
+        assert_eq!(info.label, None);
+
+        backend.set_version_label(&listed[0], Some("before the boss".to_string())).await.unwrap();
+        let listed = backend.list_saves("user", None).await.unwrap();
+        assert_eq!(listed[0].info.as_ref().unwrap().label.as_deref(), Some("before the boss"));
+        assert_eq!(listed[0].checksum, metadata.checksum);
+
+        // Uploads from before sidecars only have the bare checksum file
+        tokio::fs::remove_file(&sidecar).await.unwrap();
+        let legacy = cloud_dir.path().join(format!("{}{}", metadata.file_id, CHECKSUM_SUFFIX));
+        tokio::fs::write(&legacy, &metadata.checksum).await.unwrap();
+        let listed = backend.list_saves("user", None).await.unwrap();
+        assert_eq!(listed[0].checksum, metadata.checksum);
+        assert!(listed[0].info.is_none());
+
+        backend.delete_save(&listed[0]).await.unwrap();
+        assert!(!legacy.exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_put_archive_leaves_nothing_behind() {
+        let cloud_dir = TempDir::new().unwrap();
+        let backend = LocalFolderBackend::with_root(cloud_dir.path().to_path_buf());
+        let archive = SaveArchive::from_bytes(Bytes::from_static(b"archive")).await.unwrap();
+
+        backend.put_archive("saves/user/a.zip", &archive).await.unwrap();
+        assert_eq!(tokio::fs::read(cloud_dir.path().join("saves/user/a.zip")).await.unwrap(), b"archive");
+
+        // A directory in the way makes the final rename fail
+        tokio::fs::create_dir_all(cloud_dir.path().join("saves/user/b.zip/x")).await.unwrap();
+        assert!(backend.put_archive("saves/user/b.zip", &archive).await.is_err());
+        let partial = cloud_dir.path().join(format!("saves/user/b.zip{}", PARTIAL_SUFFIX));
+        assert!(!partial.exists());
+    }
+
+    #[test]
+    fn test_object_path_rejects_traversal() {
+        let backend = LocalFolderBackend::with_root(PathBuf::from("/srv/saves"));
+        assert!(backend.object_path("saves/user/1_a.zip").is_ok());
+        assert!(backend.object_path("saves/../../etc/passwd").is_err());
+        assert!(backend.object_path("saves//x.zip").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::version_info::{self, FORMAT_ZIP, VERSION_INFO_SUFFIX};
+use crate::{cancel, CloudBackend, ObjectInfo, SaveArchive};
+use anyhow::Result;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Where a running migration records what it has copied, in the target
+pub const MIGRATION_CHECKPOINT_KEY: &str = "migration/checkpoint.json";
+/// Objects copied between checkpoint writes
+const CHECKPOINT_EVERY: usize = 20;
+
+/// How far a migration has got, reported after every object
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationProgress {
+    /// Objects copied or skipped so far
+    pub objects_done: usize,
+    pub objects_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Objects skipped because an earlier run already copied them
+    pub skipped: usize,
+}
+
+/// Outcome of a finished migration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationReport {
+    pub copied: usize,
+    pub skipped: usize,
+    pub bytes_copied: u64,
+}
+
+/// Objects an interrupted migration already copied and verified, by key
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    /// Key -> size as verified in the target
+    migrated: BTreeMap<String, u64>,
+}
+
+/// Copy every object of `source` to `target`: save versions with their sidecars,
+/// manifests, chunks and the save index. Both should be plain storage backends (not
+/// wrapped in encryption or the index), so objects are copied byte for byte under
+/// their keys and versions keep their timestamps, labels and other metadata; encrypted
+/// objects stay encrypted with the same keys.
+///
+/// Version times come from the save index, as listing storage without one dates
+/// versions by modification time, which a copy resets. Make sure the source has an index
+/// first, e.g. by listing it through [`crate::IndexedBackend`].
+///
+/// Objects are copied one at a time through a temporary file and hashed on the way, so
+/// they never have to fit in memory. Plain archives are checked against the checksum in
+/// their sidecar before they are copied, and every copy is checked to have arrived in
+/// full. The migration is resumable:
+/// progress is checkpointed in the target, and running it again skips what was already
+/// copied. The index is copied last, so an interrupted migration never lists versions
+/// the target doesn't have yet.
+pub async fn migrate(
+    source: &dyn CloudBackend,
+    target: &dyn CloudBackend,
+    on_progress: impl Fn(&MigrationProgress),
+) -> Result<MigrationReport> {
+    // Key -> size. Versions live under the save prefix; most backends leave their sidecars
+    // out of listings, so those are looked up (their size isn't known and counts as 0).
+    let mut listed: BTreeMap<String, u64> = BTreeMap::new();
+    for object in source.list_objects("").await? {
+        // Bookkeeping of this migration and of replication to the source's mirrors
+        if object.key.starts_with("migration/") || object.key.starts_with("replication/") {
+            continue;
+        }
+        if object.key.starts_with(source.save_prefix()) {
+            for key in version_info::with_sidecar(source, &object.key).await.into_iter().skip(1) {
+                listed.entry(key).or_insert(0);
+            }
+        }
+        listed.insert(object.key, object.size_bytes);
+    }
+    let mut objects: Vec<ObjectInfo> = listed.into_iter()
+        .map(|(key, size_bytes)| ObjectInfo { key, size_bytes, last_modified: None })
+        .collect();
+    // The index last: it should only list versions that are already there
+    objects.sort_by_key(|object| object.key.starts_with("index/"));
+
+    let mut checkpoint: Checkpoint = match target.object_exists(MIGRATION_CHECKPOINT_KEY).await? {
+        true => serde_json::from_slice(&target.get_object(MIGRATION_CHECKPOINT_KEY).await?).unwrap_or_default(),
+        false => Checkpoint::default(),
+    };
+    let in_target: HashMap<String, u64> = target.list_objects("").await?
+        .into_iter()
+        .map(|object| (object.key, object.size_bytes))
+        .collect();
+
+    let mut progress = MigrationProgress {
+        objects_total: objects.len(),
+        bytes_total: objects.iter().map(|object| object.size_bytes).sum(),
+        ..MigrationProgress::default()
+    };
+    let mut report = MigrationReport::default();
+    on_progress(&progress);
+
+    let copied = async {
+        for object in &objects {
+            cancel::check()?;
+            if is_migrated(&checkpoint, &in_target, object) {
+                report.skipped += 1;
+                progress.skipped += 1;
+            } else {
+                let archive = source.get_archive(&object.key).await?;
+                verify_against_sidecar(source, &object.key, &archive).await?;
+                copy_verified(target, &object.key, &archive).await?;
+                checkpoint.migrated.insert(object.key.clone(), archive.size());
+                report.copied += 1;
+                report.bytes_copied += archive.size();
+                if report.copied % CHECKPOINT_EVERY == 0 {
+                    save_checkpoint(target, &checkpoint).await?;
+                }
+            }
+            progress.objects_done += 1;
+            progress.bytes_done += object.size_bytes;
+            on_progress(&progress);
+        }
+        Ok::<_, anyhow::Error>(())
+    }.await;
+    if let Err(e) = copied {
+        // Keep what was done for the next run
+        if report.copied > 0 {
+            let _ = save_checkpoint(target, &checkpoint).await;
+        }
+        return Err(e);
+    }
+
+    target.delete_object(MIGRATION_CHECKPOINT_KEY).await?;
+    println!(
+        "[Migration] Done: {} objects copied ({} bytes), {} already there",
+        report.copied, report.bytes_copied, report.skipped
+    );
+    Ok(report)
+}
+
+/// Whether an earlier run copied `object` and the target still holds it. Objects
+/// whose size the listing doesn't tell (sidecars) are trusted to the checkpoint.
+fn is_migrated(checkpoint: &Checkpoint, in_target: &HashMap<String, u64>, object: &ObjectInfo) -> bool {
+    match checkpoint.migrated.get(&object.key) {
+        Some(size) if object.key.ends_with(VERSION_INFO_SUFFIX) => in_target.get(&object.key).is_none_or(|s| s == size),
+        Some(size) => *size == object.size_bytes && in_target.get(&object.key) == Some(size),
+        None => false,
+    }
+}
+
+/// Fail if `archive` is a plain archive that doesn't match the checksum in its sidecar
+async fn verify_against_sidecar(source: &dyn CloudBackend, key: &str, archive: &SaveArchive) -> Result<()> {
+    if key.ends_with(VERSION_INFO_SUFFIX) {
+        return Ok(());
+    }
+    // Encrypted sidecars don't parse and are skipped, as are other formats
+    let Some(info) = version_info::load_version_info(source, key).await else {
+        return Ok(());
+    };
+    if info.archive_format != FORMAT_ZIP || info.sha256.is_empty() {
+        return Ok(());
+    }
+    if archive.checksum() != info.sha256 {
+        return Err(anyhow::anyhow!(
+            "Checksum mismatch for {} in the source: expected {}, got {}", key, info.sha256, archive.checksum()
+        ));
+    }
+    Ok(())
+}
+
+/// Upload `archive` to `key` and make sure the target holds all of it. The archive was
+/// hashed as it came from the source, so the copy is checked by its listed size rather
+/// than by downloading it again.
+async fn copy_verified(target: &dyn CloudBackend, key: &str, archive: &SaveArchive) -> Result<()> {
+    target.put_archive(key, archive).await?;
+    let stored = target.list_objects(key).await?.into_iter().find(|object| object.key == key);
+    match stored {
+        Some(object) if object.size_bytes != archive.size() => Err(anyhow::anyhow!(
+            "{} changed while being copied: expected {} bytes, got {}", key, archive.size(), object.size_bytes
+        )),
+        Some(_) => Ok(()),
+        // Most backends leave sidecars out of listings
+        None if target.object_exists(key).await? => Ok(()),
+        None => Err(anyhow::anyhow!("{} is missing from the target after copying it", key)),
+    }
+}
+
+async fn save_checkpoint(target: &dyn CloudBackend, checkpoint: &Checkpoint) -> Result<()> {
+    target.put_object(MIGRATION_CHECKPOINT_KEY, Bytes::from(serde_json::to_vec(checkpoint)?)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IndexedBackend, LocalFolderBackend};
+    use std::path::Path;
+    use steam_cloud_sync_core::GameSave;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_migration_copies_versions_and_resumes() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        let restore_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+
+        let source = LocalFolderBackend::with_root(source_dir.path().to_path_buf());
+        let target = LocalFolderBackend::with_root(target_dir.path().to_path_buf());
+        let indexed = |dir: &TempDir| IndexedBackend::new(Box::new(LocalFolderBackend::with_root(dir.path().to_path_buf())));
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+        let first = indexed(&source_dir).upload_save(&game_save, "user-1").await.unwrap();
+        indexed(&source_dir).set_version_label(&first, Some("before boss".to_string())).await.unwrap();
+        let second = indexed(&source_dir).upload_save(&game_save, "user-1").await.unwrap();
+
+        let reports = std::sync::Mutex::new(Vec::new());
+        let report = migrate(&source, &target, |progress| reports.lock().unwrap().push(progress.clone())).await.unwrap();
+        // Two archives, their sidecars and their index entries
+        assert_eq!((report.copied, report.skipped), (6, 0));
+        let last = reports.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last.objects_done, last.objects_total);
+        assert!(!target.object_exists(MIGRATION_CHECKPOINT_KEY).await.unwrap());
+
+        // Versions keep their timestamps and labels, and restore from the target
+        let listed = indexed(&target_dir).list_saves("user-1", None).await.unwrap();
+        let migrated = listed.iter().find(|save| save.file_id == first.file_id).unwrap();
+        assert_eq!(migrated.timestamp, first.timestamp);
+        assert_eq!(migrated.info.as_ref().unwrap().label.as_deref(), Some("before boss"));
+        target.download_save(&second, restore_dir.path()).await.unwrap();
+        assert_eq!(tokio::fs::read(restore_dir.path().join("slot1.sav")).await.unwrap(), b"slot one");
+
+        // An interrupted run resumes from its checkpoint
+        let checkpoint = Checkpoint {
+            migrated: [(first.file_id.clone(), first.size_bytes)].into_iter().collect(),
+        };
+        save_checkpoint(&target, &checkpoint).await.unwrap();
+        tokio::fs::remove_file(target_dir.path().join(&second.file_id)).await.unwrap();
+        let report = migrate(&source, &target, |_| {}).await.unwrap();
+        assert_eq!((report.copied, report.skipped), (5, 1));
+        assert!(target_dir.path().join(&second.file_id).exists());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_source_archive_is_not_copied() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+
+        let source = LocalFolderBackend::with_root(source_dir.path().to_path_buf());
+        let target = LocalFolderBackend::with_root(target_dir.path().to_path_buf());
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+        let metadata = source.upload_save(&game_save, "user-1").await.unwrap();
+        tokio::fs::write(source_dir.path().join(&metadata.file_id), b"not the archive").await.unwrap();
+
+        let err = migrate(&source, &target, |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+        assert!(!Path::new(&target_dir.path().join(&metadata.file_id)).exists());
+    }
+}
@@ -0,0 +1,445 @@
+use crate::progress::{self, TransferStage};
+use crate::{cancel, SaveArchive, UploadProgress};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Smallest part size used for multipart uploads (S3 requires at least 5 MiB for all but the last part)
+pub const MIN_PART_SIZE: u64 = 8 * 1024 * 1024;
+/// Both COS and S3 allow at most 10,000 parts per upload
+pub const MAX_PARTS: u64 = 10_000;
+
+/// Part size for an object of `total_bytes`, growing beyond [`MIN_PART_SIZE`] only
+/// when the object would otherwise need more than [`MAX_PARTS`] parts
+pub fn part_size_for(total_bytes: u64) -> u64 {
+    MIN_PART_SIZE.max(total_bytes.div_ceil(MAX_PARTS))
+}
+
+/// A part that has been uploaded and acknowledged by the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadedPart {
+    pub part_number: u32,
+    pub etag: String,
+    pub size: u64,
+}
+
+/// State of a multipart upload, kept so an interrupted upload can continue
+/// after a restart instead of starting over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadSession {
+    /// Upload id assigned by the server
+    pub upload_id: String,
+    /// Identifies backend, bucket, user and game, e.g. `cos:bucket:region:user:730`
+    pub scope: String,
+    pub object_key: String,
+    /// SHA256 of the complete archive; a resumed upload must send identical bytes
+    pub checksum: String,
+    pub total_bytes: u64,
+    pub part_size: u64,
+    pub parts: Vec<UploadedPart>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UploadSession {
+    pub fn new(upload_id: String, scope: String, object_key: String, checksum: String, total_bytes: u64) -> Self {
+        Self {
+            upload_id,
+            scope,
+            object_key,
+            checksum,
+            total_bytes,
+            part_size: part_size_for(total_bytes),
+            parts: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn part_count(&self) -> u32 {
+        self.total_bytes.div_ceil(self.part_size).max(1) as u32
+    }
+
+    /// Byte range `(offset, length)` of a 1-based part number
+    pub fn part_range(&self, part_number: u32) -> (u64, u64) {
+        let offset = (part_number as u64 - 1) * self.part_size;
+        (offset, self.part_size.min(self.total_bytes.saturating_sub(offset)))
+    }
+
+    pub fn has_part(&self, part_number: u32) -> bool {
+        self.parts.iter().any(|p| p.part_number == part_number)
+    }
+
+    pub fn bytes_uploaded(&self) -> u64 {
+        self.parts.iter().map(|p| p.size).sum()
+    }
+
+    /// Parts sorted by part number, as required by CompleteMultipartUpload
+    pub fn sorted_parts(&self) -> Vec<UploadedPart> {
+        let mut parts = self.parts.clone();
+        parts.sort_by_key(|p| p.part_number);
+        parts.dedup_by_key(|p| p.part_number);
+        parts
+    }
+}
+
+/// Storage for in-flight multipart uploads. Backends fall back to
+/// [`MemoryUploadSessionStore`]; the app plugs in a SQLite-backed store.
+#[async_trait]
+pub trait UploadSessionStore: Send + Sync {
+    /// Find an unfinished upload of the same archive to the same destination
+    async fn find_session(&self, scope: &str, checksum: &str) -> Result<Option<UploadSession>>;
+    async fn get_session(&self, upload_id: &str) -> Result<Option<UploadSession>>;
+    async fn save_session(&self, session: &UploadSession) -> Result<()>;
+    async fn record_part(&self, upload_id: &str, part: &UploadedPart) -> Result<()>;
+    /// Forget an upload once it completed or was aborted
+    async fn remove_session(&self, upload_id: &str) -> Result<()>;
+}
+
+/// Process-local session store; uploads resume within a run but not across restarts
+#[derive(Default)]
+pub struct MemoryUploadSessionStore {
+    sessions: Mutex<HashMap<String, UploadSession>>,
+}
+
+impl MemoryUploadSessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl UploadSessionStore for MemoryUploadSessionStore {
+    async fn find_session(&self, scope: &str, checksum: &str) -> Result<Option<UploadSession>> {
+        let sessions = self.sessions.lock().unwrap();
+        Ok(sessions.values()
+            .filter(|s| s.scope == scope && s.checksum == checksum)
+            .max_by_key(|s| s.created_at)
+            .cloned())
+    }
+
+    async fn get_session(&self, upload_id: &str) -> Result<Option<UploadSession>> {
+        Ok(self.sessions.lock().unwrap().get(upload_id).cloned())
+    }
+
+    async fn save_session(&self, session: &UploadSession) -> Result<()> {
+        self.sessions.lock().unwrap().insert(session.upload_id.clone(), session.clone());
+        Ok(())
+    }
+
+    async fn record_part(&self, upload_id: &str, part: &UploadedPart) -> Result<()> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(upload_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown upload {}", upload_id))?;
+        session.parts.retain(|p| p.part_number != part.part_number);
+        session.parts.push(part.clone());
+        Ok(())
+    }
+
+    async fn remove_session(&self, upload_id: &str) -> Result<()> {
+        self.sessions.lock().unwrap().remove(upload_id);
+        Ok(())
+    }
+}
+
+/// The calls a multipart-capable backend has to provide
+#[async_trait]
+pub(crate) trait MultipartApi: Send + Sync {
+    /// Start an upload and return the server-assigned upload id
+    async fn initiate_upload(&self, object_key: &str, checksum: &str) -> Result<String>;
+    /// Upload one part and return its ETag
+    async fn upload_part(&self, object_key: &str, upload_id: &str, part_number: u32, data: Bytes) -> Result<String>;
+    async fn complete_upload(&self, session: &UploadSession) -> Result<()>;
+    /// Discard an unfinished upload and the parts stored for it
+    async fn abort_upload(&self, object_key: &str, upload_id: &str) -> Result<()>;
+    /// Whether an error means the server no longer knows the upload id (aborted or expired)
+    fn is_unknown_upload(&self, error: &anyhow::Error) -> bool;
+}
+
+/// Where the bytes of a multipart upload come from; parts are read one at a time
+/// so only a single part is ever held in memory
+#[async_trait]
+pub(crate) trait PartSource: Send + Sync {
+    fn total_bytes(&self) -> u64;
+    async fn read_range(&self, offset: u64, len: u64) -> Result<Bytes>;
+}
+
+#[async_trait]
+impl PartSource for SaveArchive {
+    fn total_bytes(&self) -> u64 {
+        self.size()
+    }
+
+    async fn read_range(&self, offset: u64, len: u64) -> Result<Bytes> {
+        SaveArchive::read_range(self, offset, len).await
+    }
+}
+
+#[async_trait]
+impl PartSource for Bytes {
+    fn total_bytes(&self) -> u64 {
+        self.len() as u64
+    }
+
+    async fn read_range(&self, offset: u64, len: u64) -> Result<Bytes> {
+        Ok(self.slice(offset as usize..(offset + len) as usize))
+    }
+}
+
+/// Upload `data` in parts, continuing a stored session for the same scope and
+/// checksum when there is one. Returns the object key the data ended up under,
+/// which is the key of the resumed session rather than `object_key` in that case.
+/// A cancelled upload is aborted on the server instead of being kept for resuming.
+pub(crate) async fn run_multipart_upload(
+    api: &dyn MultipartApi,
+    store: &dyn UploadSessionStore,
+    scope: &str,
+    object_key: &str,
+    checksum: &str,
+    data: &dyn PartSource,
+) -> Result<String> {
+    if let Some(session) = store.find_session(scope, checksum).await? {
+        if session.total_bytes == data.total_bytes() {
+            eprintln!("[Multipart] Resuming upload {} of {} ({}/{} bytes done)",
+                session.upload_id, session.object_key, session.bytes_uploaded(), session.total_bytes);
+            match upload_remaining_parts(api, store, session.clone(), data).await {
+                Ok(()) => return Ok(session.object_key),
+                Err(e) if cancel::is_cancelled(&e) => return Err(abandon(api, store, &session, e).await),
+                Err(e) if api.is_unknown_upload(&e) => {
+                    eprintln!("[Multipart] Upload {} no longer exists on the server, starting over", session.upload_id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        store.remove_session(&session.upload_id).await?;
+    }
+
+    let upload_id = api.initiate_upload(object_key, checksum).await?;
+    let session = UploadSession::new(upload_id, scope.to_string(), object_key.to_string(), checksum.to_string(), data.total_bytes());
+    store.save_session(&session).await?;
+
+    match upload_remaining_parts(api, store, session.clone(), data).await {
+        Ok(()) => Ok(object_key.to_string()),
+        Err(e) if cancel::is_cancelled(&e) => Err(abandon(api, store, &session, e).await),
+        Err(e) => Err(e),
+    }
+}
+
+/// Abort a cancelled upload and forget its session, passing the cancellation on
+async fn abandon(api: &dyn MultipartApi, store: &dyn UploadSessionStore, session: &UploadSession, error: anyhow::Error) -> anyhow::Error {
+    eprintln!("[Multipart] Upload {} of {} was cancelled, aborting it", session.upload_id, session.object_key);
+    if let Err(e) = api.abort_upload(&session.object_key, &session.upload_id).await {
+        eprintln!("[Multipart] Failed to abort upload {}: {}", session.upload_id, e);
+    }
+    if let Err(e) = store.remove_session(&session.upload_id).await {
+        eprintln!("[Multipart] Failed to forget upload {}: {}", session.upload_id, e);
+    }
+    error
+}
+
+async fn upload_remaining_parts(
+    api: &dyn MultipartApi,
+    store: &dyn UploadSessionStore,
+    mut session: UploadSession,
+    data: &dyn PartSource,
+) -> Result<()> {
+    progress::start_stage(TransferStage::Uploading, session.total_bytes);
+    progress::advance(session.bytes_uploaded());
+
+    for part_number in 1..=session.part_count() {
+        if session.has_part(part_number) {
+            continue;
+        }
+
+        let (offset, len) = session.part_range(part_number);
+        let etag = cancel::cancellable(async {
+            let chunk = data.read_range(offset, len).await?;
+            api.upload_part(&session.object_key, &session.upload_id, part_number, chunk).await
+        }).await?;
+
+        let part = UploadedPart { part_number, etag, size: len };
+        store.record_part(&session.upload_id, &part).await?;
+        session.parts.push(part);
+    }
+
+    api.complete_upload(&session).await?;
+    store.remove_session(&session.upload_id).await?;
+    Ok(())
+}
+
+/// Upload a single part of a stored session, identified by its byte offset.
+/// The upload is completed once its last missing part arrives.
+pub(crate) async fn resume_session_part(
+    api: &dyn MultipartApi,
+    store: &dyn UploadSessionStore,
+    upload_id: &str,
+    offset: u64,
+    data: Bytes,
+) -> Result<UploadProgress> {
+    let mut session = store.get_session(upload_id).await?
+        .ok_or_else(|| anyhow::anyhow!("No resumable upload with id {}", upload_id))?;
+
+    if !offset.is_multiple_of(session.part_size) || offset >= session.total_bytes.max(1) {
+        return Err(anyhow::anyhow!(
+            "Offset {} is not a part boundary of upload {} (part size {})",
+            offset, upload_id, session.part_size
+        ));
+    }
+    let part_number = (offset / session.part_size) as u32 + 1;
+    let (_, expected_len) = session.part_range(part_number);
+    if data.len() as u64 != expected_len {
+        return Err(anyhow::anyhow!(
+            "Part {} of upload {} must be {} bytes, got {}",
+            part_number, upload_id, expected_len, data.len()
+        ));
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(&data);
+    let checksum = format!("{:x}", hasher.finalize());
+
+    if !session.has_part(part_number) {
+        let etag = api.upload_part(&session.object_key, upload_id, part_number, data).await?;
+        let part = UploadedPart { part_number, etag, size: expected_len };
+        store.record_part(upload_id, &part).await?;
+        session.parts.push(part);
+    }
+
+    let progress = UploadProgress {
+        bytes_uploaded: session.bytes_uploaded(),
+        total_bytes: session.total_bytes,
+        checksum,
+    };
+
+    if (1..=session.part_count()).all(|n| session.has_part(n)) {
+        api.complete_upload(&session).await?;
+        store.remove_session(upload_id).await?;
+    }
+
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_layout() {
+        let session = UploadSession::new("id".into(), "scope".into(), "key".into(), "sum".into(), MIN_PART_SIZE * 2 + 10);
+        assert_eq!(session.part_size, MIN_PART_SIZE);
+        assert_eq!(session.part_count(), 3);
+        assert_eq!(session.part_range(3), (MIN_PART_SIZE * 2, 10));
+
+        // 100 GiB would exceed the part limit with 8 MiB parts
+        let huge = 100 * 1024 * 1024 * 1024u64;
+        assert!(huge.div_ceil(part_size_for(huge)) <= MAX_PARTS);
+    }
+
+    /// Fake server that accepts a limited number of parts before "losing the connection"
+    struct FlakyApi {
+        parts_before_failure: Mutex<Option<usize>>,
+        uploaded: Mutex<Vec<u32>>,
+        completed: Mutex<bool>,
+        aborted: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl MultipartApi for FlakyApi {
+        async fn initiate_upload(&self, _object_key: &str, _checksum: &str) -> Result<String> {
+            Ok("upload-1".to_string())
+        }
+
+        async fn upload_part(&self, _object_key: &str, _upload_id: &str, part_number: u32, _data: Bytes) -> Result<String> {
+            let mut remaining = self.parts_before_failure.lock().unwrap();
+            if let Some(0) = *remaining {
+                *remaining = None;
+                return Err(anyhow::anyhow!("connection reset"));
+            }
+            if let Some(n) = remaining.as_mut() {
+                *n -= 1;
+            }
+            self.uploaded.lock().unwrap().push(part_number);
+            Ok(format!("etag-{}", part_number))
+        }
+
+        async fn complete_upload(&self, session: &UploadSession) -> Result<()> {
+            assert_eq!(session.sorted_parts().len() as u32, session.part_count());
+            *self.completed.lock().unwrap() = true;
+            Ok(())
+        }
+
+        async fn abort_upload(&self, _object_key: &str, _upload_id: &str) -> Result<()> {
+            *self.aborted.lock().unwrap() = true;
+            Ok(())
+        }
+
+        fn is_unknown_upload(&self, _error: &anyhow::Error) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_upload_resumes_missing_parts() {
+        let api = FlakyApi {
+            parts_before_failure: Mutex::new(Some(2)),
+            uploaded: Mutex::new(Vec::new()),
+            completed: Mutex::new(false),
+            aborted: Mutex::new(false),
+        };
+        let store = MemoryUploadSessionStore::new();
+        let data = Bytes::from(vec![7u8; (MIN_PART_SIZE * 3 + 1) as usize]);
+
+        let first = run_multipart_upload(&api, &store, "scope", "key-a", "sum", &data).await;
+        assert!(first.is_err());
+        assert_eq!(*api.uploaded.lock().unwrap(), vec![1, 2]);
+        assert_eq!(store.find_session("scope", "sum").await.unwrap().unwrap().parts.len(), 2);
+
+        // The retry generates a new key, but must finish the original object
+        let key = run_multipart_upload(&api, &store, "scope", "key-b", "sum", &data).await.unwrap();
+        assert_eq!(key, "key-a");
+        assert_eq!(*api.uploaded.lock().unwrap(), vec![1, 2, 3, 4]);
+        assert!(*api.completed.lock().unwrap());
+        assert!(store.find_session("scope", "sum").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_upload_is_aborted() {
+        let api = FlakyApi {
+            parts_before_failure: Mutex::new(None),
+            uploaded: Mutex::new(Vec::new()),
+            completed: Mutex::new(false),
+            aborted: Mutex::new(false),
+        };
+        let store = MemoryUploadSessionStore::new();
+        let data = Bytes::from(vec![7u8; (MIN_PART_SIZE * 2) as usize]);
+
+        let token = tokio_util::sync::CancellationToken::new();
+        token.cancel();
+        let result = cancel::scope(token, run_multipart_upload(&api, &store, "scope", "key", "sum", &data)).await;
+
+        assert!(cancel::is_cancelled(&result.unwrap_err()));
+        assert!(api.uploaded.lock().unwrap().is_empty());
+        assert!(*api.aborted.lock().unwrap());
+        assert!(!*api.completed.lock().unwrap());
+        assert!(store.find_session("scope", "sum").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_resume_lookup() {
+        let store = MemoryUploadSessionStore::new();
+        let session = UploadSession::new("up-1".into(), "cos:b:r:u:730".into(), "saves/u/730_x.zip".into(), "abc".into(), 100);
+        store.save_session(&session).await.unwrap();
+        store.record_part("up-1", &UploadedPart { part_number: 1, etag: "e1".into(), size: 100 }).await.unwrap();
+
+        let found = store.find_session("cos:b:r:u:730", "abc").await.unwrap().unwrap();
+        assert_eq!(found.bytes_uploaded(), 100);
+        assert!(found.has_part(1));
+        assert!(store.find_session("cos:b:r:u:730", "other").await.unwrap().is_none());
+
+        store.remove_session("up-1").await.unwrap();
+        assert!(store.get_session("up-1").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,260 @@
+use chrono::{NaiveDateTime, Timelike};
+use std::fmt;
+use std::future::Future;
+use uuid::Uuid;
+
+/// Where save versions are stored unless a backend is configured otherwise
+pub const SAVES_PREFIX: &str = "saves/";
+/// Game id of saves whose key doesn't name their game
+pub const UNKNOWN_GAME: &str = "unknown";
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+tokio::task_local! {
+    /// Upload time and id of the version being uploaded, see [`pinned`]
+    static PINNED: (NaiveDateTime, String);
+}
+
+/// Layouts of save version keys. New keys are always written in [`KeyLayout::CURRENT`];
+/// older layouts are still parsed, so versions stored by earlier releases keep listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyLayout {
+    /// `<prefix><user>/<app_id>/<name>_<timestamp>_<id><suffix>`, per-game folders
+    /// written by the S3 backend before the backends shared one layout
+    V1,
+    /// `<prefix><user>/<app_id>_<timestamp>_<id><suffix>`
+    V2,
+}
+
+impl KeyLayout {
+    pub const CURRENT: KeyLayout = KeyLayout::V2;
+}
+
+/// The storage key of a save version, which is also its `SaveMetadata::file_id`.
+/// Formatting a parsed key gives back the same string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectKey {
+    /// Start of the save tree, ending in `/`
+    pub prefix: String,
+    /// Sanitized user id
+    pub user: String,
+    pub app_id: u32,
+    /// Upload time (UTC, whole seconds)
+    pub uploaded_at: NaiveDateTime,
+    /// Unique part, a UUID for keys written by this client
+    pub id: String,
+    /// Game name; only [`KeyLayout::V1`] keys carry one
+    pub name: Option<String>,
+    /// What kind of object this is: `.zip`, `.zip.enc`, a manifest suffix, ...
+    pub suffix: String,
+    pub layout: KeyLayout,
+}
+
+impl ObjectKey {
+    /// A key for a version uploaded now, in the current layout under [`SAVES_PREFIX`]
+    pub fn new(user: &str, app_id: u32, suffix: &str) -> Self {
+        let (uploaded_at, id) = PINNED.try_with(Clone::clone).unwrap_or_else(|_| fresh_version());
+        Self {
+            prefix: SAVES_PREFIX.to_string(),
+            user: user.to_string(),
+            app_id,
+            uploaded_at,
+            id,
+            name: None,
+            suffix: suffix.to_string(),
+            layout: KeyLayout::CURRENT,
+        }
+    }
+
+    /// Store under another save tree (see [`crate::S3Config::prefix`])
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Parse a key under [`SAVES_PREFIX`], in any layout; `None` if it isn't a save version
+    /// key, such as the game-name keys of very old uploads (see [`crate::repair`])
+    pub fn parse(key: &str) -> Option<Self> {
+        Self::parse_with_prefix(key, SAVES_PREFIX)
+    }
+
+    /// [`Self::parse`] for a save tree under `prefix`
+    pub fn parse_with_prefix(key: &str, prefix: &str) -> Option<Self> {
+        let parts: Vec<&str> = key.strip_prefix(prefix)?.split('/').collect();
+        let (user, folder, file_name) = match parts.as_slice() {
+            [user, file_name] => (*user, None, *file_name),
+            [user, folder, file_name] => (*user, Some(*folder), *file_name),
+            _ => return None,
+        };
+        if user.is_empty() {
+            return None;
+        }
+
+        // The id never contains '_' or '.', so the suffix starts at the first '.' after the last '_'
+        let last_separator = file_name.rfind('_')?;
+        let suffix_start = file_name[last_separator..].find('.').map(|i| last_separator + i)?;
+        let (stem, suffix) = file_name.split_at(suffix_start);
+        let mut fields: Vec<&str> = stem.rsplitn(4, '_').collect();
+        fields.reverse();
+        let [head, date, time, id] = fields.as_slice() else {
+            return None;
+        };
+        if id.is_empty() || date.len() != 8 || time.len() != 6 {
+            return None;
+        }
+        let uploaded_at = NaiveDateTime::parse_from_str(&format!("{}_{}", date, time), TIMESTAMP_FORMAT).ok()?;
+
+        let (app_id, name, layout) = match folder {
+            None => (parse_app_id(head)?, None, KeyLayout::V2),
+            Some(folder) if !head.is_empty() => (parse_app_id(folder)?, Some(head.to_string()), KeyLayout::V1),
+            Some(_) => return None,
+        };
+        Some(Self {
+            prefix: prefix.to_string(),
+            user: user.to_string(),
+            app_id,
+            uploaded_at,
+            id: id.to_string(),
+            name,
+            suffix: suffix.to_string(),
+            layout,
+        })
+    }
+}
+
+impl fmt::Display for ObjectKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timestamp = self.uploaded_at.format(TIMESTAMP_FORMAT);
+        match self.layout {
+            KeyLayout::V1 => write!(
+                f, "{}{}/{}/{}_{}_{}{}",
+                self.prefix, self.user, self.app_id, self.name.as_deref().unwrap_or_default(), timestamp, self.id, self.suffix
+            ),
+            KeyLayout::V2 => write!(
+                f, "{}{}/{}_{}_{}{}",
+                self.prefix, self.user, self.app_id, timestamp, self.id, self.suffix
+            ),
+        }
+    }
+}
+
+fn fresh_version() -> (NaiveDateTime, String) {
+    let now = chrono::Utc::now().naive_utc();
+    (now.with_nanosecond(0).unwrap_or(now), Uuid::new_v4().to_string())
+}
+
+/// Run `future` with every [`ObjectKey::new`] in it using the same upload time and id,
+/// so the attempts of a retried upload all write the version the first one started
+/// instead of leaving a duplicate behind per attempt
+pub(crate) async fn pinned<F: Future>(future: F) -> F::Output {
+    match PINNED.try_with(|_| ()) {
+        Ok(()) => future.await,
+        Err(_) => PINNED.scope(fresh_version(), future).await,
+    }
+}
+
+fn parse_app_id(s: &str) -> Option<u32> {
+    match !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+        true => s.parse().ok(),
+        false => None,
+    }
+}
+
+/// Steam app id of a stored save, read from its key; [`UNKNOWN_GAME`] for keys that
+/// don't name their game until `repair` rewrites them
+pub fn game_id_from_key(key: &str) -> String {
+    game_id_from_key_with_prefix(key, SAVES_PREFIX)
+}
+
+/// [`game_id_from_key`] for a save tree under `prefix`
+pub fn game_id_from_key_with_prefix(key: &str, prefix: &str) -> String {
+    ObjectKey::parse_with_prefix(key, prefix)
+        .map(|key| key.app_id.to_string())
+        .unwrap_or_else(|| UNKNOWN_GAME.to_string())
+}
+
+/// Sanitized user id of a key in the save tree under `prefix`. Unlike [`ObjectKey::parse_with_prefix`]
+/// this also reads the keys of legacy saves and sidecars.
+pub fn user_from_key<'a>(key: &'a str, prefix: &str) -> Option<&'a str> {
+    match key.strip_prefix(prefix)?.split_once('/')? {
+        (user, rest) if !user.is_empty() && !rest.is_empty() => Some(user),
+        _ => None,
+    }
+}
+
+/// Whether `key` is a save version key in one of the [`KeyLayout`]s
+pub fn is_canonical_key(key: &str) -> bool {
+    ObjectKey::parse(key).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_round_trip_in_every_layout() {
+        for key in [
+            "saves/user-1/105600_20240101_120000_0b5c3f1e-8f3a-4c55-9a53-2d4c6a7b8e90.zip",
+            "saves/user-1/105600_20240101_120000_0b5c3f1e-8f3a-4c55-9a53-2d4c6a7b8e90.zip.enc",
+            "saves/user-1/105600_20240101_120000_0b5c3f1e-8f3a-4c55-9a53-2d4c6a7b8e90.manifest.json",
+            "saves/user-1/105600/save_1700000000_20240101_120000_0b5c3f1e-8f3a-4c55-9a53-2d4c6a7b8e90.zip",
+            "saves/user-1/1086940/Baldur's Gate 3_20240101_120000_0b5c3f1e-8f3a-4c55-9a53-2d4c6a7b8e90.zip",
+        ] {
+            let parsed = ObjectKey::parse(key).unwrap_or_else(|| panic!("{} should parse", key));
+            assert_eq!(parsed.to_string(), key);
+        }
+
+        let parsed = ObjectKey::parse("saves/user-1/1086940/Baldur's Gate 3_20240101_120000_abc.zip").unwrap();
+        assert_eq!(parsed.layout, KeyLayout::V1);
+        assert_eq!((parsed.app_id, parsed.name.as_deref(), parsed.suffix.as_str()), (1086940, Some("Baldur's Gate 3"), ".zip"));
+        assert_eq!(parsed.uploaded_at.format("%Y-%m-%d %H:%M:%S").to_string(), "2024-01-01 12:00:00");
+
+        let custom = ObjectKey::parse_with_prefix("backups/steam/user-1/105600_20240101_120000_abc.zip", "backups/steam/").unwrap();
+        assert_eq!((custom.user.as_str(), custom.app_id, custom.layout), ("user-1", 105600, KeyLayout::V2));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_keys_repeat_within_one_upload() {
+        let (first, second) = pinned(async {
+            let first = ObjectKey::new("user-1", 105600, ".zip");
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            (first, ObjectKey::new("user-1", 105600, ".zip"))
+        }).await;
+        assert_eq!(first, second);
+
+        let other = pinned(async { ObjectKey::new("user-1", 105600, ".zip") }).await;
+        assert_ne!(other.id, first.id);
+        assert_ne!(ObjectKey::new("user-1", 105600, ".zip").id, ObjectKey::new("user-1", 105600, ".zip").id);
+    }
+
+    #[test]
+    fn test_new_keys_use_the_current_layout() {
+        let key = ObjectKey::new("user-1", 105600, ".zip").with_prefix("backups/");
+        let formatted = key.to_string();
+        assert!(formatted.starts_with("backups/user-1/105600_"), "{}", formatted);
+        assert_eq!(ObjectKey::parse_with_prefix(&formatted, "backups/"), Some(key));
+    }
+
+    #[test]
+    fn test_game_id_from_key() {
+        assert_eq!(game_id_from_key("saves/user-1/105600_20240101_120000_abc.zip"), "105600");
+        assert_eq!(game_id_from_key("saves/user-1/105600/save_1700000000_20240101_120000_abc.zip"), "105600");
+        assert_eq!(game_id_from_key("saves/user-1/105600_20240101_120000_abc.manifest.json"), "105600");
+        // Legacy keys: the upload bug's `save_<ts>` names, and game names
+        assert_eq!(game_id_from_key("saves/user-1/save_1700000000_20240101_120000_abc.zip"), UNKNOWN_GAME);
+        assert_eq!(game_id_from_key("saves/user-1/Terraria_20240101_120000_abc.zip"), UNKNOWN_GAME);
+        assert!(!is_canonical_key("saves/user-1/Wallpaper Engine_20240101_120000_abc.zip"));
+        assert!(!is_canonical_key("chunks/user-1/ab/abcdef"));
+        assert_eq!(game_id_from_key_with_prefix("team/saves/user-1/105600_20240101_120000_abc.zip", "team/saves/"), "105600");
+        assert_eq!(game_id_from_key("team/saves/user-1/105600_20240101_120000_abc.zip"), UNKNOWN_GAME);
+    }
+
+    #[test]
+    fn test_user_from_key() {
+        assert_eq!(user_from_key("saves/user-1/105600_20240101_120000_abc.zip", SAVES_PREFIX), Some("user-1"));
+        assert_eq!(user_from_key("team/saves/user-1/Terraria_20240101_120000_abc.zip", "team/saves/"), Some("user-1"));
+        assert_eq!(user_from_key("team/saves/user-1/105600_a.zip", SAVES_PREFIX), None);
+        assert_eq!(user_from_key("saves/user-1/", SAVES_PREFIX), None);
+        assert_eq!(user_from_key("saves//a.zip", SAVES_PREFIX), None);
+    }
+}
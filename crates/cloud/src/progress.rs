@@ -0,0 +1,200 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// What an operation is doing with the bytes it reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferStage {
+    Preparing,
+    Compressing,
+    Uploading,
+    Downloading,
+    Extracting,
+}
+
+/// Byte counts of a running operation. Backends report into the tracker of the
+/// operation they run for (see [`scope`]); the service samples it for progress updates.
+#[derive(Debug)]
+pub struct TransferProgress {
+    stage: Mutex<StageState>,
+    done: AtomicU64,
+    /// Grows with every report and never resets, so a stalled operation can be told apart
+    activity: AtomicU64,
+}
+
+#[derive(Debug)]
+struct StageState {
+    stage: TransferStage,
+    /// Expected bytes for the stage, 0 if unknown
+    total: u64,
+    started: Instant,
+}
+
+/// Progress of the current stage at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressSnapshot {
+    pub stage: TransferStage,
+    pub bytes: u64,
+    /// 0 if unknown
+    pub total: u64,
+    pub bytes_per_sec: u64,
+    /// Time left at the current rate, if the total is known and bytes are moving
+    pub eta: Option<Duration>,
+}
+
+impl TransferProgress {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            stage: Mutex::new(StageState { stage: TransferStage::Preparing, total: 0, started: Instant::now() }),
+            done: AtomicU64::new(0),
+            activity: AtomicU64::new(0),
+        })
+    }
+
+    /// Start counting a new stage of `total` bytes (0 if unknown)
+    pub fn start_stage(&self, stage: TransferStage, total: u64) {
+        let mut state = self.stage.lock().unwrap();
+        *state = StageState { stage, total, started: Instant::now() };
+        self.done.store(0, Ordering::Relaxed);
+        self.activity.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, bytes: u64) {
+        self.done.fetch_add(bytes, Ordering::Relaxed);
+        self.activity.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Changes whenever the operation reports progress
+    pub(crate) fn activity(&self) -> u64 {
+        self.activity.load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let state = self.stage.lock().unwrap();
+        let bytes = self.done.load(Ordering::Relaxed);
+        let elapsed = state.started.elapsed().as_secs_f64();
+        let bytes_per_sec = match elapsed > 0.0 {
+            true => (bytes as f64 / elapsed) as u64,
+            false => 0,
+        };
+        let eta = match (state.total, bytes_per_sec) {
+            (0, _) | (_, 0) => None,
+            (total, rate) => Some(Duration::from_secs_f64(total.saturating_sub(bytes) as f64 / rate as f64)),
+        };
+        ProgressSnapshot { stage: state.stage, bytes, total: state.total, bytes_per_sec, eta }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: Arc<TransferProgress>;
+}
+
+thread_local! {
+    static CURRENT_BLOCKING: RefCell<Option<Arc<TransferProgress>>> = const { RefCell::new(None) };
+}
+
+/// Run `future` reporting its transfers into `progress`
+pub async fn scope<F: Future>(progress: Arc<TransferProgress>, future: F) -> F::Output {
+    CURRENT.scope(progress, future).await
+}
+
+/// The tracker of the operation being run, if it has one
+pub fn current() -> Option<Arc<TransferProgress>> {
+    CURRENT.try_with(Arc::clone).ok()
+        .or_else(|| CURRENT_BLOCKING.with(|progress| progress.borrow().clone()))
+}
+
+/// Run blocking `f` reporting into `progress` (see [`current`])
+pub fn blocking_scope<T>(progress: Option<Arc<TransferProgress>>, f: impl FnOnce() -> T) -> T {
+    let previous = CURRENT_BLOCKING.with(|current| current.replace(progress));
+    let result = f();
+    CURRENT_BLOCKING.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// Start a new stage on the current operation's tracker
+pub(crate) fn start_stage(stage: TransferStage, total: u64) {
+    if let Some(progress) = current() {
+        progress.start_stage(stage, total);
+    }
+}
+
+/// Count `bytes` towards the current operation's stage
+pub(crate) fn advance(bytes: u64) {
+    if let Some(progress) = current() {
+        progress.advance(bytes);
+    }
+}
+
+/// Wraps a blocking reader so everything read through it is counted as progress
+pub(crate) struct CountingRead<R> {
+    inner: R,
+    progress: Option<Arc<TransferProgress>>,
+}
+
+impl<R: Read> CountingRead<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, progress: current() }
+    }
+}
+
+impl<R: Read> Read for CountingRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(progress) = &self.progress {
+            progress.advance(read as u64);
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_rate_and_eta() {
+        let progress = TransferProgress::new();
+        progress.start_stage(TransferStage::Uploading, 1000);
+        std::thread::sleep(Duration::from_millis(50));
+        progress.advance(500);
+
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.stage, TransferStage::Uploading);
+        assert_eq!((snapshot.bytes, snapshot.total), (500, 1000));
+        assert!(snapshot.bytes_per_sec > 0 && snapshot.bytes_per_sec <= 10_000, "{}", snapshot.bytes_per_sec);
+        // Half done, so roughly as long again
+        let eta = snapshot.eta.unwrap();
+        assert!(eta >= Duration::from_millis(40) && eta < Duration::from_secs(1), "{:?}", eta);
+
+        // A new stage starts counting from zero; unknown totals have no ETA
+        progress.start_stage(TransferStage::Downloading, 0);
+        progress.advance(10);
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.bytes, 10);
+        assert_eq!(snapshot.eta, None);
+    }
+
+    #[tokio::test]
+    async fn test_scoped_reporting() {
+        advance(10); // no tracker, ignored
+
+        let progress = TransferProgress::new();
+        scope(progress.clone(), async {
+            start_stage(TransferStage::Compressing, 8);
+            advance(3);
+            let tracker = current();
+            tokio::task::spawn_blocking(move || blocking_scope(tracker, || {
+                let mut read = CountingRead::new(&b"abcde"[..]);
+                std::io::copy(&mut read, &mut std::io::sink()).unwrap();
+            })).await.unwrap();
+        }).await;
+
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.stage, TransferStage::Compressing);
+        assert_eq!((snapshot.bytes, snapshot.total), (8, 8));
+    }
+}
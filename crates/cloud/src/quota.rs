@@ -0,0 +1,122 @@
+use crate::chunked::collect_save_files;
+use anyhow::Result;
+use std::path::Path;
+
+/// Share of the quota in use from which the UI warns
+pub const QUOTA_WARNING_RATIO: f64 = 0.8;
+/// Share of the quota in use from which uploads are about to be refused
+pub const QUOTA_CRITICAL_RATIO: f64 = 0.95;
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaLevel {
+    Ok,
+    /// At least [`QUOTA_WARNING_RATIO`] in use
+    Warning,
+    /// At least [`QUOTA_CRITICAL_RATIO`] in use
+    Critical,
+    /// Full, or over because the quota was lowered
+    Exceeded,
+}
+
+/// Storage used by the user's saves against the configured quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaStatus {
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+impl QuotaStatus {
+    /// Share of the quota in use; above 1 when over it
+    pub fn fraction(&self) -> f64 {
+        match self.quota_bytes {
+            0 => 1.0,
+            quota => self.used_bytes as f64 / quota as f64,
+        }
+    }
+
+    pub fn level(&self) -> QuotaLevel {
+        match self.fraction() {
+            f if f >= 1.0 => QuotaLevel::Exceeded,
+            f if f >= QUOTA_CRITICAL_RATIO => QuotaLevel::Critical,
+            f if f >= QUOTA_WARNING_RATIO => QuotaLevel::Warning,
+            _ => QuotaLevel::Ok,
+        }
+    }
+
+    pub fn remaining_bytes(&self) -> u64 {
+        self.quota_bytes.saturating_sub(self.used_bytes)
+    }
+}
+
+/// Error of an upload refused because it would take the user's saves over the quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub used_bytes: u64,
+    /// Size of the save being uploaded, before compression
+    pub upload_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Storage quota exceeded: uploading {:.1} MB would bring saves to {:.2} GB of the {:.2} GB quota. \
+             Free space by pruning old versions, or raise the quota",
+            self.upload_bytes as f64 / (1024.0 * 1024.0),
+            (self.used_bytes + self.upload_bytes) as f64 / GB,
+            self.quota_bytes as f64 / GB,
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Whether `error` is, or was caused by, a [`QuotaExceeded`]
+pub fn is_quota_exceeded(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| cause.is::<QuotaExceeded>())
+}
+
+/// Refuse an upload of `upload_bytes` that would take `used_bytes` over `quota_bytes`
+pub fn check_quota(used_bytes: u64, upload_bytes: u64, quota_bytes: u64) -> Result<()> {
+    if used_bytes.saturating_add(upload_bytes) > quota_bytes {
+        return Err(QuotaExceeded { used_bytes, upload_bytes, quota_bytes }.into());
+    }
+    Ok(())
+}
+
+/// Total size of the files of a local save. Archives are compressed, so this is an
+/// upper bound of what uploading it stores.
+pub async fn local_save_size(save_path: &Path) -> Result<u64> {
+    let save_path = save_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut total = 0;
+        for (_, path) in collect_save_files(&save_path)? {
+            total += std::fs::metadata(&path)?.len();
+        }
+        Ok(total)
+    }).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_and_refused_uploads() {
+        let status = |used_bytes| QuotaStatus { used_bytes, quota_bytes: 1000 };
+        assert_eq!(status(500).level(), QuotaLevel::Ok);
+        assert_eq!(status(800).level(), QuotaLevel::Warning);
+        assert_eq!(status(960).level(), QuotaLevel::Critical);
+        assert_eq!(status(1200).level(), QuotaLevel::Exceeded);
+        assert_eq!(status(1200).remaining_bytes(), 0);
+
+        assert!(check_quota(900, 100, 1000).is_ok());
+        let err = check_quota(900, 101, 1000).unwrap_err();
+        assert!(is_quota_exceeded(&err));
+        assert!(is_quota_exceeded(&err.context("Upload failed")));
+        assert!(!is_quota_exceeded(&anyhow::anyhow!("Connection reset")));
+    }
+}
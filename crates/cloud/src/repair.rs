@@ -0,0 +1,258 @@
+use crate::chunked::collect_save_files;
+use crate::object_key::{self, ObjectKey};
+use crate::version_info::VersionInfo;
+use crate::{cancel, sanitize_user_id, CloudBackend};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use steam_cloud_sync_core::GameSave;
+
+/// A stored save whose key doesn't name its game, as written by old versions: game
+/// names instead of app ids (`saves/<user>/Terraria_<timestamp>_<uuid>.zip`), or the
+/// `save_<unix time>` names of an upload bug. Such saves list under "unknown".
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacySave {
+    pub key: String,
+    pub size_bytes: u64,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
+    /// Games the save may belong to, most likely first; empty if nothing matched
+    pub candidates: Vec<Candidate>,
+}
+
+/// A local game a legacy save may belong to, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub app_id: u32,
+    pub name: String,
+    /// The key carries the game's name
+    pub name_matches: bool,
+    /// Files of the archive that also exist in the game's save folder
+    pub matching_files: usize,
+    pub archive_files: usize,
+}
+
+impl Candidate {
+    fn score(&self) -> (bool, u64) {
+        let share = match self.archive_files {
+            0 => 0,
+            total => (self.matching_files as u64 * 1000) / total as u64,
+        };
+        (self.name_matches, share)
+    }
+}
+
+/// Find the user's legacy saves and guess their games from the key and from the
+/// archive contents, compared with the save folders of `local_games`. Nothing is
+/// changed; [`CloudBackend::repair_save`] moves a save once the user confirmed its game.
+pub async fn find_legacy_saves(backend: &dyn CloudBackend, user_id: &str, local_games: &[GameSave]) -> Result<Vec<LegacySave>> {
+    let games = local_games.to_vec();
+    let local_files: Vec<(GameSave, HashSet<String>)> = tokio::task::spawn_blocking(move || {
+        games.into_iter()
+            .map(|game| {
+                let files = collect_save_files(&game.save_path).unwrap_or_default()
+                    .into_iter()
+                    .map(|(relative_path, _)| relative_path)
+                    .collect();
+                (game, files)
+            })
+            .collect()
+    }).await?;
+
+    let mut legacy = Vec::new();
+    let prefix = backend.save_prefix();
+    for object in backend.list_objects(&format!("{}{}/", prefix, sanitize_user_id(user_id))).await? {
+        if !object.key.ends_with(".zip") || ObjectKey::parse_with_prefix(&object.key, prefix).is_some() {
+            continue;
+        }
+        cancel::check()?;
+
+        let name = normalize(&parse_legacy_name(&object.key).0);
+        let entries = match backend.get_archive(&object.key).await {
+            Ok(archive) => tokio::task::spawn_blocking(move || archive_entries(archive.path())).await?,
+            Err(e) => {
+                eprintln!("[Repair] Could not read {}: {}", object.key, e);
+                Vec::new()
+            }
+        };
+
+        let mut candidates: Vec<Candidate> = local_files.iter()
+            .map(|(game, files)| Candidate {
+                app_id: game.app_id,
+                name: game.name.clone(),
+                name_matches: !name.is_empty() && normalize(&game.name) == name,
+                matching_files: entries.iter().filter(|entry| files.contains(*entry)).count(),
+                archive_files: entries.len(),
+            })
+            .filter(|candidate| candidate.name_matches || candidate.matching_files > 0)
+            .collect();
+        candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.score()));
+
+        legacy.push(LegacySave {
+            key: object.key,
+            size_bytes: object.size_bytes,
+            last_modified: object.last_modified,
+            candidates,
+        });
+    }
+
+    println!("[Repair] Found {} saves stored under legacy names", legacy.len());
+    Ok(legacy)
+}
+
+/// Move a legacy save to the canonical key of `app_id`, keeping its upload time, and
+/// its sidecar with it. Returns the new key. The new key only depends on the old one,
+/// so running a repair again after it failed halfway finishes the same move.
+///
+/// This is the default of [`CloudBackend::repair_save`]; the save index overrides it
+/// to move its entry along.
+pub async fn repair_save<B: CloudBackend + ?Sized>(backend: &B, save: &LegacySave, app_id: u32, game_name: &str) -> Result<String> {
+    let new_key = canonical_key(save, app_id, backend.save_prefix())?;
+
+    if backend.object_exists(&save.key).await? {
+        // The sidecar first: while the archive is still at its old key, the move isn't done
+        let old_sidecar = VersionInfo::sidecar_key(&save.key);
+        if backend.object_exists(&old_sidecar).await? {
+            backend.move_object(&old_sidecar, &VersionInfo::sidecar_key(&new_key)).await?;
+        }
+        backend.move_object(&save.key, &new_key).await?;
+    } else if !backend.object_exists(&new_key).await? {
+        return Err(anyhow::anyhow!("Save {} no longer exists", save.key));
+    }
+
+    println!("[Repair] Moved {} to {} ({})", save.key, new_key, game_name);
+    Ok(new_key)
+}
+
+/// The current-layout key of the save, with the upload time from the old key (or its
+/// modification time) and an id derived from the old key
+fn canonical_key(save: &LegacySave, app_id: u32, prefix: &str) -> Result<String> {
+    let user = object_key::user_from_key(&save.key, prefix)
+        .ok_or_else(|| anyhow::anyhow!("{} is not a save key", save.key))?;
+    let mut key = ObjectKey::new(user, app_id, ".zip").with_prefix(prefix);
+    if let Some(uploaded_at) = parse_legacy_name(&save.key).1
+        .and_then(|timestamp| chrono::NaiveDateTime::parse_from_str(&timestamp, "%Y%m%d_%H%M%S").ok())
+        .or_else(|| save.last_modified.map(|time| time.naive_utc()))
+    {
+        key.uploaded_at = uploaded_at;
+    }
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&Sha256::digest(save.key.as_bytes())[..16]);
+    key.id = uuid::Builder::from_random_bytes(id).into_uuid().to_string();
+    Ok(key.to_string())
+}
+
+/// The name part and the `YYYYmmdd_HHMMSS` upload time of a legacy key's file name
+fn parse_legacy_name(key: &str) -> (String, Option<String>) {
+    let file_name = key.rsplit('/').next().unwrap_or(key).trim_end_matches(".zip");
+    let parts: Vec<&str> = file_name.split('_').collect();
+    let is_digits = |s: &str, len: usize| s.len() == len && s.chars().all(|c| c.is_ascii_digit());
+    match (0..parts.len().saturating_sub(1)).find(|&i| is_digits(parts[i], 8) && is_digits(parts[i + 1], 6)) {
+        Some(i) => (parts[..i].join("_"), Some(format!("{}_{}", parts[i], parts[i + 1]))),
+        None => (file_name.to_string(), None),
+    }
+}
+
+/// Lowercase letters and digits only, so "Wallpaper Engine" matches "wallpaper_engine"
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Paths of the files in a zip archive; empty if it isn't one
+fn archive_entries(path: &Path) -> Vec<String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+    match zip::ZipArchive::new(BufReader::new(file)) {
+        Ok(archive) => archive.file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(str::to_string)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IndexedBackend, LocalFolderBackend, SaveArchive};
+    use tempfile::TempDir;
+
+    async fn store_legacy(storage: &LocalFolderBackend, key: &str, save_dir: &std::path::Path) {
+        let archive = SaveArchive::create(save_dir).await.unwrap();
+        storage.put_object(key, archive.to_bytes().await.unwrap()).await.unwrap();
+    }
+
+    fn local_game(dir: &TempDir, app_id: u32, name: &str, files: &[&str]) -> GameSave {
+        let save_path = dir.path().join(app_id.to_string());
+        for file in files {
+            std::fs::create_dir_all(save_path.join(file).parent().unwrap()).unwrap();
+            std::fs::write(save_path.join(file), file.as_bytes()).unwrap();
+        }
+        GameSave { app_id, name: name.to_string(), save_path }
+    }
+
+    #[test]
+    fn test_parse_legacy_name() {
+        assert_eq!(
+            parse_legacy_name("saves/u/Wallpaper Engine_20240101_120000_abc.zip"),
+            ("Wallpaper Engine".to_string(), Some("20240101_120000".to_string()))
+        );
+        assert_eq!(
+            parse_legacy_name("saves/u/save_1700000000_20240101_120000_abc.zip"),
+            ("save_1700000000".to_string(), Some("20240101_120000".to_string()))
+        );
+        assert_eq!(parse_legacy_name("saves/u/backup.zip"), ("backup".to_string(), None));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_saves_are_identified_and_repaired() {
+        let storage_dir = TempDir::new().unwrap();
+        let saves = TempDir::new().unwrap();
+        let storage = LocalFolderBackend::with_root(storage_dir.path().to_path_buf());
+        let terraria = local_game(&saves, 105600, "Terraria", &["Players/hero.plr", "config.json"]);
+        let stardew = local_game(&saves, 413150, "Stardew Valley", &["Farm_1234/Farm_1234", "startup_preferences"]);
+
+        let named = "saves/user-1/Terraria_20240101_120000_aaaa.zip";
+        let unnamed = "saves/user-1/save_1700000000_20240102_080000_bbbb.zip";
+        store_legacy(&storage, named, &terraria.save_path).await;
+        store_legacy(&storage, unnamed, &stardew.save_path).await;
+        let current = storage.upload_save(&terraria, "user-1").await.unwrap();
+
+        let indexed = IndexedBackend::new(Box::new(LocalFolderBackend::with_root(storage_dir.path().to_path_buf())));
+        let listed = indexed.list_saves("user-1", None).await.unwrap();
+        assert_eq!(listed.iter().filter(|save| save.game_id == "unknown").count(), 2);
+        let before = listed.iter().find(|save| save.file_id == unnamed).unwrap().timestamp.clone();
+
+        let mut legacy = find_legacy_saves(&storage, "user-1", &[terraria.clone(), stardew.clone()]).await.unwrap();
+        legacy.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(legacy.iter().map(|save| save.key.as_str()).collect::<Vec<_>>(), vec![named, unnamed]);
+        let best = &legacy[0].candidates[0];
+        assert_eq!((best.app_id, best.name_matches, best.matching_files), (105600, true, 2));
+        // Only the contents tell which game the bugged upload belongs to
+        let best = &legacy[1].candidates[0];
+        assert_eq!((best.app_id, best.name_matches, best.matching_files, best.archive_files), (413150, false, 2, 2));
+        assert_eq!(legacy[1].candidates.len(), 1);
+
+        for save in &legacy {
+            let game = &save.candidates[0];
+            indexed.repair_save(save, game.app_id, &game.name).await.unwrap();
+        }
+        assert!(find_legacy_saves(&storage, "user-1", &[]).await.unwrap().is_empty());
+
+        // The index moved along, keeping upload times; the current upload is untouched
+        let listed = indexed.list_saves("user-1", Some("413150")).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].file_id.starts_with("saves/user-1/413150_20240102_080000_"));
+        assert_eq!(listed[0].timestamp, before);
+        assert_eq!(indexed.list_saves("user-1", Some("105600")).await.unwrap().len(), 2);
+        assert!(storage.object_exists(&current.file_id).await.unwrap());
+
+        let restore = TempDir::new().unwrap();
+        storage.download_save(&listed[0], restore.path()).await.unwrap();
+        assert_eq!(std::fs::read(restore.path().join("startup_preferences")).unwrap(), b"startup_preferences");
+    }
+}
@@ -0,0 +1,774 @@
+use crate::retry::is_retryable;
+use crate::{
+    sanitize_user_id, version_info, CloudBackend, ObjectInfo, ObjectKey, SaveArchive, SaveMetadata, StorageInfo,
+    UploadProgress, UploadSessionStore, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use steam_cloud_sync_core::GameSave;
+
+/// Where the deletes a replica missed are recorded, in the primary, so they are still
+/// applied after a restart. One object per replica, listing the key prefixes to delete.
+const PENDING_DELETES_PREFIX: &str = "replication/pending-deletes/";
+
+fn pending_deletes_key(replica_name: &str) -> String {
+    format!("{}{}.json", PENDING_DELETES_PREFIX, replica_name)
+}
+
+/// Whether a replica holds everything the primary holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicaState {
+    /// Not compared with the primary yet
+    Unknown,
+    InSync,
+    /// Missed writes or deletes that the next catch-up pass has to apply
+    OutOfDate,
+}
+
+/// Status of one backend of a [`ReplicatedBackend`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicaStatus {
+    pub name: String,
+    pub primary: bool,
+    /// Always `InSync` for the primary, which is the reference
+    pub state: ReplicaState,
+    /// Objects known to be missing or stale on the replica
+    pub pending_objects: usize,
+    /// Last failure reaching the backend, cleared by the next success
+    pub last_error: Option<String>,
+    /// When the replica was last found in sync with the primary
+    pub last_synced: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+struct Replica {
+    name: String,
+    backend: Box<dyn CloudBackend>,
+    tracking: Mutex<Tracking>,
+    /// Held while a catch-up pass runs, so passes don't overlap
+    catching_up: tokio::sync::Mutex<()>,
+}
+
+struct Tracking {
+    state: ReplicaState,
+    /// Keys to copy from the primary
+    pending_copies: BTreeSet<String>,
+    /// Key prefixes to delete from the replica
+    pending_deletes: BTreeSet<String>,
+    last_error: Option<String>,
+    last_synced: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Replica {
+    fn new(name: String, backend: Box<dyn CloudBackend>) -> Self {
+        Self {
+            name,
+            backend,
+            tracking: Mutex::new(Tracking {
+                state: ReplicaState::Unknown,
+                pending_copies: BTreeSet::new(),
+                pending_deletes: BTreeSet::new(),
+                last_error: None,
+                last_synced: None,
+            }),
+            catching_up: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn status(&self, primary: bool) -> ReplicaStatus {
+        let tracking = self.tracking.lock().unwrap();
+        ReplicaStatus {
+            name: self.name.clone(),
+            primary,
+            state: if primary { ReplicaState::InSync } else { tracking.state },
+            pending_objects: tracking.pending_copies.len() + tracking.pending_deletes.len(),
+            last_error: tracking.last_error.clone(),
+            last_synced: tracking.last_synced,
+        }
+    }
+
+    fn record_error(&self, error: &anyhow::Error) {
+        self.tracking.lock().unwrap().last_error = Some(format!("{:#}", error));
+    }
+
+    fn record_success(&self) {
+        self.tracking.lock().unwrap().last_error = None;
+    }
+
+    /// Note a write the replica missed
+    fn missed_copies(&self, keys: impl IntoIterator<Item = String>, error: &anyhow::Error) {
+        eprintln!("[Replication] {} is out of date: {:#}", self.name, error);
+        let mut tracking = self.tracking.lock().unwrap();
+        tracking.pending_copies.extend(keys);
+        tracking.state = ReplicaState::OutOfDate;
+        tracking.last_error = Some(format!("{:#}", error));
+    }
+
+    /// Note a delete the replica missed
+    fn missed_delete(&self, prefix: String, error: &anyhow::Error) {
+        eprintln!("[Replication] {} is out of date: {:#}", self.name, error);
+        let mut tracking = self.tracking.lock().unwrap();
+        tracking.pending_copies.retain(|key| !key.starts_with(&prefix));
+        tracking.pending_deletes.insert(prefix);
+        tracking.state = ReplicaState::OutOfDate;
+        tracking.last_error = Some(format!("{:#}", error));
+    }
+
+    /// Note that the replica may have missed writes we can't name, e.g. a resumed upload
+    fn missed_unknown(&self) {
+        self.tracking.lock().unwrap().state = ReplicaState::OutOfDate;
+    }
+}
+
+struct ReplicaSet {
+    primary: Replica,
+    secondaries: Vec<Replica>,
+}
+
+/// Writes every object to a primary backend and mirrors it to secondary backends,
+/// e.g. Tencent COS as primary and a NAS folder as secondary. It sits directly on the
+/// storage backends (below encryption and the index), so replicas hold the same objects
+/// under the same keys and any replica can serve a download.
+///
+/// The primary is authoritative: writes fail when it fails, while a secondary that
+/// misses a write is marked [`ReplicaState::OutOfDate`] and brought up to date by a
+/// catch-up pass ([`ReplicatedBackend::catch_up`], or periodically with
+/// [`ReplicatedBackend::spawn_catch_up`]). Missed deletes are also recorded in the
+/// primary, so they are applied even after a restart. Reads go to the primary and fall
+/// back to the secondaries in order when it is unreachable.
+///
+/// Clones share the same backends and status.
+#[derive(Clone)]
+pub struct ReplicatedBackend {
+    set: Arc<ReplicaSet>,
+}
+
+impl ReplicatedBackend {
+    pub fn new(
+        primary_name: impl Into<String>,
+        primary: Box<dyn CloudBackend>,
+        secondaries: Vec<(String, Box<dyn CloudBackend>)>,
+    ) -> Self {
+        Self {
+            set: Arc::new(ReplicaSet {
+                primary: Replica::new(primary_name.into(), primary),
+                secondaries: secondaries.into_iter().map(|(name, backend)| Replica::new(name, backend)).collect(),
+            }),
+        }
+    }
+
+    /// Status of the primary followed by the secondaries
+    pub fn status(&self) -> Vec<ReplicaStatus> {
+        std::iter::once(self.set.primary.status(true))
+            .chain(self.set.secondaries.iter().map(|replica| replica.status(false)))
+            .collect()
+    }
+
+    /// Bring every secondary that isn't known to be in sync up to date with the primary
+    pub async fn catch_up(&self) -> Result<()> {
+        self.set.catch_up().await
+    }
+
+    /// Run a catch-up pass now and then every `interval` until the backend is dropped
+    pub fn spawn_catch_up(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let set: Weak<ReplicaSet> = Arc::downgrade(&self.set);
+        tokio::spawn(async move {
+            loop {
+                match set.upgrade() {
+                    Some(set) => {
+                        if let Err(e) = set.catch_up().await {
+                            eprintln!("[Replication] Catch-up pass failed: {:#}", e);
+                        }
+                    }
+                    None => break,
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Run `read` on the primary, falling back to the secondaries in order when the
+    /// primary can't be reached. Other errors (e.g. a missing object) are returned as is.
+    async fn read<'a, T, F, Fut>(&'a self, operation: &str, read: F) -> Result<T>
+    where
+        F: Fn(&'a dyn CloudBackend) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let primary = &self.set.primary;
+        let primary_error = match read(primary.backend.as_ref()).await {
+            Ok(value) => {
+                primary.record_success();
+                return Ok(value);
+            }
+            Err(e) if !is_retryable(&e) => return Err(e),
+            Err(e) => e,
+        };
+        primary.record_error(&primary_error);
+
+        for replica in &self.set.secondaries {
+            eprintln!(
+                "[Replication] {} on {} failed, trying {}: {:#}",
+                operation, primary.name, replica.name, primary_error
+            );
+            match read(replica.backend.as_ref()).await {
+                Ok(value) => {
+                    replica.record_success();
+                    return Ok(value);
+                }
+                Err(e) => replica.record_error(&e),
+            }
+        }
+        Err(primary_error)
+    }
+
+    /// Write `objects` to every secondary concurrently
+    async fn mirror_data(&self, objects: &[(String, Bytes)]) {
+        futures::future::join_all(self.set.secondaries.iter().map(|replica| async move {
+            for (index, (key, data)) in objects.iter().enumerate() {
+                if let Err(e) = replica.backend.put_object(key, data.clone()).await {
+                    replica.missed_copies(objects[index..].iter().map(|(key, _)| key.clone()), &e);
+                    return;
+                }
+            }
+            replica.record_success();
+        })).await;
+    }
+}
+
+impl ReplicaSet {
+    /// Record the deletes `replica` still has to apply in the primary, or clear the record
+    async fn save_pending_deletes(&self, replica: &Replica) {
+        let pending: Vec<String> = replica.tracking.lock().unwrap().pending_deletes.iter().cloned().collect();
+        let key = pending_deletes_key(&replica.name);
+        let saved = async {
+            match pending.is_empty() {
+                true => self.primary.backend.delete_object(&key).await,
+                false => self.primary.backend.put_object(&key, Bytes::from(serde_json::to_vec(&pending)?)).await,
+            }
+        }.await;
+        if let Err(e) = saved {
+            eprintln!("[Replication] Failed to record the deletes {} missed: {:#}", replica.name, e);
+        }
+    }
+
+    /// Pick up deletes an earlier run recorded for `replica`
+    async fn load_pending_deletes(&self, replica: &Replica) -> Result<()> {
+        let key = pending_deletes_key(&replica.name);
+        if !self.primary.backend.object_exists(&key).await? {
+            return Ok(());
+        }
+        let pending: Vec<String> = serde_json::from_slice(&self.primary.backend.get_object(&key).await?)?;
+        let mut tracking = replica.tracking.lock().unwrap();
+        for prefix in pending {
+            tracking.pending_copies.retain(|key| !key.starts_with(&prefix));
+            tracking.pending_deletes.insert(prefix);
+        }
+        Ok(())
+    }
+
+    async fn catch_up(&self) -> Result<()> {
+        let stale: Vec<&Replica> = self.secondaries.iter()
+            .filter(|replica| replica.tracking.lock().unwrap().state != ReplicaState::InSync)
+            .collect();
+        if stale.is_empty() {
+            return Ok(());
+        }
+
+        let primary_objects = self.primary.backend.list_objects("").await
+            .inspect_err(|e| self.primary.record_error(e))?;
+        let mut failed = Vec::new();
+        for replica in stale {
+            if let Err(e) = self.catch_up_replica(replica, &primary_objects).await {
+                replica.record_error(&e);
+                failed.push(format!("{}: {:#}", replica.name, e));
+            }
+        }
+        match failed.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow::anyhow!("Failed to catch up replicas: {}", failed.join("; "))),
+        }
+    }
+
+    /// Delete what was deleted on the primary, then copy every object that is missing,
+    /// differs in size or is known to be stale. Objects that only exist on the replica
+    /// are kept unless they were deleted through this backend: they may be all that is
+    /// left of a save the primary lost.
+    async fn catch_up_replica(&self, replica: &Replica, primary_objects: &[ObjectInfo]) -> Result<()> {
+        let _guard = replica.catching_up.lock().await;
+        self.load_pending_deletes(replica).await?;
+        let (pending_copies, pending_deletes) = {
+            let tracking = replica.tracking.lock().unwrap();
+            (tracking.pending_copies.clone(), tracking.pending_deletes.clone())
+        };
+
+        let replica_objects: HashMap<String, u64> = replica.backend.list_objects("").await?
+            .into_iter()
+            .map(|object| (object.key, object.size_bytes))
+            .collect();
+        let primary_keys: BTreeSet<&str> = primary_objects.iter().map(|object| object.key.as_str()).collect();
+
+        let mut deleted = 0;
+        for key in replica_objects.keys() {
+            if !primary_keys.contains(key.as_str()) && pending_deletes.iter().any(|prefix| key.starts_with(prefix)) {
+                replica.backend.delete_object(key).await?;
+                deleted += 1;
+            }
+        }
+
+        let mut copied = 0;
+        for object in primary_objects {
+            let up_to_date = replica_objects.get(&object.key) == Some(&object.size_bytes)
+                && !pending_copies.contains(&object.key);
+            // The replication records only concern the primary
+            if up_to_date || object.key.starts_with(PENDING_DELETES_PREFIX) {
+                continue;
+            }
+            for key in version_info::with_sidecar(self.primary.backend.as_ref(), &object.key).await {
+                let archive = self.primary.backend.get_archive(&key).await?;
+                replica.backend.put_archive(&key, &archive).await?;
+            }
+            copied += 1;
+        }
+
+        {
+            let mut tracking = replica.tracking.lock().unwrap();
+            tracking.pending_copies.retain(|key| !pending_copies.contains(key));
+            tracking.pending_deletes.retain(|prefix| !pending_deletes.contains(prefix));
+            // Writes missed while the pass ran keep the replica out of date
+            if tracking.pending_copies.is_empty() && tracking.pending_deletes.is_empty() {
+                tracking.state = ReplicaState::InSync;
+                tracking.last_synced = Some(chrono::Utc::now());
+            }
+            tracking.last_error = None;
+        }
+        if !pending_deletes.is_empty() {
+            self.save_pending_deletes(replica).await;
+        }
+        println!("[Replication] Caught up {}: {} objects copied, {} deleted", replica.name, copied, deleted);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CloudBackend for ReplicatedBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        // Zipped once and written to every backend, rather than read back from the primary
+        let archive = SaveArchive::create(&game_save.save_path).await?;
+        let key = ObjectKey::new(&sanitize_user_id(user_id), game_save.app_id, ".zip")
+            .with_prefix(self.save_prefix())
+            .to_string();
+        self.put_archive(&key, &archive).await?;
+
+        let info = VersionInfo::describe(game_save, archive.checksum(), version_info::FORMAT_ZIP).await;
+        version_info::store_version_info(self, &key, &info).await;
+        eprintln!("[Replication] Stored {} ({} bytes)", key, archive.size());
+
+        Ok(SaveMetadata {
+            game_id: game_save.app_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            size_bytes: archive.size(),
+            checksum: archive.checksum().to_string(),
+            compressed: true,
+            encrypted: false,
+            file_id: key,
+            info: Some(info),
+        })
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        self.read("download_save", |backend| backend.download_save(metadata, local_path)).await
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        self.read("list_saves", |backend| backend.list_saves(user_id, game_id)).await
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        self.set.primary.backend.delete_save(metadata).await?;
+        futures::future::join_all(self.set.secondaries.iter().map(|replica| async move {
+            match replica.backend.delete_save(metadata).await {
+                Ok(()) => replica.record_success(),
+                Err(e) => {
+                    replica.missed_delete(metadata.file_id.clone(), &e);
+                    self.set.save_pending_deletes(replica).await;
+                }
+            }
+        })).await;
+        Ok(())
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        let progress = self.set.primary.backend.resume_upload(upload_id, offset, data).await?;
+        // The object key isn't known here, so leave finding the new object to catch-up
+        for replica in &self.set.secondaries {
+            replica.missed_unknown();
+        }
+        Ok(progress)
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.set.primary.backend.test_connection().await
+            .inspect_err(|e| self.set.primary.record_error(e))?;
+        self.set.primary.record_success();
+        // Secondaries being down doesn't stop syncing; it shows in their status
+        for replica in &self.set.secondaries {
+            match replica.backend.test_connection().await {
+                Ok(()) => replica.record_success(),
+                Err(e) => {
+                    eprintln!("[Replication] {} is unreachable: {:#}", replica.name, e);
+                    replica.record_error(&e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        self.read("get_storage_info", |backend| backend.get_storage_info(user_id)).await
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        self.read("get_bucket_storage_info", |backend| backend.get_bucket_storage_info()).await
+    }
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        // Only the primary takes resumable uploads
+        match Arc::get_mut(&mut self.set) {
+            Some(set) => set.primary.backend.set_upload_session_store(store),
+            None => eprintln!("[Replication] Upload session store set after the backend was shared; ignored"),
+        }
+    }
+
+    fn encrypts_objects(&self) -> bool {
+        self.set.primary.backend.encrypts_objects()
+    }
+
+    fn replication(&self) -> Option<ReplicatedBackend> {
+        Some(self.clone())
+    }
+
+    fn save_prefix(&self) -> &str {
+        self.set.primary.backend.save_prefix()
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        self.set.primary.backend.put_object(key, data.clone()).await?;
+        self.mirror_data(&[(key.to_string(), data)]).await;
+        Ok(())
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        self.read("get_object", |backend| backend.get_object(key)).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        self.read("get_archive", |backend| backend.get_archive(key)).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        self.set.primary.backend.put_archive(key, archive).await?;
+        futures::future::join_all(self.set.secondaries.iter().map(|replica| async move {
+            match replica.backend.put_archive(key, archive).await {
+                Ok(()) => replica.record_success(),
+                Err(e) => replica.missed_copies([key.to_string()], &e),
+            }
+        })).await;
+        Ok(())
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        self.read("object_exists", |backend| backend.object_exists(key)).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        self.read("list_objects", |backend| backend.list_objects(prefix)).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.set.primary.backend.delete_object(key).await?;
+        futures::future::join_all(self.set.secondaries.iter().map(|replica| async move {
+            match replica.backend.delete_object(key).await {
+                Ok(()) => replica.record_success(),
+                Err(e) => {
+                    replica.missed_delete(key.to_string(), &e);
+                    self.set.save_pending_deletes(replica).await;
+                }
+            }
+        })).await;
+        Ok(())
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        // Tags come from the primary so they match what `put_object_if` compares against
+        self.set.primary.backend.get_object_tagged(key).await
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        // The primary decides conflicts; secondaries follow whatever it accepted
+        if !self.set.primary.backend.put_object_if(key, data.clone(), expected_tag).await? {
+            return Ok(false);
+        }
+        self.mirror_data(&[(key.to_string(), data)]).await;
+        Ok(true)
+    }
+
+    async fn set_version_label(&self, metadata: &SaveMetadata, label: Option<String>) -> Result<VersionInfo> {
+        // The label lives in the sidecar, written through `put_object` and so mirrored
+        version_info::set_label(self, metadata, label).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalFolderBackend;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::TempDir;
+
+    /// A local folder that can be taken offline, failing everything as unreachable
+    struct Switchable {
+        inner: LocalFolderBackend,
+        online: Arc<AtomicBool>,
+    }
+
+    /// A switchable folder backend at `root` and its online switch
+    fn switchable(root: &Path) -> (Box<dyn CloudBackend>, Arc<AtomicBool>) {
+        let online = Arc::new(AtomicBool::new(true));
+        let backend = Switchable { inner: LocalFolderBackend::with_root(root.to_path_buf()), online: online.clone() };
+        (Box::new(backend), online)
+    }
+
+    impl Switchable {
+        fn check(&self) -> Result<()> {
+            match self.online.load(Ordering::SeqCst) {
+                true => Ok(()),
+                false => Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl CloudBackend for Switchable {
+        async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+            self.check()?;
+            self.inner.upload_save(game_save, user_id).await
+        }
+        async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+            self.check()?;
+            self.inner.download_save(metadata, local_path).await
+        }
+        async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+            self.check()?;
+            self.inner.list_saves(user_id, game_id).await
+        }
+        async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+            self.check()?;
+            self.inner.delete_save(metadata).await
+        }
+        async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+            self.check()?;
+            self.inner.resume_upload(upload_id, offset, data).await
+        }
+        async fn test_connection(&self) -> Result<()> {
+            self.check()
+        }
+        async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+            self.check()?;
+            self.inner.get_storage_info(user_id).await
+        }
+        async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+            self.check()?;
+            self.inner.get_bucket_storage_info().await
+        }
+        async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+            self.check()?;
+            self.inner.put_object(key, data).await
+        }
+        async fn get_object(&self, key: &str) -> Result<Bytes> {
+            self.check()?;
+            self.inner.get_object(key).await
+        }
+        async fn object_exists(&self, key: &str) -> Result<bool> {
+            self.check()?;
+            self.inner.object_exists(key).await
+        }
+        async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+            self.check()?;
+            self.inner.list_objects(prefix).await
+        }
+        async fn delete_object(&self, key: &str) -> Result<()> {
+            self.check()?;
+            self.inner.delete_object(key).await
+        }
+    }
+
+    /// A primary under a custom save tree that takes writes but can't be read back
+    struct WriteOnly(LocalFolderBackend);
+
+    #[async_trait]
+    impl CloudBackend for WriteOnly {
+        async fn upload_save(&self, _game_save: &GameSave, _user_id: &str) -> Result<SaveMetadata> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn download_save(&self, _metadata: &SaveMetadata, _local_path: &Path) -> Result<()> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn list_saves(&self, _user_id: &str, _game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn delete_save(&self, _metadata: &SaveMetadata) -> Result<()> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn resume_upload(&self, _upload_id: &str, _offset: u64, _data: Bytes) -> Result<UploadProgress> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn test_connection(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn get_storage_info(&self, _user_id: &str) -> Result<StorageInfo> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        fn save_prefix(&self) -> &str {
+            "backups/"
+        }
+        async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+            self.0.put_object(key, data).await
+        }
+        async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+            self.0.put_archive(key, archive).await
+        }
+        async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+            Err(anyhow::anyhow!("{} read back from the primary", key))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_is_written_to_every_replica_once() {
+        let primary_dir = TempDir::new().unwrap();
+        let nas_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+
+        let primary = WriteOnly(LocalFolderBackend::with_root(primary_dir.path().to_path_buf()));
+        let nas = LocalFolderBackend::with_root(nas_dir.path().to_path_buf());
+        let backend = ReplicatedBackend::new("COS", Box::new(primary), vec![("NAS".to_string(), Box::new(nas))]);
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        let metadata = backend.upload_save(&game_save, "user-1").await.unwrap();
+        assert!(metadata.file_id.starts_with("backups/user-1/105600_"), "{}", metadata.file_id);
+        for dir in [&primary_dir, &nas_dir] {
+            let (_, stored) = crate::archive::hash_file(&dir.path().join(&metadata.file_id)).unwrap();
+            assert_eq!(stored, metadata.checksum);
+            assert!(dir.path().join(VersionInfo::sidecar_key(&metadata.file_id)).exists());
+        }
+        assert_eq!(backend.status()[1].state, ReplicaState::Unknown);
+        assert!(backend.status()[1].last_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replica_catches_up_and_serves_downloads() {
+        let primary_dir = TempDir::new().unwrap();
+        let nas_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        let restore_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+
+        let (primary, primary_online) = switchable(primary_dir.path());
+        let (nas, nas_online) = switchable(nas_dir.path());
+        let backend = ReplicatedBackend::new("COS", primary, vec![("NAS".to_string(), nas)]);
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        // The NAS misses the upload and falls behind
+        nas_online.store(false, Ordering::SeqCst);
+        let metadata = backend.upload_save(&game_save, "user-1").await.unwrap();
+        let status = backend.status();
+        assert_eq!(status[0].state, ReplicaState::InSync);
+        assert_eq!(status[1].state, ReplicaState::OutOfDate);
+        assert!(status[1].pending_objects > 0);
+        assert!(status[1].last_error.is_some());
+        assert!(backend.catch_up().await.is_err());
+
+        // Back online, the catch-up pass copies what it missed
+        nas_online.store(true, Ordering::SeqCst);
+        backend.catch_up().await.unwrap();
+        let status = backend.status();
+        assert_eq!(status[1].state, ReplicaState::InSync);
+        assert_eq!(status[1].pending_objects, 0);
+        assert!(status[1].last_synced.is_some());
+        assert!(nas_dir.path().join(&metadata.file_id).exists());
+        assert!(nas_dir.path().join(VersionInfo::sidecar_key(&metadata.file_id)).exists());
+
+        // Later writes are mirrored directly
+        backend.put_object("index/user-1.json", Bytes::from_static(b"{}")).await.unwrap();
+        assert!(nas_dir.path().join("index/user-1.json").exists());
+
+        // With the primary down, downloads come from the NAS
+        primary_online.store(false, Ordering::SeqCst);
+        backend.download_save(&metadata, restore_dir.path()).await.unwrap();
+        assert_eq!(tokio::fs::read(restore_dir.path().join("slot1.sav")).await.unwrap(), b"slot one");
+        assert!(backend.status()[0].last_error.is_some());
+        // Writes still need the primary
+        assert!(backend.put_object("x", Bytes::from_static(b"x")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missed_delete_is_applied_by_catch_up() {
+        let primary_dir = TempDir::new().unwrap();
+        let nas_dir = TempDir::new().unwrap();
+        let (primary, _) = switchable(primary_dir.path());
+        let (nas, nas_online) = switchable(nas_dir.path());
+        let backend = ReplicatedBackend::new("COS", primary, vec![("NAS".to_string(), nas)]);
+
+        backend.put_object("chunks/a", Bytes::from_static(b"a")).await.unwrap();
+        // Only on the NAS, e.g. left from a save the primary lost: kept
+        tokio::fs::write(nas_dir.path().join("orphan"), b"o").await.unwrap();
+
+        nas_online.store(false, Ordering::SeqCst);
+        backend.delete_object("chunks/a").await.unwrap();
+        assert_eq!(backend.status()[1].state, ReplicaState::OutOfDate);
+
+        nas_online.store(true, Ordering::SeqCst);
+        backend.catch_up().await.unwrap();
+        assert!(!nas_dir.path().join("chunks/a").exists());
+        assert!(nas_dir.path().join("orphan").exists());
+        assert_eq!(backend.status()[1].state, ReplicaState::InSync);
+    }
+
+    #[tokio::test]
+    async fn test_missed_delete_survives_restart() {
+        let primary_dir = TempDir::new().unwrap();
+        let nas_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+        let open = || {
+            let (primary, _) = switchable(primary_dir.path());
+            let (nas, nas_online) = switchable(nas_dir.path());
+            (ReplicatedBackend::new("COS", primary, vec![("NAS".to_string(), nas)]), nas_online)
+        };
+
+        let (backend, nas_online) = open();
+        let metadata = backend.upload_save(&game_save, "user-1").await.unwrap();
+        assert!(nas_dir.path().join(&metadata.file_id).exists());
+        assert!(nas_dir.path().join(VersionInfo::sidecar_key(&metadata.file_id)).exists());
+        nas_online.store(false, Ordering::SeqCst);
+        backend.delete_save(&metadata).await.unwrap();
+        assert!(primary_dir.path().join(pending_deletes_key("NAS")).exists());
+        drop(backend);
+
+        // A new session learns about the delete from the primary
+        let (backend, _) = open();
+        backend.catch_up().await.unwrap();
+        assert!(!nas_dir.path().join(&metadata.file_id).exists());
+        assert!(!primary_dir.path().join(pending_deletes_key("NAS")).exists());
+        assert!(!nas_dir.path().join(pending_deletes_key("NAS")).exists());
+        assert_eq!(backend.status()[1].state, ReplicaState::InSync);
+    }
+}
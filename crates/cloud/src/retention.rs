@@ -0,0 +1,198 @@
+use crate::SaveMetadata;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Which save versions of a game to keep; the others are pruned after an upload.
+///
+/// The newest version, labelled versions and versions whose upload time can't be read
+/// are always kept, whatever the policy says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    /// Never prune
+    #[default]
+    KeepAll,
+    /// Keep the `count` newest versions
+    KeepLast { count: u32 },
+    /// Grandfather-father-son: the `last` newest versions, plus the newest version of
+    /// each day of the last `daily` days, each ISO week of the last `weekly` weeks and
+    /// each month of the last `monthly` months (UTC)
+    Gfs { last: u32, daily: u32, weekly: u32, monthly: u32 },
+}
+
+/// The policy of every game, with per-game overrides
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionRules {
+    pub default: RetentionPolicy,
+    /// Game id -> policy replacing the default
+    pub per_game: HashMap<String, RetentionPolicy>,
+}
+
+impl RetentionRules {
+    pub fn policy_for(&self, game_id: &str) -> RetentionPolicy {
+        self.per_game.get(game_id).copied().unwrap_or(self.default)
+    }
+}
+
+/// What applying a policy to a game's versions would do, newest first
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPlan {
+    pub keep: Vec<SaveMetadata>,
+    pub prune: Vec<SaveMetadata>,
+}
+
+impl RetentionPlan {
+    pub fn pruned_bytes(&self) -> u64 {
+        self.prune.iter().map(|save| save.size_bytes).sum()
+    }
+}
+
+/// Split the versions of one game into those `policy` keeps and those it prunes, as of
+/// `now`. Nothing is deleted; see [`crate::CloudSaveService::apply_retention`].
+pub fn plan_retention(saves: Vec<SaveMetadata>, policy: RetentionPolicy, now: DateTime<Utc>) -> RetentionPlan {
+    let mut dated: Vec<(Option<DateTime<Utc>>, SaveMetadata)> = saves.into_iter()
+        .map(|save| (uploaded_at(&save), save))
+        .collect();
+    dated.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+
+    let (last, daily, weekly, monthly) = match policy {
+        RetentionPolicy::KeepAll => {
+            return RetentionPlan { keep: dated.into_iter().map(|(_, save)| save).collect(), prune: Vec::new() };
+        }
+        RetentionPolicy::KeepLast { count } => (count, 0, 0, 0),
+        RetentionPolicy::Gfs { last, daily, weekly, monthly } => (last, daily, weekly, monthly),
+    };
+
+    let today = now.date_naive();
+    let this_month = today.year() * 12 + today.month0() as i32;
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut months = HashSet::new();
+    let mut newest_dated = 0;
+
+    let mut plan = RetentionPlan::default();
+    for (time, save) in dated {
+        let labelled = save.info.as_ref().is_some_and(|info| info.label.is_some());
+        let Some(time) = time else {
+            plan.keep.push(save);
+            continue;
+        };
+        newest_dated += 1;
+        let day = time.date_naive();
+        let week = day.iso_week();
+        let month = day.year() * 12 + day.month0() as i32;
+        let weeks_ago = (week_start(today) - week_start(day)).num_weeks();
+
+        // Always evaluate every bucket, so an older version can't claim a day, week or
+        // month whose newest version is kept for another reason
+        let in_last = newest_dated <= last.max(1);
+        let in_daily = (today - day).num_days() < daily as i64 && days.insert(day);
+        let in_weekly = weeks_ago < weekly as i64 && weeks.insert((week.year(), week.week()));
+        let in_monthly = this_month - month < monthly as i32 && months.insert(month);
+
+        if in_last || in_daily || in_weekly || in_monthly || labelled {
+            plan.keep.push(save);
+        } else {
+            plan.prune.push(save);
+        }
+    }
+    plan
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(day.weekday().num_days_from_monday() as i64)
+}
+
+fn uploaded_at(save: &SaveMetadata) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&save.timestamp).ok().map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VersionInfo;
+
+    fn save(id: &str, timestamp: &str) -> SaveMetadata {
+        SaveMetadata {
+            game_id: "105600".to_string(),
+            timestamp: timestamp.to_string(),
+            size_bytes: 10,
+            checksum: String::new(),
+            compressed: true,
+            encrypted: false,
+            file_id: id.to_string(),
+            info: None,
+        }
+    }
+
+    fn ids(saves: &[SaveMetadata]) -> Vec<&str> {
+        saves.iter().map(|save| save.file_id.as_str()).collect()
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-03-20T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_keep_last_keeps_newest_and_protected_versions() {
+        let mut labelled = save("labelled", "2024-01-01T00:00:00Z");
+        labelled.info = Some(VersionInfo { label: Some("before boss".to_string()), ..VersionInfo::default() });
+        let saves = vec![
+            save("a", "2024-03-18T10:00:00Z"),
+            save("b", "2024-03-20T10:00:00Z"),
+            labelled,
+            save("undated", ""),
+            save("c", "2024-03-19T10:00:00Z"),
+        ];
+
+        let plan = plan_retention(saves.clone(), RetentionPolicy::KeepLast { count: 2 }, now());
+        assert_eq!(ids(&plan.keep), vec!["b", "c", "labelled", "undated"]);
+        assert_eq!(ids(&plan.prune), vec!["a"]);
+
+        // The newest version is kept even by a policy of zero
+        let plan = plan_retention(saves.clone(), RetentionPolicy::KeepLast { count: 0 }, now());
+        assert_eq!(ids(&plan.prune), vec!["c", "a"]);
+
+        assert!(plan_retention(saves, RetentionPolicy::KeepAll, now()).prune.is_empty());
+    }
+
+    #[test]
+    fn test_gfs_keeps_newest_version_per_period() {
+        let saves = vec![
+            save("today-2", "2024-03-20T11:00:00Z"),
+            save("today-1", "2024-03-20T09:00:00Z"),
+            save("yesterday-2", "2024-03-19T20:00:00Z"),
+            save("yesterday-1", "2024-03-19T08:00:00Z"),
+            // Monday of this week is outside the two days
+            save("monday", "2024-03-18T08:00:00Z"),
+            save("last-week", "2024-03-14T08:00:00Z"),
+            save("last-week-early", "2024-03-11T08:00:00Z"),
+            save("february", "2024-02-10T08:00:00Z"),
+            save("january", "2024-01-05T08:00:00Z"),
+            save("last-year", "2023-12-31T08:00:00Z"),
+        ];
+        let policy = RetentionPolicy::Gfs { last: 1, daily: 2, weekly: 2, monthly: 3 };
+
+        let plan = plan_retention(saves, policy, now());
+        assert_eq!(ids(&plan.keep), vec!["today-2", "yesterday-2", "last-week", "february", "january"]);
+        assert_eq!(ids(&plan.prune), vec!["today-1", "yesterday-1", "monday", "last-week-early", "last-year"]);
+        assert_eq!(plan.pruned_bytes(), 50);
+    }
+
+    #[test]
+    fn test_policy_round_trips_as_json() {
+        let policy = RetentionPolicy::Gfs { last: 3, daily: 7, weekly: 4, monthly: 6 };
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(json, r#"{"kind":"gfs","last":3,"daily":7,"weekly":4,"monthly":6}"#);
+        assert_eq!(serde_json::from_str::<RetentionPolicy>(&json).unwrap(), policy);
+        assert_eq!(serde_json::from_str::<RetentionPolicy>(r#"{"kind":"keep_all"}"#).unwrap(), RetentionPolicy::KeepAll);
+
+        let rules = RetentionRules {
+            default: RetentionPolicy::KeepLast { count: 5 },
+            per_game: [("105600".to_string(), policy)].into_iter().collect(),
+        };
+        assert_eq!(rules.policy_for("105600"), policy);
+        assert_eq!(rules.policy_for("413150"), RetentionPolicy::KeepLast { count: 5 });
+    }
+}
@@ -0,0 +1,536 @@
+use crate::{
+    cancel, object_key, progress, CloudBackend, LegacySave, ObjectInfo, ReplicatedBackend, SaveArchive, SaveMetadata,
+    StorageInfo, TransferProgress, UploadProgress, UploadSessionStore, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use steam_cloud_sync_core::GameSave;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Connect timeout for the HTTP clients of the COS and WebDAV backends
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// HTTP client shared setup: fail fast on unreachable hosts. There is no overall
+/// `RetryingBackend` times out requests and transfers that stall instead.
+/// `RetryingBackend` bounds whole operations instead.
+pub(crate) fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+/// How `RetryingBackend` retries and bounds backend operations
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per operation, including the first one
+    pub max_attempts: u32,
+    /// Backoff before the first retry; doubled for every further retry
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Limit for a single attempt of a metadata or object request
+    pub request_timeout: Duration,
+    /// Limit for a single attempt of a long operation that reports no progress,
+    /// such as a server-side move or a repair
+    pub transfer_timeout: Duration,
+    /// How long an upload or download may go without reporting progress before the
+    /// attempt counts as hung. Transfers that keep moving are never cut off, however
+    /// large the save or slow the connection.
+    pub transfer_idle_timeout: Duration,
+    /// Limit for all attempts of an operation together, backoff included. Transfers
+    /// aren't cut off by it, but aren't retried once it has passed either.
+    pub overall_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(60),
+            transfer_timeout: Duration::from_secs(30 * 60),
+            transfer_idle_timeout: Duration::from_secs(5 * 60),
+            overall_timeout: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `retry` (0-based): exponential, capped, with the
+    /// upper half randomized so clients that failed together don't retry together
+    fn backoff(&self, retry: u32) -> Duration {
+        let full = self.initial_backoff
+            .saturating_mul(1u32 << retry.min(16))
+            .min(self.max_backoff);
+        let half = full / 2;
+        let jitter_ms = half.as_millis() as u64;
+        let jitter = if jitter_ms == 0 { 0 } else { (Uuid::new_v4().as_u128() % (jitter_ms as u128 + 1)) as u64 };
+        half + Duration::from_millis(jitter)
+    }
+}
+
+/// Whether an operation that failed with `error` may succeed when tried again:
+/// timeouts, dropped connections, throttling and 5xx responses are retryable;
+/// authentication, missing objects, checksum mismatches, cancellation and the like are not
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    if cancel::is_cancelled(error) {
+        return false;
+    }
+    for cause in error.chain() {
+        if cause.is::<tokio::time::error::Elapsed>() {
+            return true;
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = e.status() {
+                return is_retryable_status(status.as_u16());
+            }
+            if e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() {
+                return true;
+            }
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            use std::io::ErrorKind::*;
+            return matches!(
+                e.kind(),
+                TimedOut | ConnectionReset | ConnectionAborted | ConnectionRefused | NotConnected
+                    | BrokenPipe | UnexpectedEof | Interrupted | WouldBlock
+            );
+        }
+        if let Some(e) = cause.downcast_ref::<ssh2::Error>() {
+            // Socket send/receive, timeout and disconnect errors of libssh2
+            return matches!(e.code(), ssh2::ErrorCode::Session(-7 | -9 | -13 | -30 | -43));
+        }
+    }
+
+    // Most backends report HTTP failures as "<what>: <status> - <body>", and the AWS SDK
+    // describes transport failures and throttling in its messages
+    let message = error.chain().map(|cause| cause.to_string()).collect::<Vec<_>>().join(": ").to_lowercase();
+    [408, 429, 500, 502, 503, 504].iter().any(|code| message.contains(&format!(": {} ", code)))
+        || [
+            "dispatch failure", "timeout", "timed out", "connection reset", "connection closed",
+            "slowdown", "slow down", "throttl", "internalerror", "serviceunavailable", "requesttimeout",
+        ].iter().any(|needle| message.contains(needle))
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500..=599)
+}
+
+/// How long a single attempt of an operation may run
+#[derive(Debug, Clone, Copy)]
+enum Limit {
+    /// At most this long, and no longer than the operation has left overall
+    Fixed(Duration),
+    /// For as long as it keeps reporting progress (see [`RetryPolicy::transfer_idle_timeout`])
+    WhileProgressing,
+}
+
+/// Run `future` until it completes, or `None` once it has reported no progress for
+/// `idle`. Reports go to the current operation's tracker, or a private one if it has none.
+async fn until_idle<F: Future>(idle: Duration, future: F) -> Option<F::Output> {
+    let tracker = progress::current().unwrap_or_else(TransferProgress::new);
+    let future = progress::scope(tracker.clone(), future);
+    tokio::pin!(future);
+
+    let check_every = (idle / 4).max(Duration::from_millis(1));
+    let mut seen = tracker.activity();
+    let mut last_change = Instant::now();
+    loop {
+        tokio::select! {
+            output = &mut future => return Some(output),
+            _ = tokio::time::sleep(check_every) => {
+                let activity = tracker.activity();
+                if activity != seen {
+                    seen = activity;
+                    last_change = Instant::now();
+                } else if last_change.elapsed() >= idle {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Wraps any backend and retries its operations on transient failures, with jittered
+/// exponential backoff, a timeout per attempt and one for the operation as a whole.
+/// Errors classified as fatal by [`is_retryable`] are returned immediately.
+pub struct RetryingBackend {
+    inner: Box<dyn CloudBackend>,
+    policy: RetryPolicy,
+}
+
+impl RetryingBackend {
+    pub fn new(inner: Box<dyn CloudBackend>) -> Self {
+        Self::with_policy(inner, RetryPolicy::default())
+    }
+
+    pub fn with_policy(inner: Box<dyn CloudBackend>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Run `attempt` until it succeeds, fails fatally, or attempts or time run out
+    async fn run<T, F, Fut>(&self, operation: &str, limit: Limit, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let deadline = Instant::now() + self.policy.overall_timeout;
+        let max_attempts = self.policy.max_attempts.max(1);
+
+        for number in 1..=max_attempts {
+            let error = match limit {
+                Limit::Fixed(attempt_timeout) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    let limit = attempt_timeout.min(remaining);
+                    match tokio::time::timeout(limit, attempt()).await {
+                        Ok(Ok(value)) => return Ok(value),
+                        Ok(Err(e)) => e,
+                        Err(elapsed) => anyhow::Error::new(elapsed)
+                            .context(format!("{} timed out after {}s", operation, limit.as_secs())),
+                    }
+                }
+                Limit::WhileProgressing => {
+                    let idle = self.policy.transfer_idle_timeout;
+                    match until_idle(idle, attempt()).await {
+                        Some(Ok(value)) => return Ok(value),
+                        Some(Err(e)) => e,
+                        None => anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::TimedOut))
+                            .context(format!("{} made no progress for {}s", operation, idle.as_secs())),
+                    }
+                }
+            };
+
+            if !is_retryable(&error) {
+                return Err(error);
+            }
+            if number == max_attempts {
+                eprintln!("[Retry] Giving up on {} after {} attempts: {:#}", operation, number, error);
+                return Err(error);
+            }
+
+            let backoff = self.policy.backoff(number - 1);
+            if Instant::now() + backoff >= deadline {
+                eprintln!("[Retry] Giving up on {}: no time left for another attempt: {:#}", operation, error);
+                return Err(error);
+            }
+            eprintln!(
+                "[Retry] {} failed (attempt {}/{}), retrying in {}ms: {:#}",
+                operation, number, max_attempts, backoff.as_millis(), error
+            );
+            cancel::cancellable(async {
+                tokio::time::sleep(backoff).await;
+                Ok(())
+            }).await?;
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+}
+
+#[async_trait]
+impl CloudBackend for RetryingBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        // Every attempt writes the same version, so one that landed unnoticed isn't duplicated
+        object_key::pinned(
+            self.run("upload_save", Limit::WhileProgressing, || self.inner.upload_save(game_save, user_id))
+        ).await
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        self.run("download_save", Limit::WhileProgressing, || self.inner.download_save(metadata, local_path)).await
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        self.run("list_saves", Limit::Fixed(self.policy.request_timeout), || self.inner.list_saves(user_id, game_id)).await
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        self.run("delete_save", Limit::Fixed(self.policy.request_timeout), || self.inner.delete_save(metadata)).await
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        self.run("resume_upload", Limit::WhileProgressing, || self.inner.resume_upload(upload_id, offset, data.clone())).await
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // A connection test should report problems, not hide them behind retries
+        tokio::time::timeout(self.policy.request_timeout, self.inner.test_connection()).await
+            .map_err(|e| anyhow::Error::new(e).context("Connection test timed out"))?
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        self.run("get_storage_info", Limit::Fixed(self.policy.request_timeout), || self.inner.get_storage_info(user_id)).await
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        self.run("get_bucket_storage_info", Limit::Fixed(self.policy.request_timeout), || self.inner.get_bucket_storage_info()).await
+    }
+
+    fn set_upload_session_store(&mut self, store: Arc<dyn UploadSessionStore>) {
+        self.inner.set_upload_session_store(store);
+    }
+
+    fn encrypts_objects(&self) -> bool {
+        self.inner.encrypts_objects()
+    }
+
+    fn replication(&self) -> Option<ReplicatedBackend> {
+        self.inner.replication()
+    }
+
+    fn save_prefix(&self) -> &str {
+        self.inner.save_prefix()
+    }
+
+    async fn estimate_upload(&self, game_save: &GameSave, user_id: &str) -> Result<u64> {
+        self.run("estimate_upload", Limit::Fixed(self.policy.transfer_timeout), || self.inner.estimate_upload(game_save, user_id)).await
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        self.run("put_object", Limit::Fixed(self.policy.request_timeout), || self.inner.put_object(key, data.clone())).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        self.run("get_object", Limit::Fixed(self.policy.request_timeout), || self.inner.get_object(key)).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        self.run("get_archive", Limit::WhileProgressing, || self.inner.get_archive(key)).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        self.run("put_archive", Limit::WhileProgressing, || self.inner.put_archive(key, archive)).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        self.run("object_exists", Limit::Fixed(self.policy.request_timeout), || self.inner.object_exists(key)).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        self.run("list_objects", Limit::Fixed(self.policy.request_timeout), || self.inner.list_objects(prefix)).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.run("delete_object", Limit::Fixed(self.policy.request_timeout), || self.inner.delete_object(key)).await
+    }
+
+    async fn move_object(&self, from: &str, to: &str) -> Result<()> {
+        // A retry after the copy landed finds the object moved; that is reported, not repeated
+        self.run("move_object", Limit::Fixed(self.policy.transfer_timeout), || self.inner.move_object(from, to)).await
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        self.run("get_object_tagged", Limit::Fixed(self.policy.request_timeout), || self.inner.get_object_tagged(key)).await
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        // If a timed-out attempt did land, the retry sees a changed tag and reports a
+        // conflict; callers then re-read, which is what they do for real conflicts too
+        self.run("put_object_if", Limit::Fixed(self.policy.request_timeout), || self.inner.put_object_if(key, data.clone(), expected_tag)).await
+    }
+
+    async fn set_version_label(&self, metadata: &SaveMetadata, label: Option<String>) -> Result<VersionInfo> {
+        self.run("set_version_label", Limit::Fixed(self.policy.request_timeout), || self.inner.set_version_label(metadata, label.clone())).await
+    }
+
+    async fn repair_save(&self, save: &LegacySave, app_id: u32, game_name: &str) -> Result<String> {
+        // Repairs pick up where a failed attempt stopped, so they can be retried as a whole
+        self.run("repair_save", Limit::Fixed(self.policy.transfer_timeout), || self.inner.repair_save(save, app_id, game_name)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ObjectKey, TransferStage};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    /// Fails `list_saves` and `upload_save` with a dropped connection a set number of
+    /// times, and `get_storage_info` with a permission error every time. Downloads
+    /// trickle in slowly, or hang without progress when the target is named "stalled".
+    struct FlakyBackend {
+        failures_left: AtomicU32,
+        calls: Arc<AtomicU32>,
+        uploaded_keys: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FlakyBackend {
+        fn fail_transiently(&self) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failures_left.load(Ordering::SeqCst) > 0 {
+                self.failures_left.fetch_sub(1, Ordering::SeqCst);
+                return Err(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into());
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl CloudBackend for FlakyBackend {
+        async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+            let key = ObjectKey::new(user_id, game_save.app_id, ".zip").to_string();
+            self.uploaded_keys.lock().unwrap().push(key.clone());
+            self.fail_transiently()?;
+            Ok(SaveMetadata {
+                game_id: game_save.app_id.to_string(),
+                timestamp: String::new(),
+                size_bytes: 0,
+                checksum: String::new(),
+                compressed: true,
+                encrypted: false,
+                file_id: key,
+                info: None,
+            })
+        }
+        async fn download_save(&self, _metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+            if local_path.ends_with("stalled") {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                return Ok(());
+            }
+            progress::start_stage(TransferStage::Downloading, 20);
+            for _ in 0..20 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                progress::advance(1);
+            }
+            Ok(())
+        }
+        async fn list_saves(&self, _user_id: &str, _game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+            self.fail_transiently()?;
+            Ok(Vec::new())
+        }
+        async fn delete_save(&self, _metadata: &SaveMetadata) -> Result<()> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn resume_upload(&self, _upload_id: &str, _offset: u64, _data: Bytes) -> Result<UploadProgress> {
+            Err(anyhow::anyhow!("not used in this test"))
+        }
+        async fn test_connection(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn get_storage_info(&self, _user_id: &str) -> Result<StorageInfo> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow::anyhow!("Failed to get storage info from Tencent COS: 403 Forbidden - AccessDenied"))
+        }
+        async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok((0, 0))
+        }
+    }
+
+    fn backend(failures: u32) -> (RetryingBackend, Arc<AtomicU32>) {
+        let (retrying, calls, _) = backend_with_keys(failures);
+        (retrying, calls)
+    }
+
+    fn backend_with_keys(failures: u32) -> (RetryingBackend, Arc<AtomicU32>, Arc<Mutex<Vec<String>>>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let uploaded_keys = Arc::new(Mutex::new(Vec::new()));
+        let inner = FlakyBackend {
+            failures_left: AtomicU32::new(failures),
+            calls: calls.clone(),
+            uploaded_keys: uploaded_keys.clone(),
+        };
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            request_timeout: Duration::from_millis(50),
+            transfer_idle_timeout: Duration::from_millis(80),
+            ..RetryPolicy::default()
+        };
+        (RetryingBackend::with_policy(Box::new(inner), policy), calls, uploaded_keys)
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_retried() {
+        let (retrying, calls) = backend(2);
+        assert!(retrying.list_saves("user", None).await.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let (retrying, calls) = backend(5);
+        assert!(retrying.list_saves("user", None).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fatal_errors_and_timeouts() {
+        let (retrying, calls) = backend(0);
+        assert!(retrying.get_storage_info("user").await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let (retrying, calls) = backend(0);
+        let err = retrying.get_bucket_storage_info().await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retried_uploads_keep_their_key() {
+        let (retrying, calls, uploaded_keys) = backend_with_keys(2);
+        let game_save = GameSave { app_id: 105600, name: "Terraria".to_string(), save_path: "save".into() };
+        let metadata = retrying.upload_save(&game_save, "player").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(*uploaded_keys.lock().unwrap(), vec![metadata.file_id.clone(); 3]);
+
+        // The next upload is a new version
+        let next = retrying.upload_save(&game_save, "player").await.unwrap();
+        assert_ne!(next.file_id, metadata.file_id);
+    }
+
+    #[tokio::test]
+    async fn test_transfers_time_out_only_when_stalled() {
+        let (retrying, _, _) = backend_with_keys(0);
+        let metadata = SaveMetadata {
+            game_id: "105600".to_string(),
+            timestamp: String::new(),
+            size_bytes: 20,
+            checksum: String::new(),
+            compressed: true,
+            encrypted: false,
+            file_id: "saves/player/x.zip".to_string(),
+            info: None,
+        };
+
+        // Takes longer than the idle timeout, but keeps reporting progress
+        let started = Instant::now();
+        retrying.download_save(&metadata, Path::new("restored")).await.unwrap();
+        assert!(started.elapsed() > Duration::from_millis(150));
+
+        let err = retrying.download_save(&metadata, Path::new("stalled")).await.unwrap_err();
+        assert!(err.to_string().contains("made no progress"), "{}", err);
+    }
+
+    #[test]
+    fn test_error_classification() {
+        assert!(is_retryable(&anyhow::anyhow!("Failed to upload x to Tencent COS: 503 Service Unavailable - busy")));
+        assert!(is_retryable(&anyhow::anyhow!("Failed to list objects on Tencent COS: 429 Too Many Requests - ")));
+        assert!(is_retryable(&anyhow::anyhow!("dispatch failure")));
+        assert!(!is_retryable(&anyhow::anyhow!("Failed to delete x from WebDAV: 401 Unauthorized - ")));
+        assert!(!is_retryable(&anyhow::anyhow!("Checksum mismatch for x: expected a, got b")));
+        assert!(!is_retryable(&std::io::Error::from(std::io::ErrorKind::NotFound).into()));
+        assert!(is_retryable(&std::io::Error::from(std::io::ErrorKind::TimedOut).into()));
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::default();
+        for retry in 0..10 {
+            let backoff = policy.backoff(retry);
+            let full = (policy.initial_backoff * (1 << retry)).min(policy.max_backoff);
+            assert!(backoff >= full / 2 && backoff <= full, "{:?} outside {:?}", backoff, full);
+        }
+    }
+}
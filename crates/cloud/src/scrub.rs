@@ -0,0 +1,329 @@
+use crate::chunked::ChunkedBackend;
+use crate::encryption::is_tampered;
+use crate::incremental::IncrementalBackend;
+use crate::retry::is_retryable;
+use crate::version_info::{self, FORMAT_CHUNKED, FORMAT_FILES};
+use crate::{CloudBackend, SaveMetadata};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::BufReader;
+use std::path::Path;
+use uuid::Uuid;
+
+/// What scrubbing a save version found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrubOutcome {
+    /// Matches its recorded checksum and restores
+    Ok,
+    /// Stored, but doesn't match its checksum, fails authentication or doesn't open
+    Corrupt,
+    /// Listed, but its object is gone
+    Missing,
+    /// No checksum to compare against, or it couldn't be read (e.g. offline or a
+    /// missing encryption key)
+    Unverifiable,
+}
+
+impl ScrubOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScrubOutcome::Ok => "ok",
+            ScrubOutcome::Corrupt => "corrupt",
+            ScrubOutcome::Missing => "missing",
+            ScrubOutcome::Unverifiable => "unverifiable",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [ScrubOutcome::Ok, ScrubOutcome::Corrupt, ScrubOutcome::Missing, ScrubOutcome::Unverifiable]
+            .into_iter()
+            .find(|outcome| outcome.as_str() == value)
+    }
+}
+
+/// The check of one save version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrubResult {
+    pub file_id: String,
+    pub game_id: String,
+    pub size_bytes: u64,
+    pub outcome: ScrubOutcome,
+    /// Why the version isn't `Ok`
+    pub detail: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Outcome of a scrub run
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrubReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Versions the user has; fewer are checked when scrubbing a sample
+    pub total_versions: usize,
+    pub results: Vec<ScrubResult>,
+}
+
+impl ScrubReport {
+    pub fn count(&self, outcome: ScrubOutcome) -> usize {
+        self.results.iter().filter(|result| result.outcome == outcome).count()
+    }
+
+    /// Versions that aren't `Ok`
+    pub fn problems(&self) -> impl Iterator<Item = &ScrubResult> {
+        self.results.iter().filter(|result| result.outcome != ScrubOutcome::Ok)
+    }
+}
+
+/// `sample` versions picked at random, or all of them
+pub fn sample_versions(mut saves: Vec<SaveMetadata>, sample: Option<usize>) -> Vec<SaveMetadata> {
+    if let Some(sample) = sample.filter(|&sample| sample < saves.len()) {
+        saves.sort_by_cached_key(|_| Uuid::new_v4());
+        saves.truncate(sample);
+    }
+    saves
+}
+
+/// Download a version, compare it with its recorded SHA256 and make sure it opens.
+/// `backend` should be the full stack the version was uploaded through, so encrypted
+/// versions are decrypted and manifests find their chunks.
+pub async fn scrub_version(backend: &dyn CloudBackend, save: &SaveMetadata) -> ScrubResult {
+    let (outcome, detail) = match check_version(backend, save).await {
+        Ok(()) => (ScrubOutcome::Ok, None),
+        Err((outcome, detail)) => (outcome, Some(detail)),
+    };
+    ScrubResult {
+        file_id: save.file_id.clone(),
+        game_id: save.game_id.clone(),
+        size_bytes: save.size_bytes,
+        outcome,
+        detail,
+        checked_at: Utc::now(),
+    }
+}
+
+async fn check_version(backend: &dyn CloudBackend, save: &SaveMetadata) -> Result<(), (ScrubOutcome, String)> {
+    let unverifiable = |e: anyhow::Error| (ScrubOutcome::Unverifiable, e.to_string());
+    if !backend.object_exists(&save.file_id).await.map_err(unverifiable)? {
+        return Err((ScrubOutcome::Missing, format!("{} is not in storage", save.file_id)));
+    }
+
+    let expected = version_info::expected_checksum(backend, save).await;
+    if expected.len() != 64 {
+        return Err((ScrubOutcome::Unverifiable, "No SHA256 recorded for this version".to_string()));
+    }
+    // Streamed into a temporary file and hashed on the way. Ciphertext that fails
+    // authentication was damaged in storage; other read errors (e.g. a different key)
+    // leave the version unverified.
+    let corrupt = |e: anyhow::Error| (ScrubOutcome::Corrupt, e.to_string());
+    let archive = backend.get_archive(&save.file_id).await
+        .map_err(|e| if is_tampered(&e) { corrupt(e) } else { unverifiable(e) })?;
+    if archive.checksum() != expected {
+        return Err((ScrubOutcome::Corrupt, format!("Checksum mismatch: expected {}, got {}", expected, archive.checksum())));
+    }
+
+    if is_manifest(save) {
+        // Rebuilding the save fetches and verifies every chunk or file it lists; failing
+        // to reach storage says nothing about the version
+        let scratch = tempfile::TempDir::new().map_err(|e| unverifiable(e.into()))?;
+        backend.download_save(save, &scratch.path().join("restore")).await
+            .map_err(|e| if is_retryable(&e) { unverifiable(e) } else { corrupt(e) })
+    } else {
+        let path = archive.path().to_path_buf();
+        tokio::task::spawn_blocking(move || test_archive(&path))
+            .await
+            .map_err(|e| unverifiable(e.into()))?
+            .map_err(corrupt)
+    }
+}
+
+fn is_manifest(save: &SaveMetadata) -> bool {
+    match save.info.as_ref().map(|info| info.archive_format.as_str()) {
+        Some(FORMAT_CHUNKED | FORMAT_FILES) => true,
+        _ => ChunkedBackend::is_manifest(&save.file_id) || IncrementalBackend::is_file_manifest(&save.file_id),
+    }
+}
+
+/// Read every entry of a zip archive to the end, which checks their CRCs
+fn test_archive(path: &Path) -> anyhow::Result<()> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(std::fs::File::open(path)?))
+        .map_err(|e| anyhow::anyhow!("Archive doesn't open: {}", e))?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let name = entry.name().to_string();
+        std::io::copy(&mut entry, &mut std::io::sink())
+            .map_err(|e| anyhow::anyhow!("Archive entry {} is damaged: {}", name, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EncryptedBackend, Keyring, LocalFolderBackend, ObjectInfo, StorageInfo, UploadProgress};
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use steam_cloud_sync_core::GameSave;
+    use tempfile::TempDir;
+
+    /// Local storage whose stored files time out while `offline` is set
+    struct FlakyStorage {
+        inner: LocalFolderBackend,
+        offline: AtomicBool,
+    }
+
+    #[async_trait]
+    impl CloudBackend for FlakyStorage {
+        async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> anyhow::Result<SaveMetadata> {
+            self.inner.upload_save(game_save, user_id).await
+        }
+        async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> anyhow::Result<()> {
+            self.inner.download_save(metadata, local_path).await
+        }
+        async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> anyhow::Result<Vec<SaveMetadata>> {
+            self.inner.list_saves(user_id, game_id).await
+        }
+        async fn delete_save(&self, metadata: &SaveMetadata) -> anyhow::Result<()> {
+            self.inner.delete_save(metadata).await
+        }
+        async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> anyhow::Result<UploadProgress> {
+            self.inner.resume_upload(upload_id, offset, data).await
+        }
+        async fn test_connection(&self) -> anyhow::Result<()> {
+            self.inner.test_connection().await
+        }
+        async fn get_storage_info(&self, user_id: &str) -> anyhow::Result<StorageInfo> {
+            self.inner.get_storage_info(user_id).await
+        }
+        async fn get_bucket_storage_info(&self) -> anyhow::Result<(u64, u32)> {
+            self.inner.get_bucket_storage_info().await
+        }
+        async fn put_object(&self, key: &str, data: Bytes) -> anyhow::Result<()> {
+            self.inner.put_object(key, data).await
+        }
+        async fn get_object(&self, key: &str) -> anyhow::Result<Bytes> {
+            if key.starts_with("files/") && self.offline.load(Ordering::SeqCst) {
+                return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "connection timed out").into());
+            }
+            self.inner.get_object(key).await
+        }
+        async fn object_exists(&self, key: &str) -> anyhow::Result<bool> {
+            self.inner.object_exists(key).await
+        }
+        async fn list_objects(&self, prefix: &str) -> anyhow::Result<Vec<ObjectInfo>> {
+            self.inner.list_objects(prefix).await
+        }
+        async fn delete_object(&self, key: &str) -> anyhow::Result<()> {
+            self.inner.delete_object(key).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scrub_flags_corrupt_and_missing_versions() {
+        let storage_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        let storage = LocalFolderBackend::with_root(storage_dir.path().to_path_buf());
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        let intact = storage.upload_save(&game_save, "user-1").await.unwrap();
+        let result = scrub_version(&storage, &intact).await;
+        assert_eq!((result.outcome, result.detail), (ScrubOutcome::Ok, None));
+
+        let corrupt = storage.upload_save(&game_save, "user-1").await.unwrap();
+        tokio::fs::write(storage_dir.path().join(&corrupt.file_id), b"not the archive").await.unwrap();
+        let result = scrub_version(&storage, &corrupt).await;
+        assert_eq!(result.outcome, ScrubOutcome::Corrupt);
+        assert!(result.detail.unwrap().contains("Checksum mismatch"));
+
+        let missing = storage.upload_save(&game_save, "user-1").await.unwrap();
+        tokio::fs::remove_file(storage_dir.path().join(&missing.file_id)).await.unwrap();
+        assert_eq!(scrub_version(&storage, &missing).await.outcome, ScrubOutcome::Missing);
+
+        // Without a sidecar or a SHA256 in the listing there's nothing to compare against
+        let unrecorded = storage.upload_save(&game_save, "user-1").await.unwrap();
+        tokio::fs::remove_file(storage_dir.path().join(crate::VersionInfo::sidecar_key(&unrecorded.file_id))).await.unwrap();
+        let listed = SaveMetadata { checksum: String::new(), info: None, ..unrecorded };
+        assert_eq!(scrub_version(&storage, &listed).await.outcome, ScrubOutcome::Unverifiable);
+
+        assert_eq!(sample_versions(vec![intact.clone(), corrupt, missing], Some(2)).len(), 2);
+        assert_eq!(sample_versions(vec![intact], Some(5)).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_scrub_checks_encrypted_versions_against_plaintext() {
+        let storage_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        let keyring_path = storage_dir.path().join("keyring.json");
+        Keyring::create("passphrase").unwrap().0.save(&keyring_path).unwrap();
+        let storage = LocalFolderBackend::with_root(storage_dir.path().join("storage"));
+        let backend = EncryptedBackend::new(Box::new(storage), keyring_path, "passphrase".to_string());
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        let metadata = backend.upload_save(&game_save, "user-1").await.unwrap();
+        assert_eq!(scrub_version(&backend, &metadata).await.outcome, ScrubOutcome::Ok);
+
+        // Flipping a byte of the ciphertext fails authentication
+        let path = storage_dir.path().join("storage").join(&metadata.file_id);
+        let mut data = tokio::fs::read(&path).await.unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        tokio::fs::write(&path, data).await.unwrap();
+        let result = scrub_version(&backend, &metadata).await;
+        assert_eq!(result.outcome, ScrubOutcome::Corrupt);
+        assert!(result.detail.unwrap().contains("tampered"));
+
+        // A keyring that never sealed the version can't tell whether it's intact
+        let other_keyring = storage_dir.path().join("other-keyring.json");
+        Keyring::create("passphrase").unwrap().0.save(&other_keyring).unwrap();
+        let other = EncryptedBackend::new(
+            Box::new(LocalFolderBackend::with_root(storage_dir.path().join("storage"))), other_keyring, "passphrase".to_string(),
+        );
+        let result = scrub_version(&other, &metadata).await;
+        assert_eq!(result.outcome, ScrubOutcome::Unverifiable);
+        assert!(result.detail.unwrap().contains("different key"));
+    }
+
+    #[tokio::test]
+    async fn test_scrub_rebuilds_file_manifests_and_tells_outages_from_damage() {
+        let storage_dir = TempDir::new().unwrap();
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+        let backend = IncrementalBackend::new(Box::new(FlakyStorage {
+            inner: LocalFolderBackend::with_root(storage_dir.path().to_path_buf()),
+            offline: AtomicBool::new(false),
+        }));
+        let game_save = GameSave { app_id: 105600, name: "Test Game".to_string(), save_path: save_dir.path().to_path_buf() };
+
+        // Without its sidecar a file manifest is still recognised by its key
+        let uploaded = backend.upload_save(&game_save, "user-1").await.unwrap();
+        tokio::fs::remove_file(storage_dir.path().join(crate::VersionInfo::sidecar_key(&uploaded.file_id))).await.unwrap();
+        let listed = SaveMetadata { info: None, ..uploaded };
+        assert_eq!(scrub_version(&backend, &listed).await.outcome, ScrubOutcome::Ok);
+
+        // A timeout while fetching its files leaves the version unverified, not corrupt
+        let flaky = IncrementalBackend::new(Box::new(FlakyStorage {
+            inner: LocalFolderBackend::with_root(storage_dir.path().to_path_buf()),
+            offline: AtomicBool::new(true),
+        }));
+        let result = scrub_version(&flaky, &listed).await;
+        assert_eq!(result.outcome, ScrubOutcome::Unverifiable);
+        assert!(result.detail.unwrap().contains("Missing content"));
+
+        // A file that is really gone is damage
+        for blob in backend.list_objects("files/").await.unwrap() {
+            tokio::fs::remove_file(storage_dir.path().join(&blob.key)).await.unwrap();
+        }
+        assert_eq!(scrub_version(&backend, &listed).await.outcome, ScrubOutcome::Corrupt);
+    }
+
+    #[test]
+    fn test_outcomes_round_trip() {
+        for outcome in [ScrubOutcome::Ok, ScrubOutcome::Corrupt, ScrubOutcome::Missing, ScrubOutcome::Unverifiable] {
+            assert_eq!(ScrubOutcome::parse(outcome.as_str()), Some(outcome));
+        }
+        assert_eq!(ScrubOutcome::parse("bogus"), None);
+    }
+}
@@ -0,0 +1,852 @@
+use crate::{
+    cancel,
+    object_key::{self, ObjectKey}, progress, sanitize_user_id, throttle::ThrottledRead,
+    version_info::{self, VERSION_INFO_SUFFIX}, CloudBackend, ObjectInfo, SaveArchive, SaveMetadata,
+    StorageInfo, TransferStage, UploadProgress, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use ssh2::{CheckResult, FileStat, KnownHostFileKind, OpenFlags, OpenType, RenameFlags, Session, Sftp};
+use std::io::{Read, Seek, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use steam_cloud_sync_core::GameSave;
+use uuid::Uuid;
+
+/// Suffix of the sidecar file that stored the SHA256 of an archive before version
+/// info sidecars (`.meta.json`) replaced it; still read for older uploads
+const CHECKSUM_SUFFIX: &str = ".sha256";
+/// Marker in temporary upload names, renamed away on completion
+const PARTIAL_SUFFIX: &str = ".partial";
+/// Directory (relative to the remote root) holding in-flight resumable uploads
+const UPLOADS_DIR: &str = ".uploads";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// A read or write on an established connection that doesn't complete within this fails,
+/// so a server that stops answering can't hang a transfer
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+/// Idle sessions older than this are dropped rather than reused, as the server may have closed them
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How to authenticate against the SSH server
+#[derive(Debug, Clone)]
+pub enum SftpAuth {
+    Password(String),
+    /// OpenSSH private key file, optionally protected by a passphrase
+    KeyFile { private_key: PathBuf, passphrase: Option<String> },
+    /// Keys offered by a running ssh-agent / Pageant
+    Agent,
+}
+
+#[derive(Debug, Clone)]
+pub struct SftpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub auth: SftpAuth,
+    /// Absolute or home-relative directory on the server that holds all objects
+    pub remote_root: String,
+}
+
+impl Default for SftpConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 22,
+            username: String::new(),
+            auth: SftpAuth::Agent,
+            remote_root: "steam-cloud-sync".to_string(),
+        }
+    }
+}
+
+/// The file operations below the backend's atomic writes and listings, so they can be
+/// tested without a server
+trait RemoteFs {
+    fn exists(&self, path: &str) -> Result<bool>;
+    fn mkdir(&self, path: &str) -> Result<()>;
+    /// Create or truncate `path` and copy `data` into it
+    fn write(&self, path: &str, data: &mut dyn Read) -> Result<()>;
+    /// Rename `from` to `to`. SFTP v3 servers, OpenSSH's among them, fail if `to` exists.
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+    fn unlink(&self, path: &str) -> Result<()>;
+    /// Entries of `dir`; `None` if it doesn't exist
+    fn readdir(&self, dir: &str) -> Result<Option<Vec<(PathBuf, FileStat)>>>;
+}
+
+impl RemoteFs for Sftp {
+    fn exists(&self, path: &str) -> Result<bool> {
+        match self.stat(Path::new(path)) {
+            Ok(_) => Ok(true),
+            // SSH_FX_NO_SUCH_FILE
+            Err(e) if e.code() == ssh2::ErrorCode::SFTP(2) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn mkdir(&self, path: &str) -> Result<()> {
+        Ok(Sftp::mkdir(self, Path::new(path), 0o755)?)
+    }
+
+    fn write(&self, path: &str, data: &mut dyn Read) -> Result<()> {
+        let mut file = self.create(Path::new(path))?;
+        std::io::copy(data, &mut file)?;
+        file.fsync().ok(); // fsync@openssh.com is optional
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // Servers speaking SFTP v5 or later honour the flags; v3 servers ignore them
+        let flags = RenameFlags::ATOMIC | RenameFlags::OVERWRITE | RenameFlags::NATIVE;
+        Ok(Sftp::rename(self, Path::new(from), Path::new(to), Some(flags))?)
+    }
+
+    fn unlink(&self, path: &str) -> Result<()> {
+        Ok(Sftp::unlink(self, Path::new(path))?)
+    }
+
+    fn readdir(&self, dir: &str) -> Result<Option<Vec<(PathBuf, FileStat)>>> {
+        match Sftp::readdir(self, Path::new(dir)) {
+            Ok(entries) => Ok(Some(entries)),
+            // SSH_FX_NO_SUCH_FILE
+            Err(e) if e.code() == ssh2::ErrorCode::SFTP(2) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Cloud backend for any server reachable over SSH. Objects use the same key
+/// layout as the other backends (`saves/<user>/<app_id>_<timestamp>_<uuid>.zip`),
+/// stored as files below `remote_root`.
+///
+/// libssh2 is blocking, so every operation runs on the blocking pool. The last
+/// session is kept for a short while so bursts of small operations (such as
+/// chunk uploads) don't pay for a new SSH handshake each time.
+pub struct SftpBackend {
+    config: SftpConfig,
+    idle_session: Arc<Mutex<Option<(Sftp, Instant)>>>,
+}
+
+impl Default for SftpBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SftpBackend {
+    pub fn new() -> Self {
+        Self::with_config(SftpConfig::default())
+    }
+
+    pub fn with_config(config: SftpConfig) -> Self {
+        Self { config, idle_session: Arc::new(Mutex::new(None)) }
+    }
+
+    /// Map an object key to a remote path below the root, rejecting keys that would escape it
+    fn remote_path(remote_root: &str, object_key: &str) -> Result<String> {
+        let mut path = remote_root.trim_end_matches('/').to_string();
+        for part in object_key.trim_start_matches('/').split('/') {
+            if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+                return Err(anyhow::anyhow!("Invalid object key: {}", object_key));
+            }
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(part);
+        }
+        Ok(path)
+    }
+
+    fn calculate_sha256(data: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Connect, verify the host key and authenticate
+    fn connect(config: &SftpConfig) -> Result<Sftp> {
+        let address = (config.host.as_str(), config.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Could not resolve SFTP host {}", config.host))?;
+        let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+            .map_err(|e| anyhow::anyhow!("Cannot connect to {}:{}: {}", config.host, config.port, e))?;
+        tcp.set_read_timeout(Some(IO_TIMEOUT))?;
+        tcp.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        let mut session = Session::new()?;
+        session.set_timeout(IO_TIMEOUT.as_millis() as u32);
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+        Self::check_host_key(&session, config)?;
+
+        match &config.auth {
+            SftpAuth::Password(password) => session.userauth_password(&config.username, password)?,
+            SftpAuth::KeyFile { private_key, passphrase } => {
+                session.userauth_pubkey_file(&config.username, None, private_key, passphrase.as_deref())?
+            }
+            SftpAuth::Agent => session.userauth_agent(&config.username)?,
+        }
+        if !session.authenticated() {
+            return Err(anyhow::anyhow!("SFTP authentication failed for user {}", config.username));
+        }
+
+        Ok(session.sftp()?)
+    }
+
+    /// Refuse to talk to a server whose key differs from `~/.ssh/known_hosts`. The key of
+    /// a host missing from the file is added to it, matching `StrictHostKeyChecking=accept-new`,
+    /// so a different key on a later connection is caught.
+    fn check_host_key(session: &Session, config: &SftpConfig) -> Result<()> {
+        let known_hosts_path = dirs::home_dir()
+            .map(|home| home.join(".ssh").join("known_hosts"))
+            .ok_or_else(|| anyhow::anyhow!("Cannot verify the SFTP host key: no home directory to keep known_hosts in"))?;
+        let (key, key_type) = session.host_key().ok_or_else(|| anyhow::anyhow!("SFTP server sent no host key"))?;
+
+        let mut known_hosts = session.known_hosts()?;
+        if known_hosts_path.exists() {
+            known_hosts.read_file(&known_hosts_path, KnownHostFileKind::OpenSSH)?;
+        }
+
+        match known_hosts.check_port(&config.host, config.port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::NotFound => {
+                let name = known_hosts_name(&config.host, config.port);
+                known_hosts.add(&name, key, "", key_type.into())?;
+                let host = known_hosts.hosts()?.into_iter()
+                    .rev()
+                    .find(|host| host.name() == Some(name.as_str()))
+                    .ok_or_else(|| anyhow::anyhow!("Failed to record the host key of {}", config.host))?;
+                let line = known_hosts.write_string(&host, KnownHostFileKind::OpenSSH)?;
+                append_line(&known_hosts_path, line.trim_end())
+                    .map_err(|e| anyhow::anyhow!("Failed to add the host key of {} to {}: {}", config.host, known_hosts_path.display(), e))?;
+                eprintln!("[SFTP] Added the host key of {} to {}", name, known_hosts_path.display());
+                Ok(())
+            }
+            CheckResult::Mismatch => Err(anyhow::anyhow!(
+                "Host key for {} does not match {}. Refusing to connect.",
+                config.host, known_hosts_path.display()
+            )),
+            CheckResult::Failure => Err(anyhow::anyhow!("Failed to check host key for {}", config.host)),
+        }
+    }
+
+    /// Run a blocking closure with an SFTP session, reusing the idle one if it is recent
+    async fn with_sftp<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Sftp, &SftpConfig) -> Result<T> + Send + 'static,
+    {
+        let config = self.config.clone();
+        let idle_session = self.idle_session.clone();
+        // Blocking reads and writes check the operation's token, so cancelling stops them too,
+        // and report to its progress tracker
+        let token = cancel::current();
+        let tracker = progress::current();
+        tokio::task::spawn_blocking(move || cancel::blocking_scope(token, || progress::blocking_scope(tracker, || {
+            let cached = idle_session.lock().unwrap().take()
+                .filter(|(_, last_used)| last_used.elapsed() < SESSION_IDLE_TIMEOUT);
+            let sftp = match cached {
+                Some((sftp, _)) => sftp,
+                None => Self::connect(&config)?,
+            };
+
+            let result = f(&sftp, &config);
+            // A failed operation may have left the session unusable
+            if result.is_ok() {
+                *idle_session.lock().unwrap() = Some((sftp, Instant::now()));
+            }
+            result
+        }))).await?
+    }
+
+    /// Create `dir` and all of its parents (SFTP mkdir is not recursive)
+    fn mkdir_all(fs: &dyn RemoteFs, dir: &str) -> Result<()> {
+        let mut current = if dir.starts_with('/') { "/".to_string() } else { String::new() };
+        for part in dir.split('/').filter(|p| !p.is_empty()) {
+            current.push_str(part);
+            if !fs.exists(&current).unwrap_or(false) {
+                fs.mkdir(&current)?;
+            }
+            current.push('/');
+        }
+        Ok(())
+    }
+
+    /// Write a file under a temporary name, then rename it over the target
+    fn write_atomic(fs: &dyn RemoteFs, path: &str, data: &mut dyn Read) -> Result<()> {
+        if let Some((parent, _)) = path.rsplit_once('/') {
+            Self::mkdir_all(fs, parent)?;
+        }
+
+        let partial = format!("{}.{}{}", path, Uuid::new_v4().simple(), PARTIAL_SUFFIX);
+        if let Err(e) = fs.write(&partial, &mut ThrottledRead::uploading(data)) {
+            // Cancelled or interrupted: don't leave the partial file behind
+            let _ = fs.unlink(&partial);
+            return Err(e);
+        }
+
+        if let Err(e) = Self::replace(fs, &partial, path) {
+            let _ = fs.unlink(&partial);
+            return Err(anyhow::anyhow!("Failed to move {} into place: {}", path, e));
+        }
+        Ok(())
+    }
+
+    /// Rename `from` over `to`. SFTP v3 servers refuse to rename onto an existing file,
+    /// and posix-rename@openssh.com isn't available through libssh2's bindings, so the
+    /// old file is moved aside first and only deleted once the new one is in place.
+    fn replace(fs: &dyn RemoteFs, from: &str, to: &str) -> Result<()> {
+        let Err(e) = fs.rename(from, to) else {
+            return Ok(());
+        };
+        if !fs.exists(to)? {
+            return Err(e);
+        }
+
+        // Named like a partial write, so listings skip it if we get interrupted
+        let old = format!("{}.{}{}", to, Uuid::new_v4().simple(), PARTIAL_SUFFIX);
+        fs.rename(to, &old)?;
+        if let Err(e) = fs.rename(from, to) {
+            let _ = fs.rename(&old, to);
+            return Err(e);
+        }
+        if let Err(e) = fs.unlink(&old) {
+            eprintln!("⚠️  Failed to remove the replaced {}: {}", old, e);
+        }
+        Ok(())
+    }
+
+    /// Recursively list all files below `dir` as (key relative to the root, size, mtime).
+    /// Sidecars, partial writes and in-flight uploads are skipped.
+    fn collect_files(fs: &dyn RemoteFs, root: &str, dir: &str, out: &mut Vec<(String, u64, Option<u64>)>) -> Result<()> {
+        // Missing: nothing uploaded yet
+        let Some(entries) = fs.readdir(dir)? else {
+            return Ok(());
+        };
+
+        for (path, stat) in entries {
+            let path = path.to_string_lossy().replace('\\', "/");
+            let name = path.rsplit('/').next().unwrap_or_default().to_string();
+            if name == "." || name == ".." {
+                continue;
+            }
+
+            let key = path
+                .strip_prefix(root.trim_end_matches('/'))
+                .unwrap_or(&path)
+                .trim_start_matches('/')
+                .to_string();
+
+            if stat.is_dir() {
+                if key == UPLOADS_DIR {
+                    continue;
+                }
+                Self::collect_files(fs, root, &path, out)?;
+            } else if !name.ends_with(CHECKSUM_SUFFIX) && !name.ends_with(VERSION_INFO_SUFFIX) && !name.ends_with(PARTIAL_SUFFIX) {
+                out.push((key, stat.size.unwrap_or(0), stat.mtime));
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_files(&self, prefix: &str) -> Result<Vec<(String, u64, Option<u64>)>> {
+        let prefix = prefix.trim_end_matches('/').to_string();
+        self.with_sftp(move |sftp, config| {
+            let dir = if prefix.is_empty() {
+                config.remote_root.clone()
+            } else {
+                Self::remote_path(&config.remote_root, &prefix)?
+            };
+            let mut files = Vec::new();
+            Self::collect_files(sftp, &config.remote_root, &dir, &mut files)?;
+            Ok(files)
+        }).await
+    }
+}
+
+/// How known_hosts names a host: `host`, or `[host]:port` off the default port
+fn known_hosts_name(host: &str, port: u16) -> String {
+    match port {
+        22 => host.to_string(),
+        port => format!("[{}]:{}", host, port),
+    }
+}
+
+/// Append `line` to a text file, creating it and its directory if needed
+fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let existing = std::fs::read(path).unwrap_or_default();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    if existing.last().is_some_and(|byte| *byte != b'\n') {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", line)
+}
+
+#[async_trait]
+impl CloudBackend for SftpBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let archive = SaveArchive::create(&game_save.save_path).await?;
+        let checksum = archive.checksum().to_string();
+        let size_bytes = archive.size();
+
+        let object_key = ObjectKey::new(&sanitize_user_id(user_id), game_save.app_id, ".zip").to_string();
+
+        let info = VersionInfo::describe(game_save, &checksum, version_info::FORMAT_ZIP).await;
+
+        let key = object_key.clone();
+        let sidecar = serde_json::to_vec(&info)?;
+        let archive_path = archive.path().to_path_buf();
+        progress::start_stage(TransferStage::Uploading, size_bytes);
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            Self::write_atomic(sftp, &path, &mut std::fs::File::open(&archive_path)?)?;
+            // The version is stored either way; a missing sidecar only costs verification
+            if let Err(e) = Self::write_atomic(sftp, &format!("{}{}", path, VERSION_INFO_SUFFIX), &mut sidecar.as_slice()) {
+                eprintln!("⚠️  Failed to store version info for {}: {}", key, e);
+            }
+            Ok(())
+        }).await?;
+
+        eprintln!("[SFTP] Uploaded {} ({} bytes)", object_key, size_bytes);
+
+        Ok(SaveMetadata {
+            game_id: game_save.app_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            size_bytes,
+            checksum,
+            compressed: true,
+            encrypted: false,
+            file_id: object_key,
+            info: Some(info),
+        })
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        let key = metadata.file_id.clone();
+        let archive = self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            let mut file = sftp.open(Path::new(&path))
+                .map_err(|e| anyhow::anyhow!("Failed to open {} on SFTP server: {}", key, e))?;
+            SaveArchive::receive_blocking(&mut file)
+        }).await?;
+
+        let expected_checksum = version_info::expected_checksum(self, metadata).await;
+        if expected_checksum.len() == 64 && archive.checksum() != expected_checksum {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                metadata.file_id, expected_checksum, archive.checksum()
+            ));
+        }
+
+        archive.restore_to(local_path).await?;
+
+        Ok(())
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        let prefix = format!("saves/{}/", sanitize_user_id(user_id));
+        let archives: Vec<_> = self.list_files(&prefix).await?
+            .into_iter()
+            .filter(|(key, _, _)| key.ends_with(".zip"))
+            .collect();
+
+        // Read all sidecars in one session, falling back to the bare checksum of older uploads
+        let keys: Vec<String> = archives.iter().map(|(key, _, _)| key.clone()).collect();
+        let sidecars = self.with_sftp(move |sftp, config| {
+            let mut sidecars = Vec::with_capacity(keys.len());
+            for key in keys {
+                let path = Self::remote_path(&config.remote_root, &key)?;
+                let mut json = Vec::new();
+                let info = sftp.open(Path::new(&format!("{}{}", path, VERSION_INFO_SUFFIX))).ok()
+                    .and_then(|mut file| file.read_to_end(&mut json).ok())
+                    .and_then(|_| serde_json::from_slice::<VersionInfo>(&json).ok());
+                if let Some(info) = info {
+                    sidecars.push((info.sha256.clone(), Some(info)));
+                    continue;
+                }
+
+                let mut checksum = String::new();
+                if let Ok(mut file) = sftp.open(Path::new(&format!("{}{}", path, CHECKSUM_SUFFIX))) {
+                    let _ = file.read_to_string(&mut checksum);
+                }
+                sidecars.push((checksum.trim().to_string(), None));
+            }
+            Ok(sidecars)
+        }).await?;
+
+        let mut saves: Vec<SaveMetadata> = archives.into_iter()
+            .zip(sidecars)
+            .map(|((key, size, mtime), (checksum, info))| SaveMetadata {
+                game_id: object_key::game_id_from_key(&key),
+                timestamp: mtime
+                    .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+                    .unwrap_or_else(chrono::Utc::now)
+                    .to_rfc3339(),
+                size_bytes: size,
+                checksum,
+                compressed: true,
+                encrypted: false,
+                file_id: key,
+                info,
+            })
+            .collect();
+
+        if let Some(gid) = game_id {
+            saves.retain(|save| save.game_id == gid);
+        }
+
+        saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(saves)
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        let key = metadata.file_id.clone();
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            sftp.unlink(Path::new(&path))
+                .map_err(|e| anyhow::anyhow!("Failed to delete {} from SFTP server: {}", key, e))?;
+
+            // The sidecars are optional (older or hand-copied archives may not have them)
+            let _ = sftp.unlink(Path::new(&format!("{}{}", path, CHECKSUM_SUFFIX)));
+            let _ = sftp.unlink(Path::new(&format!("{}{}", path, VERSION_INFO_SUFFIX)));
+            Ok(())
+        }).await
+    }
+
+    async fn resume_upload(&self, upload_id: &str, offset: u64, data: Bytes) -> Result<UploadProgress> {
+        let upload_id = upload_id.chars()
+            .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+            .collect::<String>();
+        if upload_id.is_empty() {
+            return Err(anyhow::anyhow!("Invalid upload id"));
+        }
+
+        let checksum = Self::calculate_sha256(&data);
+        let uploaded = self.with_sftp(move |sftp, config| {
+            let dir = Self::remote_path(&config.remote_root, UPLOADS_DIR)?;
+            Self::mkdir_all(sftp, &dir)?;
+            let path = format!("{}/{}{}", dir, upload_id, PARTIAL_SUFFIX);
+
+            let mut file = sftp.open_mode(Path::new(&path), OpenFlags::WRITE | OpenFlags::CREATE, 0o644, OpenType::File)?;
+            let current_len = file.stat()?.size.unwrap_or(0);
+            if offset > current_len {
+                return Err(anyhow::anyhow!(
+                    "Cannot resume upload {} at offset {}: only {} bytes received so far",
+                    upload_id, offset, current_len
+                ));
+            }
+
+            file.seek(std::io::SeekFrom::Start(offset))?;
+            file.write_all(&data)?;
+            Ok(offset + data.len() as u64)
+        }).await?;
+
+        Ok(UploadProgress {
+            bytes_uploaded: uploaded,
+            total_bytes: uploaded,
+            checksum,
+        })
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        eprintln!("[SFTP] Testing connection to {}@{}:{}", self.config.username, self.config.host, self.config.port);
+        self.with_sftp(|sftp, config| {
+            Self::mkdir_all(sftp, &config.remote_root)
+                .map_err(|e| anyhow::anyhow!("Cannot create remote folder {}: {}", config.remote_root, e))?;
+
+            // Make sure the folder is actually writable
+            let probe = format!("{}/.probe-{}", config.remote_root.trim_end_matches('/'), Uuid::new_v4());
+            sftp.create(Path::new(&probe))
+                .map_err(|e| anyhow::anyhow!("Remote folder {} is not writable: {}", config.remote_root, e))?;
+            sftp.unlink(Path::new(&probe))?;
+            Ok(())
+        }).await
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        let prefix = format!("saves/{}/", sanitize_user_id(user_id));
+        let versions: Vec<_> = self.list_files(&prefix).await?
+            .into_iter()
+            .filter(|(key, _, _)| version_info::counts_towards_usage(key))
+            .collect();
+
+        Ok(StorageInfo {
+            used_bytes: versions.iter().map(|(_, size, _)| size).sum(),
+            total_bytes: None, // SFTP v3 has no portable way to query free space
+            file_count: versions.len() as u32,
+            bucket_used_bytes: None, // Will be filled by combined call
+            bucket_total_objects: None, // Will be filled by combined call
+        })
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        let files = self.list_files("").await?;
+        Ok((files.iter().map(|(_, size, _)| size).sum(), files.len() as u32))
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        let key = key.to_string();
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            Self::write_atomic(sftp, &path, &mut data.as_ref())
+        }).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        let key = key.to_string();
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            let mut file = sftp.open(Path::new(&path))
+                .map_err(|e| anyhow::anyhow!("Failed to open {} on SFTP server: {}", key, e))?;
+            let mut data = Vec::new();
+            ThrottledRead::downloading(&mut file).read_to_end(&mut data)?;
+            Ok(Bytes::from(data))
+        }).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        let key = key.to_string();
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            let mut file = sftp.open(Path::new(&path))
+                .map_err(|e| anyhow::anyhow!("Failed to open {} on SFTP server: {}", key, e))?;
+            SaveArchive::receive_blocking(&mut file)
+        }).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        let key = key.to_string();
+        let local_path = archive.path().to_path_buf();
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            let mut file = std::io::BufReader::new(std::fs::File::open(&local_path)?);
+            Self::write_atomic(sftp, &path, &mut file)
+        }).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        let key = key.to_string();
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            match sftp.stat(Path::new(&path)) {
+                Ok(_) => Ok(true),
+                // SSH_FX_NO_SUCH_FILE
+                Err(e) if e.code() == ssh2::ErrorCode::SFTP(2) => Ok(false),
+                Err(e) => Err(e.into()),
+            }
+        }).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        // Walk the deepest directory the prefix names, then match the rest of it
+        let dir = prefix.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        Ok(self.list_files(dir).await?
+            .into_iter()
+            .filter(|(key, _, _)| key.starts_with(prefix))
+            .map(|(key, size, mtime)| ObjectInfo {
+                key,
+                size_bytes: size,
+                last_modified: mtime.and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0)),
+            })
+            .collect())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        let key = key.to_string();
+        self.with_sftp(move |sftp, config| {
+            let path = Self::remote_path(&config.remote_root, &key)?;
+            match sftp.unlink(Path::new(&path)) {
+                Err(e) if e.code() != ssh2::ErrorCode::SFTP(2) => {
+                    Err(anyhow::anyhow!("Failed to delete {} from SFTP server: {}", key, e))
+                }
+                _ => Ok(()),
+            }
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// In-memory server with SFTP v3 semantics: renames never overwrite
+    #[derive(Default)]
+    struct MockFs {
+        files: Mutex<BTreeMap<String, Vec<u8>>>,
+        dirs: Mutex<BTreeSet<String>>,
+        /// Renames of a file with this content fail, e.g. because the server is out of quota
+        unmovable: Option<Vec<u8>>,
+    }
+
+    impl MockFs {
+        fn read(&self, path: &str) -> Option<Vec<u8>> {
+            self.files.lock().unwrap().get(path).cloned()
+        }
+
+        fn paths(&self) -> Vec<String> {
+            self.files.lock().unwrap().keys().cloned().collect()
+        }
+    }
+
+    impl RemoteFs for MockFs {
+        fn exists(&self, path: &str) -> Result<bool> {
+            Ok(self.files.lock().unwrap().contains_key(path) || self.dirs.lock().unwrap().contains(path))
+        }
+
+        fn mkdir(&self, path: &str) -> Result<()> {
+            self.dirs.lock().unwrap().insert(path.to_string());
+            Ok(())
+        }
+
+        fn write(&self, path: &str, data: &mut dyn Read) -> Result<()> {
+            let mut content = Vec::new();
+            let result = data.read_to_end(&mut content);
+            self.files.lock().unwrap().insert(path.to_string(), content);
+            result?;
+            Ok(())
+        }
+
+        fn rename(&self, from: &str, to: &str) -> Result<()> {
+            let mut files = self.files.lock().unwrap();
+            if files.contains_key(to) || (self.unmovable.is_some() && files.get(from) == self.unmovable.as_ref()) {
+                return Err(anyhow::anyhow!("SSH_FX_FAILURE"));
+            }
+            let data = files.remove(from).ok_or_else(|| anyhow::anyhow!("SSH_FX_NO_SUCH_FILE"))?;
+            files.insert(to.to_string(), data);
+            Ok(())
+        }
+
+        fn unlink(&self, path: &str) -> Result<()> {
+            self.files.lock().unwrap().remove(path);
+            Ok(())
+        }
+
+        fn readdir(&self, dir: &str) -> Result<Option<Vec<(PathBuf, FileStat)>>> {
+            if !self.dirs.lock().unwrap().contains(dir) {
+                return Ok(None);
+            }
+            let stat = |perm, size| FileStat { size: Some(size), uid: None, gid: None, perm: Some(perm), atime: None, mtime: Some(1_700_000_000) };
+            let prefix = format!("{}/", dir);
+            let mut entries = Vec::new();
+            for sub in self.dirs.lock().unwrap().iter() {
+                if sub.strip_prefix(&prefix).is_some_and(|rest| !rest.contains('/')) {
+                    entries.push((PathBuf::from(sub), stat(0o040755, 0)));
+                }
+            }
+            for (path, data) in self.files.lock().unwrap().iter() {
+                if path.strip_prefix(&prefix).is_some_and(|rest| !rest.contains('/')) {
+                    entries.push((PathBuf::from(path), stat(0o100644, data.len() as u64)));
+                }
+            }
+            Ok(Some(entries))
+        }
+    }
+
+    #[test]
+    fn test_known_hosts_entries() {
+        assert_eq!(known_hosts_name("nas.local", 22), "nas.local");
+        assert_eq!(known_hosts_name("nas.local", 2222), "[nas.local]:2222");
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".ssh").join("known_hosts");
+        append_line(&path, "nas.local ssh-ed25519 AAAA").unwrap();
+        std::fs::write(&path, b"nas.local ssh-ed25519 AAAA").unwrap(); // Hand-edited, no final newline
+        append_line(&path, "[nas.local]:2222 ssh-ed25519 BBBB").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "nas.local ssh-ed25519 AAAA\n[nas.local]:2222 ssh-ed25519 BBBB\n"
+        );
+    }
+
+    #[test]
+    fn test_write_atomic_overwrites_existing_key() {
+        let fs = MockFs::default();
+        let path = "/srv/games/saves/index.json";
+        SftpBackend::write_atomic(&fs, path, &mut &b"first"[..]).unwrap();
+        assert!(fs.exists("/srv/games/saves").unwrap());
+
+        SftpBackend::write_atomic(&fs, path, &mut &b"second"[..]).unwrap();
+        assert_eq!(fs.read(path).unwrap(), b"second");
+        assert_eq!(fs.paths(), vec![path.to_string()]);
+    }
+
+    #[test]
+    fn test_failed_replace_keeps_the_old_file() {
+        let path = "/srv/games/saves/index.json";
+        let fs = MockFs { unmovable: Some(b"new".to_vec()), ..MockFs::default() };
+        fs.files.lock().unwrap().insert(path.to_string(), b"old".to_vec());
+
+        let err = SftpBackend::write_atomic(&fs, path, &mut &b"new"[..]).unwrap_err();
+        assert!(err.to_string().contains("Failed to move"), "{}", err);
+        assert_eq!(fs.read(path).unwrap(), b"old");
+        assert_eq!(fs.paths(), vec![path.to_string()]);
+    }
+
+    #[test]
+    fn test_interrupted_write_leaves_no_partial_file() {
+        struct Interrupted;
+        impl Read for Interrupted {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset"))
+            }
+        }
+
+        let fs = MockFs::default();
+        let path = "/srv/games/saves/index.json";
+        fs.files.lock().unwrap().insert(path.to_string(), b"old".to_vec());
+        assert!(SftpBackend::write_atomic(&fs, path, &mut Interrupted).is_err());
+        assert_eq!(fs.read(path).unwrap(), b"old");
+        assert_eq!(fs.paths(), vec![path.to_string()]);
+    }
+
+    #[test]
+    fn test_listing_skips_sidecars_and_partial_files() {
+        let fs = MockFs::default();
+        for dir in ["/srv", "/srv/saves", "/srv/saves/user-1", "/srv/.uploads"] {
+            fs.mkdir(dir).unwrap();
+        }
+        for (path, data) in [
+            ("/srv/saves/user-1/1_a.zip", &b"archive"[..]),
+            ("/srv/saves/user-1/1_a.zip.meta.json", b"{}"),
+            ("/srv/saves/user-1/1_a.zip.sha256", b"abc"),
+            ("/srv/saves/user-1/1_b.zip.0123.partial", b"half"),
+            ("/srv/.uploads/0456.partial", b"part"),
+            ("/srv/index.json", b"[]"),
+        ] {
+            fs.files.lock().unwrap().insert(path.to_string(), data.to_vec());
+        }
+
+        let mut files = Vec::new();
+        SftpBackend::collect_files(&fs, "/srv/", "/srv", &mut files).unwrap();
+        files.sort();
+        assert_eq!(files, vec![
+            ("index.json".to_string(), 2, Some(1_700_000_000)),
+            ("saves/user-1/1_a.zip".to_string(), 7, Some(1_700_000_000)),
+        ]);
+
+        // Nothing uploaded yet
+        let mut files = Vec::new();
+        SftpBackend::collect_files(&fs, "/srv", "/srv/saves/user-2", &mut files).unwrap();
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_remote_path() {
+        assert_eq!(
+            SftpBackend::remote_path("/srv/games/", "saves/user/1_a.zip").unwrap(),
+            "/srv/games/saves/user/1_a.zip"
+        );
+        assert_eq!(SftpBackend::remote_path("", "saves/x.zip").unwrap(), "saves/x.zip");
+        assert!(SftpBackend::remote_path("/srv", "saves/../../etc/passwd").is_err());
+        assert!(SftpBackend::remote_path("/srv", "saves//x.zip").is_err());
+    }
+}
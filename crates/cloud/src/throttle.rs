@@ -0,0 +1,239 @@
+use crate::cancel::{self, Cancelled};
+use crate::progress;
+use anyhow::Result;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Read size when draining a throttled download
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A token bucket limiting throughput to a byte rate, shared by every transfer in
+/// one direction. Transfers take what they send or receive from the bucket and wait
+/// while it is in debt. The rate can be changed at any time; 0 means unlimited.
+pub struct TokenBucket {
+    bytes_per_sec: AtomicU64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    /// Bytes that may pass right now; negative while transfers wait for earlier ones
+    available: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            bytes_per_sec: AtomicU64::new(bytes_per_sec.unwrap_or(0)),
+            state: Mutex::new(BucketState { available: 0.0, updated: Instant::now() }),
+        }
+    }
+
+    /// The current limit, `None` if unlimited
+    pub fn rate(&self) -> Option<u64> {
+        match self.bytes_per_sec.load(Ordering::Relaxed) {
+            0 => None,
+            rate => Some(rate),
+        }
+    }
+
+    pub fn set_rate(&self, bytes_per_sec: Option<u64>) {
+        let rate = bytes_per_sec.unwrap_or(0);
+        if self.bytes_per_sec.swap(rate, Ordering::Relaxed) != rate {
+            // Start over so debt built up under the old limit doesn't carry over
+            let mut state = self.state.lock().unwrap();
+            state.available = 0.0;
+            state.updated = Instant::now();
+        }
+    }
+
+    /// Take `bytes` from the bucket, returning how long the caller has to wait
+    fn reserve(&self, bytes: usize) -> Duration {
+        let rate = self.bytes_per_sec.load(Ordering::Relaxed);
+        if rate == 0 || bytes == 0 {
+            return Duration::ZERO;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        // Up to one second's worth may accumulate while idle
+        let refill = now.duration_since(state.updated).as_secs_f64() * rate as f64;
+        state.available = (state.available + refill).min(rate as f64);
+        state.updated = now;
+        state.available -= bytes as f64;
+
+        if state.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.available / rate as f64)
+        }
+    }
+
+    /// Wait until `bytes` may be transferred
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Blocking counterpart of [`TokenBucket::acquire`] for synchronous transports
+    pub fn acquire_blocking(&self, bytes: usize) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// Upload and download limits applied to all backends
+pub struct Throttle {
+    pub upload: TokenBucket,
+    pub download: TokenBucket,
+}
+
+/// The process-wide throttle, unlimited until [`set_bandwidth_limits`] is called
+pub fn throttle() -> &'static Throttle {
+    static THROTTLE: OnceLock<Throttle> = OnceLock::new();
+    THROTTLE.get_or_init(|| Throttle {
+        upload: TokenBucket::new(None),
+        download: TokenBucket::new(None),
+    })
+}
+
+/// Set the upload and download limits in bytes per second (`None`: unlimited).
+/// Transfers in progress pick up the new limits immediately.
+pub fn set_bandwidth_limits(upload_bytes_per_sec: Option<u64>, download_bytes_per_sec: Option<u64>) {
+    throttle().upload.set_rate(upload_bytes_per_sec);
+    throttle().download.set_rate(download_bytes_per_sec);
+}
+
+/// Throttle a body stream being uploaded; it fails once the operation is cancelled
+pub(crate) fn upload_stream<S>(stream: S) -> impl Stream<Item = std::io::Result<Bytes>>
+where
+    S: Stream<Item = std::io::Result<Bytes>>,
+{
+    stream.then(|chunk| async move {
+        let data = chunk?;
+        cancel::check().map_err(|_| std::io::Error::other(Cancelled))?;
+        throttle().upload.acquire(data.len()).await;
+        progress::advance(data.len() as u64);
+        Ok(data)
+    })
+}
+
+/// A throttled request body for data already in memory, sent in slices so the
+/// limit applies while it is sent rather than before
+pub(crate) fn upload_body(data: Bytes) -> reqwest::Body {
+    if throttle().upload.rate().is_none() {
+        progress::advance(data.len() as u64);
+        return reqwest::Body::from(data);
+    }
+    let slices: Vec<Result<Bytes, std::io::Error>> = (0..data.len())
+        .step_by(READ_CHUNK_SIZE)
+        .map(|start| Ok(data.slice(start..(start + READ_CHUNK_SIZE).min(data.len()))))
+        .collect();
+    reqwest::Body::wrap_stream(upload_stream(futures::stream::iter(slices)))
+}
+
+/// Wait until a fully buffered upload (e.g. one multipart part) may be sent
+pub(crate) async fn before_upload(len: usize) {
+    throttle().upload.acquire(len).await;
+    progress::advance(len as u64);
+}
+
+/// Read a download to the end, throttled
+pub(crate) async fn read_download<R: AsyncRead + Unpin>(mut reader: R) -> Result<Bytes> {
+    let mut data = BytesMut::new();
+    let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        cancel::check()?;
+        throttle().download.acquire(read).await;
+        progress::advance(read as u64);
+        data.extend_from_slice(&buffer[..read]);
+    }
+    Ok(data.freeze())
+}
+
+/// Read the body of a successful HTTP response, throttled
+pub(crate) async fn read_response(response: reqwest::Response) -> Result<Bytes> {
+    let stream = response.bytes_stream()
+        .map(|chunk| chunk.map_err(std::io::Error::other));
+    read_download(tokio_util::io::StreamReader::new(stream)).await
+}
+
+/// Wraps a blocking reader so everything read through it counts against a bucket
+/// and towards the operation's progress
+pub(crate) struct ThrottledRead<'a, R> {
+    inner: R,
+    bucket: &'a TokenBucket,
+}
+
+impl<'a, R: Read> ThrottledRead<'a, R> {
+    pub fn uploading(inner: R) -> Self {
+        Self { inner, bucket: &throttle().upload }
+    }
+
+    pub fn downloading(inner: R) -> Self {
+        Self { inner, bucket: &throttle().download }
+    }
+}
+
+impl<R: Read> Read for ThrottledRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        cancel::check().map_err(|_| std::io::Error::other(Cancelled))?;
+        let read = self.inner.read(buf)?;
+        self.bucket.acquire_blocking(read);
+        progress::advance(read as u64);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_bucket_never_waits() {
+        let bucket = TokenBucket::new(None);
+        assert_eq!(bucket.reserve(100 * 1024 * 1024), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bucket_limits_rate_and_can_be_changed() {
+        let bucket = TokenBucket::new(Some(1000));
+        // Starts empty: 500 bytes need half a second
+        let wait = bucket.reserve(500);
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500), "{:?}", wait);
+        // Further bytes queue up behind the earlier ones
+        let wait = bucket.reserve(1000);
+        assert!(wait > Duration::from_millis(1400) && wait <= Duration::from_millis(1500), "{:?}", wait);
+
+        bucket.set_rate(Some(10_000));
+        let wait = bucket.reserve(1000);
+        assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100), "{:?}", wait);
+
+        bucket.set_rate(None);
+        assert_eq!(bucket.rate(), None);
+        assert_eq!(bucket.reserve(1_000_000), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits() {
+        let bucket = TokenBucket::new(Some(100_000));
+        let start = Instant::now();
+        for _ in 0..4 {
+            bucket.acquire(5_000).await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(180), "{:?}", start.elapsed());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::chunked::collect_save_files;
+use crate::index::device_name;
+use crate::{CloudBackend, SaveMetadata};
+use anyhow::Result;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use steam_cloud_sync_core::GameSave;
+
+/// Suffix of the JSON sidecar stored next to each save version
+pub const VERSION_INFO_SUFFIX: &str = ".meta.json";
+
+/// Whether an object counts towards the user's storage: versions (archives, encrypted
+/// archives and manifests) do, sidecars and partial writes don't, whichever backend
+/// lists them
+pub(crate) fn counts_towards_usage(key: &str) -> bool {
+    !key.ends_with(VERSION_INFO_SUFFIX) && !key.ends_with(".sha256") && !key.ends_with(".partial")
+}
+
+/// `archive_format` values
+pub const FORMAT_ZIP: &str = "zip";
+pub const FORMAT_ENCRYPTED_ZIP: &str = "zip+xchacha20poly1305";
+pub const FORMAT_CHUNKED: &str = "chunked-manifest";
+pub const FORMAT_FILES: &str = "file-manifest";
+
+/// Everything known about a save version beyond what the storage listing says
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct VersionInfo {
+    /// SHA256 of the stored archive or manifest
+    pub sha256: String,
+    /// Newest modification time among the save's files when it was uploaded (RFC 3339)
+    #[serde(default)]
+    pub local_modified_at: Option<String>,
+    #[serde(default)]
+    pub game_name: String,
+    /// Name of the machine that uploaded the version
+    #[serde(default)]
+    pub device: String,
+    #[serde(default)]
+    pub archive_format: String,
+    #[serde(default)]
+    pub file_count: u32,
+    /// Free-form label set by the user
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl VersionInfo {
+    pub fn sidecar_key(file_id: &str) -> String {
+        format!("{}{}", file_id, VERSION_INFO_SUFFIX)
+    }
+
+    /// Describe a save that is being uploaded
+    pub async fn describe(game_save: &GameSave, sha256: &str, archive_format: &str) -> Self {
+        let save_path = game_save.save_path.clone();
+        let (file_count, local_modified_at) = tokio::task::spawn_blocking(move || scan_save(&save_path))
+            .await
+            .unwrap_or_default();
+
+        Self {
+            sha256: sha256.to_string(),
+            local_modified_at,
+            game_name: game_save.name.clone(),
+            device: device_name(),
+            archive_format: archive_format.to_string(),
+            file_count,
+            label: None,
+        }
+    }
+}
+
+/// File count and newest modification time of a save
+fn scan_save(save_path: &Path) -> (u32, Option<String>) {
+    let files = collect_save_files(save_path).unwrap_or_default();
+    let newest = files.iter()
+        .filter_map(|(_, path)| std::fs::metadata(path).ok()?.modified().ok())
+        .max()
+        .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339());
+    (files.len() as u32, newest)
+}
+
+/// `key` and, if it is a save version with one, its sidecar. Most backends leave
+/// sidecars out of `list_objects`, so copies have to take them along explicitly.
+pub(crate) async fn with_sidecar<B: CloudBackend + ?Sized>(backend: &B, key: &str) -> Vec<String> {
+    let mut keys = vec![key.to_string()];
+    if !key.ends_with(VERSION_INFO_SUFFIX) {
+        let sidecar = VersionInfo::sidecar_key(key);
+        if backend.object_exists(&sidecar).await.unwrap_or(false) {
+            keys.push(sidecar);
+        }
+    }
+    keys
+}
+
+/// Write the sidecar of a version. Failures are logged only: the version itself is stored.
+pub(crate) async fn store_version_info<B: CloudBackend + ?Sized>(backend: &B, file_id: &str, info: &VersionInfo) {
+    let result = match serde_json::to_vec(info) {
+        Ok(data) => backend.put_object(&VersionInfo::sidecar_key(file_id), Bytes::from(data)).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        eprintln!("⚠️  Failed to store version info for {}: {}", file_id, e);
+    }
+}
+
+/// Read the sidecar of a version; `None` if it has none (older uploads) or it is unreadable
+pub(crate) async fn load_version_info<B: CloudBackend + ?Sized>(backend: &B, file_id: &str) -> Option<VersionInfo> {
+    let key = VersionInfo::sidecar_key(file_id);
+    match backend.get_object_tagged(&key).await {
+        Ok(Some((data, _))) => serde_json::from_slice(&data)
+            .map_err(|e| eprintln!("⚠️  Ignoring invalid version info {}: {}", key, e))
+            .ok(),
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("⚠️  Failed to read version info {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Fill in `info` from the sidecars of listed saves, replacing listing checksums
+/// (such as ETags) with the recorded SHA256
+pub(crate) async fn attach_version_info<B: CloudBackend + ?Sized>(backend: &B, saves: &mut [SaveMetadata]) {
+    let infos = futures::future::join_all(saves.iter().map(|save| load_version_info(backend, &save.file_id))).await;
+    for (save, info) in saves.iter_mut().zip(infos) {
+        if let Some(info) = info {
+            if info.sha256.len() == 64 {
+                save.checksum = info.sha256.clone();
+            }
+            save.info = Some(info);
+        }
+    }
+}
+
+/// The SHA256 a download should be verified against: the listed checksum if it is
+/// one, otherwise the one recorded in the sidecar
+pub(crate) async fn expected_checksum<B: CloudBackend + ?Sized>(backend: &B, metadata: &SaveMetadata) -> String {
+    if metadata.checksum.len() == 64 {
+        return metadata.checksum.clone();
+    }
+    if let Some(info) = metadata.info.as_ref().filter(|info| info.sha256.len() == 64) {
+        return info.sha256.clone();
+    }
+    load_version_info(backend, &metadata.file_id).await
+        .map(|info| info.sha256)
+        .filter(|sha256| sha256.len() == 64)
+        .unwrap_or_else(|| metadata.checksum.clone())
+}
+
+/// Set or clear the user label of a version
+pub(crate) async fn set_label<B: CloudBackend + ?Sized>(backend: &B, metadata: &SaveMetadata, label: Option<String>) -> Result<VersionInfo> {
+    let mut info = match load_version_info(backend, &metadata.file_id).await {
+        Some(info) => info,
+        // Older uploads have no sidecar yet; start one from what the listing knows
+        None => VersionInfo {
+            sha256: metadata.checksum.clone(),
+            ..metadata.info.clone().unwrap_or_default()
+        },
+    };
+    info.label = label.filter(|label| !label.trim().is_empty());
+    backend.put_object(&VersionInfo::sidecar_key(&metadata.file_id), Bytes::from(serde_json::to_vec(&info)?)).await?;
+    Ok(info)
+}
@@ -0,0 +1,687 @@
+use crate::{
+    content_tag,
+    object_key::{self, ObjectKey}, put_object_if_by_content, sanitize_user_id, throttle,
+    version_info::{self, VERSION_INFO_SUFFIX}, CloudBackend, ObjectInfo, SaveArchive, SaveMetadata,
+    StorageInfo, UploadProgress, VersionInfo,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{Method, StatusCode};
+use std::path::Path;
+use steam_cloud_sync_core::GameSave;
+
+/// Suffix of the sidecar object that stored the SHA256 of an archive before version
+/// info sidecars (`.meta.json`) replaced it; still read for older uploads.
+/// Plain WebDAV servers have no per-object user metadata we can rely on.
+const CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// Properties requested in every PROPFIND
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop>
+    <d:resourcetype/>
+    <d:getcontentlength/>
+    <d:getlastmodified/>
+    <d:quota-used-bytes/>
+    <d:quota-available-bytes/>
+  </d:prop>
+</d:propfind>"#;
+
+/// Cloud backend for WebDAV servers such as Nextcloud, ownCloud or Apache mod_dav.
+///
+/// `base_url` is the collection all objects live under, e.g.
+/// `https://cloud.example.com/remote.php/dav/files/alice/GameSyncer` for Nextcloud.
+/// Objects use the same key layout as Tencent COS, with `/` mapped to collections.
+pub struct WebDavBackend {
+    client: reqwest::Client,
+    base_url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+/// One `<response>` element of a PROPFIND multistatus reply
+#[derive(Debug, Clone, Default, PartialEq)]
+struct DavEntry {
+    /// Percent-decoded path from `<href>`
+    href: String,
+    is_collection: bool,
+    size: u64,
+    last_modified: Option<String>,
+    quota_used_bytes: Option<u64>,
+    quota_available_bytes: Option<i64>,
+}
+
+impl Default for WebDavBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebDavBackend {
+    pub fn new() -> Self {
+        Self {
+            client: crate::retry::http_client(),
+            base_url: "http://localhost/webdav".to_string(),
+            username: None,
+            password: None,
+        }
+    }
+
+    pub fn with_credentials(base_url: String, username: String, password: String) -> Self {
+        Self {
+            client: crate::retry::http_client(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            username: (!username.is_empty()).then_some(username),
+            password: (!password.is_empty()).then_some(password),
+        }
+    }
+
+    /// Build the URL of an object (or collection) key, percent-encoding each segment
+    fn object_url(&self, object_key: &str) -> Result<String> {
+        let mut url = self.base_url.clone();
+        for part in object_key.trim_matches('/').split('/').filter(|p| !p.is_empty()) {
+            if part == "." || part == ".." {
+                return Err(anyhow::anyhow!("Invalid object key: {}", object_key));
+            }
+            url.push('/');
+            url.push_str(&urlencoding::encode(part));
+        }
+        if object_key.ends_with('/') {
+            url.push('/');
+        }
+        Ok(url)
+    }
+
+    /// Path component of the base URL, used to turn `<href>` values back into keys
+    fn base_path(&self) -> String {
+        reqwest::Url::parse(&self.base_url)
+            .map(|url| urlencoding::decode(url.path()).map(|p| p.into_owned()).unwrap_or_else(|_| url.path().to_string()))
+            .unwrap_or_default()
+            .trim_matches('/')
+            .to_string()
+    }
+
+    fn key_from_href(&self, href: &str) -> Option<String> {
+        // Some servers return absolute URLs instead of paths
+        let path = match reqwest::Url::parse(href) {
+            Ok(url) => urlencoding::decode(url.path()).ok()?.into_owned(),
+            Err(_) => href.to_string(),
+        };
+        let path = path.trim_matches('/');
+        let base = self.base_path();
+
+        if base.is_empty() {
+            return Some(path.to_string());
+        }
+        if path == base {
+            return Some(String::new());
+        }
+        path.strip_prefix(&base)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .map(|rest| rest.to_string())
+    }
+
+    fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        }
+    }
+
+    /// Create every parent collection of `object_key` (MKCOL is not recursive)
+    async fn ensure_collections(&self, object_key: &str) -> Result<()> {
+        let mkcol = Method::from_bytes(b"MKCOL")?;
+        let parts: Vec<&str> = object_key.trim_matches('/').split('/').collect();
+        let mut collection = String::new();
+
+        for part in &parts[..parts.len().saturating_sub(1)] {
+            collection.push_str(part);
+            collection.push('/');
+
+            let response = self.request(mkcol.clone(), &self.object_url(&collection)?).send().await?;
+            // 405 Method Not Allowed means the collection already exists
+            if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(anyhow::anyhow!("Failed to create WebDAV collection {}: {} - {}", collection, status, body));
+            }
+        }
+        Ok(())
+    }
+
+    /// PUT a body produced by `body`, which is called again if the request has to be retried
+    async fn put_body(&self, object_key: &str, len: u64, body: impl Fn() -> Result<reqwest::Body>) -> Result<()> {
+        self.put_body_if(object_key, len, body, None).await.map(|_| ())
+    }
+
+    /// PUT with an optional precondition header (`If-Match` / `If-None-Match`).
+    /// Returns `false` if the server rejected the write because the precondition failed.
+    async fn put_body_if(
+        &self,
+        object_key: &str,
+        len: u64,
+        body: impl Fn() -> Result<reqwest::Body>,
+        precondition: Option<(&str, &str)>,
+    ) -> Result<bool> {
+        let url = self.object_url(object_key)?;
+        let send = |body: reqwest::Body| {
+            let request = self.request(Method::PUT, &url)
+                .header("Content-Type", "application/octet-stream")
+                .header("Content-Length", len);
+            let request = match precondition {
+                Some((name, value)) => request.header(name, value),
+                None => request,
+            };
+            request.body(body).send()
+        };
+
+        let mut response = send(body()?).await?;
+        // 409 Conflict: a parent collection is missing, create it and retry once
+        if response.status() == StatusCode::CONFLICT {
+            self.ensure_collections(object_key).await?;
+            response = send(body()?).await?;
+        }
+
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to upload {} to WebDAV: {} - {}", object_key, status, body));
+        }
+        Ok(true)
+    }
+
+    async fn get_response(&self, object_key: &str) -> Result<reqwest::Response> {
+        let response = self.request(Method::GET, &self.object_url(object_key)?).send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to download {} from WebDAV: {} - {}", object_key, status, body));
+        }
+        Ok(response)
+    }
+
+    /// PROPFIND a collection or object. A missing collection yields an empty list.
+    async fn propfind(&self, object_key: &str, depth: &str) -> Result<Vec<DavEntry>> {
+        let response = self
+            .request(Method::from_bytes(b"PROPFIND")?, &self.object_url(object_key)?)
+            .header("Depth", depth)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::MULTI_STATUS => Ok(parse_multistatus(&response.text().await?)),
+            StatusCode::NOT_FOUND => Ok(Vec::new()),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(anyhow::anyhow!("Access denied. Check your WebDAV username and password."))
+            }
+            status => {
+                let body = response.text().await.unwrap_or_default();
+                Err(anyhow::anyhow!("WebDAV PROPFIND on '{}' failed: {} - {}", object_key, status, body))
+            }
+        }
+    }
+
+    /// List every file below a collection, walking it with Depth: 1 requests
+    /// (many servers, Nextcloud included, refuse Depth: infinity)
+    async fn list_files(&self, prefix: &str) -> Result<Vec<(String, DavEntry)>> {
+        let mut files = Vec::new();
+        let mut pending = vec![prefix.trim_matches('/').to_string()];
+
+        while let Some(collection) = pending.pop() {
+            for entry in self.propfind(&format!("{}/", collection), "1").await? {
+                let Some(key) = self.key_from_href(&entry.href) else { continue };
+                if key == collection {
+                    continue;
+                }
+                if entry.is_collection {
+                    pending.push(key);
+                } else {
+                    files.push((key, entry));
+                }
+            }
+        }
+        Ok(files)
+    }
+}
+
+#[async_trait]
+impl CloudBackend for WebDavBackend {
+    async fn upload_save(&self, game_save: &GameSave, user_id: &str) -> Result<SaveMetadata> {
+        let archive = SaveArchive::create(&game_save.save_path).await?;
+        let checksum = archive.checksum().to_string();
+        let size_bytes = archive.size();
+
+        let object_key = ObjectKey::new(&sanitize_user_id(user_id), game_save.app_id, ".zip").to_string();
+
+        self.put_archive(&object_key, &archive).await?;
+        let info = VersionInfo::describe(game_save, &checksum, version_info::FORMAT_ZIP).await;
+        version_info::store_version_info(self, &object_key, &info).await;
+
+        eprintln!("[WebDAV] Uploaded {} ({} bytes)", object_key, size_bytes);
+
+        Ok(SaveMetadata {
+            game_id: game_save.app_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            size_bytes,
+            checksum,
+            compressed: true,
+            encrypted: false,
+            file_id: object_key,
+            info: Some(info),
+        })
+    }
+
+    async fn download_save(&self, metadata: &SaveMetadata, local_path: &Path) -> Result<()> {
+        let archive = self.get_archive(&metadata.file_id).await?;
+
+        let expected_checksum = version_info::expected_checksum(self, metadata).await;
+        if expected_checksum.len() == 64 && archive.checksum() != expected_checksum {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                metadata.file_id, expected_checksum, archive.checksum()
+            ));
+        }
+
+        archive.restore_to(local_path).await?;
+
+        Ok(())
+    }
+
+    async fn list_saves(&self, user_id: &str, game_id: Option<&str>) -> Result<Vec<SaveMetadata>> {
+        let prefix = format!("saves/{}", sanitize_user_id(user_id));
+        let files = self.list_files(&prefix).await?;
+
+        // Only fetch sidecars the listing shows, to save a request per version
+        let info_sidecars: std::collections::HashSet<&str> = files.iter()
+            .filter_map(|(key, _)| key.strip_suffix(VERSION_INFO_SUFFIX))
+            .collect();
+        let checksum_sidecars: std::collections::HashSet<&str> = files.iter()
+            .filter_map(|(key, _)| key.strip_suffix(CHECKSUM_SUFFIX))
+            .collect();
+
+        let mut saves = Vec::new();
+        for (key, entry) in files.iter().filter(|(key, _)| key.ends_with(".zip")) {
+            let info = if info_sidecars.contains(key.as_str()) {
+                version_info::load_version_info(self, key).await
+            } else {
+                None
+            };
+            let checksum = match &info {
+                Some(info) => info.sha256.clone(),
+                None if checksum_sidecars.contains(key.as_str()) => {
+                    self.get_object(&format!("{}{}", key, CHECKSUM_SUFFIX)).await
+                        .map(|data| String::from_utf8_lossy(&data).trim().to_string())
+                        .unwrap_or_default()
+                }
+                None => String::new(),
+            };
+
+            saves.push(SaveMetadata {
+                game_id: object_key::game_id_from_key(key),
+                timestamp: entry.last_modified.clone().unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                size_bytes: entry.size,
+                checksum,
+                compressed: true,
+                encrypted: false,
+                file_id: key.clone(),
+                info,
+            });
+        }
+
+        if let Some(gid) = game_id {
+            saves.retain(|save| save.game_id == gid);
+        }
+
+        saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(saves)
+    }
+
+    async fn delete_save(&self, metadata: &SaveMetadata) -> Result<()> {
+        let response = self.request(Method::DELETE, &self.object_url(&metadata.file_id)?).send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to delete {} from WebDAV: {} - {}", metadata.file_id, status, body));
+        }
+
+        // The sidecars are optional (older or hand-copied archives may not have them)
+        let _ = self.delete_object(&format!("{}{}", metadata.file_id, CHECKSUM_SUFFIX)).await;
+        let _ = self.delete_object(&VersionInfo::sidecar_key(&metadata.file_id)).await;
+        Ok(())
+    }
+
+    async fn resume_upload(&self, upload_id: &str, _offset: u64, _data: Bytes) -> Result<UploadProgress> {
+        // Plain WebDAV has no standard partial PUT; uploads are always sent whole
+        Err(anyhow::anyhow!("Resumable uploads are not supported by the WebDAV backend (upload {})", upload_id))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        eprintln!("[WebDAV] Testing connection to {}", self.base_url);
+        let response = self
+            .request(Method::from_bytes(b"PROPFIND")?, &format!("{}/", self.base_url))
+            .header("Depth", "0")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Cannot reach WebDAV server {}: {}", self.base_url, e))?;
+
+        match response.status() {
+            StatusCode::MULTI_STATUS => Ok(()),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(anyhow::anyhow!("Access denied. Check your WebDAV username and password."))
+            }
+            StatusCode::NOT_FOUND => Err(anyhow::anyhow!("WebDAV folder {} does not exist", self.base_url)),
+            status => Err(anyhow::anyhow!("WebDAV server returned HTTP {} for {}", status, self.base_url)),
+        }
+    }
+
+    async fn get_storage_info(&self, user_id: &str) -> Result<StorageInfo> {
+        let prefix = format!("saves/{}", sanitize_user_id(user_id));
+        let versions: Vec<_> = self.list_files(&prefix).await?
+            .into_iter()
+            .filter(|(key, _)| version_info::counts_towards_usage(key))
+            .collect();
+
+        // quota-available-bytes is negative when the server has no quota set
+        let root = self.propfind("", "0").await?.into_iter().next().unwrap_or_default();
+        let total_bytes = match (root.quota_used_bytes, root.quota_available_bytes) {
+            (Some(used), Some(available)) if available >= 0 => Some(used + available as u64),
+            _ => None,
+        };
+
+        Ok(StorageInfo {
+            used_bytes: versions.iter().map(|(_, entry)| entry.size).sum(),
+            total_bytes,
+            file_count: versions.len() as u32,
+            bucket_used_bytes: None, // Will be filled by combined call
+            bucket_total_objects: None, // Will be filled by combined call
+        })
+    }
+
+    async fn get_bucket_storage_info(&self) -> Result<(u64, u32)> {
+        let files = self.list_files("").await?;
+        let summed: u64 = files.iter().map(|(_, entry)| entry.size).sum();
+
+        // Prefer the server's own accounting when it reports one
+        let root = self.propfind("", "0").await?.into_iter().next().unwrap_or_default();
+        Ok((root.quota_used_bytes.unwrap_or(summed), files.len() as u32))
+    }
+
+    async fn put_object(&self, key: &str, data: Bytes) -> Result<()> {
+        self.put_body(key, data.len() as u64, || Ok(throttle::upload_body(data.clone()))).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        throttle::read_response(self.get_response(key).await?).await
+    }
+
+    async fn get_archive(&self, key: &str) -> Result<SaveArchive> {
+        SaveArchive::receive_response(self.get_response(key).await?).await
+    }
+
+    async fn put_archive(&self, key: &str, archive: &SaveArchive) -> Result<()> {
+        self.put_body(key, archive.size(), || archive.body()).await
+    }
+
+    async fn object_exists(&self, key: &str) -> Result<bool> {
+        Ok(!self.propfind(key, "0").await?.is_empty())
+    }
+
+    async fn get_object_tagged(&self, key: &str) -> Result<Option<(Bytes, String)>> {
+        let response = self.request(Method::GET, &self.object_url(key)?).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to download {} from WebDAV: {} - {}", key, status, body));
+        }
+
+        let etag = response.headers().get("ETag")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let data = throttle::read_response(response).await?;
+        // Servers without ETags fall back to comparing content
+        let tag = etag.unwrap_or_else(|| content_tag(&data));
+        Ok(Some((data, tag)))
+    }
+
+    async fn put_object_if(&self, key: &str, data: Bytes, expected_tag: Option<&str>) -> Result<bool> {
+        let precondition = match expected_tag {
+            Some(tag) if tag.starts_with("sha256:") => {
+                return put_object_if_by_content(self, key, data, expected_tag).await;
+            }
+            Some(tag) => ("If-Match", tag),
+            None => ("If-None-Match", "*"),
+        };
+        self.put_body_if(key, data.len() as u64, || Ok(throttle::upload_body(data.clone())), Some(precondition)).await
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        // Walk the deepest collection the prefix names, then match the rest of it
+        let collection = prefix.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        Ok(self.list_files(collection).await?
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, entry)| ObjectInfo {
+                key,
+                size_bytes: entry.size,
+                last_modified: entry.last_modified.as_deref()
+                    .and_then(|m| chrono::DateTime::parse_from_rfc2822(m).ok())
+                    .map(|m| m.with_timezone(&chrono::Utc)),
+            })
+            .collect())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        let response = self.request(Method::DELETE, &self.object_url(key)?).send().await?;
+        if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to delete {} from WebDAV: {} - {}", key, status, body));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a PROPFIND `207 Multi-Status` body. Only properties from `200 OK`
+/// propstat blocks are read, since servers report unknown properties in a 404 block.
+fn parse_multistatus(body: &str) -> Vec<DavEntry> {
+    let mut entries = Vec::new();
+
+    for response in xml_elements(body, "response") {
+        let Some(href) = xml_elements(response, "href").first().map(|h| xml_unescape(h.trim())) else {
+            continue;
+        };
+        let mut entry = DavEntry {
+            href: urlencoding::decode(&href).map(|h| h.into_owned()).unwrap_or(href),
+            ..DavEntry::default()
+        };
+
+        for propstat in xml_elements(response, "propstat") {
+            let ok = xml_elements(propstat, "status").first().is_none_or(|s| s.contains(" 200"));
+            if !ok {
+                continue;
+            }
+
+            let prop = |name: &str| xml_elements(propstat, name).first().map(|v| v.trim().to_string());
+
+            if let Some(resource_type) = prop("resourcetype") {
+                entry.is_collection = !xml_elements(&resource_type, "collection").is_empty();
+            }
+            if let Some(size) = prop("getcontentlength").and_then(|s| s.parse().ok()) {
+                entry.size = size;
+            }
+            if let Some(modified) = prop("getlastmodified") {
+                entry.last_modified = chrono::DateTime::parse_from_rfc2822(&modified)
+                    .ok()
+                    .map(|dt| dt.with_timezone(&chrono::Utc).to_rfc3339());
+            }
+            if let Some(used) = prop("quota-used-bytes").and_then(|s| s.parse().ok()) {
+                entry.quota_used_bytes = Some(used);
+            }
+            if let Some(available) = prop("quota-available-bytes").and_then(|s| s.parse().ok()) {
+                entry.quota_available_bytes = Some(available);
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    entries
+}
+
+/// Inner text of every element with the given local name, ignoring namespace prefixes
+/// (`<d:href>`, `<D:href>` and `<href>` all match "href"). Self-closing elements yield "".
+fn xml_elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('>') else { break };
+        let tag = &after[..end];
+        rest = &after[end + 1..];
+
+        if tag.starts_with('/') || tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let full_name = tag.trim_end_matches('/').split_whitespace().next().unwrap_or("");
+        if full_name.rsplit(':').next() != Some(name) {
+            continue;
+        }
+
+        if self_closing {
+            found.push("");
+            continue;
+        }
+
+        let close = format!("</{}>", full_name);
+        match rest.find(&close) {
+            Some(pos) => {
+                found.push(&rest[..pos]);
+                rest = &rest[pos + close.len()..];
+            }
+            None => break,
+        }
+    }
+
+    found
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const NEXTCLOUD_LISTING: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns" xmlns:oc="http://owncloud.org/ns"><d:response><d:href>/remote.php/dav/files/alice/saves/user-1/</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype><d:quota-used-bytes>1234</d:quota-used-bytes><d:quota-available-bytes>-3</d:quota-available-bytes></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat><d:propstat><d:prop><d:getcontentlength/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat></d:response><d:response><d:href>/remote.php/dav/files/alice/saves/user-1/105600_20240101_120000_a%20b.zip</d:href><d:propstat><d:prop><d:resourcetype/><d:getcontentlength>512</d:getcontentlength><d:getlastmodified>Mon, 01 Jan 2024 12:00:00 GMT</d:getlastmodified></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response></d:multistatus>"#;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let entries = parse_multistatus(NEXTCLOUD_LISTING);
+        assert_eq!(entries.len(), 2);
+
+        assert!(entries[0].is_collection);
+        assert_eq!(entries[0].quota_used_bytes, Some(1234));
+        assert_eq!(entries[0].quota_available_bytes, Some(-3));
+
+        assert!(!entries[1].is_collection);
+        assert_eq!(entries[1].href, "/remote.php/dav/files/alice/saves/user-1/105600_20240101_120000_a b.zip");
+        assert_eq!(entries[1].size, 512);
+        assert_eq!(entries[1].last_modified.as_deref(), Some("2024-01-01T12:00:00+00:00"));
+
+        let backend = WebDavBackend::with_credentials(
+            "https://cloud.example.com/remote.php/dav/files/alice".to_string(),
+            "alice".to_string(),
+            "secret".to_string(),
+        );
+        assert_eq!(backend.key_from_href(&entries[1].href).as_deref(), Some("saves/user-1/105600_20240101_120000_a b.zip"));
+        assert_eq!(backend.key_from_href("/remote.php/dav/files/bob/x.zip"), None);
+    }
+
+    #[tokio::test]
+    async fn test_webdav_upload_and_list() {
+        let server = MockServer::start().await;
+        let save_dir = TempDir::new().unwrap();
+        tokio::fs::write(save_dir.path().join("slot1.sav"), b"slot one").await.unwrap();
+
+        // First PUT hits a missing parent collection, the retry succeeds
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(409))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&server)
+            .await;
+        Mock::given(method("MKCOL"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let backend = WebDavBackend::with_credentials(format!("{}/dav", server.uri()), "alice".to_string(), "secret".to_string());
+        let game_save = GameSave {
+            app_id: 105600,
+            name: "Terraria".to_string(),
+            save_path: save_dir.path().to_path_buf(),
+        };
+        let metadata = backend.upload_save(&game_save, "user-1").await.unwrap();
+        assert!(metadata.file_id.starts_with("saves/user-1/105600_"));
+
+        let listing = format!(
+            r#"<D:multistatus xmlns:D="DAV:"><D:response><D:href>/dav/saves/user-1/</D:href><D:propstat><D:prop><D:resourcetype><D:collection/></D:resourcetype></D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response><D:response><D:href>/dav/{key}</D:href><D:propstat><D:prop><D:resourcetype/><D:getcontentlength>{size}</D:getcontentlength></D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response><D:response><D:href>/dav/{key}.sha256</D:href><D:propstat><D:prop><D:resourcetype/><D:getcontentlength>64</D:getcontentlength></D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response></D:multistatus>"#,
+            key = metadata.file_id,
+            size = metadata.size_bytes,
+        );
+        Mock::given(method("PROPFIND"))
+            .and(path("/dav/saves/user-1/"))
+            .and(header("Depth", "1"))
+            .respond_with(ResponseTemplate::new(207).set_body_string(listing))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/dav/{}.sha256", metadata.file_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(metadata.checksum.clone()))
+            .mount(&server)
+            .await;
+
+        let saves = backend.list_saves("user-1", Some("105600")).await.unwrap();
+        assert_eq!(saves.len(), 1);
+        assert_eq!(saves[0].file_id, metadata.file_id);
+        assert_eq!(saves[0].checksum, metadata.checksum);
+        assert_eq!(saves[0].size_bytes, metadata.size_bytes);
+
+        // A corrupted object must not be extracted
+        Mock::given(method("GET"))
+            .and(path(format!("/dav/{}", metadata.file_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"garbage".to_vec()))
+            .mount(&server)
+            .await;
+        let target = save_dir.path().join("restore");
+        assert!(backend.download_save(&saves[0], &target).await.is_err());
+    }
+}
@@ -132,8 +132,8 @@ impl ConfigStore {
             INSERT OR REPLACE INTO game_configs (
                 game_id, enabled, auto_sync, last_sync_at, local_path, cloud_path,
                 exclusion_patterns, compression_enabled, max_versions, sync_direction,
-                created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
+                retention_policy, created_at, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?12,
                      COALESCE((SELECT created_at FROM game_configs WHERE game_id = ?1), ?11), ?11)
             "#,
         )
@@ -148,6 +148,7 @@ impl ConfigStore {
         .bind(config.max_versions)
         .bind(&config.sync_direction)
         .bind(&now)
+        .bind(&config.retention_policy)
         .execute(&self.db.pool)
         .await?;
         
@@ -225,37 +226,58 @@ impl ConfigStore {
         Ok(result.rows_affected() > 0)
     }
     
-    /// Initialize default application configurations
+    /// Initialize default application configurations. Settings that are already
+    /// stored keep their values, so this is safe to run on every start.
     pub async fn init_default_configs(&self) -> Result<()> {
         // Cloud backend settings
-        self.set_string_config("cloud.backend_type", "tencent_cos").await?;
-        self.set_string_config("cloud.tencent_secret_id", "").await?;
-        self.set_string_config("cloud.tencent_secret_key", "").await?;
-        self.set_string_config("cloud.tencent_bucket", "steam-cloud-sync").await?;
-        self.set_string_config("cloud.tencent_region", "ap-beijing").await?;
-        self.set_string_config("cloud.s3_access_key", "").await?;
-        self.set_string_config("cloud.s3_secret_key", "").await?;
-        self.set_string_config("cloud.s3_bucket", "steam-cloud-sync").await?;
-        self.set_string_config("cloud.s3_region", "us-east-1").await?;
+        self.init_default_config("cloud.backend_type", "tencent_cos", "string").await?;
+        self.init_default_config("cloud.tencent_secret_id", "", "string").await?;
+        self.init_default_config("cloud.tencent_secret_key", "", "string").await?;
+        self.init_default_config("cloud.tencent_bucket", "steam-cloud-sync", "string").await?;
+        self.init_default_config("cloud.tencent_region", "ap-beijing", "string").await?;
+        self.init_default_config("cloud.s3_access_key", "", "string").await?;
+        self.init_default_config("cloud.s3_secret_key", "", "string").await?;
+        self.init_default_config("cloud.s3_bucket", "steam-cloud-sync", "string").await?;
+        self.init_default_config("cloud.s3_region", "us-east-1", "string").await?;
         
         // Application settings
-        self.set_bool_config("app.auto_start", false).await?;
-        self.set_bool_config("app.minimize_to_tray", true).await?;
-        self.set_bool_config("app.auto_sync", false).await?;
-        self.set_number_config("app.sync_interval_minutes", 60).await?;
-        self.set_string_config("app.language", "en").await?;
-        self.set_string_config("app.user_id", &uuid::Uuid::new_v4().to_string()).await?;
+        self.init_default_config("app.auto_start", "false", "boolean").await?;
+        self.init_default_config("app.minimize_to_tray", "true", "boolean").await?;
+        self.init_default_config("app.auto_sync", "false", "boolean").await?;
+        self.init_default_config("app.sync_interval_minutes", "60", "number").await?;
+        self.init_default_config("app.language", "en", "string").await?;
+        self.init_default_config("app.user_id", &uuid::Uuid::new_v4().to_string(), "string").await?;
         
-        // Sync settings
-        self.set_bool_config("sync.compression_enabled", true).await?;
-        self.set_number_config("sync.max_versions_per_game", 5).await?;
-        self.set_bool_config("sync.rate_limiting_enabled", false).await?;
-        self.set_number_config("sync.rate_limit_mbps", 10).await?;
-        self.set_number_config("sync.parallel_operations", 3).await?;
+        // Sync settings. Bandwidth limits are app settings, applied to every transfer by the UI.
+        self.init_default_config("sync.compression_enabled", "true", "boolean").await?;
+        self.init_default_config("sync.max_versions_per_game", "5", "number").await?;
+        self.init_default_config("sync.parallel_operations", "3", "number").await?;
         
         // Storage settings
-        self.set_number_config("storage.max_total_size_gb", 100).await?;
-        self.set_number_config("storage.cleanup_older_than_days", 90).await?;
+        self.init_default_config("storage.max_total_size_gb", "100", "number").await?;
+        self.init_default_config("storage.cleanup_older_than_days", "90", "number").await?;
+        self.init_default_config("storage.scrub_interval_hours", "168", "number").await?;
+        self.init_default_config("storage.scrub_sample_size", "20", "number").await?;
+        
+        Ok(())
+    }
+    
+    /// Store `value` under `key` unless the key already has a value
+    async fn init_default_config(&self, key: &str, value: &str, config_type: &str) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO app_configs (key, value, config_type, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?4)
+            "#,
+        )
+        .bind(key)
+        .bind(value)
+        .bind(config_type)
+        .bind(&now)
+        .execute(&self.db.pool)
+        .await?;
         
         Ok(())
     }
@@ -82,6 +82,19 @@ impl Database {
         Ok(db)
     }
     
+    /// A private in-memory database with all tables created. The pool holds a single
+    /// connection, since every SQLite connection to `:memory:` opens a database of its own.
+    #[cfg(test)]
+    pub(crate) async fn in_memory() -> Result<Self> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        let db = Self { pool };
+        db.run_migrations().await?;
+        Ok(db)
+    }
+
     /// Run database migrations
     async fn run_migrations(&self) -> Result<()> {
         // Create cloud_operations table
@@ -136,8 +149,9 @@ impl Database {
                 cloud_path TEXT,
                 exclusion_patterns TEXT,
                 compression_enabled BOOLEAN NOT NULL DEFAULT true,
-                max_versions INTEGER DEFAULT 5,
+                max_versions INTEGER,
                 sync_direction TEXT DEFAULT 'bidirectional',
+                retention_policy TEXT,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )
@@ -146,6 +160,22 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // Databases from before retention was enforced lack the policy column. Their
+        // `max_versions` could only hold the default, so those games follow the global setting.
+        let (has_retention_policy,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM pragma_table_info('game_configs') WHERE name = 'retention_policy'"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if has_retention_policy == 0 {
+            sqlx::query("ALTER TABLE game_configs ADD COLUMN retention_policy TEXT")
+                .execute(&self.pool)
+                .await?;
+            sqlx::query("UPDATE game_configs SET max_versions = NULL")
+                .execute(&self.pool)
+                .await?;
+        }
+
         // Create app_configs table
         sqlx::query(
             r#"
@@ -179,6 +209,71 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // Create multipart upload tables (resumable uploads)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS multipart_uploads (
+                upload_id TEXT PRIMARY KEY NOT NULL,
+                scope TEXT NOT NULL,
+                object_key TEXT NOT NULL,
+                checksum TEXT NOT NULL,
+                total_bytes INTEGER NOT NULL,
+                part_size INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS multipart_upload_parts (
+                upload_id TEXT NOT NULL REFERENCES multipart_uploads(upload_id) ON DELETE CASCADE,
+                part_number INTEGER NOT NULL,
+                etag TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                PRIMARY KEY (upload_id, part_number)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create scrub tables (verification of stored versions)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS scrub_runs (
+                id TEXT PRIMARY KEY NOT NULL,
+                started_at TEXT NOT NULL,
+                finished_at TEXT NOT NULL,
+                total_versions INTEGER NOT NULL,
+                checked INTEGER NOT NULL,
+                corrupt INTEGER NOT NULL,
+                missing INTEGER NOT NULL,
+                unverifiable INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS scrub_results (
+                file_id TEXT PRIMARY KEY NOT NULL,
+                game_id TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                outcome TEXT NOT NULL,
+                detail TEXT,
+                checked_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
         // Create indexes
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_cloud_operations_game_id ON cloud_operations(game_id)")
             .execute(&self.pool)
@@ -200,6 +295,14 @@ impl Database {
             .execute(&self.pool)
             .await?;
 
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_multipart_uploads_scope ON multipart_uploads(scope, checksum)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_scrub_runs_finished_at ON scrub_runs(finished_at)")
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
     
@@ -2,11 +2,15 @@ pub mod database;
 pub mod models;
 pub mod cloud_history;
 pub mod config_store;
+pub mod multipart_uploads;
+pub mod scrub_results;
 
 pub use database::*;
 pub use models::*;
 pub use cloud_history::*;
 pub use config_store::*;
+pub use multipart_uploads::*;
+pub use scrub_results::*;
 
 use anyhow::Result;
 use std::path::PathBuf;
@@ -92,17 +96,23 @@ pub struct PersistenceManager {
     pub database: Database,
     pub cloud_history: CloudHistoryStore,
     pub config_store: ConfigStore,
+    pub multipart_uploads: MultipartUploadStore,
+    pub scrub_results: ScrubResultStore,
 }
 
 impl PersistenceManager {
     pub fn new(database: Database) -> Self {
         let cloud_history = CloudHistoryStore::new(database.clone());
         let config_store = ConfigStore::new(database.clone());
+        let multipart_uploads = MultipartUploadStore::new(database.clone());
+        let scrub_results = ScrubResultStore::new(database.clone());
         
         Self {
             database,
             cloud_history,
             config_store,
+            multipart_uploads,
+            scrub_results,
         }
     }
 }
\ No newline at end of file
@@ -228,8 +228,10 @@ pub struct GameConfig {
     pub cloud_path: Option<String>,
     pub exclusion_patterns: Option<String>, // JSON array of patterns
     pub compression_enabled: bool,
-    pub max_versions: Option<i32>,
+    pub max_versions: Option<i32>, // Versions to keep; None follows `sync.max_versions_per_game`
     pub sync_direction: Option<String>, // "bidirectional", "upload_only", "download_only"
+    #[serde(default)]
+    pub retention_policy: Option<String>, // JSON retention policy, overrides max_versions
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -246,8 +248,9 @@ impl GameConfig {
             cloud_path: None,
             exclusion_patterns: None,
             compression_enabled: true,
-            max_versions: Some(5),
+            max_versions: None,
             sync_direction: Some("bidirectional".to_string()),
+            retention_policy: None,
             created_at: now,
             updated_at: now,
         }
@@ -279,6 +282,52 @@ impl AppConfig {
     }
 }
 
+/// Multipart upload in flight, kept so an interrupted upload can resume after a restart
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MultipartUpload {
+    pub upload_id: String,
+    pub scope: String, // backend, bucket, user and game the upload belongs to
+    pub object_key: String,
+    pub checksum: String, // SHA256 of the complete archive
+    pub total_bytes: i64,
+    pub part_size: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A finished part of a multipart upload
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MultipartUploadPart {
+    pub upload_id: String,
+    pub part_number: i64,
+    pub etag: String,
+    pub size: i64,
+}
+
+/// Summary of a scrub run over the stored save versions
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ScrubRun {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub total_versions: i64,
+    pub checked: i64, // less than total_versions when a sample was scrubbed
+    pub corrupt: i64,
+    pub missing: i64,
+    pub unverifiable: i64,
+}
+
+/// Latest scrub result of a save version
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ScrubRecord {
+    pub file_id: String,
+    pub game_id: String,
+    pub size_bytes: i64,
+    pub outcome: String, // ok, corrupt, missing or unverifiable
+    pub detail: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
 /// Cloud backend statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloudStats {
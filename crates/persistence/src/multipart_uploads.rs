@@ -0,0 +1,241 @@
+use crate::{models::*, Database};
+use anyhow::Result;
+use chrono::Utc;
+
+/// Store for in-flight multipart uploads and their finished parts
+#[derive(Debug, Clone)]
+pub struct MultipartUploadStore {
+    db: Database,
+}
+
+impl MultipartUploadStore {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Insert or update an upload, replacing its recorded parts
+    pub async fn save_upload(&self, upload: &MultipartUpload, parts: &[MultipartUploadPart]) -> Result<()> {
+        let mut tx = self.db.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO multipart_uploads (
+                upload_id, scope, object_key, checksum, total_bytes, part_size, created_at, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+        )
+        .bind(&upload.upload_id)
+        .bind(&upload.scope)
+        .bind(&upload.object_key)
+        .bind(&upload.checksum)
+        .bind(upload.total_bytes)
+        .bind(upload.part_size)
+        .bind(upload.created_at.to_rfc3339())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM multipart_upload_parts WHERE upload_id = ?1")
+            .bind(&upload.upload_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for part in parts {
+            sqlx::query(
+                "INSERT INTO multipart_upload_parts (upload_id, part_number, etag, size) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .bind(&upload.upload_id)
+            .bind(part.part_number)
+            .bind(&part.etag)
+            .bind(part.size)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Record a finished part and touch the upload's `updated_at`
+    pub async fn record_part(&self, part: &MultipartUploadPart) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO multipart_upload_parts (upload_id, part_number, etag, size)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+        )
+        .bind(&part.upload_id)
+        .bind(part.part_number)
+        .bind(&part.etag)
+        .bind(part.size)
+        .execute(&self.db.pool)
+        .await?;
+
+        sqlx::query("UPDATE multipart_uploads SET updated_at = ?1 WHERE upload_id = ?2")
+            .bind(Utc::now().to_rfc3339())
+            .bind(&part.upload_id)
+            .execute(&self.db.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get an upload and its finished parts
+    pub async fn get_upload(&self, upload_id: &str) -> Result<Option<(MultipartUpload, Vec<MultipartUploadPart>)>> {
+        let upload = sqlx::query_as::<_, MultipartUpload>(
+            "SELECT * FROM multipart_uploads WHERE upload_id = ?1",
+        )
+        .bind(upload_id)
+        .fetch_optional(&self.db.pool)
+        .await?;
+
+        match upload {
+            Some(upload) => {
+                let parts = self.get_parts(&upload.upload_id).await?;
+                Ok(Some((upload, parts)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Find the most recent unfinished upload of the same archive to the same destination
+    pub async fn find_upload(&self, scope: &str, checksum: &str) -> Result<Option<(MultipartUpload, Vec<MultipartUploadPart>)>> {
+        let upload_id: Option<(String,)> = sqlx::query_as(
+            r#"
+            SELECT upload_id FROM multipart_uploads
+            WHERE scope = ?1 AND checksum = ?2
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(scope)
+        .bind(checksum)
+        .fetch_optional(&self.db.pool)
+        .await?;
+
+        match upload_id {
+            Some((upload_id,)) => self.get_upload(&upload_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Get the finished parts of an upload, ordered by part number
+    pub async fn get_parts(&self, upload_id: &str) -> Result<Vec<MultipartUploadPart>> {
+        let parts = sqlx::query_as::<_, MultipartUploadPart>(
+            "SELECT * FROM multipart_upload_parts WHERE upload_id = ?1 ORDER BY part_number",
+        )
+        .bind(upload_id)
+        .fetch_all(&self.db.pool)
+        .await?;
+
+        Ok(parts)
+    }
+
+    /// Get all unfinished uploads
+    pub async fn get_all_uploads(&self) -> Result<Vec<MultipartUpload>> {
+        let uploads = sqlx::query_as::<_, MultipartUpload>(
+            "SELECT * FROM multipart_uploads ORDER BY created_at DESC",
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+
+        Ok(uploads)
+    }
+
+    /// Forget an upload and its parts
+    pub async fn remove_upload(&self, upload_id: &str) -> Result<()> {
+        let mut tx = self.db.pool.begin().await?;
+
+        sqlx::query("DELETE FROM multipart_upload_parts WHERE upload_id = ?1")
+            .bind(upload_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM multipart_uploads WHERE upload_id = ?1")
+            .bind(upload_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Forget uploads that haven't progressed in a while (servers expire them anyway)
+    pub async fn cleanup_stale_uploads(&self, older_than_days: i64) -> Result<u64> {
+        let cutoff_date = Utc::now() - chrono::Duration::days(older_than_days);
+
+        sqlx::query(
+            r#"
+            DELETE FROM multipart_upload_parts WHERE upload_id IN (
+                SELECT upload_id FROM multipart_uploads WHERE updated_at < ?1
+            )
+            "#,
+        )
+        .bind(cutoff_date.to_rfc3339())
+        .execute(&self.db.pool)
+        .await?;
+
+        let result = sqlx::query("DELETE FROM multipart_uploads WHERE updated_at < ?1")
+            .bind(cutoff_date.to_rfc3339())
+            .execute(&self.db.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(upload_id: &str, checksum: &str) -> MultipartUpload {
+        MultipartUpload {
+            upload_id: upload_id.to_string(),
+            scope: "s3:saves-bucket:user-1:105600".to_string(),
+            object_key: "saves/user-1/105600_a.zip".to_string(),
+            checksum: checksum.to_string(),
+            total_bytes: 20 * 1024 * 1024,
+            part_size: 8 * 1024 * 1024,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn part(upload_id: &str, part_number: i64) -> MultipartUploadPart {
+        MultipartUploadPart {
+            upload_id: upload_id.to_string(),
+            part_number,
+            etag: format!("\"etag-{}\"", part_number),
+            size: 8 * 1024 * 1024,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_finds_recorded_parts() {
+        let db = Database::in_memory().await.unwrap();
+        let store = MultipartUploadStore::new(db.clone());
+        store.save_upload(&upload("upload-1", "aaaa"), &[]).await.unwrap();
+        store.save_upload(&upload("upload-2", "bbbb"), &[part("upload-2", 1)]).await.unwrap();
+        store.record_part(&part("upload-1", 2)).await.unwrap();
+        store.record_part(&part("upload-1", 1)).await.unwrap();
+        // A retried part replaces the earlier attempt
+        store.record_part(&part("upload-1", 2)).await.unwrap();
+
+        // As after a restart: a new store over the same database
+        let store = MultipartUploadStore::new(db);
+        let (found, parts) = store.find_upload("s3:saves-bucket:user-1:105600", "aaaa").await.unwrap().unwrap();
+        assert_eq!(found.upload_id, "upload-1");
+        assert_eq!(found.object_key, "saves/user-1/105600_a.zip");
+        assert_eq!(parts.iter().map(|part| part.part_number).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(parts[1].etag, "\"etag-2\"");
+        assert!(store.find_upload("s3:saves-bucket:user-1:105600", "cccc").await.unwrap().is_none());
+        assert!(store.find_upload("s3:other-bucket:user-1:105600", "aaaa").await.unwrap().is_none());
+
+        store.remove_upload("upload-1").await.unwrap();
+        assert!(store.find_upload("s3:saves-bucket:user-1:105600", "aaaa").await.unwrap().is_none());
+        assert!(store.get_parts("upload-1").await.unwrap().is_empty());
+        assert_eq!(store.get_all_uploads().await.unwrap().len(), 1);
+        assert_eq!(store.cleanup_stale_uploads(0).await.unwrap(), 1);
+        assert!(store.get_parts("upload-2").await.unwrap().is_empty());
+    }
+}
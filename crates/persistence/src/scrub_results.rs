@@ -0,0 +1,91 @@
+use crate::{models::*, Database};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+/// Store for scrub runs and the latest result of every scrubbed save version
+#[derive(Debug, Clone)]
+pub struct ScrubResultStore {
+    db: Database,
+}
+
+impl ScrubResultStore {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Insert or replace the result of a version
+    pub async fn record_result(&self, record: &ScrubRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO scrub_results (
+                file_id, game_id, size_bytes, outcome, detail, checked_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(&record.file_id)
+        .bind(&record.game_id)
+        .bind(record.size_bytes)
+        .bind(&record.outcome)
+        .bind(&record.detail)
+        .bind(record.checked_at.to_rfc3339())
+        .execute(&self.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn record_run(&self, run: &ScrubRun) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO scrub_runs (
+                id, started_at, finished_at, total_versions, checked, corrupt, missing, unverifiable
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+        )
+        .bind(&run.id)
+        .bind(run.started_at.to_rfc3339())
+        .bind(run.finished_at.to_rfc3339())
+        .bind(run.total_versions)
+        .bind(run.checked)
+        .bind(run.corrupt)
+        .bind(run.missing)
+        .bind(run.unverifiable)
+        .execute(&self.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The most recent finished run, to tell when the next one is due
+    pub async fn get_latest_run(&self) -> Result<Option<ScrubRun>> {
+        let run = sqlx::query_as::<_, ScrubRun>(
+            "SELECT * FROM scrub_runs ORDER BY finished_at DESC LIMIT 1",
+        )
+        .fetch_optional(&self.db.pool)
+        .await?;
+
+        Ok(run)
+    }
+
+    /// Versions whose latest result isn't ok, most recently checked first
+    pub async fn get_problems(&self) -> Result<Vec<ScrubRecord>> {
+        let records = sqlx::query_as::<_, ScrubRecord>(
+            "SELECT * FROM scrub_results WHERE outcome != 'ok' ORDER BY checked_at DESC",
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Forget results from before `cutoff`. After scrubbing every version, these are
+    /// of versions that were deleted since.
+    pub async fn remove_results_checked_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM scrub_results WHERE checked_at < ?1")
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.db.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
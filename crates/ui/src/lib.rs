@@ -9,6 +9,7 @@ pub mod service_manager;
 pub mod view_model;
 pub mod ui;
 pub mod settings;
+pub mod upload_sessions;
 
 pub use pages::*;
 pub use models::*;
@@ -17,6 +18,7 @@ pub use service_manager::*;
 pub use view_model::*; // 重新添加新的AppViewModel导出
 pub use ui::*;
 pub use settings::*;
+pub use upload_sessions::*;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum SyncState {
@@ -83,7 +85,66 @@ impl LocalizationManager {
             ("zh-CN", "SaveBackendSettings") => "保存后端设置".to_string(),
             ("zh-CN", "Application") => "应用程序".to_string(),
             ("zh-CN", "StartWithWindows") => "随Windows启动".to_string(),
+            ("zh-CN", "RunningOperations") => "正在进行的操作".to_string(),
+            ("zh-CN", "Cancel") => "取消".to_string(),
+            ("zh-CN", "CancelAll") => "全部取消".to_string(),
+            ("zh-CN", "MirrorBackends") => "同时镜像到:".to_string(),
+            ("zh-CN", "MirrorBackendsHint") => "每次上传也写入这些后端；主后端不可用时从镜像下载。请先选中该后端并填写其设置。".to_string(),
+            ("zh-CN", "MirrorNotConfigured") => "请先选中该后端并填写其设置".to_string(),
+            ("zh-CN", "MirrorInSync") => "已同步".to_string(),
+            ("zh-CN", "MirrorOutOfDate") => "落后".to_string(),
+            ("zh-CN", "MirrorChecking") => "检查中".to_string(),
+            ("zh-CN", "MirrorCatchUp") => "立即追赶同步".to_string(),
+            ("zh-CN", "MigrateFrom") => "从其他后端迁移:".to_string(),
+            ("zh-CN", "MigrateHint") => "将所有存档版本及其信息复制到当前选中的后端。中断后再次运行会从断点继续。".to_string(),
+            ("zh-CN", "MigrateStart") => "复制到当前后端".to_string(),
+            ("zh-CN", "MigrateDone") => "迁移完成，已复制".to_string(),
+            ("zh-CN", "MigrateSkipped") => "个已存在".to_string(),
+            ("zh-CN", "MigrateFailed") => "迁移失败，可再次运行以继续".to_string(),
+            ("zh-CN", "RepairLegacy") => "旧版存档:".to_string(),
+            ("zh-CN", "RepairLegacyHint") => "旧版本上传的存档没有记录游戏 ID，会显示为“未知”。根据文件名和存档内容与本地存档文件夹的比对推测所属游戏，确认后移动到正确的位置。".to_string(),
+            ("zh-CN", "RepairFind") => "查找".to_string(),
+            ("zh-CN", "RepairNone") => "没有需要修复的存档".to_string(),
+            ("zh-CN", "RepairNoMatch") => "未找到匹配的游戏".to_string(),
+            ("zh-CN", "RepairNameMatch") => "文件名匹配".to_string(),
+            ("zh-CN", "RepairFilesMatch") => "个文件匹配".to_string(),
+            ("zh-CN", "RepairConfirm") => "移动到此游戏".to_string(),
+            ("zh-CN", "RepairFailed") => "修复失败".to_string(),
+            ("zh-CN", "RetentionDefault") => "版本保留:".to_string(),
+            ("zh-CN", "RetentionHint") => "每次上传成功后自动删除旧版本。带标签的版本和最新版本始终保留。".to_string(),
+            ("zh-CN", "RetentionKeepAll") => "全部保留".to_string(),
+            ("zh-CN", "RetentionKeepLast") => "保留最近 N 个".to_string(),
+            ("zh-CN", "RetentionGfs") => "祖父-父-子".to_string(),
+            ("zh-CN", "RetentionLast") => "最近".to_string(),
+            ("zh-CN", "RetentionDaily") => "每日(天)".to_string(),
+            ("zh-CN", "RetentionWeekly") => "每周(周)".to_string(),
+            ("zh-CN", "RetentionMonthly") => "每月(月)".to_string(),
+            ("zh-CN", "RetentionSave") => "保存".to_string(),
+            ("zh-CN", "RetentionGame") => "单个游戏:".to_string(),
+            ("zh-CN", "RetentionOwnPolicy") => "使用单独的策略".to_string(),
+            ("zh-CN", "RetentionPreview") => "预览".to_string(),
+            ("zh-CN", "RetentionPruneNow") => "立即清理".to_string(),
+            ("zh-CN", "RetentionNothing") => "没有需要删除的版本".to_string(),
+            ("zh-CN", "RetentionWouldPrune") => "个版本将被删除".to_string(),
+            ("zh-CN", "RetentionKept") => "个保留".to_string(),
+            ("zh-CN", "RetentionPruned") => "个旧版本已删除".to_string(),
+            ("zh-CN", "RetentionFailed") => "版本保留操作失败".to_string(),
             ("zh-CN", "EnableRateLimiting") => "启用速率限制".to_string(),
+            ("zh-CN", "UploadSpeedLimit") => "上传速度 (MB/s):".to_string(),
+            ("zh-CN", "DownloadSpeedLimit") => "下载速度 (MB/s):".to_string(),
+            ("zh-CN", "DownloadSpeedLimitHint") => "0 表示不限制下载速度".to_string(),
+            ("zh-CN", "VersionStorage") => "版本存储方式:".to_string(),
+            ("zh-CN", "VersionStorageArchive") => "完整压缩包".to_string(),
+            ("zh-CN", "VersionStorageIncremental") => "增量文件".to_string(),
+            ("zh-CN", "VersionStorageIncrementalHint") => "每个版本记录文件清单，只上传有变化的文件".to_string(),
+            ("zh-CN", "VersionStorageChunked") => "数据块去重".to_string(),
+            ("zh-CN", "VersionStorageChunkedHint") => "将存档切分为数据块，新版本只上传变化的部分".to_string(),
+            ("zh-CN", "EncryptSaves") => "客户端加密存档".to_string(),
+            ("zh-CN", "EncryptSavesHint") => "上传前在本机加密，密钥不会离开本机。在其他设备上恢复时需要复制密钥环文件".to_string(),
+            ("zh-CN", "EncryptionPassphrase") => "加密口令:".to_string(),
+            ("zh-CN", "EncryptionPassphraseHint") => "口令不会保存到磁盘，每次启动后需要重新输入".to_string(),
+            ("zh-CN", "NewEncryptionPassphrase") => "新口令:".to_string(),
+            ("zh-CN", "ChangePassphrase") => "更改口令".to_string(),
             ("zh-CN", "GameName") => "游戏名称".to_string(),
             ("zh-CN", "Direction") => "方向".to_string(),
             ("zh-CN", "Timestamp") => "时间戳".to_string(),
@@ -99,6 +160,8 @@ impl LocalizationManager {
             ("zh-CN", "Downloading") => "下载中...".to_string(),
             ("zh-CN", "RefreshCloudSaves") => "刷新云端存档".to_string(),
             ("zh-CN", "DefaultDownloadLocation") => "默认下载位置".to_string(),
+            ("zh-CN", "LocalFolderBackend") => "本地文件夹 / NAS".to_string(),
+            ("zh-CN", "LocalFolderPath") => "备份文件夹：".to_string(),
             (_, "AppTitle") => "SteamCloudSync".to_string(),
             (_, "SyncNow") => "Sync Now".to_string(),
             (_, "Home") => "Home".to_string(),
@@ -114,7 +177,66 @@ impl LocalizationManager {
             (_, "SaveBackendSettings") => "Save Backend Settings".to_string(),
             (_, "Application") => "Application".to_string(),
             (_, "StartWithWindows") => "Start with Windows".to_string(),
+            (_, "RunningOperations") => "Running operations".to_string(),
+            (_, "Cancel") => "Cancel".to_string(),
+            (_, "CancelAll") => "Cancel all".to_string(),
+            (_, "MirrorBackends") => "Also mirror uploads to:".to_string(),
+            (_, "MirrorBackendsHint") => "Every upload is also written to these backends, and downloads fall back to them when the selected backend is unreachable. Select a backend once to fill in its settings.".to_string(),
+            (_, "MirrorNotConfigured") => "Select this backend and fill in its settings first".to_string(),
+            (_, "MirrorInSync") => "In sync".to_string(),
+            (_, "MirrorOutOfDate") => "Out of date".to_string(),
+            (_, "MirrorChecking") => "Checking".to_string(),
+            (_, "MirrorCatchUp") => "Catch up now".to_string(),
+            (_, "MigrateFrom") => "Migrate from:".to_string(),
+            (_, "MigrateHint") => "Copies every save version with its details into the selected backend. An interrupted migration continues where it stopped when run again.".to_string(),
+            (_, "MigrateStart") => "Copy to selected backend".to_string(),
+            (_, "MigrateDone") => "Migration finished, copied".to_string(),
+            (_, "MigrateSkipped") => "already there".to_string(),
+            (_, "MigrateFailed") => "Migration failed; run it again to continue".to_string(),
+            (_, "RepairLegacy") => "Legacy saves:".to_string(),
+            (_, "RepairLegacyHint") => "Saves uploaded by old versions don't record their game and show up as unknown. Their game is guessed from the file name and by comparing the archive with your local save folders; once confirmed, they are moved to the right place.".to_string(),
+            (_, "RepairFind") => "Find".to_string(),
+            (_, "RepairNone") => "No saves need repair".to_string(),
+            (_, "RepairNoMatch") => "No matching game found".to_string(),
+            (_, "RepairNameMatch") => "name matches".to_string(),
+            (_, "RepairFilesMatch") => "files match".to_string(),
+            (_, "RepairConfirm") => "Move to this game".to_string(),
+            (_, "RepairFailed") => "Repair failed".to_string(),
+            (_, "RetentionDefault") => "Version retention:".to_string(),
+            (_, "RetentionHint") => "Old versions are deleted after each successful upload. Labelled versions and the newest version are always kept.".to_string(),
+            (_, "RetentionKeepAll") => "Keep all".to_string(),
+            (_, "RetentionKeepLast") => "Keep last N".to_string(),
+            (_, "RetentionGfs") => "Grandfather-father-son".to_string(),
+            (_, "RetentionLast") => "Last".to_string(),
+            (_, "RetentionDaily") => "Daily (days)".to_string(),
+            (_, "RetentionWeekly") => "Weekly (weeks)".to_string(),
+            (_, "RetentionMonthly") => "Monthly (months)".to_string(),
+            (_, "RetentionSave") => "Save".to_string(),
+            (_, "RetentionGame") => "Per game:".to_string(),
+            (_, "RetentionOwnPolicy") => "Own policy".to_string(),
+            (_, "RetentionPreview") => "Preview".to_string(),
+            (_, "RetentionPruneNow") => "Prune now".to_string(),
+            (_, "RetentionNothing") => "Nothing to prune".to_string(),
+            (_, "RetentionWouldPrune") => "versions would be deleted".to_string(),
+            (_, "RetentionKept") => "kept".to_string(),
+            (_, "RetentionPruned") => "old versions deleted".to_string(),
+            (_, "RetentionFailed") => "Retention failed".to_string(),
             (_, "EnableRateLimiting") => "Enable rate limiting".to_string(),
+            (_, "UploadSpeedLimit") => "Upload speed (MB/s):".to_string(),
+            (_, "DownloadSpeedLimit") => "Download speed (MB/s):".to_string(),
+            (_, "DownloadSpeedLimitHint") => "0 leaves downloads unlimited".to_string(),
+            (_, "VersionStorage") => "Version storage:".to_string(),
+            (_, "VersionStorageArchive") => "Full archives".to_string(),
+            (_, "VersionStorageIncremental") => "Incremental files".to_string(),
+            (_, "VersionStorageIncrementalHint") => "Record a file list per version and upload only files that changed".to_string(),
+            (_, "VersionStorageChunked") => "Deduplicated chunks".to_string(),
+            (_, "VersionStorageChunkedHint") => "Split saves into chunks so new versions only upload what changed".to_string(),
+            (_, "EncryptSaves") => "Encrypt saves on this computer".to_string(),
+            (_, "EncryptSavesHint") => "Saves are encrypted before upload and keys never leave this machine. To restore elsewhere, copy the keyring file".to_string(),
+            (_, "EncryptionPassphrase") => "Passphrase:".to_string(),
+            (_, "EncryptionPassphraseHint") => "The passphrase isn't saved to disk; enter it again after each start".to_string(),
+            (_, "NewEncryptionPassphrase") => "New passphrase:".to_string(),
+            (_, "ChangePassphrase") => "Change passphrase".to_string(),
             (_, "GameName") => "Game Name".to_string(),
             (_, "Direction") => "Direction".to_string(),
             (_, "Timestamp") => "Timestamp".to_string(),
@@ -130,6 +252,8 @@ impl LocalizationManager {
             (_, "Downloading") => "Downloading...".to_string(),
             (_, "RefreshCloudSaves") => "Refresh Cloud Saves".to_string(),
             (_, "DefaultDownloadLocation") => "Default Download Location".to_string(),
+            (_, "LocalFolderBackend") => "Local Folder / NAS".to_string(),
+            (_, "LocalFolderPath") => "Backup folder:".to_string(),
             _ => key.to_string(),
         }
     }
@@ -3,7 +3,8 @@ use crate::AppViewModel;
 use std::sync::Arc;
 use egui::{Color32, ProgressBar, ScrollArea, RichText};
 use std::collections::HashMap;
-use steam_cloud_sync_cloud::SaveMetadata;
+use steam_cloud_sync_cloud::{QuotaLevel, QuotaStatus, RetentionPlan, SaveMetadata, ScrubOutcome};
+use steam_cloud_sync_persistence::{ScrubRecord, ScrubRun};
 use rfd::AsyncFileDialog;
 use chrono;
 
@@ -14,6 +15,33 @@ pub struct CloudSaveVersion {
     pub is_current_local: bool,
 }
 
+/// Storage quota shown above the saves, loaded in the background
+#[derive(Clone, Debug, Default)]
+pub struct QuotaPanel {
+    pub busy: bool,
+    pub loaded_at: Option<std::time::Instant>,
+    /// Quota in GB, `None` when unlimited
+    pub quota_gb: Option<u64>,
+    pub status: Option<QuotaStatus>,
+    /// What a cleanup by retention policies would delete, until confirmed
+    pub cleanup: Option<RetentionPlan>,
+    pub message: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Results of verifying the stored versions, loaded in the background
+#[derive(Clone, Debug, Default)]
+pub struct ScrubPanel {
+    pub busy: bool,
+    /// A scrub started from this page is running
+    pub scrubbing: bool,
+    pub loaded_at: Option<std::time::Instant>,
+    pub last_run: Option<ScrubRun>,
+    /// Versions found corrupt, missing or unverifiable
+    pub problems: Vec<ScrubRecord>,
+    pub error: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct CloudSaveGameEntry {
     pub game: GameWithSave,
@@ -47,6 +75,13 @@ pub struct CloudSavesPage {
     pub total_cloud_size: u64,
     pub total_versions: usize,
     
+    // 存储配额
+    pub quota: Arc<std::sync::Mutex<QuotaPanel>>,
+    pub quota_gb_draft: Option<u64>,
+    
+    // 存档完整性检查
+    pub scrub: Arc<std::sync::Mutex<ScrubPanel>>,
+    
     // Context
     pub view_model: Option<Arc<AppViewModel>>,
     pub settings: Option<AppSettings>,
@@ -65,6 +100,9 @@ impl CloudSavesPage {
             selected_versions: HashMap::new(),
             total_cloud_size: 0,
             total_versions: 0,
+            quota: Arc::new(std::sync::Mutex::new(QuotaPanel::default())),
+            quota_gb_draft: None,
+            scrub: Arc::new(std::sync::Mutex::new(ScrubPanel::default())),
             view_model: None,
             settings: None,
         }
@@ -420,6 +458,11 @@ pub fn show_cloud_saves_page(
     
     ui.separator();
     
+    show_quota_panel(ui, page, &view_model, games);
+    show_scrub_panel(ui, page, &view_model);
+    
+    ui.separator();
+    
     // Toolbar
     ui.horizontal(|ui| {
         // Filter
@@ -673,6 +716,262 @@ pub fn show_cloud_saves_page(
         });
 }
 
+/// Storage used against the quota, with warnings as it fills up and a cleanup by
+/// retention policies once it is close to full
+fn show_quota_panel(
+    ui: &mut egui::Ui,
+    page: &mut CloudSavesPage,
+    view_model: &Arc<AppViewModel>,
+    games: &Arc<std::sync::Mutex<Vec<GameWithSave>>>,
+) {
+    let panel = page.quota.lock().unwrap().clone();
+    let stale = panel.loaded_at.map_or(true, |loaded_at| loaded_at.elapsed().as_secs() > 30);
+    if stale && !panel.busy {
+        let vm = view_model.clone();
+        let quota = page.quota.clone();
+        quota.lock().unwrap().busy = true;
+        tokio::spawn(async move {
+            let result = vm.quota_status().await;
+            let mut panel = quota.lock().unwrap();
+            panel.busy = false;
+            panel.loaded_at = Some(std::time::Instant::now());
+            match result {
+                Ok((quota_gb, status)) => {
+                    panel.quota_gb = quota_gb;
+                    panel.status = status;
+                    panel.error = None;
+                }
+                Err(e) => panel.error = Some(format!("{:#}", e)),
+            }
+        });
+    }
+    
+    ui.horizontal(|ui| {
+        ui.label("Storage quota:");
+        match &panel.status {
+            Some(status) => {
+                ui.add(ProgressBar::new(status.fraction().min(1.0) as f32)
+                    .desired_width(250.0)
+                    .text(format!(
+                        "{:.2} / {:.2} GB ({:.0}%)",
+                        status.used_bytes as f64 / (1024.0 * 1024.0 * 1024.0),
+                        status.quota_bytes as f64 / (1024.0 * 1024.0 * 1024.0),
+                        status.fraction() * 100.0
+                    )));
+            }
+            None => {
+                ui.label("Unlimited");
+            }
+        }
+        
+        ui.separator();
+        
+        let draft = page.quota_gb_draft.get_or_insert(panel.quota_gb.unwrap_or(0));
+        ui.add(egui::DragValue::new(draft).clamp_range(0..=100_000).suffix(" GB"))
+            .on_hover_text("0 leaves storage unlimited");
+        let draft = *draft;
+        if ui.add_enabled(!panel.busy && draft != panel.quota_gb.unwrap_or(0), egui::Button::new("Save")).clicked() {
+            let vm = view_model.clone();
+            let quota = page.quota.clone();
+            quota.lock().unwrap().busy = true;
+            tokio::spawn(async move {
+                let result = vm.set_quota_gb(draft).await;
+                let mut panel = quota.lock().unwrap();
+                panel.busy = false;
+                // Reload the status with the new quota
+                panel.loaded_at = None;
+                panel.error = result.err().map(|e| format!("{:#}", e));
+            });
+        }
+        
+        if panel.busy {
+            ui.spinner();
+        }
+    });
+    
+    let level = panel.status.map(|status| status.level()).unwrap_or(QuotaLevel::Ok);
+    match level {
+        QuotaLevel::Ok => {}
+        QuotaLevel::Warning => {
+            ui.colored_label(Color32::YELLOW, "⚠ Cloud storage is filling up");
+        }
+        QuotaLevel::Critical => {
+            ui.colored_label(Color32::RED, "⚠ Cloud storage is almost full; uploads will soon be refused");
+        }
+        QuotaLevel::Exceeded => {
+            ui.colored_label(Color32::RED, "✗ Storage quota reached; uploads are refused until space is freed");
+        }
+    }
+    
+    if level != QuotaLevel::Ok || panel.cleanup.is_some() {
+        ui.horizontal(|ui| {
+            match &panel.cleanup {
+                None => {
+                    if ui.add_enabled(!panel.busy, egui::Button::new("🧹 Free space with retention policies...")).clicked() {
+                        let vm = view_model.clone();
+                        let quota = page.quota.clone();
+                        quota.lock().unwrap().busy = true;
+                        tokio::spawn(async move {
+                            let result = vm.preview_quota_cleanup().await;
+                            let mut panel = quota.lock().unwrap();
+                            panel.busy = false;
+                            match result {
+                                Ok(plan) => {
+                                    panel.cleanup = Some(plan);
+                                    panel.message = None;
+                                    panel.error = None;
+                                }
+                                Err(e) => panel.error = Some(format!("{:#}", e)),
+                            }
+                        });
+                    }
+                }
+                Some(plan) if plan.prune.is_empty() => {
+                    ui.label("Retention policies keep every version; delete versions by hand or raise the quota");
+                    if ui.button("OK").clicked() {
+                        page.quota.lock().unwrap().cleanup = None;
+                    }
+                }
+                Some(plan) => {
+                    ui.label(format!(
+                        "{} old versions ({:.1} MB) would be deleted",
+                        plan.prune.len(),
+                        plan.pruned_bytes() as f64 / (1024.0 * 1024.0)
+                    ));
+                    if ui.add_enabled(!panel.busy, egui::Button::new("🗑 Delete them")).clicked() {
+                        let vm = view_model.clone();
+                        let quota = page.quota.clone();
+                        let games = games.clone();
+                        quota.lock().unwrap().busy = true;
+                        tokio::spawn(async move {
+                            let result = vm.apply_quota_cleanup().await;
+                            if let Ok(updated_games) = vm.force_scan_games().await {
+                                *games.lock().unwrap() = updated_games;
+                            }
+                            let mut panel = quota.lock().unwrap();
+                            panel.busy = false;
+                            panel.cleanup = None;
+                            panel.loaded_at = None;
+                            match result {
+                                Ok(plan) => {
+                                    panel.message = Some(format!(
+                                        "Deleted {} old versions ({:.1} MB)",
+                                        plan.prune.len(),
+                                        plan.pruned_bytes() as f64 / (1024.0 * 1024.0)
+                                    ));
+                                    panel.error = None;
+                                }
+                                Err(e) => panel.error = Some(format!("{:#}", e)),
+                            }
+                        });
+                    }
+                    if ui.add_enabled(!panel.busy, egui::Button::new("Cancel")).clicked() {
+                        page.quota.lock().unwrap().cleanup = None;
+                    }
+                }
+            }
+        });
+    }
+    
+    if let Some(message) = &panel.message {
+        ui.colored_label(Color32::GREEN, format!("✔ {}", message));
+    }
+    if let Some(error) = &panel.error {
+        ui.colored_label(Color32::RED, format!("❌ {}", error));
+    }
+}
+
+/// When the stored versions were last verified, a way to verify them now and the
+/// versions found damaged
+fn show_scrub_panel(ui: &mut egui::Ui, page: &mut CloudSavesPage, view_model: &Arc<AppViewModel>) {
+    let panel = page.scrub.lock().unwrap().clone();
+    let stale = panel.loaded_at.map_or(true, |loaded_at| loaded_at.elapsed().as_secs() > 60);
+    if stale && !panel.busy {
+        let vm = view_model.clone();
+        let scrub = page.scrub.clone();
+        scrub.lock().unwrap().busy = true;
+        tokio::spawn(async move {
+            let result = vm.scrub_status().await;
+            let mut panel = scrub.lock().unwrap();
+            panel.busy = false;
+            panel.loaded_at = Some(std::time::Instant::now());
+            match result {
+                Ok((last_run, problems)) => {
+                    panel.last_run = last_run;
+                    panel.problems = problems;
+                    panel.error = None;
+                }
+                Err(e) => panel.error = Some(format!("{:#}", e)),
+            }
+        });
+    }
+    
+    ui.horizontal(|ui| {
+        ui.label("Integrity:");
+        match &panel.last_run {
+            Some(run) => {
+                ui.label(format!(
+                    "last checked {} ({} of {} versions)",
+                    run.finished_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                    run.checked,
+                    run.total_versions
+                ));
+            }
+            None => {
+                ui.label("versions have not been verified yet");
+            }
+        }
+        
+        if ui.add_enabled(!panel.scrubbing, egui::Button::new("🔍 Verify all versions"))
+            .on_hover_text("Download every stored version and check it against its checksum")
+            .clicked()
+        {
+            let vm = view_model.clone();
+            let scrub = page.scrub.clone();
+            scrub.lock().unwrap().scrubbing = true;
+            tokio::spawn(async move {
+                let result = vm.scrub(None).await;
+                let mut panel = scrub.lock().unwrap();
+                panel.scrubbing = false;
+                panel.loaded_at = None;
+                panel.error = result.err().map(|e| format!("{:#}", e));
+            });
+        }
+        
+        if panel.scrubbing {
+            ui.spinner();
+            ui.label("Verifying...");
+        }
+    });
+    
+    if !panel.problems.is_empty() {
+        egui::CollapsingHeader::new(
+            RichText::new(format!("⚠ {} damaged or unverifiable versions", panel.problems.len())).color(Color32::RED)
+        )
+        .id_source("scrub_problems")
+        .show(ui, |ui| {
+            for record in &panel.problems {
+                ui.horizontal(|ui| {
+                    let (color, label) = match ScrubOutcome::parse(&record.outcome) {
+                        Some(ScrubOutcome::Corrupt) => (Color32::RED, "Corrupt"),
+                        Some(ScrubOutcome::Missing) => (Color32::RED, "Missing"),
+                        _ => (Color32::YELLOW, "Unverifiable"),
+                    };
+                    ui.colored_label(color, label);
+                    ui.label(record.file_id.as_str());
+                    if let Some(detail) = &record.detail {
+                        ui.label(RichText::new(detail).weak());
+                    }
+                });
+            }
+        });
+    }
+    
+    if let Some(error) = &panel.error {
+        ui.colored_label(Color32::RED, format!("❌ {}", error));
+    }
+}
+
 fn show_game_entry(ui: &mut egui::Ui, page: &mut CloudSavesPage, entry: CloudSaveGameEntry, game_id: String) {
     ui.group(|ui| {
         // Game header
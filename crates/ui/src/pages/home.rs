@@ -194,27 +194,7 @@ pub fn show_home_page(ui: &mut egui::Ui, games: &[GameWithSave], home: &mut Home
                                     // Find the specific save to delete
                                     if let Some(save_to_delete) = saves.iter().find(|s| s.file_id == version_id_clone) {
                                         // Delete through cloud backend
-                                        let backend = match settings_clone.selected_backend {
-                                            steam_cloud_sync_cloud::BackendType::TencentCOS => {
-                                                steam_cloud_sync_cloud::backend_with_settings(
-                                                    settings_clone.selected_backend,
-                                                    Some((
-                                                        settings_clone.tencent_secret_id.clone(),
-                                                        settings_clone.tencent_secret_key.clone(),
-                                                        settings_clone.tencent_bucket.clone(),
-                                                        settings_clone.tencent_region.clone(),
-                                                    )),
-                                                    None,
-                                                )
-                                            }
-                                            steam_cloud_sync_cloud::BackendType::S3 => {
-                                                steam_cloud_sync_cloud::backend_with_settings(
-                                                    settings_clone.selected_backend,
-                                                    None,
-                                                    Some((settings_clone.s3_bucket.clone(), "saves/".to_string())),
-                                                )
-                                            }
-                                        };
+                                        let backend = settings_clone.create_backend(settings_clone.selected_backend);
                                         
                                         if let Err(e) = backend.delete_save(save_to_delete).await {
                                             eprintln!("Failed to delete save: {}", e);
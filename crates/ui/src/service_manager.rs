@@ -6,20 +6,39 @@ use uuid::Uuid;
 use chrono;
 
 use steam_cloud_sync_cloud::{
-    CloudSaveService, ProgressUpdate, SyncResult
+    CloudSaveService, OperationStatus, OperationType, ProgressUpdate, ReplicaStatus, ReplicatedBackend,
+    QuotaStatus, RetentionPlan, RetentionPolicy, RetentionRules, RunningOperation, ScrubOutcome, ScrubReport,
+    SyncResult, DEFAULT_CONCURRENCY,
 };
 use steam_cloud_sync_persistence::{
     PersistenceManager, CloudOperation, CloudOperationType, CloudOperationStatus,
-    GameConfig
+    GameConfig, ScrubRecord, ScrubRun
 };
-use crate::{AppSettings, GameWithSave};
+use crate::{AppSettings, GameWithSave, PersistentUploadSessionStore};
+
+/// Progress updates kept for the UI before the oldest are dropped
+const MAX_PENDING_PROGRESS_UPDATES: usize = 256;
+/// Change in progress worth writing to the database
+const PROGRESS_WRITE_STEP: f32 = 0.05;
+/// How often mirrors that fell behind are brought up to date
+const MIRROR_CATCH_UP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// Versions kept per game when `sync.max_versions_per_game` isn't set
+const DEFAULT_MAX_VERSIONS: i64 = 5;
+const GB: u64 = 1024 * 1024 * 1024;
+/// How often the scrub schedule checks whether a scrub is due
+const SCRUB_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
 
 /// Service manager that coordinates cloud operations with persistence
 pub struct ServiceManager {
     pub cloud_service: CloudSaveService,
     pub persistence: Option<Arc<PersistenceManager>>, // Made optional for graceful degradation
-    progress_rx: Arc<Mutex<mpsc::UnboundedReceiver<ProgressUpdate>>>,
+    /// Progress updates not yet collected by the UI
+    progress_updates: Arc<Mutex<Vec<ProgressUpdate>>>,
     active_operations: Arc<Mutex<HashMap<Uuid, CloudOperation>>>,
+    /// Set when uploads are mirrored to several backends
+    replication: Option<ReplicatedBackend>,
+    /// Held while a scrub runs, so scheduled and manual scrubs don't overlap
+    scrubbing: Mutex<()>,
     /// Whether the service manager is running in degraded mode (without database)
     pub degraded_mode: bool,
 }
@@ -46,26 +65,67 @@ impl ServiceManager {
         
         // Create cloud backend - this is the core functionality
         println!("☁️ [DEBUG] Creating cloud backend...");
-        let backend = steam_cloud_sync_cloud::backend_with_settings(
-            settings.selected_backend,
-            Some((
-                settings.tencent_secret_id.clone(),
-                settings.tencent_secret_key.clone(),
-                settings.tencent_bucket.clone(),
-                settings.tencent_region.clone(),
-            )),
-            Some((settings.s3_bucket.clone(), "saves/".to_string())),
-        );
+        let mut backend = settings.create_backend(settings.selected_backend);
+        if let Some(persistence) = &persistence {
+            // Record multipart uploads in SQLite so interrupted uploads resume after a restart
+            backend.set_upload_session_store(Arc::new(PersistentUploadSessionStore::new(
+                persistence.multipart_uploads.clone(),
+            )));
+        }
+        let replication = backend.replication();
+        if let Some(replication) = &replication {
+            // Mirrors start out unchecked; the first pass runs right away
+            replication.spawn_catch_up(MIRROR_CATCH_UP_INTERVAL);
+            println!("🪞 [DEBUG] Mirroring uploads to {} backends", replication.status().len());
+        }
         println!("✅ [DEBUG] Cloud backend created");
         
         // Create progress channel
         let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+        let progress_updates = Arc::new(Mutex::new(Vec::new()));
+        let active_operations = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(Self::track_progress(
+            progress_rx,
+            persistence.clone(),
+            active_operations.clone(),
+            progress_updates.clone(),
+        ));
         println!("📡 [DEBUG] Progress channel created");
         
+        // Batch operations and sync-all run `sync.parallel_operations` games at a time
+        let concurrency = match &persistence {
+            Some(persistence) => persistence.config_store.get_number_config("sync.parallel_operations").await
+                .ok()
+                .flatten()
+                .filter(|n| *n > 0)
+                .map(|n| n as usize)
+                .unwrap_or(DEFAULT_CONCURRENCY),
+            None => DEFAULT_CONCURRENCY,
+        };
+        
+        // Without stored settings nothing is pruned
+        let retention = match &persistence {
+            Some(persistence) => Self::load_retention_rules(persistence).await,
+            None => RetentionRules::default(),
+        };
+        
+        // Uploads stop at `storage.max_total_size_gb`; 0 or unset is unlimited
+        let quota_bytes = match &persistence {
+            Some(persistence) => persistence.config_store.get_number_config("storage.max_total_size_gb").await
+                .ok()
+                .flatten()
+                .filter(|gb| *gb > 0)
+                .map(|gb| gb as u64 * GB),
+            None => None,
+        };
+        
         // Create cloud service with progress tracking and user ID
         let cloud_service = CloudSaveService::new(backend)
             .with_user_id(settings.user_id.clone())
-            .with_progress_channel(progress_tx);
+            .with_progress_channel(progress_tx)
+            .with_concurrency(concurrency)
+            .with_retention(retention)
+            .with_quota(quota_bytes);
         println!("🔄 [DEBUG] Cloud service created with user_id: {}", settings.user_id);
         
         let degraded_mode = persistence.is_none();
@@ -80,12 +140,156 @@ impl ServiceManager {
         Ok(Self {
             cloud_service,
             persistence,
-            progress_rx: Arc::new(Mutex::new(progress_rx)),
-            active_operations: Arc::new(Mutex::new(HashMap::new())),
+            progress_updates,
+            active_operations,
+            replication,
+            scrubbing: Mutex::new(()),
             degraded_mode,
         })
     }
     
+    /// Retention rules from the settings: `sync.retention_policy`, or else keep
+    /// `sync.max_versions_per_game` versions of every game, overridden by each game's
+    /// own policy or `max_versions`
+    async fn load_retention_rules(persistence: &PersistenceManager) -> RetentionRules {
+        let store = &persistence.config_store;
+        let stored_default = store.get_json_config("sync.retention_policy").await
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_value(value).ok());
+        let default = match stored_default {
+            Some(policy) => policy,
+            None => keep_last(store.get_number_config("sync.max_versions_per_game").await
+                .ok()
+                .flatten()
+                .unwrap_or(DEFAULT_MAX_VERSIONS)),
+        };
+        
+        let mut per_game = HashMap::new();
+        match store.get_all_game_configs().await {
+            Ok(configs) => {
+                for config in configs {
+                    if let Some(policy) = game_retention_policy(&config) {
+                        per_game.insert(config.game_id, policy);
+                    }
+                }
+            }
+            Err(e) => println!("⚠️ [DEBUG] Failed to load per-game retention policies: {}", e),
+        }
+        
+        println!("🧹 [DEBUG] Retention: {:?} by default, {} games with their own policy", default, per_game.len());
+        RetentionRules { default, per_game }
+    }
+    
+    /// Keep operation history in step with the cloud service's progress updates: an
+    /// operation is recorded when it starts, its progress is updated while it runs and
+    /// its final status is stored when it ends
+    async fn track_progress(
+        mut progress_rx: mpsc::UnboundedReceiver<ProgressUpdate>,
+        persistence: Option<Arc<PersistenceManager>>,
+        active_operations: Arc<Mutex<HashMap<Uuid, CloudOperation>>>,
+        progress_updates: Arc<Mutex<Vec<ProgressUpdate>>>,
+    ) {
+        // Cloud service operation id -> (history record id, progress last written to the database)
+        let mut records: HashMap<Uuid, (Uuid, f32)> = HashMap::new();
+        
+        while let Some(update) = progress_rx.recv().await {
+            {
+                let mut pending = progress_updates.lock().await;
+                if pending.len() >= MAX_PENDING_PROGRESS_UPDATES {
+                    pending.remove(0);
+                }
+                pending.push(update.clone());
+            }
+            
+            let operation_type = match update.operation_type {
+                OperationType::Upload => CloudOperationType::Upload,
+                OperationType::Download => CloudOperationType::Download,
+                OperationType::Restore => CloudOperationType::Restore,
+                OperationType::Delete => CloudOperationType::Delete,
+                // Listings and batches aren't kept in history
+                OperationType::List | OperationType::Batch => continue,
+            };
+            let progress = (update.total_bytes > 0)
+                .then(|| (update.bytes_processed as f32 / update.total_bytes as f32).min(1.0));
+            
+            match update.status {
+                OperationStatus::Starting => {
+                    let mut operation = CloudOperation::new(update.game_id.clone(), operation_type);
+                    operation.status = CloudOperationStatus::InProgress;
+                    operation.progress = Some(0.0);
+                    if let Some(persistence) = &persistence {
+                        match persistence.cloud_history.create_operation(operation.clone()).await {
+                            Ok(stored) => operation = stored,
+                            Err(e) => println!("⚠️ [DEBUG] Failed to create operation record: {}", e),
+                        }
+                    }
+                    records.insert(update.operation_id, (operation.id, 0.0));
+                    active_operations.lock().await.insert(operation.id, operation);
+                }
+                OperationStatus::InProgress => {
+                    let Some((record_id, written)) = records.get_mut(&update.operation_id) else { continue };
+                    if let Some(operation) = active_operations.lock().await.get_mut(record_id) {
+                        operation.progress = progress.or(operation.progress);
+                        if update.total_bytes > 0 {
+                            operation.file_size = Some(update.total_bytes as i64);
+                        }
+                    }
+                    
+                    // The database only gets every few percent
+                    if let (Some(persistence), Some(progress)) = (&persistence, progress) {
+                        if progress - *written >= PROGRESS_WRITE_STEP {
+                            *written = progress;
+                            if let Err(e) = persistence.cloud_history
+                                .update_operation_progress(*record_id, CloudOperationStatus::InProgress, Some(progress))
+                                .await
+                            {
+                                println!("⚠️ [DEBUG] Failed to update operation progress: {}", e);
+                            }
+                        }
+                    }
+                }
+                OperationStatus::Completed | OperationStatus::Failed | OperationStatus::Cancelled => {
+                    let Some((record_id, _)) = records.remove(&update.operation_id) else { continue };
+                    let (status, progress) = match update.status {
+                        OperationStatus::Completed => (CloudOperationStatus::Completed, Some(1.0)),
+                        OperationStatus::Cancelled => (CloudOperationStatus::Cancelled, progress),
+                        _ => (CloudOperationStatus::Failed, progress),
+                    };
+                    
+                    let stored = match &persistence {
+                        Some(persistence) => {
+                            let result = match (&status, &update.error) {
+                                (CloudOperationStatus::Failed, Some(error)) => persistence.cloud_history
+                                    .update_operation_error(record_id, error.clone())
+                                    .await,
+                                _ => persistence.cloud_history
+                                    .update_operation_progress(record_id, status.clone(), progress)
+                                    .await,
+                            };
+                            if let Err(e) = &result {
+                                println!("⚠️ [DEBUG] Failed to record final operation status: {}", e);
+                            }
+                            result.is_ok()
+                        }
+                        None => false,
+                    };
+                    
+                    let mut active = active_operations.lock().await;
+                    if stored {
+                        active.remove(&record_id);
+                    } else if let Some(operation) = active.get_mut(&record_id) {
+                        // Degraded mode: history lives in memory
+                        operation.status = status;
+                        operation.progress = progress;
+                        operation.error_message = update.error.clone();
+                        operation.completed_at = Some(chrono::Utc::now());
+                    }
+                }
+            }
+        }
+    }
+    
     /// Try to initialize persistence layer - returns error if it fails
     async fn try_initialize_persistence() -> Result<PersistenceManager> {
         println!("💾 [DEBUG] Attempting to initialize persistence layer...");
@@ -122,145 +326,32 @@ impl ServiceManager {
         }
     }
     
-    /// Upload a save file with optional tracking (works in degraded mode)
+    /// Upload a save file (works in degraded mode). The operation is recorded in
+    /// history from its progress updates, see [`ServiceManager::track_progress`].
     pub async fn upload_save(&self, game_id: &str, local_path: &std::path::Path) -> Result<steam_cloud_sync_cloud::SaveMetadata> {
-        // Always perform the cloud upload - this is the core functionality
         let metadata = self.cloud_service.upload_save(game_id, local_path).await?;
         
-        // Create operation record for tracking (always do this for history)
-        let operation = CloudOperation::new(
-            game_id.to_string(),
-            CloudOperationType::Upload,
-        );
-        
-        let operation_id = operation.id;
-        
-        // Try to store in database if persistence is available
         if let Some(persistence) = &self.persistence {
-            println!("💾 [DEBUG] Storing upload operation in database");
-            match persistence.cloud_history.create_operation(operation.clone()).await {
-                Ok(stored_operation) => {
-                    println!("✅ [DEBUG] Operation stored in database successfully");
-                    
-                    // Store in active operations
-                    {
-                        let mut active = self.active_operations.lock().await;
-                        active.insert(operation_id, stored_operation.clone());
-                    }
-                    
-                    // Update operation as completed
-                    if let Err(e) = persistence.cloud_history
-                        .update_operation_progress(operation_id, CloudOperationStatus::Completed, Some(1.0))
-                        .await 
-                    {
-                        println!("⚠️ [DEBUG] Failed to update operation progress: {}", e);
-                    } else {
-                        println!("✅ [DEBUG] Operation marked as completed in database");
-                    }
-                    
-                    // Update game config last sync time
-                    if let Err(e) = persistence.config_store
-                        .update_game_last_sync(game_id)
-                        .await 
-                    {
-                        println!("⚠️ [DEBUG] Failed to update game last sync: {}", e);
-                    }
-                    
-                    // Remove from active operations (since it's completed)
-                    {
-                        let mut active = self.active_operations.lock().await;
-                        active.remove(&operation_id);
-                    }
-                }
-                Err(e) => {
-                    println!("❌ [DEBUG] Failed to create operation record in database: {}", e);
-                    println!("📱 [DEBUG] Storing operation in memory only (degraded mode)");
-                    
-                    // Store in memory as fallback - mark as completed
-                    let mut completed_operation = operation.clone();
-                    completed_operation.status = CloudOperationStatus::Completed;
-                    completed_operation.completed_at = Some(chrono::Utc::now());
-                    completed_operation.file_size = Some(metadata.size_bytes as i64);
-                    completed_operation.file_path = Some(local_path.to_string_lossy().to_string());
-                    
-                    {
-                        let mut active = self.active_operations.lock().await;
-                        active.insert(operation_id, completed_operation);
-                    }
-                }
-            }
-        } else {
-            println!("📱 [DEBUG] No persistence available - storing upload operation in memory only");
-            
-            // Store in memory only (degraded mode) - mark as completed
-            let mut completed_operation = operation.clone();
-            completed_operation.status = CloudOperationStatus::Completed;
-            completed_operation.completed_at = Some(chrono::Utc::now());
-            completed_operation.file_size = Some(metadata.size_bytes as i64);
-            completed_operation.file_path = Some(local_path.to_string_lossy().to_string());
-            
+            // Update game config last sync time
+            if let Err(e) = persistence.config_store
+                .update_game_last_sync(game_id)
+                .await 
             {
-                let mut active = self.active_operations.lock().await;
-                active.insert(operation_id, completed_operation);
+                println!("⚠️ [DEBUG] Failed to update game last sync: {}", e);
             }
         }
         
-        println!("🎉 [DEBUG] Upload completed successfully for game {} - history recorded", game_id);
+        println!("🎉 [DEBUG] Upload completed successfully for game {}", game_id);
         Ok(metadata)
     }
     
-    /// Download a save file with optional tracking (works in degraded mode)
+    /// Download a save file (works in degraded mode)
     pub async fn download_save(
         &self, 
         save_metadata: &steam_cloud_sync_cloud::SaveMetadata, 
         local_path: &std::path::Path
     ) -> Result<()> {
-        // Always perform the cloud download - this is the core functionality
-        self.cloud_service.download_save(save_metadata, local_path).await?;
-        
-        // Only track in database if persistence is available
-        if let Some(persistence) = &self.persistence {
-            // Create operation record for history tracking
-            let operation = CloudOperation::new(
-                save_metadata.game_id.clone(),
-                CloudOperationType::Download,
-            );
-            
-            let operation_id = operation.id;
-            
-            // Try to store in persistence (but don't fail if it doesn't work)
-            match persistence.cloud_history.create_operation(operation).await {
-                Ok(stored_operation) => {
-                    // Store in active operations
-                    {
-                        let mut active = self.active_operations.lock().await;
-                        active.insert(operation_id, stored_operation);
-                    }
-                    
-                    // Update operation as completed
-                    if let Err(e) = persistence.cloud_history
-                        .update_operation_progress(operation_id, CloudOperationStatus::Completed, Some(1.0))
-                        .await 
-                    {
-                        println!("⚠️ [DEBUG] Failed to update operation progress: {}", e);
-                    }
-                    
-                    // Remove from active operations
-                    {
-                        let mut active = self.active_operations.lock().await;
-                        active.remove(&operation_id);
-                    }
-                }
-                Err(e) => {
-                    println!("⚠️ [DEBUG] Failed to create operation record (degraded mode): {}", e);
-                    // Don't fail - the download succeeded
-                }
-            }
-        } else {
-            println!("📱 [DEBUG] Download completed in degraded mode - no history tracking");
-        }
-        
-        Ok(())
+        self.cloud_service.download_save(save_metadata, local_path).await
     }
     
     /// Restore a save file with backup (works in degraded mode)
@@ -269,68 +360,12 @@ impl ServiceManager {
         save_metadata: &steam_cloud_sync_cloud::SaveMetadata,
         local_path: &std::path::Path
     ) -> Result<()> {
-        // Always perform the cloud restore - this is the core functionality
-        self.cloud_service.restore_save(save_metadata, local_path).await?;
-        
-        // Only track in database if persistence is available
-        if let Some(persistence) = &self.persistence {
-            let operation = CloudOperation::new(
-                save_metadata.game_id.clone(),
-                CloudOperationType::Restore,
-            );
-            let operation_id = operation.id;
-            
-            match persistence.cloud_history.create_operation(operation).await {
-                Ok(_) => {
-                    if let Err(e) = persistence.cloud_history
-                        .update_operation_progress(operation_id, CloudOperationStatus::Completed, Some(1.0))
-                        .await 
-                    {
-                        println!("⚠️ [DEBUG] Failed to update restore operation progress: {}", e);
-                    }
-                }
-                Err(e) => {
-                    println!("⚠️ [DEBUG] Failed to create restore operation record: {}", e);
-                }
-            }
-        } else {
-            println!("📱 [DEBUG] Restore completed in degraded mode - no history tracking");
-        }
-        
-        Ok(())
+        self.cloud_service.restore_save(save_metadata, local_path).await
     }
     
     /// Delete a save file (works in degraded mode)
     pub async fn delete_save(&self, save_metadata: &steam_cloud_sync_cloud::SaveMetadata) -> Result<()> {
-        // Always perform the cloud delete - this is the core functionality
-        self.cloud_service.delete_save(save_metadata).await?;
-        
-        // Only track in database if persistence is available
-        if let Some(persistence) = &self.persistence {
-            let operation = CloudOperation::new(
-                save_metadata.game_id.clone(),
-                CloudOperationType::Delete,
-            );
-            let operation_id = operation.id;
-            
-            match persistence.cloud_history.create_operation(operation).await {
-                Ok(_) => {
-                    if let Err(e) = persistence.cloud_history
-                        .update_operation_progress(operation_id, CloudOperationStatus::Completed, Some(1.0))
-                        .await 
-                    {
-                        println!("⚠️ [DEBUG] Failed to update delete operation progress: {}", e);
-                    }
-                }
-                Err(e) => {
-                    println!("⚠️ [DEBUG] Failed to create delete operation record: {}", e);
-                }
-            }
-        } else {
-            println!("📱 [DEBUG] Delete completed in degraded mode - no history tracking");
-        }
-        
-        Ok(())
+        self.cloud_service.delete_save(save_metadata).await
     }
     
     /// List saves for a game (always works)
@@ -408,6 +443,31 @@ impl ServiceManager {
         }
     }
     
+    /// Sync several games as one batch of the cloud service, which runs as many at a time
+    /// as it allows, keeps each game's transfers in order, reports batch progress and can
+    /// be cancelled as a whole. Results are in the order of `games`.
+    pub async fn sync_games(&self, games: &[GameWithSave]) -> Vec<Result<SyncResult>> {
+        let items = games.iter()
+            .filter_map(|game| game.save_info.as_ref().map(|save_info| (game.game.id.clone(), save_info.save_path.clone())))
+            .collect();
+        let mut synced = self.cloud_service.sync_all_games(items).await.into_iter();
+        
+        let mut results = Vec::with_capacity(games.len());
+        for game in games {
+            let result = match &game.save_info {
+                Some(_) => synced.next().expect("the batch returns a result per game"),
+                None => Err(anyhow::anyhow!("Game has no save path configured")),
+            };
+            if let (Ok(SyncResult::Uploaded(_)), Some(persistence)) = (&result, &self.persistence) {
+                if let Err(e) = persistence.config_store.update_game_last_sync(&game.game.id).await {
+                    println!("⚠️ [DEBUG] Failed to update game last sync: {}", e);
+                }
+            }
+            results.push(result);
+        }
+        results
+    }
+    
     /// Get recent operations for UI display (graceful degradation)
     pub async fn get_recent_operations(&self, limit: Option<i32>) -> Result<Vec<CloudOperation>> {
         if let Some(persistence) = &self.persistence {
@@ -676,15 +736,229 @@ impl ServiceManager {
     
     /// Process progress updates (should be called periodically) - always works
     pub async fn process_progress_updates(&self) -> Vec<ProgressUpdate> {
-        let mut progress_rx = self.progress_rx.lock().await;
-        let mut updates = Vec::new();
+        std::mem::take(&mut *self.progress_updates.lock().await)
+    }
+    
+    /// Cloud operations currently running, with the ids used to cancel them
+    pub fn running_operations(&self) -> Vec<(Uuid, RunningOperation)> {
+        self.cloud_service.running_operations()
+    }
+    
+    /// Cancel one running operation
+    pub fn cancel_operation(&self, operation_id: Uuid) -> bool {
+        println!("🛑 [DEBUG] Cancelling operation {}", operation_id);
+        self.cloud_service.cancel_operation(operation_id)
+    }
+    
+    /// Cancel all running and queued operations
+    pub fn cancel_all(&self) {
+        println!("🛑 [DEBUG] Cancelling all operations");
+        self.cloud_service.cancel_all();
+    }
+    
+    /// Status of each backend uploads are mirrored to; empty without mirrors
+    pub fn mirror_status(&self) -> Vec<ReplicaStatus> {
+        self.replication.as_ref().map(|replication| replication.status()).unwrap_or_default()
+    }
+    
+    /// Bring mirrors that fell behind up to date now
+    pub async fn catch_up_mirrors(&self) -> Result<()> {
+        match &self.replication {
+            Some(replication) => replication.catch_up().await,
+            None => Ok(()),
+        }
+    }
+    
+    /// Retention rules applied after each upload
+    pub fn retention_rules(&self) -> RetentionRules {
+        self.cloud_service.retention()
+    }
+    
+    /// Set the retention policy of games without their own (graceful degradation)
+    pub async fn set_default_retention(&self, policy: RetentionPolicy) -> Result<()> {
+        if let Some(persistence) = &self.persistence {
+            if let Err(e) = persistence.config_store.set_json_config("sync.retention_policy", &serde_json::to_value(policy)?).await {
+                println!("⚠️ [DEBUG] Failed to save retention policy: {}", e);
+                println!("📱 [DEBUG] Retention policy changes will not persist");
+            }
+        }
+        
+        let mut rules = self.cloud_service.retention();
+        rules.default = policy;
+        self.cloud_service.set_retention(rules);
+        Ok(())
+    }
+    
+    /// Give a game its own retention policy, or `None` to follow the default again
+    pub async fn set_game_retention(&self, game_id: &str, policy: Option<RetentionPolicy>) -> Result<()> {
+        let mut config = self.get_game_config(game_id).await?
+            .unwrap_or_else(|| GameConfig::new(game_id.to_string()));
+        config.retention_policy = policy.map(|policy| serde_json::to_string(&policy)).transpose()?;
+        config.max_versions = None;
+        self.set_game_config(config).await?;
+        
+        let mut rules = self.cloud_service.retention();
+        match policy {
+            Some(policy) => rules.per_game.insert(game_id.to_string(), policy),
+            None => rules.per_game.remove(game_id),
+        };
+        self.cloud_service.set_retention(rules);
+        Ok(())
+    }
+    
+    /// Which versions of a game its retention policy would prune now (dry run)
+    pub async fn preview_retention(&self, game_id: &str) -> Result<RetentionPlan> {
+        self.cloud_service.preview_retention(game_id).await
+    }
+    
+    /// Prune the versions of a game its retention policy doesn't keep
+    pub async fn apply_retention(&self, game_id: &str) -> Result<RetentionPlan> {
+        self.cloud_service.apply_retention(game_id).await
+    }
+    
+    /// The storage quota in GB; `None` is unlimited
+    pub fn quota_gb(&self) -> Option<u64> {
+        self.cloud_service.quota().map(|bytes| bytes / GB)
+    }
+    
+    /// Change the storage quota, 0 for unlimited (graceful degradation)
+    pub async fn set_quota_gb(&self, gb: u64) -> Result<()> {
+        if let Some(persistence) = &self.persistence {
+            if let Err(e) = persistence.config_store.set_number_config("storage.max_total_size_gb", gb as i64).await {
+                println!("⚠️ [DEBUG] Failed to save storage quota: {}", e);
+                println!("📱 [DEBUG] Storage quota changes will not persist");
+            }
+        }
         
-        // Collect all available progress updates
-        while let Ok(update) = progress_rx.try_recv() {
-            updates.push(update);
+        self.cloud_service.set_quota((gb > 0).then_some(gb * GB));
+        Ok(())
+    }
+    
+    /// Storage used against the quota; `None` without a quota
+    pub async fn quota_status(&self) -> Result<Option<QuotaStatus>> {
+        self.cloud_service.quota_status().await
+    }
+    
+    /// What pruning every game by its retention policy would free (dry run)
+    pub async fn preview_quota_cleanup(&self) -> Result<RetentionPlan> {
+        self.cloud_service.preview_retention_all().await
+    }
+    
+    /// Prune every game by its retention policy to free space
+    pub async fn apply_quota_cleanup(&self) -> Result<RetentionPlan> {
+        self.cloud_service.apply_retention_all().await
+    }
+    
+    /// Check the user's save versions (all of them, or `sample` picked at random) against
+    /// their checksums and record the results (graceful degradation)
+    pub async fn scrub(&self, sample: Option<usize>) -> Result<ScrubReport> {
+        let Ok(_running) = self.scrubbing.try_lock() else {
+            return Err(anyhow::anyhow!("A scrub is already running"));
+        };
+        println!("🔍 [DEBUG] Scrubbing {} save versions", sample.map_or("all".to_string(), |n| n.to_string()));
+        let report = self.cloud_service.scrub(sample, |_| {}).await?;
+        
+        if let Some(persistence) = &self.persistence {
+            if let Err(e) = Self::record_scrub(persistence, &report).await {
+                println!("⚠️ [DEBUG] Failed to record scrub results: {}", e);
+            }
+        }
+        Ok(report)
+    }
+    
+    async fn record_scrub(persistence: &PersistenceManager, report: &ScrubReport) -> Result<()> {
+        let store = &persistence.scrub_results;
+        for result in &report.results {
+            store.record_result(&ScrubRecord {
+                file_id: result.file_id.clone(),
+                game_id: result.game_id.clone(),
+                size_bytes: result.size_bytes as i64,
+                outcome: result.outcome.as_str().to_string(),
+                detail: result.detail.clone(),
+                checked_at: result.checked_at,
+            }).await?;
+        }
+        if report.results.len() == report.total_versions {
+            // Every version still stored was checked just now
+            store.remove_results_checked_before(report.started_at).await?;
+        }
+        store.record_run(&ScrubRun {
+            id: Uuid::new_v4().to_string(),
+            started_at: report.started_at,
+            finished_at: report.finished_at,
+            total_versions: report.total_versions as i64,
+            checked: report.results.len() as i64,
+            corrupt: report.count(ScrubOutcome::Corrupt) as i64,
+            missing: report.count(ScrubOutcome::Missing) as i64,
+            unverifiable: report.count(ScrubOutcome::Unverifiable) as i64,
+        }).await
+    }
+    
+    /// The last recorded scrub run; `None` before the first one or without the database
+    pub async fn last_scrub(&self) -> Result<Option<ScrubRun>> {
+        match &self.persistence {
+            Some(persistence) => persistence.scrub_results.get_latest_run().await,
+            None => Ok(None),
+        }
+    }
+    
+    /// Versions the latest scrubs found corrupt, missing or unverifiable
+    pub async fn scrub_problems(&self) -> Result<Vec<ScrubRecord>> {
+        match &self.persistence {
+            Some(persistence) => persistence.scrub_results.get_problems().await,
+            None => Ok(Vec::new()),
+        }
+    }
+    
+    /// Scrub a sample of `storage.scrub_sample_size` versions (0: all of them) every
+    /// `storage.scrub_interval_hours` hours (0: never). Needs the database, which knows
+    /// when the last scrub ran; stops once the service manager is dropped.
+    pub fn spawn_scrub_schedule(self: &Arc<Self>) {
+        if self.persistence.is_none() {
+            println!("🔶 [DEBUG] No database; scrubs only run on demand");
+            return;
+        }
+        let service_manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(SCRUB_CHECK_INTERVAL).await;
+                let Some(service_manager) = service_manager.upgrade() else { break };
+                let Some((due, sample)) = service_manager.scrub_due().await else { continue };
+                if !due {
+                    continue;
+                }
+                match service_manager.scrub(sample).await {
+                    Ok(report) => println!(
+                        "🔍 [DEBUG] Scheduled scrub checked {} versions, {} with problems",
+                        report.results.len(), report.problems().count()
+                    ),
+                    Err(e) => println!("⚠️ [DEBUG] Scheduled scrub failed: {}", e),
+                }
+            }
+        });
+    }
+    
+    /// Whether the schedule says a scrub is due, and its sample size
+    async fn scrub_due(&self) -> Option<(bool, Option<usize>)> {
+        let persistence = self.persistence.as_ref()?;
+        let store = &persistence.config_store;
+        let interval_hours = store.get_number_config("storage.scrub_interval_hours").await.ok().flatten()?;
+        if interval_hours <= 0 {
+            return None;
         }
+        let sample = store.get_number_config("storage.scrub_sample_size").await.ok().flatten()
+            .filter(|n| *n > 0)
+            .map(|n| n as usize);
         
-        updates
+        let due = match persistence.scrub_results.get_latest_run().await {
+            Ok(Some(run)) => chrono::Utc::now() - run.finished_at >= chrono::Duration::hours(interval_hours),
+            Ok(None) => true,
+            Err(e) => {
+                println!("⚠️ [DEBUG] Failed to read the last scrub: {}", e);
+                false
+            }
+        };
+        Some((due, sample))
     }
     
     /// Get active operations - always works
@@ -692,4 +966,25 @@ impl ServiceManager {
         let active = self.active_operations.lock().await;
         active.clone()
     }
-}
\ No newline at end of file
+}
+/// Keep the `count` newest versions; zero or less keeps everything
+fn keep_last(count: i64) -> RetentionPolicy {
+    match u32::try_from(count) {
+        Ok(count) if count > 0 => RetentionPolicy::KeepLast { count },
+        _ => RetentionPolicy::KeepAll,
+    }
+}
+
+/// The policy a game's configuration sets, if any
+fn game_retention_policy(config: &GameConfig) -> Option<RetentionPolicy> {
+    match &config.retention_policy {
+        Some(json) => match serde_json::from_str(json) {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                println!("⚠️ [DEBUG] Ignoring invalid retention policy of game {}: {}", config.game_id, e);
+                None
+            }
+        },
+        None => config.max_versions.map(|count| keep_last(count as i64)),
+    }
+}
@@ -1,9 +1,21 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use anyhow::Result;
-use steam_cloud_sync_cloud::BackendType;
+use steam_cloud_sync_cloud::{BackendType, ChunkedBackend, CloudBackend, EncryptedBackend, IncrementalBackend, IndexedBackend, ReplicatedBackend, S3Config, S3Provider, SftpAuth, SftpConfig};
 use chrono;
 
+/// Storage layout for uploaded save versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VersionStorage {
+    /// One zip archive per version
+    #[default]
+    Archive,
+    /// A file manifest per version; only changed files are uploaded
+    IncrementalFiles,
+    /// Content-defined chunks shared between versions
+    Chunked,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     pub selected_backend: BackendType,
@@ -24,16 +36,75 @@ pub struct AppSettings {
     pub s3_secret_key: String,
     pub s3_bucket: String,
     pub s3_region: String,
+    #[serde(default)]
+    pub s3_provider: S3Provider,
+    /// Custom endpoint for S3-compatible services (empty for AWS)
+    #[serde(default)]
+    pub s3_endpoint_url: String,
+    #[serde(default)]
+    pub s3_force_path_style: bool,
+    
+    // Local folder / NAS settings
+    #[serde(default)]
+    pub local_folder_path: String,
+    
+    // WebDAV settings (Nextcloud, ownCloud, ...)
+    #[serde(default)]
+    pub webdav_url: String,
+    #[serde(default)]
+    pub webdav_username: String,
+    #[serde(default)]
+    pub webdav_password: String,
+    
+    // SFTP settings
+    #[serde(default)]
+    pub sftp_host: String,
+    #[serde(default = "default_sftp_port")]
+    pub sftp_port: u16,
+    #[serde(default)]
+    pub sftp_username: String,
+    /// Used when no private key is set
+    #[serde(default)]
+    pub sftp_password: String,
+    #[serde(default)]
+    pub sftp_private_key_path: String,
+    #[serde(default = "default_sftp_remote_root")]
+    pub sftp_remote_root: String,
     
     // Application settings
     pub auto_start: bool,
     pub rate_limit_enabled: bool,
+    /// Upload limit in MB/s while rate limiting is enabled
     pub rate_limit_value: f32,
+    /// Download limit in MB/s while rate limiting is enabled, 0 for unlimited
+    #[serde(default)]
+    pub rate_limit_download_value: f32,
+    /// How save versions are laid out in storage
+    #[serde(default)]
+    pub version_storage: VersionStorage,
+    /// Encrypt saves on this machine before they are uploaded
+    #[serde(default)]
+    pub encryption_enabled: bool,
+    /// Entered once per session and never written to disk, where it would sit next to
+    /// the keyring it unlocks
+    #[serde(skip)]
+    pub encryption_passphrase: String,
+    /// Backends every upload is mirrored to besides the selected one, using their own settings
+    #[serde(default)]
+    pub mirror_backends: Vec<BackendType>,
     
     // Download settings
     pub default_download_path: Option<String>,
 }
 
+fn default_sftp_port() -> u16 {
+    22
+}
+
+fn default_sftp_remote_root() -> String {
+    "steam-cloud-sync".to_string()
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         // Generate a default user ID based on current timestamp
@@ -52,15 +123,141 @@ impl Default for AppSettings {
             s3_secret_key: String::new(),
             s3_bucket: "steam-cloud-sync".to_string(),
             s3_region: "us-east-1".to_string(),
+            s3_provider: S3Provider::Aws,
+            s3_endpoint_url: String::new(),
+            s3_force_path_style: false,
+            local_folder_path: String::new(),
+            webdav_url: String::new(),
+            webdav_username: String::new(),
+            webdav_password: String::new(),
+            sftp_host: String::new(),
+            sftp_port: default_sftp_port(),
+            sftp_username: String::new(),
+            sftp_password: String::new(),
+            sftp_private_key_path: String::new(),
+            sftp_remote_root: default_sftp_remote_root(),
             auto_start: false,
             rate_limit_enabled: false,
             rate_limit_value: 10.0,
+            rate_limit_download_value: 0.0,
+            version_storage: VersionStorage::default(),
+            encryption_enabled: false,
+            encryption_passphrase: String::new(),
+            mirror_backends: Vec::new(),
             default_download_path: None,
         }
     }
 }
 
 impl AppSettings {
+    /// Create a cloud backend of the given type from the configured credentials
+    pub fn create_backend(&self, kind: BackendType) -> Box<dyn CloudBackend> {
+        self.apply_bandwidth_limits();
+        let mut backend = self.storage_backend(kind);
+        
+        // Mirrors hold the same objects as the selected backend, so they go right on the storage
+        let mirrors: Vec<(String, Box<dyn CloudBackend>)> = self.mirror_backends.iter()
+            .filter(|mirror| **mirror != kind && self.is_backend_configured(**mirror))
+            .map(|mirror| (format!("{:?}", mirror), self.storage_backend(*mirror)))
+            .collect();
+        if !mirrors.is_empty() {
+            backend = Box::new(ReplicatedBackend::new(format!("{:?}", kind), backend, mirrors));
+        }
+        
+        // Encryption sits directly on the storage so chunks and manifests are covered too
+        if self.encryption_enabled {
+            let keyring_path = Self::get_keyring_path().unwrap_or_else(|_| PathBuf::from("keyring.json"));
+            backend = Box::new(EncryptedBackend::new(backend, keyring_path, self.encryption_passphrase.clone()));
+        }
+        
+        let backend: Box<dyn CloudBackend> = match self.version_storage {
+            VersionStorage::Archive => backend,
+            VersionStorage::IncrementalFiles => Box::new(IncrementalBackend::new(backend)),
+            VersionStorage::Chunked => Box::new(ChunkedBackend::new(backend)),
+        };
+        
+        // The save index lists every version kind, so it goes on top
+        Box::new(IndexedBackend::new(backend))
+    }
+    
+    /// The plain storage backend of the given type, from its configured credentials
+    pub fn storage_backend(&self, kind: BackendType) -> Box<dyn CloudBackend> {
+        steam_cloud_sync_cloud::backend_with_settings(
+            kind,
+            Some((
+                self.tencent_secret_id.clone(),
+                self.tencent_secret_key.clone(),
+                self.tencent_bucket.clone(),
+                self.tencent_region.clone(),
+            )),
+            Some(S3Config {
+                bucket: self.s3_bucket.clone(),
+                prefix: "saves/".to_string(),
+                region: self.s3_region.clone(),
+                access_key: self.s3_access_key.clone(),
+                secret_key: self.s3_secret_key.clone(),
+                endpoint_url: (!self.s3_endpoint_url.is_empty()).then(|| self.s3_endpoint_url.clone()),
+                force_path_style: self.s3_force_path_style,
+            }),
+            (!self.local_folder_path.is_empty()).then(|| PathBuf::from(&self.local_folder_path)),
+            Some((
+                self.webdav_url.clone(),
+                self.webdav_username.clone(),
+                self.webdav_password.clone(),
+            )),
+            Some(self.sftp_config()),
+        )
+    }
+    
+    /// Switch to an S3-compatible provider, filling in its region, endpoint and addressing defaults
+    pub fn apply_s3_provider(&mut self, provider: S3Provider) {
+        self.s3_provider = provider;
+        if provider != S3Provider::Custom {
+            self.s3_region = provider.default_region().to_string();
+        }
+        self.s3_endpoint_url = provider.endpoint_url(&self.s3_region).unwrap_or_default();
+        self.s3_force_path_style = provider.force_path_style();
+    }
+    
+    /// SFTP connection settings; a private key takes precedence over the password,
+    /// and with neither set the running ssh-agent is used
+    pub fn sftp_config(&self) -> SftpConfig {
+        let auth = if !self.sftp_private_key_path.is_empty() {
+            SftpAuth::KeyFile {
+                private_key: PathBuf::from(&self.sftp_private_key_path),
+                passphrase: (!self.sftp_password.is_empty()).then(|| self.sftp_password.clone()),
+            }
+        } else if !self.sftp_password.is_empty() {
+            SftpAuth::Password(self.sftp_password.clone())
+        } else {
+            SftpAuth::Agent
+        };
+        
+        SftpConfig {
+            host: self.sftp_host.clone(),
+            port: self.sftp_port,
+            username: self.sftp_username.clone(),
+            auth,
+            remote_root: self.sftp_remote_root.clone(),
+        }
+    }
+    
+    /// Whether the credentials required by the selected backend are filled in
+    pub fn has_backend_config(&self) -> bool {
+        self.is_backend_configured(self.selected_backend)
+    }
+    
+    /// Whether the credentials required by a backend are filled in
+    pub fn is_backend_configured(&self, kind: BackendType) -> bool {
+        match kind {
+            BackendType::TencentCOS => !self.tencent_secret_id.is_empty() && !self.tencent_secret_key.is_empty(),
+            BackendType::S3 => !self.s3_access_key.is_empty() && !self.s3_secret_key.is_empty(),
+            BackendType::LocalFolder => !self.local_folder_path.is_empty(),
+            BackendType::WebDav => !self.webdav_url.is_empty(),
+            BackendType::Sftp => !self.sftp_host.is_empty() && !self.sftp_username.is_empty(),
+        }
+    }
+    
     pub fn get_config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
@@ -70,22 +267,70 @@ impl AppSettings {
         Ok(config_dir.join("settings.json"))
     }
     
+    /// The encryption keyring, next to the settings file. It never leaves this machine.
+    pub fn get_keyring_path() -> Result<PathBuf> {
+        Ok(Self::get_config_path()?.with_file_name("keyring.json"))
+    }
+    
     pub fn load() -> Result<AppSettings> {
         let config_path = Self::get_config_path()?;
         
         if config_path.exists() {
             let content = std::fs::read_to_string(&config_path)?;
             let settings: AppSettings = serde_json::from_str(&content)?;
+            // Older versions saved the passphrase; rewrite the file without it
+            if content.contains("\"encryption_passphrase\"") {
+                settings.save()?;
+            }
             Ok(settings)
         } else {
             Ok(AppSettings::default())
         }
     }
     
+    /// Apply the rate limit settings to all transfers, including ones in progress
+    pub fn apply_bandwidth_limits(&self) {
+        let to_bytes_per_sec = |mb_per_sec: f32| {
+            (self.rate_limit_enabled && mb_per_sec > 0.0).then(|| (mb_per_sec as f64 * 1024.0 * 1024.0) as u64)
+        };
+        steam_cloud_sync_cloud::set_bandwidth_limits(
+            to_bytes_per_sec(self.rate_limit_value),
+            to_bytes_per_sec(self.rate_limit_download_value),
+        );
+    }
+
     pub fn save(&self) -> Result<()> {
         let config_path = Self::get_config_path()?;
-        let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(&config_path, content)?;
+        std::fs::write(&config_path, self.to_json()?)?;
         Ok(())
     }
+    
+    /// The settings file content
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase_is_never_saved() {
+        let settings = AppSettings {
+            encryption_enabled: true,
+            encryption_passphrase: "correct horse battery staple".to_string(),
+            ..AppSettings::default()
+        };
+        let content = settings.to_json().unwrap();
+        assert!(!content.contains("correct horse battery staple"));
+        assert!(!content.contains("encryption_passphrase"));
+
+        // Files written by older versions still load, without the passphrase
+        let mut old: serde_json::Value = serde_json::from_str(&content).unwrap();
+        old["encryption_passphrase"] = "correct horse battery staple".into();
+        let loaded: AppSettings = serde_json::from_value(old).unwrap();
+        assert!(loaded.encryption_enabled);
+        assert!(loaded.encryption_passphrase.is_empty());
+    }
 }
\ No newline at end of file
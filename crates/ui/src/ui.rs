@@ -1,6 +1,9 @@
 use eframe::egui;
-use crate::{AppViewModel, LocalizationManager, SyncHistoryItem, GameWithSave, AppSettings};
-use steam_cloud_sync_cloud::BackendType;
+use crate::{AppViewModel, LocalizationManager, SyncHistoryItem, GameWithSave, AppSettings, VersionStorage};
+use steam_cloud_sync_cloud::{
+    BackendType, LegacySave, MigrationProgress, MigrationReport, ReplicaState, RetentionPlan, RetentionPolicy, RetentionRules,
+    S3Provider,
+};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::sync::mpsc;
@@ -21,6 +24,34 @@ pub enum ConnectionTestStatus {
     Failed(String),
 }
 
+#[derive(Clone, Debug)]
+pub enum MigrationStatus {
+    None,
+    Running(MigrationProgress),
+    Done(MigrationReport),
+    Failed(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LegacyRepairState {
+    pub busy: bool,
+    /// `None` until searched
+    pub saves: Option<Vec<LegacySave>>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RetentionState {
+    pub busy: bool,
+    /// Rules in effect; `None` until the service manager is ready
+    pub rules: Option<RetentionRules>,
+    /// Dry run of a game's policy, by game id
+    pub preview: Option<(String, RetentionPlan)>,
+    /// Versions deleted by the last prune
+    pub pruned: Option<usize>,
+    pub error: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct UndoNotification {
     pub game_id: String,
@@ -31,6 +62,7 @@ pub struct UndoNotification {
 #[derive(Clone, Debug)]
 pub enum UIMessage {
     UpdateDefaultDownloadPath(Option<String>),
+    UpdateLocalFolderPath(String),
 }
 
 pub struct SteamCloudSyncApp {
@@ -44,6 +76,19 @@ pub struct SteamCloudSyncApp {
     pub settings: AppSettings,
     pub connection_test_result: Option<Result<(), String>>,
     pub connection_test_status: Arc<Mutex<ConnectionTestStatus>>,
+    // Backend to copy saves from into the selected one
+    pub migration_source: Option<BackendType>,
+    pub migration_status: Arc<std::sync::Mutex<MigrationStatus>>,
+    // Saves stored under legacy names, and the game chosen for each (by key)
+    pub legacy_repair: Arc<std::sync::Mutex<LegacyRepairState>>,
+    pub legacy_choices: std::collections::HashMap<String, u32>,
+    // Version retention, and the policies being edited until they are saved
+    pub retention: Arc<std::sync::Mutex<RetentionState>>,
+    pub retention_default: Option<RetentionPolicy>,
+    pub retention_game: Option<String>,
+    pub retention_game_policy: Option<RetentionPolicy>,
+    // Passphrase being entered to replace the current encryption passphrase
+    pub new_encryption_passphrase: String,
     // Cloud saves page state
     pub cloud_saves_page: Option<crate::pages::cloud_saves::CloudSavesPage>,
     // History page state  
@@ -101,6 +146,15 @@ impl Default for SteamCloudSyncApp {
             settings,
             connection_test_result: None,
             connection_test_status: Arc::new(Mutex::new(ConnectionTestStatus::None)),
+            migration_source: None,
+            migration_status: Arc::new(std::sync::Mutex::new(MigrationStatus::None)),
+            legacy_repair: Arc::new(std::sync::Mutex::new(LegacyRepairState::default())),
+            legacy_choices: std::collections::HashMap::new(),
+            retention: Arc::new(std::sync::Mutex::new(RetentionState::default())),
+            retention_default: None,
+            retention_game: None,
+            retention_game_policy: None,
+            new_encryption_passphrase: String::new(),
             cloud_saves_page: None,
             history_page: None,
             // Initialize grouping state
@@ -139,6 +193,14 @@ impl eframe::App for SteamCloudSyncApp {
                             }
                         }
                     }
+                    UIMessage::UpdateLocalFolderPath(path) => {
+                        self.settings.local_folder_path = path;
+                        if self.settings.auto_save_on_change {
+                            if let Err(e) = self.settings.save() {
+                                eprintln!("Failed to save settings: {}", e);
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -254,13 +316,19 @@ impl SteamCloudSyncApp {
         // Check if cloud settings are configured
         let has_tencent_config = !settings.tencent_secret_id.is_empty() && !settings.tencent_secret_key.is_empty();
         let has_s3_config = !settings.s3_access_key.is_empty() && !settings.s3_secret_key.is_empty();
+        let has_local_config = !settings.local_folder_path.is_empty();
+        let has_webdav_config = !settings.webdav_url.is_empty();
+        let has_sftp_config = !settings.sftp_host.is_empty() && !settings.sftp_username.is_empty();
         
         println!("🔧 [DEBUG] Cloud configuration check:");
         println!("   - Tencent COS configured: {}", has_tencent_config);
         println!("   - S3 configured: {}", has_s3_config);
+        println!("   - Local folder configured: {}", has_local_config);
+        println!("   - WebDAV configured: {}", has_webdav_config);
+        println!("   - SFTP configured: {}", has_sftp_config);
         println!("   - Selected backend: {:?}", settings.selected_backend);
         
-        if !has_tencent_config && !has_s3_config {
+        if !has_tencent_config && !has_s3_config && !has_local_config && !has_webdav_config && !has_sftp_config {
             println!("⚠️ [DEBUG] No cloud storage credentials configured - service manager may not work properly");
         }
         
@@ -451,14 +519,7 @@ impl SteamCloudSyncApp {
                         ui.label("Loading storage info...");
                     } else {
                         // Check if cloud storage is configured
-                        let has_cloud_config = match self.settings.selected_backend {
-                            steam_cloud_sync_cloud::BackendType::TencentCOS => {
-                                !self.settings.tencent_secret_id.is_empty() && !self.settings.tencent_secret_key.is_empty()
-                            },
-                            steam_cloud_sync_cloud::BackendType::S3 => {
-                                !self.settings.s3_access_key.is_empty() && !self.settings.s3_secret_key.is_empty()
-                            },
-                        };
+                        let has_cloud_config = self.settings.has_backend_config();
                         
                         if !has_cloud_config {
                             ui.label("⚙ Configure cloud storage in Settings");
@@ -483,6 +544,8 @@ impl SteamCloudSyncApp {
                 });
             });
             
+            self.show_running_operations(ui);
+            
             ui.separator();
             
             // Group games by sync state
@@ -562,6 +625,480 @@ impl SteamCloudSyncApp {
         });
     }
 
+    /// Running cloud operations, each with a cancel button, plus one to cancel them all
+    fn show_running_operations(&mut self, ui: &mut egui::Ui) {
+        let mut running = self.view_model.running_operations();
+        if running.is_empty() {
+            return;
+        }
+        running.sort_by(|(_, a), (_, b)| a.game_id.cmp(&b.game_id));
+        
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.strong(format!("{} ({})", self.localization.get_string("RunningOperations"), running.len()));
+            if ui.button(format!("⏹ {}", self.localization.get_string("CancelAll"))).clicked() {
+                let view_model = self.view_model.clone();
+                tokio::spawn(async move {
+                    view_model.cancel_all_operations().await;
+                });
+            }
+        });
+        for (operation_id, operation) in running {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("{:?} · {}", operation.operation_type, operation.game_id));
+                if ui.small_button(format!("✖ {}", self.localization.get_string("Cancel"))).clicked() {
+                    let view_model = self.view_model.clone();
+                    tokio::spawn(async move {
+                        view_model.cancel_operation(operation_id).await;
+                    });
+                }
+            });
+            if let Some(progress) = &operation.progress {
+                let snapshot = progress.snapshot();
+                let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+                let mut text = match snapshot.total {
+                    0 => format!("{:?} · {:.1} MB", snapshot.stage, mb(snapshot.bytes)),
+                    total => format!("{:?} · {:.1} / {:.1} MB", snapshot.stage, mb(snapshot.bytes), mb(total)),
+                };
+                if snapshot.bytes_per_sec > 0 {
+                    text.push_str(&format!(" · {:.2} MB/s", mb(snapshot.bytes_per_sec)));
+                }
+                if let Some(eta) = snapshot.eta {
+                    text.push_str(&format!(" · {}s", eta.as_secs()));
+                }
+                let fraction = match snapshot.total {
+                    0 => 0.0,
+                    total => (snapshot.bytes as f32 / total as f32).min(1.0),
+                };
+                ui.add(egui::ProgressBar::new(fraction).text(text).animate(snapshot.total == 0));
+            }
+        }
+        // Keep the list current while operations finish in the background
+        ui.ctx().request_repaint_after(std::time::Duration::from_millis(500));
+    }
+    
+    /// Backends to mirror uploads to, and how far each mirror is behind
+    fn show_mirror_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(self.localization.get_string("MirrorBackends"))
+            .on_hover_text(self.localization.get_string("MirrorBackendsHint"));
+        ui.horizontal_wrapped(|ui| {
+            for (kind, name) in [
+                (BackendType::TencentCOS, "Tencent Cloud COS"),
+                (BackendType::S3, "S3"),
+                (BackendType::LocalFolder, "Local folder / NAS"),
+                (BackendType::WebDav, "WebDAV"),
+                (BackendType::Sftp, "SFTP"),
+            ] {
+                if kind == self.settings.selected_backend {
+                    continue;
+                }
+                let mut mirrored = self.settings.mirror_backends.contains(&kind);
+                let configured = self.settings.is_backend_configured(kind);
+                let response = ui.add_enabled(configured, egui::Checkbox::new(&mut mirrored, name));
+                if !configured {
+                    response.on_disabled_hover_text(self.localization.get_string("MirrorNotConfigured"));
+                } else if response.changed() {
+                    self.settings.mirror_backends.retain(|mirror| *mirror != kind);
+                    if mirrored {
+                        self.settings.mirror_backends.push(kind);
+                    }
+                }
+            }
+        });
+        
+        let status = self.view_model.mirror_status();
+        if status.is_empty() {
+            return;
+        }
+        for replica in status.iter().filter(|replica| !replica.primary) {
+            ui.horizontal(|ui| {
+                let (color, state) = match replica.state {
+                    ReplicaState::InSync => (egui::Color32::GREEN, self.localization.get_string("MirrorInSync")),
+                    ReplicaState::OutOfDate => (egui::Color32::YELLOW, format!(
+                        "{} ({})", self.localization.get_string("MirrorOutOfDate"), replica.pending_objects
+                    )),
+                    ReplicaState::Unknown => (egui::Color32::GRAY, self.localization.get_string("MirrorChecking")),
+                };
+                ui.label(&replica.name);
+                let label = ui.colored_label(color, state);
+                if let Some(error) = &replica.last_error {
+                    label.on_hover_text(error);
+                }
+                if let Some(synced) = replica.last_synced {
+                    ui.small(synced.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+                }
+            });
+        }
+        if ui.button(self.localization.get_string("MirrorCatchUp")).clicked() {
+            let view_model = self.view_model.clone();
+            tokio::spawn(async move {
+                if let Err(e) = view_model.catch_up_mirrors().await {
+                    eprintln!("Failed to catch up mirrors: {}", e);
+                }
+            });
+        }
+    }
+    
+    /// Copy all save versions from another configured backend into the selected one
+    fn show_migration(&mut self, ui: &mut egui::Ui) {
+        let status = self.migration_status.lock().unwrap().clone();
+        let running = matches!(status, MigrationStatus::Running(_));
+        let sources: Vec<BackendType> = [
+            BackendType::TencentCOS, BackendType::S3, BackendType::LocalFolder, BackendType::WebDav, BackendType::Sftp,
+        ].into_iter()
+            .filter(|kind| *kind != self.settings.selected_backend && self.settings.is_backend_configured(*kind))
+            .collect();
+        if self.migration_source.is_some_and(|source| !sources.contains(&source)) {
+            self.migration_source = None;
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label(self.localization.get_string("MigrateFrom"))
+                .on_hover_text(self.localization.get_string("MigrateHint"));
+            egui::ComboBox::from_id_source("migration_source")
+                .selected_text(self.migration_source.map(|kind| format!("{:?}", kind)).unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for kind in &sources {
+                        ui.selectable_value(&mut self.migration_source, Some(*kind), format!("{:?}", kind));
+                    }
+                });
+            
+            let can_start = !running && self.migration_source.is_some();
+            if ui.add_enabled(can_start, egui::Button::new(self.localization.get_string("MigrateStart"))).clicked() {
+                if let Some(from) = self.migration_source {
+                    let view_model = self.view_model.clone();
+                    let settings = self.settings.clone();
+                    let to = settings.selected_backend;
+                    let migration_status = self.migration_status.clone();
+                    *migration_status.lock().unwrap() = MigrationStatus::Running(MigrationProgress::default());
+                    tokio::spawn(async move {
+                        let result = view_model.migrate_storage(&settings, from, to, |progress| {
+                            *migration_status.lock().unwrap() = MigrationStatus::Running(progress.clone());
+                        }).await;
+                        *migration_status.lock().unwrap() = match result {
+                            Ok(report) => MigrationStatus::Done(report),
+                            Err(e) => MigrationStatus::Failed(format!("{:#}", e)),
+                        };
+                    });
+                }
+            }
+        });
+        
+        match status {
+            MigrationStatus::None => {}
+            MigrationStatus::Running(progress) => {
+                let fraction = match progress.objects_total {
+                    0 => 0.0,
+                    total => progress.objects_done as f32 / total as f32,
+                };
+                ui.add(egui::ProgressBar::new(fraction).text(format!(
+                    "{} / {} · {:.1} / {:.1} MB",
+                    progress.objects_done, progress.objects_total,
+                    progress.bytes_done as f64 / (1024.0 * 1024.0), progress.bytes_total as f64 / (1024.0 * 1024.0)
+                )));
+                ui.ctx().request_repaint_after(std::time::Duration::from_millis(250));
+            }
+            MigrationStatus::Done(report) => {
+                ui.colored_label(egui::Color32::GREEN, format!(
+                    "✓ {}: {} ({:.1} MB), {} {}",
+                    self.localization.get_string("MigrateDone"), report.copied,
+                    report.bytes_copied as f64 / (1024.0 * 1024.0),
+                    report.skipped, self.localization.get_string("MigrateSkipped")
+                ));
+            }
+            MigrationStatus::Failed(error) => {
+                ui.colored_label(egui::Color32::RED, format!("✗ {}", self.localization.get_string("MigrateFailed")))
+                    .on_hover_text(error);
+            }
+        }
+    }
+    
+    /// Find saves stored under legacy names and move each to the game the user confirms
+    fn show_legacy_repair(&mut self, ui: &mut egui::Ui) {
+        let state = self.legacy_repair.lock().unwrap().clone();
+        
+        ui.horizontal(|ui| {
+            ui.label(self.localization.get_string("RepairLegacy"))
+                .on_hover_text(self.localization.get_string("RepairLegacyHint"));
+            if ui.add_enabled(!state.busy, egui::Button::new(self.localization.get_string("RepairFind"))).clicked() {
+                let view_model = self.view_model.clone();
+                let settings = self.settings.clone();
+                let legacy_repair = self.legacy_repair.clone();
+                legacy_repair.lock().unwrap().busy = true;
+                tokio::spawn(async move {
+                    let result = view_model.find_legacy_saves(&settings).await;
+                    let mut state = legacy_repair.lock().unwrap();
+                    state.busy = false;
+                    match result {
+                        Ok(saves) => {
+                            state.saves = Some(saves);
+                            state.error = None;
+                        }
+                        Err(e) => state.error = Some(format!("{:#}", e)),
+                    }
+                });
+            }
+            if state.busy {
+                ui.spinner();
+                ui.ctx().request_repaint_after(std::time::Duration::from_millis(250));
+            }
+        });
+        
+        if let Some(error) = &state.error {
+            ui.colored_label(egui::Color32::RED, format!("✗ {}", self.localization.get_string("RepairFailed")))
+                .on_hover_text(error);
+        }
+        let Some(saves) = &state.saves else {
+            return;
+        };
+        if saves.is_empty() {
+            ui.label(self.localization.get_string("RepairNone"));
+            return;
+        }
+        
+        let name_match = self.localization.get_string("RepairNameMatch");
+        let files_match = self.localization.get_string("RepairFilesMatch");
+        let no_match = self.localization.get_string("RepairNoMatch");
+        let confirm = self.localization.get_string("RepairConfirm");
+        // Saves nothing matched can still be assigned to any local game
+        let local_games: Vec<(u32, String, String)> = self.games_cache.lock().unwrap().iter()
+            .filter_map(|game| game.save_info.as_ref())
+            .map(|save| (save.app_id, save.name.clone(), format!("{} ({})", save.name, save.app_id)))
+            .collect();
+        
+        for save in saves {
+            let options: Vec<(u32, String, String)> = match save.candidates.is_empty() {
+                true => local_games.clone(),
+                false => save.candidates.iter().map(|candidate| {
+                    let mut text = format!("{} ({})", candidate.name, candidate.app_id);
+                    if candidate.name_matches {
+                        text.push_str(&format!(" · {}", name_match));
+                    }
+                    if candidate.matching_files > 0 {
+                        text.push_str(&format!(" · {}/{} {}", candidate.matching_files, candidate.archive_files, files_match));
+                    }
+                    (candidate.app_id, candidate.name.clone(), text)
+                }).collect(),
+            };
+            
+            ui.horizontal(|ui| {
+                let file_name = save.key.rsplit('/').next().unwrap_or(&save.key);
+                ui.label(file_name).on_hover_text(save.key.as_str());
+                ui.small(format!("{:.1} MB", save.size_bytes as f64 / (1024.0 * 1024.0)));
+                if save.candidates.is_empty() {
+                    ui.colored_label(egui::Color32::GRAY, no_match.as_str());
+                }
+                let Some(first) = options.first() else {
+                    return;
+                };
+                
+                let chosen = self.legacy_choices.entry(save.key.clone()).or_insert(first.0);
+                egui::ComboBox::from_id_source(("legacy_repair", &save.key))
+                    .selected_text(options.iter().find(|option| option.0 == *chosen).map(|option| option.2.clone()).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for (app_id, _, text) in &options {
+                            ui.selectable_value(chosen, *app_id, text.as_str());
+                        }
+                    });
+                let chosen = *chosen;
+                
+                if ui.add_enabled(!state.busy, egui::Button::new(confirm.as_str())).clicked() {
+                    if let Some((app_id, game_name, _)) = options.iter().find(|option| option.0 == chosen).cloned() {
+                        let view_model = self.view_model.clone();
+                        let settings = self.settings.clone();
+                        let legacy_repair = self.legacy_repair.clone();
+                        let save = save.clone();
+                        legacy_repair.lock().unwrap().busy = true;
+                        tokio::spawn(async move {
+                            let result = view_model.repair_legacy_save(&settings, &save, app_id, &game_name).await;
+                            let mut state = legacy_repair.lock().unwrap();
+                            state.busy = false;
+                            match result {
+                                Ok(_) => {
+                                    if let Some(saves) = &mut state.saves {
+                                        saves.retain(|legacy| legacy.key != save.key);
+                                    }
+                                    state.error = None;
+                                }
+                                Err(e) => state.error = Some(format!("{:#}", e)),
+                            }
+                        });
+                    }
+                }
+            });
+        }
+    }
+    
+    /// Which versions are kept after uploads, by default and per game, with a dry run
+    /// before pruning by hand
+    fn show_retention(&mut self, ui: &mut egui::Ui) {
+        let state = self.retention.lock().unwrap().clone();
+        let Some(rules) = state.rules.clone() else {
+            if !state.busy {
+                let view_model = self.view_model.clone();
+                let retention = self.retention.clone();
+                retention.lock().unwrap().busy = true;
+                tokio::spawn(async move {
+                    let rules = view_model.retention_rules().await;
+                    let mut state = retention.lock().unwrap();
+                    state.busy = false;
+                    state.rules = rules;
+                });
+            }
+            ui.ctx().request_repaint_after(std::time::Duration::from_secs(1));
+            return;
+        };
+        let save_text = self.localization.get_string("RetentionSave");
+        
+        ui.horizontal(|ui| {
+            ui.label(self.localization.get_string("RetentionDefault"))
+                .on_hover_text(self.localization.get_string("RetentionHint"));
+            let policy = self.retention_default.get_or_insert(rules.default);
+            retention_policy_editor(ui, "retention_default", policy, &self.localization);
+            let policy = *policy;
+            if ui.add_enabled(!state.busy && policy != rules.default, egui::Button::new(save_text.as_str())).clicked() {
+                self.save_retention(None, Some(policy));
+            }
+        });
+        
+        let games: Vec<(String, String)> = self.games_cache.lock().unwrap().iter()
+            .filter(|game| game.save_info.is_some() || !game.cloud_saves.is_empty())
+            .map(|game| (game.game.id.clone(), game.game.name.clone()))
+            .collect();
+        ui.horizontal(|ui| {
+            ui.label(self.localization.get_string("RetentionGame"));
+            let previous = self.retention_game.clone();
+            let selected_name = games.iter()
+                .find(|(id, _)| Some(id) == self.retention_game.as_ref())
+                .map(|(_, name)| name.clone())
+                .unwrap_or_default();
+            egui::ComboBox::from_id_source("retention_game")
+                .selected_text(selected_name)
+                .show_ui(ui, |ui| {
+                    for (id, name) in &games {
+                        ui.selectable_value(&mut self.retention_game, Some(id.clone()), name.as_str());
+                    }
+                });
+            if self.retention_game != previous {
+                self.retention_game_policy = self.retention_game.as_ref().and_then(|id| rules.per_game.get(id).copied());
+                let mut state = self.retention.lock().unwrap();
+                state.preview = None;
+                state.pruned = None;
+            }
+        });
+        let Some(game_id) = self.retention_game.clone() else {
+            return;
+        };
+        
+        ui.horizontal(|ui| {
+            let mut own = self.retention_game_policy.is_some();
+            if ui.checkbox(&mut own, self.localization.get_string("RetentionOwnPolicy")).changed() {
+                self.retention_game_policy = own.then(|| rules.policy_for(&game_id));
+            }
+            if let Some(policy) = &mut self.retention_game_policy {
+                retention_policy_editor(ui, ("retention_game", &game_id), policy, &self.localization);
+            }
+            let changed = self.retention_game_policy != rules.per_game.get(&game_id).copied();
+            if ui.add_enabled(!state.busy && changed, egui::Button::new(save_text.as_str())).clicked() {
+                self.save_retention(Some(game_id.clone()), self.retention_game_policy);
+            }
+        });
+        
+        let preview = state.preview.as_ref().filter(|(id, _)| *id == game_id).map(|(_, plan)| plan);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!state.busy, egui::Button::new(self.localization.get_string("RetentionPreview"))).clicked() {
+                let view_model = self.view_model.clone();
+                let retention = self.retention.clone();
+                let game_id = game_id.clone();
+                retention.lock().unwrap().busy = true;
+                tokio::spawn(async move {
+                    let result = view_model.preview_retention(&game_id).await;
+                    let mut state = retention.lock().unwrap();
+                    state.busy = false;
+                    state.pruned = None;
+                    match result {
+                        Ok(plan) => {
+                            state.preview = Some((game_id, plan));
+                            state.error = None;
+                        }
+                        Err(e) => state.error = Some(format!("{:#}", e)),
+                    }
+                });
+            }
+            // Only what the preview showed gets pruned
+            let can_prune = !state.busy && preview.is_some_and(|plan| !plan.prune.is_empty());
+            if ui.add_enabled(can_prune, egui::Button::new(self.localization.get_string("RetentionPruneNow"))).clicked() {
+                let view_model = self.view_model.clone();
+                let settings = self.settings.clone();
+                let retention = self.retention.clone();
+                let game_id = game_id.clone();
+                retention.lock().unwrap().busy = true;
+                tokio::spawn(async move {
+                    let result = view_model.apply_retention(&settings, &game_id).await;
+                    let mut state = retention.lock().unwrap();
+                    state.busy = false;
+                    state.preview = None;
+                    match result {
+                        Ok(plan) => {
+                            state.pruned = Some(plan.prune.len());
+                            state.error = None;
+                        }
+                        Err(e) => state.error = Some(format!("{:#}", e)),
+                    }
+                });
+            }
+            if state.busy {
+                ui.spinner();
+                ui.ctx().request_repaint_after(std::time::Duration::from_millis(250));
+            }
+        });
+        
+        if let Some(plan) = preview {
+            if plan.prune.is_empty() {
+                ui.label(self.localization.get_string("RetentionNothing"));
+            } else {
+                ui.label(format!(
+                    "{} {} ({:.1} MB), {} {}",
+                    plan.prune.len(), self.localization.get_string("RetentionWouldPrune"),
+                    plan.pruned_bytes() as f64 / (1024.0 * 1024.0),
+                    plan.keep.len(), self.localization.get_string("RetentionKept")
+                ));
+                for save in &plan.prune {
+                    ui.small(format!("🗑 {}", save.timestamp)).on_hover_text(save.file_id.as_str());
+                }
+            }
+        }
+        if let Some(pruned) = state.pruned {
+            ui.colored_label(egui::Color32::GREEN, format!("✓ {} {}", pruned, self.localization.get_string("RetentionPruned")));
+        }
+        if let Some(error) = &state.error {
+            ui.colored_label(egui::Color32::RED, format!("✗ {}", self.localization.get_string("RetentionFailed")))
+                .on_hover_text(error);
+        }
+    }
+    
+    /// Save the default policy (`game_id` is `None`) or a game's own policy, where `None`
+    /// makes the game follow the default again. The rules are reloaded afterwards.
+    fn save_retention(&self, game_id: Option<String>, policy: Option<RetentionPolicy>) {
+        let view_model = self.view_model.clone();
+        let retention = self.retention.clone();
+        retention.lock().unwrap().busy = true;
+        tokio::spawn(async move {
+            let result = match (&game_id, policy) {
+                (Some(game_id), policy) => view_model.set_game_retention(game_id, policy).await,
+                (None, Some(policy)) => view_model.set_default_retention(policy).await,
+                (None, None) => Ok(()),
+            };
+            let rules = view_model.retention_rules().await;
+            let mut state = retention.lock().unwrap();
+            state.busy = false;
+            state.preview = None;
+            state.rules = rules;
+            state.error = result.err().map(|e| format!("{:#}", e));
+        });
+    }
+    
     fn show_cloud_saves_page(&mut self, ui: &mut egui::Ui) {
         // Initialize cloud saves page if needed
         if self.cloud_saves_page.is_none() {
@@ -660,7 +1197,10 @@ impl SteamCloudSyncApp {
                     ui.strong(&self.localization.get_string("CloudBackend"));
                     
                     ui.radio_value(&mut self.settings.selected_backend, BackendType::TencentCOS, "Tencent Cloud COS");
-                    ui.radio_value(&mut self.settings.selected_backend, BackendType::S3, "Amazon S3");
+                    ui.radio_value(&mut self.settings.selected_backend, BackendType::S3, "Amazon S3 / S3-compatible");
+                    ui.radio_value(&mut self.settings.selected_backend, BackendType::LocalFolder, &self.localization.get_string("LocalFolderBackend"));
+                    ui.radio_value(&mut self.settings.selected_backend, BackendType::WebDav, "WebDAV (Nextcloud / ownCloud)");
+                    ui.radio_value(&mut self.settings.selected_backend, BackendType::Sftp, "SFTP (SSH)");
                     
                     match self.settings.selected_backend {
                         BackendType::TencentCOS => {
@@ -675,15 +1215,81 @@ impl SteamCloudSyncApp {
                                 .on_hover_text("COS Region (e.g., ap-beijing)");
                         }
                         BackendType::S3 => {
-                            ui.label("AWS Credentials:");
+                            let mut provider = self.settings.s3_provider;
+                            egui::ComboBox::from_label("Provider")
+                                .selected_text(provider.label())
+                                .show_ui(ui, |ui| {
+                                    for option in S3Provider::ALL {
+                                        ui.selectable_value(&mut provider, option, option.label());
+                                    }
+                                });
+                            if provider != self.settings.s3_provider {
+                                self.settings.apply_s3_provider(provider);
+                            }
+                            
+                            ui.label("S3 Credentials:");
                             ui.text_edit_singleline(&mut self.settings.s3_access_key)
-                                .on_hover_text("AWS Access Key ID");
-                            ui.text_edit_singleline(&mut self.settings.s3_secret_key)
-                                .on_hover_text("AWS Secret Access Key");
+                                .on_hover_text("Access Key ID");
+                            ui.add(egui::TextEdit::singleline(&mut self.settings.s3_secret_key).password(true))
+                                .on_hover_text("Secret Access Key");
                             ui.text_edit_singleline(&mut self.settings.s3_bucket)
                                 .on_hover_text("S3 Bucket Name");
                             ui.text_edit_singleline(&mut self.settings.s3_region)
-                                .on_hover_text("AWS Region");
+                                .on_hover_text("Region (e.g., us-east-1, auto for Cloudflare R2)");
+                            
+                            if self.settings.s3_provider != S3Provider::Aws {
+                                ui.text_edit_singleline(&mut self.settings.s3_endpoint_url)
+                                    .on_hover_text("Endpoint URL (e.g., http://nas.local:9000)");
+                                ui.checkbox(&mut self.settings.s3_force_path_style, "Path-style addressing")
+                                    .on_hover_text("Required by MinIO and most self-hosted servers");
+                            }
+                        }
+                        BackendType::LocalFolder => {
+                            ui.label(&self.localization.get_string("LocalFolderPath"));
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut self.settings.local_folder_path)
+                                    .on_hover_text("Folder on a NAS share or external drive, e.g. \\\\nas\\backups or E:\\GameSaves");
+                                
+                                if ui.button("📁 Browse...").clicked() {
+                                    let tx = self.ui_message_tx.clone();
+                                    tokio::spawn(async move {
+                                        if let Some(folder) = rfd::AsyncFileDialog::new()
+                                            .set_title("选择备份文件夹 / Select Backup Folder")
+                                            .pick_folder()
+                                            .await {
+                                            let path = folder.path().to_string_lossy().to_string();
+                                            println!("Selected local backup folder: {}", path);
+                                            let _ = tx.send(UIMessage::UpdateLocalFolderPath(path));
+                                        }
+                                    });
+                                }
+                            });
+                        }
+                        BackendType::WebDav => {
+                            ui.label("WebDAV Server:");
+                            ui.text_edit_singleline(&mut self.settings.webdav_url)
+                                .on_hover_text("Folder URL, e.g. https://cloud.example.com/remote.php/dav/files/<user>/GameSyncer");
+                            ui.text_edit_singleline(&mut self.settings.webdav_username)
+                                .on_hover_text("WebDAV Username");
+                            ui.add(egui::TextEdit::singleline(&mut self.settings.webdav_password).password(true))
+                                .on_hover_text("WebDAV Password (use an app password for Nextcloud)");
+                        }
+                        BackendType::Sftp => {
+                            ui.label("SFTP Server:");
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut self.settings.sftp_host)
+                                    .on_hover_text("SSH Host");
+                                ui.add(egui::DragValue::new(&mut self.settings.sftp_port).clamp_range(1..=65535))
+                                    .on_hover_text("SSH Port");
+                            });
+                            ui.text_edit_singleline(&mut self.settings.sftp_username)
+                                .on_hover_text("SSH Username");
+                            ui.text_edit_singleline(&mut self.settings.sftp_private_key_path)
+                                .on_hover_text("Private key file (e.g. ~/.ssh/id_ed25519). Leave empty to use a password or ssh-agent");
+                            ui.add(egui::TextEdit::singleline(&mut self.settings.sftp_password).password(true))
+                                .on_hover_text("Password, or the key passphrase when a private key is set");
+                            ui.text_edit_singleline(&mut self.settings.sftp_remote_root)
+                                .on_hover_text("Remote folder for backups (relative paths start in the home directory)");
                         }
                     }
                     
@@ -698,6 +1304,12 @@ impl SteamCloudSyncApp {
                             .on_hover_text("Automatically save settings when changed");
                     });
                     
+                    ui.separator();
+                    self.show_mirror_settings(ui);
+                    ui.separator();
+                    self.show_migration(ui);
+                    ui.separator();
+                    self.show_legacy_repair(ui);
                     ui.separator();
                     
                     // Connection test section
@@ -774,11 +1386,63 @@ impl SteamCloudSyncApp {
                     
                     if self.settings.rate_limit_enabled {
                         ui.horizontal(|ui| {
-                            ui.label("Upload speed (MB/s):");
+                            ui.label(self.localization.get_string("UploadSpeedLimit"));
                             ui.add(egui::Slider::new(&mut self.settings.rate_limit_value, 1.0..=100.0));
                         });
+                        ui.horizontal(|ui| {
+                            ui.label(self.localization.get_string("DownloadSpeedLimit"));
+                            ui.add(egui::Slider::new(&mut self.settings.rate_limit_download_value, 0.0..=100.0))
+                                .on_hover_text(self.localization.get_string("DownloadSpeedLimitHint"));
+                        });
+                    }
+                    // Takes effect immediately, also for transfers already running
+                    self.settings.apply_bandwidth_limits();
+                    
+                    ui.horizontal(|ui| {
+                        ui.label(self.localization.get_string("VersionStorage"));
+                        ui.radio_value(&mut self.settings.version_storage, VersionStorage::Archive, self.localization.get_string("VersionStorageArchive"));
+                        ui.radio_value(&mut self.settings.version_storage, VersionStorage::IncrementalFiles, self.localization.get_string("VersionStorageIncremental"))
+                            .on_hover_text(self.localization.get_string("VersionStorageIncrementalHint"));
+                        ui.radio_value(&mut self.settings.version_storage, VersionStorage::Chunked, self.localization.get_string("VersionStorageChunked"))
+                            .on_hover_text(self.localization.get_string("VersionStorageChunkedHint"));
+                    });
+                    
+                    ui.checkbox(&mut self.settings.encryption_enabled, &self.localization.get_string("EncryptSaves"))
+                        .on_hover_text(self.localization.get_string("EncryptSavesHint"));
+                    if self.settings.encryption_enabled {
+                        ui.horizontal(|ui| {
+                            ui.label(self.localization.get_string("EncryptionPassphrase"));
+                            ui.add(egui::TextEdit::singleline(&mut self.settings.encryption_passphrase).password(true))
+                                .on_hover_text(self.localization.get_string("EncryptionPassphraseHint"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label(self.localization.get_string("NewEncryptionPassphrase"));
+                            ui.add(egui::TextEdit::singleline(&mut self.new_encryption_passphrase).password(true));
+                            if ui.button(&self.localization.get_string("ChangePassphrase")).clicked() {
+                                // Re-wraps the data key only; existing cloud saves stay readable
+                                let result = AppSettings::get_keyring_path().and_then(|path| {
+                                    steam_cloud_sync_cloud::encryption::rotate_passphrase(
+                                        &path,
+                                        &self.settings.encryption_passphrase,
+                                        &self.new_encryption_passphrase,
+                                    )
+                                });
+                                match result {
+                                    Ok(()) => {
+                                        self.settings.encryption_passphrase = std::mem::take(&mut self.new_encryption_passphrase);
+                                        if let Err(e) = self.settings.save() {
+                                            eprintln!("Failed to save settings: {}", e);
+                                        }
+                                        self.status_text = "Encryption passphrase changed".to_string();
+                                    }
+                                    Err(e) => self.status_text = format!("Failed to change passphrase: {}", e),
+                                }
+                            }
+                        });
                     }
                     
+                    ui.separator();
+                    self.show_retention(ui);
                     ui.separator();
                     
                     // Download settings
@@ -1090,4 +1754,39 @@ impl SteamCloudSyncApp {
             }
         });
     }
-}
\ No newline at end of file
+}
+/// Kind and counts of a retention policy
+fn retention_policy_editor(ui: &mut egui::Ui, id: impl std::hash::Hash, policy: &mut RetentionPolicy, localization: &LocalizationManager) {
+    let kinds = [
+        (RetentionPolicy::KeepAll, "RetentionKeepAll"),
+        (RetentionPolicy::KeepLast { count: 5 }, "RetentionKeepLast"),
+        (RetentionPolicy::Gfs { last: 3, daily: 7, weekly: 4, monthly: 6 }, "RetentionGfs"),
+    ];
+    let current = kinds.iter()
+        .position(|(kind, _)| std::mem::discriminant(kind) == std::mem::discriminant(&*policy))
+        .unwrap_or(0);
+    let mut selected = current;
+    egui::ComboBox::from_id_source(id)
+        .selected_text(localization.get_string(kinds[current].1))
+        .show_ui(ui, |ui| {
+            for (i, (_, key)) in kinds.iter().enumerate() {
+                ui.selectable_value(&mut selected, i, localization.get_string(key));
+            }
+        });
+    if selected != current {
+        *policy = kinds[selected].0;
+    }
+    
+    match policy {
+        RetentionPolicy::KeepAll => {}
+        RetentionPolicy::KeepLast { count } => {
+            ui.add(egui::DragValue::new(count).clamp_range(1..=1000));
+        }
+        RetentionPolicy::Gfs { last, daily, weekly, monthly } => {
+            for (value, key) in [(last, "RetentionLast"), (daily, "RetentionDaily"), (weekly, "RetentionWeekly"), (monthly, "RetentionMonthly")] {
+                ui.label(localization.get_string(key));
+                ui.add(egui::DragValue::new(value).clamp_range(0..=1000));
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use steam_cloud_sync_cloud::{UploadSession, UploadSessionStore, UploadedPart};
+use steam_cloud_sync_persistence::{MultipartUpload, MultipartUploadPart, MultipartUploadStore};
+
+/// Keeps the cloud backends' multipart upload sessions in SQLite, so an
+/// interrupted upload resumes after the app restarts
+pub struct PersistentUploadSessionStore {
+    store: MultipartUploadStore,
+}
+
+impl PersistentUploadSessionStore {
+    pub fn new(store: MultipartUploadStore) -> Self {
+        Self { store }
+    }
+
+    fn to_session(upload: MultipartUpload, parts: Vec<MultipartUploadPart>) -> UploadSession {
+        UploadSession {
+            upload_id: upload.upload_id,
+            scope: upload.scope,
+            object_key: upload.object_key,
+            checksum: upload.checksum,
+            total_bytes: upload.total_bytes as u64,
+            part_size: upload.part_size as u64,
+            parts: parts.into_iter()
+                .map(|part| UploadedPart {
+                    part_number: part.part_number as u32,
+                    etag: part.etag,
+                    size: part.size as u64,
+                })
+                .collect(),
+            created_at: upload.created_at,
+        }
+    }
+
+    fn to_part(upload_id: &str, part: &UploadedPart) -> MultipartUploadPart {
+        MultipartUploadPart {
+            upload_id: upload_id.to_string(),
+            part_number: part.part_number as i64,
+            etag: part.etag.clone(),
+            size: part.size as i64,
+        }
+    }
+}
+
+#[async_trait]
+impl UploadSessionStore for PersistentUploadSessionStore {
+    async fn find_session(&self, scope: &str, checksum: &str) -> Result<Option<UploadSession>> {
+        Ok(self.store.find_upload(scope, checksum).await?
+            .map(|(upload, parts)| Self::to_session(upload, parts)))
+    }
+
+    async fn get_session(&self, upload_id: &str) -> Result<Option<UploadSession>> {
+        Ok(self.store.get_upload(upload_id).await?
+            .map(|(upload, parts)| Self::to_session(upload, parts)))
+    }
+
+    async fn save_session(&self, session: &UploadSession) -> Result<()> {
+        let upload = MultipartUpload {
+            upload_id: session.upload_id.clone(),
+            scope: session.scope.clone(),
+            object_key: session.object_key.clone(),
+            checksum: session.checksum.clone(),
+            total_bytes: session.total_bytes as i64,
+            part_size: session.part_size as i64,
+            created_at: session.created_at,
+            updated_at: chrono::Utc::now(),
+        };
+        let parts: Vec<_> = session.parts.iter()
+            .map(|part| Self::to_part(&session.upload_id, part))
+            .collect();
+
+        self.store.save_upload(&upload, &parts).await
+    }
+
+    async fn record_part(&self, upload_id: &str, part: &UploadedPart) -> Result<()> {
+        self.store.record_part(&Self::to_part(upload_id, part)).await
+    }
+
+    async fn remove_session(&self, upload_id: &str) -> Result<()> {
+        self.store.remove_upload(upload_id).await
+    }
+}
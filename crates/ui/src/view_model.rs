@@ -3,7 +3,11 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use crate::{GameWithSave, AppSettings, ServiceManager, SyncState, SaveDetectionStatus, SyncHistoryItem, UndoableSync};
 use steam_cloud_sync_core::{scan_installed_games, locate_save};
-use steam_cloud_sync_cloud::{SaveMetadata, StorageInfo};
+use steam_cloud_sync_cloud::{
+    CloudBackend, LegacySave, MigrationProgress, MigrationReport, QuotaStatus, RetentionPlan, RetentionPolicy,
+    RetentionRules, RetryingBackend, SaveMetadata, ScrubReport, StorageInfo,
+};
+use steam_cloud_sync_persistence::{ScrubRecord, ScrubRun};
 
 /// Main application view model that manages state and operations
 #[derive(Clone)]
@@ -65,16 +69,43 @@ impl AppViewModel {
             steam_cloud_sync_cloud::BackendType::S3 => {
                 println!("   - S3 bucket: {}", settings.s3_bucket);
                 println!("   - S3 region: {}", settings.s3_region);
+                println!("   - S3 provider: {:?}", settings.s3_provider);
+                println!("   - S3 endpoint: {}", if settings.s3_endpoint_url.is_empty() { "(AWS default)" } else { &settings.s3_endpoint_url });
                 println!("   - S3 Access Key configured: {}", !settings.s3_access_key.is_empty());
                 println!("   - S3 Secret Key configured: {}", !settings.s3_secret_key.is_empty());
                 
                 if settings.s3_access_key.is_empty() || settings.s3_secret_key.is_empty() {
                     return Err(anyhow::anyhow!("S3 credentials not configured. Please set Access Key and Secret Key in settings."));
                 }
+            },
+            steam_cloud_sync_cloud::BackendType::LocalFolder => {
+                println!("   - Local folder: {}", settings.local_folder_path);
+                
+                if settings.local_folder_path.is_empty() {
+                    return Err(anyhow::anyhow!("Local folder not configured. Please choose a backup folder in settings."));
+                }
+            }
+            steam_cloud_sync_cloud::BackendType::WebDav => {
+                println!("   - WebDAV URL: {}", settings.webdav_url);
+                println!("   - WebDAV user: {}", settings.webdav_username);
+                
+                if settings.webdav_url.is_empty() {
+                    return Err(anyhow::anyhow!("WebDAV server not configured. Please set the WebDAV URL in settings."));
+                }
+            }
+            steam_cloud_sync_cloud::BackendType::Sftp => {
+                println!("   - SFTP server: {}@{}:{}", settings.sftp_username, settings.sftp_host, settings.sftp_port);
+                println!("   - SFTP remote root: {}", settings.sftp_remote_root);
+                println!("   - SFTP private key configured: {}", !settings.sftp_private_key_path.is_empty());
+                
+                if settings.sftp_host.is_empty() || settings.sftp_username.is_empty() {
+                    return Err(anyhow::anyhow!("SFTP server not configured. Please set host and username in settings."));
+                }
             }
         }
         
         let service_manager = Arc::new(ServiceManager::new(settings).await?);
+        service_manager.spawn_scrub_schedule();
         
         let mut sm = self.service_manager.lock().await;
         *sm = Some(service_manager);
@@ -293,11 +324,12 @@ impl AppViewModel {
             return Ok(());
         }
         
-        for (i, game) in enabled_games.iter().enumerate() {
-            println!("🎯 [DEBUG] Syncing game {}/{}: {} ({})", 
-                i + 1, enabled_games.len(), game.game.name, game.game.id);
-            
-            match service_manager.sync_game(game).await {
+        println!("🎯 [DEBUG] Syncing {} games, {} at a time", 
+            enabled_games.len(), service_manager.cloud_service.concurrency());
+        
+        let results = service_manager.sync_games(&enabled_games).await;
+        for (game, result) in enabled_games.iter().zip(results) {
+            match result {
                 Ok(_) => {
                     println!("✅ [DEBUG] Successfully synced: {}", game.game.name);
                 }
@@ -579,6 +611,45 @@ impl AppViewModel {
         service_manager.get_database_stats().await
     }
     
+    /// Cloud operations currently running; empty while the service manager is busy being replaced
+    pub fn running_operations(&self) -> Vec<(uuid::Uuid, steam_cloud_sync_cloud::RunningOperation)> {
+        match self.service_manager.try_lock() {
+            Ok(sm) => sm.as_ref().map(|sm| sm.running_operations()).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+    
+    /// Cancel a running cloud operation
+    pub async fn cancel_operation(&self, operation_id: uuid::Uuid) -> bool {
+        match self.get_service_manager().await {
+            Some(service_manager) => service_manager.cancel_operation(operation_id),
+            None => false,
+        }
+    }
+    
+    /// Cancel every running cloud operation
+    pub async fn cancel_all_operations(&self) {
+        if let Some(service_manager) = self.get_service_manager().await {
+            service_manager.cancel_all();
+        }
+    }
+    
+    /// Status of the backends uploads are mirrored to, without waiting for the service manager
+    pub fn mirror_status(&self) -> Vec<steam_cloud_sync_cloud::ReplicaStatus> {
+        match self.service_manager.try_lock() {
+            Ok(sm) => sm.as_ref().map(|sm| sm.mirror_status()).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+    
+    /// Bring mirrors that fell behind up to date now
+    pub async fn catch_up_mirrors(&self) -> Result<()> {
+        match self.get_service_manager().await {
+            Some(service_manager) => service_manager.catch_up_mirrors().await,
+            None => Ok(()),
+        }
+    }
+    
     /// Process progress updates
     pub async fn process_progress_updates(&self) -> Vec<steam_cloud_sync_cloud::ProgressUpdate> {
         if let Some(service_manager) = self.get_service_manager().await {
@@ -709,31 +780,141 @@ impl AppViewModel {
     /// Test cloud backend connection
     pub async fn test_cloud_backend(&self, backend_type: steam_cloud_sync_cloud::BackendType, settings: &AppSettings) -> Result<()> {
         // Create a temporary backend for testing
-        let backend = match backend_type {
-            steam_cloud_sync_cloud::BackendType::TencentCOS => {
-                steam_cloud_sync_cloud::backend_with_settings(
-                    backend_type,
-                    Some((
-                        settings.tencent_secret_id.clone(),
-                        settings.tencent_secret_key.clone(),
-                        settings.tencent_bucket.clone(),
-                        settings.tencent_region.clone(),
-                    )),
-                    None,
-                )
-            }
-            steam_cloud_sync_cloud::BackendType::S3 => {
-                steam_cloud_sync_cloud::backend_with_settings(
-                    backend_type,
-                    None,
-                    Some((settings.s3_bucket.clone(), "saves/".to_string())),
-                )
-            }
-        };
+        let backend = settings.create_backend(backend_type);
 
         backend.test_connection().await
     }
     
+    /// Copy every save version from one configured backend to another
+    pub async fn migrate_storage(
+        &self,
+        settings: &AppSettings,
+        from: steam_cloud_sync_cloud::BackendType,
+        to: steam_cloud_sync_cloud::BackendType,
+        on_progress: impl Fn(&MigrationProgress),
+    ) -> Result<MigrationReport> {
+        // Version times travel in the save index; listing through it creates one if missing
+        settings.create_backend(from).list_saves(&settings.user_id, None).await?;
+        
+        let source = RetryingBackend::new(settings.storage_backend(from));
+        let target = RetryingBackend::new(settings.storage_backend(to));
+        target.test_connection().await?;
+        steam_cloud_sync_cloud::migrate(&source, &target, on_progress).await
+    }
+    
+    /// Saves stored under legacy names, with the local games they may belong to
+    pub async fn find_legacy_saves(&self, settings: &AppSettings) -> Result<Vec<LegacySave>> {
+        let local_games: Vec<steam_cloud_sync_core::GameSave> = {
+            let cache = self.cache.lock().await;
+            cache.games.iter().filter_map(|game| game.save_info.clone()).collect()
+        };
+        let backend = RetryingBackend::new(settings.create_backend(settings.selected_backend));
+        steam_cloud_sync_cloud::find_legacy_saves(&backend, &settings.user_id, &local_games).await
+    }
+    
+    /// Move a legacy save to the game the user confirmed for it
+    pub async fn repair_legacy_save(&self, settings: &AppSettings, save: &LegacySave, app_id: u32, game_name: &str) -> Result<String> {
+        let backend = RetryingBackend::new(settings.create_backend(settings.selected_backend));
+        backend.repair_save(save, app_id, game_name).await
+    }
+    
+    /// Retention rules applied after uploads; `None` until the service manager is ready
+    pub async fn retention_rules(&self) -> Option<RetentionRules> {
+        self.get_service_manager().await.map(|service_manager| service_manager.retention_rules())
+    }
+    
+    /// Set the retention policy of games without their own
+    pub async fn set_default_retention(&self, policy: RetentionPolicy) -> Result<()> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        service_manager.set_default_retention(policy).await
+    }
+    
+    /// Give a game its own retention policy, or `None` to follow the default
+    pub async fn set_game_retention(&self, game_id: &str, policy: Option<RetentionPolicy>) -> Result<()> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        service_manager.set_game_retention(game_id, policy).await
+    }
+    
+    /// Versions of a game its retention policy would prune, without deleting them
+    pub async fn preview_retention(&self, game_id: &str) -> Result<RetentionPlan> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        service_manager.preview_retention(game_id).await
+    }
+    
+    /// Prune a game's versions now, then refresh its cloud saves
+    pub async fn apply_retention(&self, settings: &AppSettings, game_id: &str) -> Result<RetentionPlan> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        let plan = service_manager.apply_retention(game_id).await?;
+        self.refresh_cloud_saves(settings, game_id).await?;
+        Ok(plan)
+    }
+    
+    /// The storage quota in GB (`None` is unlimited) and the storage used against it
+    pub async fn quota_status(&self) -> Result<(Option<u64>, Option<QuotaStatus>)> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        Ok((service_manager.quota_gb(), service_manager.quota_status().await?))
+    }
+    
+    /// Change the storage quota, 0 for unlimited
+    pub async fn set_quota_gb(&self, gb: u64) -> Result<()> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        service_manager.set_quota_gb(gb).await
+    }
+    
+    /// Versions every game's retention policy would prune, to free space
+    pub async fn preview_quota_cleanup(&self) -> Result<RetentionPlan> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        service_manager.preview_quota_cleanup().await
+    }
+    
+    /// Prune every game by its retention policy to free space
+    pub async fn apply_quota_cleanup(&self) -> Result<RetentionPlan> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        service_manager.apply_quota_cleanup().await
+    }
+    
+    /// The last scrub run and the versions found damaged so far
+    pub async fn scrub_status(&self) -> Result<(Option<ScrubRun>, Vec<ScrubRecord>)> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        Ok((service_manager.last_scrub().await?, service_manager.scrub_problems().await?))
+    }
+    
+    /// Verify every stored version now, or `sample` of them
+    pub async fn scrub(&self, sample: Option<usize>) -> Result<ScrubReport> {
+        let Some(service_manager) = self.get_service_manager().await else {
+            return Err(anyhow::anyhow!("Service manager not initialized"));
+        };
+        
+        service_manager.scrub(sample).await
+    }
+    
     /// Check if scanning
     pub async fn is_scanning(&self) -> bool {
         let cache = self.cache.lock().await;